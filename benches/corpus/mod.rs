@@ -0,0 +1,61 @@
+//! Generators for representative documents. Sizes are parameters, so the
+//! same shapes can be benchmarked at different scales.
+
+use std::fmt::Write;
+
+/// A typical application config with a few dozen keys.
+pub fn small_config() -> String {
+	let mut s = String::from("name: 'service'\nversion: 3\ndebug: false\n");
+	s.push_str("server:\n\thost: 'localhost'\n\tport: 8080\n\ttimeouts: [5 30 60]\n");
+	s.push_str("database:\n\turl: 'postgres://localhost/app'\n\tpool: 16\n");
+	s.push_str("features:--\n");
+	for i in 0..10 {
+		writeln!(
+			s,
+			"\t-\n\t\tname: 'feature-{i}'\n\t\tenabled: {}",
+			i % 2 == 0
+		)
+		.unwrap();
+	}
+	s
+}
+
+/// An expanded array of `len` small objects.
+pub fn large_array(len: usize) -> String {
+	let mut s = String::from("items:--\n");
+	for i in 0..len {
+		writeln!(
+			s,
+			"\t-\n\t\tid: {i}\n\t\tlabel: 'item {i}'\n\t\tweight: {}\n\t\ttags: ['a' 'b' 'c']",
+			i as f32 * 0.5
+		)
+		.unwrap();
+	}
+	s
+}
+
+/// Objects nested `depth` levels deep, with a value at every level.
+pub fn deep_nesting(depth: usize) -> String {
+	let mut s = String::new();
+	for level in 0..depth {
+		let indent = "\t".repeat(level);
+		writeln!(s, "{indent}value: {level}\n{indent}child:").unwrap();
+	}
+	s
+}
+
+/// `count` keys holding multi-line strings of `lines` lines each.
+pub fn multi_line_strings(count: usize, lines: usize) -> String {
+	let mut s = String::new();
+	for i in 0..count {
+		writeln!(s, "text{i}: |").unwrap();
+		for line in 0..lines {
+			writeln!(
+				s,
+				"\tline {line} of a longer paragraph with 'quotes' and # signs"
+			)
+			.unwrap();
+		}
+	}
+	s
+}
@@ -0,0 +1,66 @@
+//! Parsing and encoding benchmarks, run with `cargo bench`. Compare against a
+//! baseline with `cargo bench -- --save-baseline main` on the base branch and
+//! `cargo bench -- --baseline main` on the change.
+
+mod corpus;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use kvon_rs::{encode_string, parse_string, EncodeOptions};
+
+fn documents() -> Vec<(&'static str, String)> {
+	vec![
+		("small_config", corpus::small_config()),
+		("large_array", corpus::large_array(10_000)),
+		("deep_nesting", corpus::deep_nesting(200)),
+		("multi_line_strings", corpus::multi_line_strings(100, 100)),
+	]
+}
+
+fn parse(c: &mut Criterion) {
+	let mut group = c.benchmark_group("parse");
+	for (name, source) in documents() {
+		group.throughput(Throughput::Bytes(source.len() as u64));
+		group.bench_function(name, |b| {
+			b.iter(|| parse_string(black_box(&source)).unwrap())
+		});
+	}
+	group.finish();
+}
+
+/// Parsing into an arena, see `kvon_rs::arena`. Run with
+/// `cargo bench --features arena`.
+#[cfg(feature = "arena")]
+fn parse_in(c: &mut Criterion) {
+	use kvon_rs::arena::{parse_in, Bump};
+
+	let mut group = c.benchmark_group("parse_in");
+	for (name, source) in documents() {
+		group.throughput(Throughput::Bytes(source.len() as u64));
+		group.bench_function(name, |b| {
+			b.iter(|| {
+				let arena = Bump::new();
+				parse_in(&arena, black_box(&source)).unwrap();
+			})
+		});
+	}
+	group.finish();
+}
+
+fn encode(c: &mut Criterion) {
+	let mut group = c.benchmark_group("encode");
+	let options = EncodeOptions::new();
+	for (name, source) in documents() {
+		let value = parse_string(&source).unwrap();
+		group.throughput(Throughput::Bytes(source.len() as u64));
+		group.bench_function(name, |b| {
+			b.iter(|| encode_string(black_box(&value), &options))
+		});
+	}
+	group.finish();
+}
+
+#[cfg(not(feature = "arena"))]
+criterion_group!(benches, parse, encode);
+#[cfg(feature = "arena")]
+criterion_group!(benches, parse, parse_in, encode);
+criterion_main!(benches);
@@ -0,0 +1,60 @@
+use std::{sync::Arc, thread, time::Instant};
+
+use kvon_rs::{parse_string, path::PathSegment, shared::SharedValue, value::Value};
+
+static SOURCE: &str = "
+server:
+	host: 'localhost'
+	port: 8080
+routes:--
+	-
+		path: '/'
+		handler: 'index'
+	-
+		path: '/about'
+		handler: 'about'
+";
+
+const THREADS: usize = 8;
+const READS: usize = 100_000;
+
+fn main() {
+	let config = Arc::new(parse_string(SOURCE).unwrap());
+
+	// every thread reads the same parsed config through an `Arc<Value>`
+	let start = Instant::now();
+	thread::scope(|s| {
+		for _ in 0..THREADS {
+			let config = Arc::clone(&config);
+			s.spawn(move || {
+				let path = [
+					PathSegment::Key("routes".to_string()),
+					PathSegment::Index(1),
+					PathSegment::Key("handler".to_string()),
+				];
+				for _ in 0..READS {
+					let port = config.get("server").and_then(|s| s.get("port"));
+					assert_eq!(port, Some(&Value::from(8080)));
+					assert_eq!(config.get_path(&path), Some(&Value::from("about")));
+				}
+			});
+		}
+	});
+	println!("Arc<Value>: {:?}", start.elapsed());
+
+	// a `SharedValue` can be cloned into each thread without copying it
+	let shared = SharedValue::from(Arc::unwrap_or_clone(config));
+	let start = Instant::now();
+	thread::scope(|s| {
+		for _ in 0..THREADS {
+			let config = shared.clone();
+			s.spawn(move || {
+				for _ in 0..READS {
+					let host = config.get("server").and_then(|s| s.get("host"));
+					assert_eq!(host.unwrap().get_string(), Ok("localhost"));
+				}
+			});
+		}
+	});
+	println!("SharedValue: {:?}", start.elapsed());
+}
@@ -0,0 +1,86 @@
+use std::{
+	alloc::{GlobalAlloc, Layout, System},
+	fmt::Write,
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+use kvon_rs::{
+	parse_string,
+	shared::{parse_string_interned, SharedValue, StringInterner},
+	ParserOptions,
+};
+
+/// Counts the bytes currently allocated on the heap, and the most that were
+/// allocated at once.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+		PEAK.fetch_max(allocated, Ordering::Relaxed);
+		System.alloc(layout)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+		System.dealloc(ptr, layout)
+	}
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const EVENTS: usize = 50_000;
+
+fn main() {
+	// analytics events whose string fields only take a few distinct values
+	let mut source = String::from("events:--\n");
+	for i in 0..EVENTS {
+		let kind = ["page_view", "click", "scroll", "purchase"][i % 4];
+		let country = ["germany", "france", "united_states"][i % 3];
+		writeln!(
+			source,
+			"\t-\n\t\tkind: '{kind}'\n\t\tcountry: '{country}'\n\t\tbrowser: 'firefox'\n\t\tduration: {i}"
+		)
+		.unwrap();
+	}
+
+	let (shared, plain) = measure(|| SharedValue::from(parse_string(&source).unwrap()));
+	drop(shared);
+	let (shared, converted) = measure(|| {
+		SharedValue::interned(parse_string(&source).unwrap(), &mut StringInterner::new())
+	});
+	drop(shared);
+	let mut interner = StringInterner::new();
+	let (shared, interned) =
+		measure(|| parse_string_interned(&source, &ParserOptions::new(), &mut interner).unwrap());
+
+	println!("{EVENTS} events with {} distinct strings", interner.len());
+	println!("{:<22} {:>10} {:>10}", "", "retained", "peak");
+	for (name, (retained, peak)) in [
+		("SharedValue::from", plain),
+		("SharedValue::interned", converted),
+		("parse_string_interned", interned),
+	] {
+		println!(
+			"{name:<22} {:>6} KiB {:>6} KiB",
+			retained / 1024,
+			peak / 1024
+		);
+	}
+	drop(shared);
+}
+
+/// Returns the bytes that `f` allocated and kept, and the most it had
+/// allocated at once.
+fn measure<T>(f: impl FnOnce() -> T) -> (T, (usize, usize)) {
+	let before = ALLOCATED.load(Ordering::Relaxed);
+	PEAK.store(before, Ordering::Relaxed);
+	let value = f();
+	let retained = ALLOCATED.load(Ordering::Relaxed) - before;
+	let peak = PEAK.load(Ordering::Relaxed) - before;
+	(value, (retained, peak))
+}
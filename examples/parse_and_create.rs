@@ -1,6 +1,6 @@
 use kvon_rs::{object, parse_string};
 
-static SOURCE: &'static str = "
+static SOURCE: &str = "
 a:
 	b: 0
 c: [1 2 [3 4]]
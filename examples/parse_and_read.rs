@@ -5,7 +5,7 @@ use kvon_rs::{
 	value::{GetterResult, PrimitiveValue, Value},
 };
 
-static SOURCE: &'static str = "
+static SOURCE: &str = "
 a:
 	b: 0
 c: [1 2 [3 4]]
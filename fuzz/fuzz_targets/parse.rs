@@ -0,0 +1,12 @@
+//! Feeds arbitrary input to the parser, which must return an error instead of
+//! panicking on invalid documents.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	if let Ok(source) = std::str::from_utf8(data) {
+		let _ = kvon_rs::parse_string(source);
+	}
+});
@@ -0,0 +1,76 @@
+//! Encodes arbitrary values and parses them back, which must produce the
+//! original value.
+
+#![no_main]
+
+use std::collections::HashMap;
+
+use arbitrary::{Arbitrary, Unstructured};
+use kvon_rs::{encode_string, parse_string, value::Value, EncodeOptions};
+use libfuzzer_sys::fuzz_target;
+
+/// Limits the nesting, so that inputs are spent on breadth rather than
+/// recursion.
+const MAX_DEPTH: usize = 4;
+
+/// A document, whose root is always an object.
+#[derive(Debug)]
+struct Document(Value);
+
+impl<'a> Arbitrary<'a> for Document {
+	fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+		Ok(Self(Value::Object(object(u, 0)?)))
+	}
+}
+
+fn object(u: &mut Unstructured, depth: usize) -> arbitrary::Result<HashMap<String, Value>> {
+	let mut obj = HashMap::new();
+	for _ in 0..u.int_in_range(0..=4)? {
+		obj.insert(key(u)?, value(u, depth + 1)?);
+	}
+	Ok(obj)
+}
+
+/// Keys made of characters that never need quoting.
+fn key(u: &mut Unstructured) -> arbitrary::Result<String> {
+	const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789_-";
+	let len = u.int_in_range(1..=8)?;
+	(0..len).map(|_| Ok(*u.choose(CHARS)? as char)).collect()
+}
+
+fn value(u: &mut Unstructured, depth: usize) -> arbitrary::Result<Value> {
+	let kinds = if depth < MAX_DEPTH { 6 } else { 4 };
+	Ok(match u.int_in_range(0..=kinds - 1)? {
+		0 => Value::null(),
+		1 => Value::from(bool::arbitrary(u)?),
+		2 => {
+			let n = f32::arbitrary(u)?;
+			Value::from(if n.is_finite() { n } else { 0. })
+		}
+		3 => Value::from(string(u)?),
+		4 => Value::Array(
+			(0..u.int_in_range(0..=4)?)
+				.map(|_| value(u, depth + 1))
+				.collect::<arbitrary::Result<_>>()?,
+		),
+		_ => Value::Object(object(u, depth)?),
+	})
+}
+
+/// Strings without control characters other than line breaks.
+fn string(u: &mut Unstructured) -> arbitrary::Result<String> {
+	let s = String::arbitrary(u)?;
+	Ok(s.chars()
+		.filter(|c| *c == '\n' || !c.is_control())
+		.collect())
+}
+
+fuzz_target!(|document: Document| {
+	let encoded = encode_string(&document.0, &EncodeOptions::new());
+	let parsed = parse_string(&encoded)
+		.unwrap_or_else(|e| panic!("failed to parse encoded document: {e}\n{encoded}"));
+	assert_eq!(
+		parsed, document.0,
+		"round trip changed the document\n{encoded}"
+	);
+});
@@ -0,0 +1,113 @@
+//! Parsing of `#[kvon(...)]` attributes.
+
+use syn::{Attribute, LitStr, Path, Result};
+
+/// How a missing field is filled in.
+pub enum FieldDefault {
+	/// `#[kvon(default)]`, using [Default::default].
+	Trait,
+	/// `#[kvon(default = "path")]`, calling the given function.
+	Function(Path),
+}
+
+/// Attributes of fields and enum variants.
+#[derive(Default)]
+pub struct FieldAttrs {
+	pub rename: Option<String>,
+	pub default: Option<FieldDefault>,
+	pub skip_if_none: bool,
+	pub flatten: bool,
+}
+
+impl FieldAttrs {
+	pub fn parse(attrs: &[Attribute]) -> Result<Self> {
+		let mut result = Self::default();
+
+		for attr in attrs.iter().filter(|attr| attr.path().is_ident("kvon")) {
+			attr.parse_nested_meta(|meta| {
+				if meta.path.is_ident("rename") {
+					let name: LitStr = meta.value()?.parse()?;
+					result.rename = Some(name.value());
+				} else if meta.path.is_ident("default") {
+					result.default = Some(if meta.input.peek(syn::Token![=]) {
+						let path: LitStr = meta.value()?.parse()?;
+						FieldDefault::Function(path.parse()?)
+					} else {
+						FieldDefault::Trait
+					});
+				} else if meta.path.is_ident("skip_if_none") {
+					result.skip_if_none = true;
+				} else if meta.path.is_ident("flatten") {
+					result.flatten = true;
+				} else {
+					return Err(meta.error("unknown kvon field attribute"));
+				}
+				Ok(())
+			})?;
+		}
+
+		Ok(result)
+	}
+}
+
+/// How an enum is represented.
+#[derive(Default)]
+pub enum EnumRepr {
+	/// `Unit` for unit variants and `Variant: content` otherwise.
+	#[default]
+	External,
+	/// `#[kvon(tag = "type")]`, the variant name is stored next to the
+	/// variant's fields.
+	Internal(String),
+	/// `#[kvon(untagged)]`, only the content is stored.
+	Untagged,
+}
+
+/// Attributes of structs and enums.
+#[derive(Default)]
+pub struct ContainerAttrs {
+	pub repr: EnumRepr,
+}
+
+impl ContainerAttrs {
+	pub fn parse(attrs: &[Attribute]) -> Result<Self> {
+		let mut result = Self::default();
+
+		for attr in attrs.iter().filter(|attr| attr.path().is_ident("kvon")) {
+			attr.parse_nested_meta(|meta| {
+				if meta.path.is_ident("tag") {
+					let tag: LitStr = meta.value()?.parse()?;
+					result.repr = EnumRepr::Internal(tag.value());
+				} else if meta.path.is_ident("untagged") {
+					result.repr = EnumRepr::Untagged;
+				} else {
+					return Err(meta.error("unknown kvon container attribute"));
+				}
+				Ok(())
+			})?;
+		}
+
+		Ok(result)
+	}
+}
+
+/// The kinds of enum variants that can be derived.
+pub enum VariantKind<'a> {
+	Unit,
+	Newtype,
+	Struct(&'a syn::FieldsNamed),
+}
+
+impl<'a> VariantKind<'a> {
+	pub fn of(variant: &'a syn::Variant) -> Result<Self> {
+		match &variant.fields {
+			syn::Fields::Unit => Ok(Self::Unit),
+			syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => Ok(Self::Newtype),
+			syn::Fields::Named(fields) => Ok(Self::Struct(fields)),
+			syn::Fields::Unnamed(_) => Err(syn::Error::new_spanned(
+				variant,
+				"tuple variants must have exactly one field",
+			)),
+		}
+	}
+}
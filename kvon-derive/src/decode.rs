@@ -0,0 +1,221 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DataEnum, DeriveInput, Error, Fields, FieldsNamed, Result};
+
+use crate::attr::{ContainerAttrs, EnumRepr, FieldAttrs, FieldDefault, VariantKind};
+
+pub fn derive(input: DeriveInput) -> Result<TokenStream> {
+	let name = &input.ident;
+
+	let mut generics = input.generics.clone();
+	for param in generics.type_params_mut() {
+		param
+			.bounds
+			.push(syn::parse_quote!(::kvon_rs::decode::Decode));
+	}
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+	// enums accept any value, as checking them would require knowing which
+	// variant a value is
+	let mut schema = quote!();
+	let body = match &input.data {
+		Data::Struct(data) => {
+			let Fields::Named(fields) = &data.fields else {
+				return Err(Error::new_spanned(
+					&input.ident,
+					"KvonDecode can only be derived for structs with named fields",
+				));
+			};
+			schema = struct_schema(fields)?;
+			let fields = decode_fields(fields)?;
+			quote! {
+				let obj = ::kvon_rs::decode::expect_object(value)?;
+				::std::result::Result::Ok(Self { #(#fields,)* })
+			}
+		}
+		Data::Enum(data) => decode_enum(&input, data)?,
+		Data::Union(_) => {
+			return Err(Error::new_spanned(
+				&input.ident,
+				"KvonDecode can't be derived for unions",
+			))
+		}
+	};
+
+	Ok(quote! {
+		impl #impl_generics ::kvon_rs::decode::Decode for #name #ty_generics #where_clause {
+			fn decode(
+				value: &::kvon_rs::value::Value,
+			) -> ::std::result::Result<Self, ::kvon_rs::decode::DecodeError> {
+				#body
+			}
+
+			#schema
+		}
+	})
+}
+
+/// Generates the `schema` method of a struct, listing its keys.
+fn struct_schema(fields: &FieldsNamed) -> Result<TokenStream> {
+	let mut statements = Vec::new();
+	for field in &fields.named {
+		let ty = &field.ty;
+		let attrs = FieldAttrs::parse(&field.attrs)?;
+		statements.push(if attrs.flatten {
+			quote!(<#ty as ::kvon_rs::decode::Decode>::schema().flatten_into(&mut fields, &mut open);)
+		} else {
+			let key = attrs
+				.rename
+				.unwrap_or_else(|| field.ident.as_ref().unwrap().to_string());
+			quote!(fields.push((
+				::std::borrow::Cow::Borrowed(#key),
+				::kvon_rs::schema::SchemaRef::Fn(<#ty as ::kvon_rs::decode::Decode>::schema),
+			));)
+		});
+	}
+
+	Ok(quote! {
+		#[allow(unused_mut)]
+		fn schema() -> ::kvon_rs::schema::Schema {
+			let mut fields = ::std::vec::Vec::new();
+			let mut open = false;
+			#(#statements)*
+			::kvon_rs::schema::Schema::Object { fields, open }
+		}
+	})
+}
+
+/// Generates `field: expression` pairs decoding the fields from the object
+/// `obj`, which is the content of `value`.
+fn decode_fields(fields: &FieldsNamed) -> Result<Vec<TokenStream>> {
+	fields
+		.named
+		.iter()
+		.map(|field| {
+			let ident = field.ident.as_ref().unwrap();
+			let attrs = FieldAttrs::parse(&field.attrs)?;
+			let key = attrs.rename.unwrap_or_else(|| ident.to_string());
+
+			let value = match attrs.default {
+				// flattened fields are decoded from the whole object
+				_ if attrs.flatten => quote!(::kvon_rs::decode::Decode::decode(value)?),
+				None => quote!(::kvon_rs::decode::field(obj, #key)?),
+				Some(FieldDefault::Trait) => quote!(
+					::kvon_rs::decode::field_or_else(obj, #key, ::std::default::Default::default)?
+				),
+				Some(FieldDefault::Function(path)) => {
+					quote!(::kvon_rs::decode::field_or_else(obj, #key, #path)?)
+				}
+			};
+			Ok(quote!(#ident: #value))
+		})
+		.collect()
+}
+
+fn decode_enum(input: &DeriveInput, data: &DataEnum) -> Result<TokenStream> {
+	let repr = ContainerAttrs::parse(&input.attrs)?.repr;
+
+	let mut names = Vec::new();
+	// expressions constructing each variant from its content in `value`
+	let mut constructors = Vec::new();
+	let mut kinds = Vec::new();
+	for variant in &data.variants {
+		let ident = &variant.ident;
+		let attrs = FieldAttrs::parse(&variant.attrs)?;
+		names.push(attrs.rename.unwrap_or_else(|| ident.to_string()));
+
+		let kind = VariantKind::of(variant)?;
+		constructors.push(match &kind {
+			VariantKind::Unit => quote!(::std::result::Result::Ok(Self::#ident)),
+			VariantKind::Newtype => quote!(::std::result::Result::Ok(Self::#ident(
+				::kvon_rs::decode::Decode::decode(value)?
+			))),
+			VariantKind::Struct(fields) => {
+				let fields = decode_fields(fields)?;
+				quote! {{
+					let obj = ::kvon_rs::decode::expect_object(value)?;
+					::std::result::Result::Ok(Self::#ident { #(#fields,)* })
+				}}
+			}
+		});
+		kinds.push(kind);
+	}
+
+	let unknown_variant = quote! {
+		other => ::std::result::Result::Err(::kvon_rs::decode::DecodeError::unknown_variant(
+			other,
+			&[#(#names),*],
+		)),
+	};
+
+	Ok(match repr {
+		EnumRepr::External => {
+			let unit_arms = names
+				.iter()
+				.zip(&kinds)
+				.filter(|(_, kind)| matches!(kind, VariantKind::Unit))
+				.map(|(name, _)| name);
+			let unit_constructors = constructors
+				.iter()
+				.zip(&kinds)
+				.filter(|(_, kind)| matches!(kind, VariantKind::Unit))
+				.map(|(constructor, _)| constructor);
+			quote! {
+				let (variant, value) = ::kvon_rs::decode::external_variant(value)?;
+				match value {
+					// unit variants are written as plain strings
+					::std::option::Option::None => match variant {
+						#(#unit_arms => #unit_constructors,)*
+						#unknown_variant
+					},
+					::std::option::Option::Some(value) => {
+						let result = (|| match variant {
+							#(#names => #constructors,)*
+							#unknown_variant
+						})();
+						result.map_err(|e| e.within(::kvon_rs::path::PathSegment::Key(
+							::std::string::ToString::to_string(variant),
+						)))
+					}
+				}
+			}
+		}
+		EnumRepr::Internal(tag) => quote! {
+			let obj = ::kvon_rs::decode::expect_object(value)?;
+			let variant: ::std::string::String = ::kvon_rs::decode::field(obj, #tag)?;
+			match variant.as_str() {
+				#(#names => #constructors,)*
+				#unknown_variant
+			}
+		},
+		EnumRepr::Untagged => {
+			let name = input.ident.to_string();
+			let attempts = constructors.iter().zip(&kinds).map(|(constructor, kind)| {
+				// unit variants are written as null
+				let check = match kind {
+					VariantKind::Unit => {
+						quote!(if !value.get_primitive().is_ok_and(|p| p.is_null()) {
+							return ::std::result::Result::Err(
+								::kvon_rs::decode::DecodeError::invalid_type("null", value),
+							);
+						})
+					}
+					_ => quote!(),
+				};
+				quote! {
+					let result: ::std::result::Result<Self, ::kvon_rs::decode::DecodeError> =
+						(|| { #check #constructor })();
+					if let ::std::result::Result::Ok(decoded) = result {
+						return ::std::result::Result::Ok(decoded);
+					}
+				}
+			});
+			quote! {
+				#(#attempts)*
+				::std::result::Result::Err(::kvon_rs::decode::DecodeError::invalid_value(
+					::std::format!("the value doesn't match any variant of {}", #name),
+				))
+			}
+		}
+	})
+}
@@ -0,0 +1,213 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DataEnum, DeriveInput, Error, Fields, FieldsNamed, Result};
+
+use crate::attr::{ContainerAttrs, EnumRepr, FieldAttrs, VariantKind};
+
+pub fn derive(input: DeriveInput) -> Result<TokenStream> {
+	let name = &input.ident;
+
+	let mut generics = input.generics.clone();
+	for param in generics.type_params_mut() {
+		param
+			.bounds
+			.push(syn::parse_quote!(::kvon_rs::encode::Encode));
+	}
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+	let body = match &input.data {
+		Data::Struct(data) => {
+			let Fields::Named(fields) = &data.fields else {
+				return Err(Error::new_spanned(
+					&input.ident,
+					"KvonEncode can only be derived for structs with named fields",
+				));
+			};
+			encode_struct(fields)?
+		}
+		Data::Enum(data) => encode_enum(&input, data)?,
+		Data::Union(_) => {
+			return Err(Error::new_spanned(
+				&input.ident,
+				"KvonEncode can't be derived for unions",
+			))
+		}
+	};
+
+	Ok(quote! {
+		impl #impl_generics ::kvon_rs::encode::Encode for #name #ty_generics #where_clause {
+			#body
+		}
+	})
+}
+
+/// Generates statements inserting the fields into the map `obj`. Fields are
+/// accessed through `access`, e.g. `&self.field`.
+fn insert_fields(
+	fields: &FieldsNamed,
+	access: impl Fn(&syn::Ident) -> TokenStream,
+) -> Result<Vec<TokenStream>> {
+	fields
+		.named
+		.iter()
+		.map(|field| {
+			let ident = field.ident.as_ref().unwrap();
+			let attrs = FieldAttrs::parse(&field.attrs)?;
+			let key = attrs.rename.clone().unwrap_or_else(|| ident.to_string());
+			let field = access(ident);
+
+			if attrs.flatten {
+				return Ok(quote! {
+					if let ::kvon_rs::value::Value::Object(inner) =
+						::kvon_rs::encode::Encode::encode(#field)
+					{
+						obj.extend(inner);
+					}
+				});
+			}
+
+			let skip = skip_condition(&attrs, &field);
+			Ok(quote! {
+				if !#skip {
+					obj.insert(
+						::std::string::ToString::to_string(#key),
+						::kvon_rs::encode::Encode::encode(#field),
+					);
+				}
+			})
+		})
+		.collect()
+}
+
+fn skip_condition(attrs: &FieldAttrs, field: &TokenStream) -> TokenStream {
+	if attrs.skip_if_none {
+		quote!(::kvon_rs::encode::Encode::is_none(#field))
+	} else {
+		quote!(false)
+	}
+}
+
+fn encode_struct(fields: &FieldsNamed) -> Result<TokenStream> {
+	let inserts = insert_fields(fields, |ident| quote!(&self.#ident))?;
+
+	let writes = fields
+		.named
+		.iter()
+		.map(|field| {
+			let ident = field.ident.as_ref().unwrap();
+			let attrs = FieldAttrs::parse(&field.attrs)?;
+			let key = attrs.rename.clone().unwrap_or_else(|| ident.to_string());
+			let field = quote!(&self.#ident);
+
+			if attrs.flatten {
+				return Ok(quote! {
+					::kvon_rs::encode::Encode::write_entries(#field, w)?;
+				});
+			}
+
+			let skip = skip_condition(&attrs, &field);
+			Ok(quote! {
+				if !#skip {
+					::kvon_rs::writer::KvonWrite::key(w, #key)?;
+					::kvon_rs::encode::Encode::write(#field, w)?;
+				}
+			})
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	Ok(quote! {
+		fn encode(&self) -> ::kvon_rs::value::Value {
+			let mut obj = ::std::collections::HashMap::new();
+			#(#inserts)*
+			::kvon_rs::value::Value::Object(obj)
+		}
+
+		fn write<W: ::kvon_rs::writer::KvonWrite + ?Sized>(
+			&self,
+			w: &mut W,
+		) -> ::std::io::Result<()> {
+			::kvon_rs::writer::KvonWrite::begin_object(w)?;
+			self.write_entries(w)?;
+			::kvon_rs::writer::KvonWrite::end(w)
+		}
+
+		fn write_entries<W: ::kvon_rs::writer::KvonWrite + ?Sized>(
+			&self,
+			w: &mut W,
+		) -> ::std::io::Result<()> {
+			#(#writes)*
+			::std::result::Result::Ok(())
+		}
+	})
+}
+
+fn encode_enum(input: &DeriveInput, data: &DataEnum) -> Result<TokenStream> {
+	let repr = ContainerAttrs::parse(&input.attrs)?.repr;
+
+	let arms = data
+		.variants
+		.iter()
+		.map(|variant| {
+			let ident = &variant.ident;
+			let attrs = FieldAttrs::parse(&variant.attrs)?;
+			let name = attrs.rename.unwrap_or_else(|| ident.to_string());
+
+			// the pattern matching the variant and the expression encoding its
+			// content, `None` for unit variants
+			let (pattern, content) = match VariantKind::of(variant)? {
+				VariantKind::Unit => (quote!(Self::#ident), None),
+				VariantKind::Newtype => (
+					quote!(Self::#ident(inner)),
+					Some(quote!(::kvon_rs::encode::Encode::encode(inner))),
+				),
+				VariantKind::Struct(fields) => {
+					let bindings = fields
+						.named
+						.iter()
+						.map(|field| field.ident.as_ref().unwrap())
+						.collect::<Vec<_>>();
+					let inserts = insert_fields(fields, |ident| quote!(#ident))?;
+					(
+						quote!(Self::#ident { #(#bindings),* }),
+						Some(quote! {{
+							let mut obj = ::std::collections::HashMap::new();
+							#(#inserts)*
+							::kvon_rs::value::Value::Object(obj)
+						}}),
+					)
+				}
+			};
+
+			let encoded = match (&repr, content) {
+				(EnumRepr::External, None) => quote!(::kvon_rs::value::Value::from(#name)),
+				(EnumRepr::External, Some(content)) => {
+					quote!(::kvon_rs::value::Value::key_value_pair(#name, #content))
+				}
+				(EnumRepr::Internal(tag), None) => {
+					quote!(::kvon_rs::value::Value::key_value_pair(#tag, #name))
+				}
+				(EnumRepr::Internal(tag), Some(content)) => quote! {{
+					let mut value = #content;
+					if let ::kvon_rs::value::Value::Object(obj) = &mut value {
+						obj.insert(
+							::std::string::ToString::to_string(#tag),
+							::kvon_rs::value::Value::from(#name),
+						);
+					}
+					value
+				}},
+				(EnumRepr::Untagged, None) => quote!(::kvon_rs::value::Value::null()),
+				(EnumRepr::Untagged, Some(content)) => content,
+			};
+			Ok(quote!(#pattern => #encoded,))
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	Ok(quote! {
+		fn encode(&self) -> ::kvon_rs::value::Value {
+			match self {
+				#(#arms)*
+			}
+		}
+	})
+}
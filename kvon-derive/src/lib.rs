@@ -0,0 +1,59 @@
+//! Derive macros for [kvon-rs](https://github.com/kvon-format/kvon-rs). Use
+//! them through the `derive` feature of `kvon-rs` instead of depending on
+//! this crate directly.
+
+mod attr;
+mod decode;
+mod encode;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Implements `kvon_rs::decode::Decode` for a struct with named fields or an
+/// enum. See [KvonEncode] for how enums are represented.
+///
+/// Fields accept the following attributes:
+/// - `#[kvon(rename = "name")]`: reads the field from a different key.
+/// - `#[kvon(default)]`: uses `Default::default()` if the key is missing.
+/// - `#[kvon(default = "path::to::function")]`: calls the function if the
+///   key is missing.
+/// - `#[kvon(flatten)]`: decodes the field from the whole object instead of
+///   from one of its keys.
+///
+/// For structs, `Decode::schema` lists the struct's keys, which
+/// `kvon_rs::schema::unknown_keys` uses to report misspelled keys.
+#[proc_macro_derive(KvonDecode, attributes(kvon))]
+pub fn derive_decode(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	decode::derive(input)
+		.unwrap_or_else(syn::Error::into_compile_error)
+		.into()
+}
+
+/// Implements `kvon_rs::encode::Encode` for a struct with named fields or an
+/// enum. Fields are written in declaration order.
+///
+/// Enums are externally tagged by default: unit variants are written as the
+/// variant's name, e.g. `'Red'`, and other variants as an object with the
+/// variant's name as its only key, e.g. `Rgb: [255 0 0]`. The representation
+/// can be changed on the enum:
+/// - `#[kvon(tag = "type")]`: the name is stored in the given key next to
+///   the variant's fields, e.g. `type: 'Circle'` and `radius: 1`.
+/// - `#[kvon(untagged)]`: only the variant's content is stored, unit
+///   variants are written as `null`. Decoding picks the first variant that
+///   matches.
+///
+/// Variants accept `#[kvon(rename = "name")]`.
+///
+/// Fields accept the following attributes:
+/// - `#[kvon(rename = "name")]`: writes the field to a different key.
+/// - `#[kvon(skip_if_none)]`: omits the key if the field is `None`.
+/// - `#[kvon(flatten)]`: writes the entries of the field's object into the
+///   struct's object.
+#[proc_macro_derive(KvonEncode, attributes(kvon))]
+pub fn derive_encode(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	encode::derive(input)
+		.unwrap_or_else(syn::Error::into_compile_error)
+		.into()
+}
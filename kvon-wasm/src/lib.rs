@@ -0,0 +1,27 @@
+//! JavaScript bindings for [kvon_rs], built with `wasm-pack build kvon-wasm`.
+//!
+//! The bindings only call the string based [parse_string] and
+//! [try_encode_string]. The file based functions of kvon-rs compile for
+//! `wasm32-unknown-unknown` too, but fail at runtime there, so they aren't
+//! exposed.
+
+use kvon_rs::{encoder::try_encode_string, parse_string, value::Value, EncodeOptions};
+use serde::Serialize;
+use serde_wasm_bindgen::Serializer;
+use wasm_bindgen::prelude::*;
+
+/// Parses a KVON document into a plain JavaScript object.
+#[wasm_bindgen]
+pub fn parse(source: &str) -> Result<JsValue, JsValue> {
+	let value = parse_string(source).map_err(|e| JsValue::from_str(&e.to_string()))?;
+	value
+		.serialize(&Serializer::json_compatible())
+		.map_err(JsValue::from)
+}
+
+/// Encodes a plain JavaScript object into a KVON document.
+#[wasm_bindgen]
+pub fn encode(value: JsValue) -> Result<String, JsValue> {
+	let value: Value = serde_wasm_bindgen::from_value(value)?;
+	try_encode_string(&value, &EncodeOptions::new()).map_err(|e| JsValue::from_str(&e.to_string()))
+}
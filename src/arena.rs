@@ -0,0 +1,261 @@
+//! Parsing documents into an arena, so that all of their nodes are
+//! allocated together and freed at once when the arena is dropped.
+//!
+//! ```rust
+//! use kvon_rs::arena::{parse_in, ArenaValue, Bump};
+//!
+//! let arena = Bump::new();
+//! let value = parse_in(&arena, "server:\n  host: 'localhost'\n  ports: [80 443]\n").unwrap();
+//! let server = value.get("server").unwrap();
+//! assert_eq!(server.get("host").unwrap().get_string(), Ok("localhost"));
+//! assert_eq!(server.get("ports").and_then(|ports| ports.get_index(1)).unwrap().get_number(), Ok(443.));
+//! assert_eq!(value.to_value(), kvon_rs::parse_string("server:\n  host: 'localhost'\n  ports: [80 443]\n").unwrap());
+//! ```
+//!
+//! Objects are lists of entries in the order of the document instead of
+//! hash maps, and keys and strings are slices of the arena, so parsing
+//! doesn't allocate per object or key. Looking up a key is linear in the
+//! size of the object. Documents are parsed with the default
+//! [ParserOptions].
+
+use bumpalo::collections::Vec as BumpVec;
+
+pub use bumpalo::Bump;
+
+use crate::{
+	parser::{Entries, Items, Tree},
+	value::{GetterResult, PrimitiveValue, RawValue, Value},
+	Parser, ParserOptions, ParserResult,
+};
+
+/// A [Value] whose nodes are allocated in an arena, see [parse_in].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArenaValue<'a> {
+	/// A primitive other than a string.
+	Primitive(PrimitiveValue),
+	String(&'a str),
+	/// The entries of an object, in the order of the document.
+	Object(BumpVec<'a, (&'a str, ArenaValue<'a>)>),
+	Array(BumpVec<'a, ArenaValue<'a>>),
+}
+
+#[allow(clippy::result_unit_err)]
+impl<'a> ArenaValue<'a> {
+	pub fn is_object(&self) -> bool {
+		matches!(self, Self::Object(_))
+	}
+
+	pub fn is_array(&self) -> bool {
+		matches!(self, Self::Array(_))
+	}
+
+	pub fn get_entries(&self) -> GetterResult<&[(&'a str, ArenaValue<'a>)]> {
+		match self {
+			Self::Object(entries) => Ok(entries),
+			_ => Err(()),
+		}
+	}
+
+	pub fn get_vector(&self) -> GetterResult<&[ArenaValue<'a>]> {
+		match self {
+			Self::Array(arr) => Ok(arr),
+			_ => Err(()),
+		}
+	}
+
+	/// Returns the primitive, which is only possible for non string
+	/// primitives. See [ArenaValue::get_string] for strings.
+	pub fn get_primitive(&self) -> GetterResult<&PrimitiveValue> {
+		match self {
+			Self::Primitive(primitive) => Ok(primitive),
+			_ => Err(()),
+		}
+	}
+
+	pub fn get_string(&self) -> GetterResult<&'a str> {
+		match self {
+			Self::String(s) => Ok(s),
+			_ => Err(()),
+		}
+	}
+
+	pub fn get_number(&self) -> GetterResult<f32> {
+		self.get_primitive()?.get_number()
+	}
+
+	pub fn get_boolean(&self) -> GetterResult<bool> {
+		self.get_primitive()?.get_boolean()
+	}
+
+	/// Returns the value of a key of an object.
+	pub fn get(&self, key: &str) -> Option<&ArenaValue<'a>> {
+		self.get_entries()
+			.ok()?
+			.iter()
+			.find(|(k, _)| *k == key)
+			.map(|(_, value)| value)
+	}
+
+	/// Returns an element of an array.
+	pub fn get_index(&self, index: usize) -> Option<&ArenaValue<'a>> {
+		self.get_vector().ok()?.get(index)
+	}
+
+	/// Copies the value out of the arena.
+	pub fn to_value(&self) -> Value {
+		match self {
+			Self::Primitive(p) => Value::Primitive(p.clone()),
+			Self::String(s) => Value::from(*s),
+			Self::Object(entries) => Value::Object(
+				entries
+					.iter()
+					.map(|(key, value)| (key.to_string(), value.to_value()))
+					.collect(),
+			),
+			Self::Array(arr) => Value::Array(arr.iter().map(ArenaValue::to_value).collect()),
+		}
+	}
+
+	/// Copies a value into the arena.
+	fn alloc(arena: &'a Bump, value: Value) -> Self {
+		match value {
+			Value::Primitive(PrimitiveValue::String(s)) => Self::String(arena.alloc_str(&s)),
+			Value::Primitive(p) => Self::Primitive(p),
+			Value::Object(obj) => Self::Object(BumpVec::from_iter_in(
+				obj.into_iter()
+					.map(|(key, value)| (&*arena.alloc_str(&key), Self::alloc(arena, value))),
+				arena,
+			)),
+			Value::Array(arr) => Self::Array(BumpVec::from_iter_in(
+				arr.into_iter().map(|value| Self::alloc(arena, value)),
+				arena,
+			)),
+			Value::Raw(_) => unreachable!("raw values need ParserOptions::raw_paths"),
+		}
+	}
+
+	fn primitive(arena: &'a Bump, primitive: PrimitiveValue) -> Self {
+		match primitive {
+			PrimitiveValue::String(s) => Self::String(arena.alloc_str(&s)),
+			p => Self::Primitive(p),
+		}
+	}
+}
+
+/// Parses a document into values allocated in `arena`.
+pub fn parse_in<'a>(arena: &'a Bump, source: &str) -> ParserResult<ArenaValue<'a>> {
+	let mut parser = Parser::with_builder(ParserOptions::new(), arena);
+	for line in source.lines() {
+		parser.next_line(line)?;
+	}
+	parser.finish_tree()
+}
+
+impl<'a> Tree for ArenaValue<'a> {
+	type Builder = &'a Bump;
+	type Map = BumpVec<'a, (&'a str, ArenaValue<'a>)>;
+	type List = BumpVec<'a, ArenaValue<'a>>;
+
+	fn primitive(arena: &mut &'a Bump, primitive: PrimitiveValue) -> Self {
+		Self::primitive(arena, primitive)
+	}
+
+	fn value(arena: &mut &'a Bump, value: Value) -> Self {
+		Self::alloc(arena, value)
+	}
+
+	fn raw(_: &mut &'a Bump, _: RawValue) -> Self {
+		unreachable!("raw values need ParserOptions::raw_paths")
+	}
+
+	fn new_object(arena: &&'a Bump, capacity: usize) -> Self::Map {
+		BumpVec::with_capacity_in(capacity, arena)
+	}
+
+	fn new_array(arena: &&'a Bump) -> Self::List {
+		BumpVec::new_in(arena)
+	}
+
+	fn object(entries: Self::Map) -> Self {
+		Self::Object(entries)
+	}
+
+	fn array(items: Self::List) -> Self {
+		Self::Array(items)
+	}
+
+	fn into_object(self) -> Option<Self::Map> {
+		match self {
+			Self::Object(entries) => Some(entries),
+			_ => None,
+		}
+	}
+
+	fn into_array(self) -> Option<Self::List> {
+		match self {
+			Self::Array(items) => Some(items),
+			_ => None,
+		}
+	}
+
+	fn as_object(&self) -> Option<&Self::Map> {
+		match self {
+			Self::Object(entries) => Some(entries),
+			_ => None,
+		}
+	}
+
+	fn object_mut(&mut self) -> Option<&mut Self::Map> {
+		match self {
+			Self::Object(entries) => Some(entries),
+			_ => None,
+		}
+	}
+}
+
+/// Keys assigned twice keep the position of their first assignment.
+impl<'a> Entries<ArenaValue<'a>> for BumpVec<'a, (&'a str, ArenaValue<'a>)> {
+	fn len(&self) -> usize {
+		self.as_slice().len()
+	}
+
+	fn get(&self, key: &str) -> Option<&ArenaValue<'a>> {
+		self.iter().find(|(k, _)| *k == key).map(|(_, value)| value)
+	}
+
+	fn get_mut(&mut self, key: &str) -> Option<&mut ArenaValue<'a>> {
+		self.iter_mut()
+			.find(|(k, _)| *k == key)
+			.map(|(_, value)| value)
+	}
+
+	fn insert(&mut self, key: String, value: ArenaValue<'a>) -> Option<ArenaValue<'a>> {
+		match Entries::get_mut(self, &key) {
+			Some(existing) => Some(std::mem::replace(existing, value)),
+			None => {
+				let key = self.bump().alloc_str(&key);
+				self.push((key, value));
+				None
+			}
+		}
+	}
+
+	fn remove(&mut self, key: &str) -> Option<ArenaValue<'a>> {
+		let index = self.iter().position(|(k, _)| *k == key)?;
+		Some(BumpVec::remove(self, index).1)
+	}
+}
+
+impl<'a> Items<ArenaValue<'a>> for BumpVec<'a, ArenaValue<'a>> {
+	fn len(&self) -> usize {
+		self.as_slice().len()
+	}
+
+	fn push(&mut self, value: ArenaValue<'a>) {
+		BumpVec::push(self, value)
+	}
+
+	fn pop(&mut self) -> Option<ArenaValue<'a>> {
+		BumpVec::pop(self)
+	}
+}
@@ -0,0 +1,288 @@
+//! Command line tools for KVON documents.
+//!
+//! ```text
+//! kvon gen [--name <Name>] [<file>]
+//! kvon fmt [--verify] [<file>]
+//! kvon set [--schema <schema>] <path> <value> [<file>]
+//! kvon diff <old> <new>
+//! kvon patch <patch> [<file>]
+//! kvon render [--list] <template> [<params>]
+//! kvon explain [--kvon] [--path <path>] <file>...
+//! ```
+//!
+//! Documents are read from standard input if no file is given.
+
+use std::{
+	env,
+	io::{self, IsTerminal, Read},
+	process::ExitCode,
+};
+
+use kvon_rs::{
+	codegen,
+	diff::diff as diff_values,
+	edit::{set_input, EditError},
+	encode_string,
+	explain::explain as explain_layers,
+	format::{format_str_with_value, rewrite_entry, FormatOptions, CONFIG_FILE_NAME},
+	json_schema::from_json_schema,
+	layers::LayeredConfig,
+	parse_string, parse_string_output,
+	patch::{apply_patch_file, encode_patch},
+	path::KvonPath,
+	roundtrip::verify_encoding,
+	template::Template,
+	value::Value,
+	EncodeOptions, ParserOptions,
+};
+
+const USAGE: &str = "\
+usage: kvon <command> [<args>]
+
+commands:
+    gen [--name <Name>] [<file>]    print Rust structs and an object! literal for a document
+    fmt [--verify] [<file>]         print a document with normalized indention and spacing,
+                                    keeping comments, blank lines and the order of keys.
+                                    Reads options from the closest .kvonfmt file.
+                                    --verify fails instead if that changes the value
+    set [--schema <schema>] <path> <value> [<file>]
+                                    print a document with the value at a path such as
+                                    'server.port' replaced. --schema checks the value
+                                    against a JSON Schema written in KVON
+    diff <old> <new>                print a patch with the changes between two documents
+    patch <patch> [<file>]          print a document with a patch applied, failing if it
+                                    doesn't hold the values the patch replaces
+    render [--list] <template> [<params>]
+                                    print a template with its placeholders replaced by
+                                    parameters. --list prints the parameters it needs
+    explain [--kvon] [--path <path>] <file>...
+                                    print where the values of layered documents come from
+                                    and which values they override, later files taking
+                                    precedence. --path only explains the values at a path";
+
+fn main() -> ExitCode {
+	let args: Vec<String> = env::args().skip(1).collect();
+	let result = match args.first().map(String::as_str) {
+		Some("gen") => gen(&args[1..]),
+		Some("fmt") => fmt(&args[1..]),
+		Some("set") => set(&args[1..]),
+		Some("diff") => diff(&args[1..]),
+		Some("patch") => patch(&args[1..]),
+		Some("render") => render(&args[1..]),
+		Some("explain") => explain(&args[1..]),
+		Some("-h" | "--help") => {
+			println!("{USAGE}");
+			Ok(())
+		}
+		Some(command) => Err(format!("unknown command '{command}'\n\n{USAGE}")),
+		None => Err(USAGE.to_string()),
+	};
+
+	match result {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(message) => {
+			eprintln!("{message}");
+			ExitCode::FAILURE
+		}
+	}
+}
+
+fn gen(args: &[String]) -> Result<(), String> {
+	let mut name = "Config".to_string();
+	let mut file = None;
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--name" => {
+				name = args.next().ok_or("expected a name after '--name'")?.clone();
+			}
+			arg if file.is_none() && !arg.starts_with('-') => file = Some(arg.to_string()),
+			arg => return Err(format!("unexpected argument '{arg}'\n\n{USAGE}")),
+		}
+	}
+
+	let value = read_document(file.as_deref())?;
+	print!("{}", codegen::generate(&name, &value));
+	Ok(())
+}
+
+fn fmt(args: &[String]) -> Result<(), String> {
+	let mut verify = false;
+	let mut file = None;
+	for arg in args {
+		match arg.as_str() {
+			"--verify" => verify = true,
+			arg if file.is_none() && !arg.starts_with('-') => file = Some(arg.to_string()),
+			arg => return Err(format!("unexpected argument '{arg}'\n\n{USAGE}")),
+		}
+	}
+
+	let (source, name) = read_source(file.as_deref())?;
+	let options = FormatOptions::discover(file.as_deref().unwrap_or("."))
+		.map_err(|e| format!("{CONFIG_FILE_NAME}: {e}"))?;
+	let (formatted, value) =
+		format_str_with_value(&source, &options).map_err(|e| format!("{name}:{e}"))?;
+	// sorting and deduplicating arrays is meant to change the value
+	if verify {
+		verify_encoding(&value, formatted.clone()).map_err(|e| format!("{name}:{e}"))?;
+	}
+	print!("{formatted}");
+	Ok(())
+}
+
+fn set(args: &[String]) -> Result<(), String> {
+	let mut schema_file = None;
+	let mut positional = Vec::new();
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--schema" => {
+				schema_file = Some(args.next().ok_or("expected a file after '--schema'")?);
+			}
+			arg if positional.len() < 3 && !arg.starts_with('-') => positional.push(arg),
+			arg => return Err(format!("unexpected argument '{arg}'\n\n{USAGE}")),
+		}
+	}
+	let [path, input, file @ ..] = positional.as_slice() else {
+		return Err(format!("expected a path and a value\n\n{USAGE}"));
+	};
+
+	let path: KvonPath = path.parse().map_err(|e| format!("{path}: {e}"))?;
+	let schema = match schema_file {
+		Some(schema_file) => {
+			let json_schema = read_document(Some(schema_file))?;
+			Some(from_json_schema(&json_schema).map_err(|e| format!("{schema_file}: {e}"))?)
+		}
+		None => None,
+	};
+
+	let (source, name) = read_source(file.first().copied())?;
+	let mut value = parse_string(&source).map_err(|e| format!("{name}:{e}"))?;
+	set_input(&mut value, &path, input, schema.as_ref()).map_err(|e| match e {
+		EditError::Parse(e) => format!("<value>:{e}"),
+		e => e.to_string(),
+	})?;
+	// only the entry is rewritten, keeping the comments and order of the rest
+	let rewritten = rewrite_entry(&source, &value, &path).map_err(|e| format!("{name}:{e}"))?;
+	print!("{rewritten}");
+	Ok(())
+}
+
+fn diff(args: &[String]) -> Result<(), String> {
+	let [old, new] = args else {
+		return Err(format!("expected two files\n\n{USAGE}"));
+	};
+	let old = read_document(Some(old))?;
+	let new = read_document(Some(new))?;
+	print!("{}", encode_patch(&diff_values(&old, &new)));
+	Ok(())
+}
+
+fn patch(args: &[String]) -> Result<(), String> {
+	let [patch, file @ ..] = args else {
+		return Err(format!("expected a patch file\n\n{USAGE}"));
+	};
+	if file.len() > 1 || args.iter().any(|arg| arg.starts_with('-')) {
+		return Err(format!("unexpected arguments\n\n{USAGE}"));
+	}
+
+	let (source, name) = read_source(file.first().map(String::as_str))?;
+	let mut output =
+		parse_string_output(&source, &ParserOptions::new()).map_err(|e| format!("{name}:{e}"))?;
+	apply_patch_file(&mut output.value, patch).map_err(|e| format!("{patch}: {e}"))?;
+	let encoded = encode_string(&output.value, &output.encode_options());
+	println!("{}", encoded.trim_start_matches('\n'));
+	Ok(())
+}
+
+fn render(args: &[String]) -> Result<(), String> {
+	let mut list = false;
+	let mut files = Vec::new();
+	for arg in args {
+		match arg.as_str() {
+			"--list" => list = true,
+			arg if files.len() < 2 && !arg.starts_with('-') => files.push(arg),
+			arg => return Err(format!("unexpected argument '{arg}'\n\n{USAGE}")),
+		}
+	}
+	let [template, params @ ..] = files.as_slice() else {
+		return Err(format!("expected a template\n\n{USAGE}"));
+	};
+
+	let (source, name) = read_source(Some(template))?;
+	let template = Template::parse(&source).map_err(|e| format!("{name}:{e}"))?;
+	if list {
+		for parameter in template.parameters() {
+			println!("{parameter}");
+		}
+		return Ok(());
+	}
+
+	let params = read_document(params.first().copied())?;
+	let value = template
+		.render(&params)
+		.map_err(|e| format!("{name}: {e}"))?;
+	let encoded = encode_string(&value, &EncodeOptions::new());
+	println!("{}", encoded.trim_start_matches('\n'));
+	Ok(())
+}
+
+fn explain(args: &[String]) -> Result<(), String> {
+	let mut kvon = false;
+	let mut path = KvonPath::root();
+	let mut files = Vec::new();
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--kvon" => kvon = true,
+			"--path" => {
+				let arg = args.next().ok_or(format!("expected a path\n\n{USAGE}"))?;
+				path = arg.parse().map_err(|e| format!("{arg}: {e}"))?;
+			}
+			arg if !arg.starts_with('-') => files.push(arg),
+			arg => return Err(format!("unexpected argument '{arg}'\n\n{USAGE}")),
+		}
+	}
+	if files.is_empty() {
+		return Err(format!("expected at least one file\n\n{USAGE}"));
+	}
+
+	let mut config = LayeredConfig::new().with_spans();
+	for file in files {
+		config.add_file(file).map_err(|e| e.to_string())?;
+	}
+	let report = explain_layers(&config).within(&path);
+	if kvon {
+		print!("{}", report.to_kvon());
+	} else if io::stdout().is_terminal() {
+		print!("{}", report.to_ansi());
+	} else {
+		print!("{}", report.to_table());
+	}
+	Ok(())
+}
+
+/// Parses the document in `file`, or in standard input if `file` is `None`.
+fn read_document(file: Option<&str>) -> Result<Value, String> {
+	let (source, name) = read_source(file)?;
+	parse_string(&source).map_err(|e| format!("{name}:{e}"))
+}
+
+/// Reads `file`, or standard input if `file` is `None`, returning the
+/// source and the name to report errors with.
+fn read_source(file: Option<&str>) -> Result<(String, &str), String> {
+	let (source, name) = match file {
+		Some(file) => (
+			std::fs::read_to_string(file).map_err(|e| format!("{file}: {e}"))?,
+			file,
+		),
+		None => {
+			let mut source = String::new();
+			io::stdin()
+				.read_to_string(&mut source)
+				.map_err(|e| format!("<stdin>: {e}"))?;
+			(source, "<stdin>")
+		}
+	};
+	Ok((source, name))
+}
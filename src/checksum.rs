@@ -0,0 +1,34 @@
+//! Checksums of documents, to detect hand-edits to machine-managed files.
+//! [EncodeOptions::checksum](crate::EncodeOptions::checksum) writes a
+//! `# sha256: ...` line at the top of a document, and the parser reports a
+//! [Warning::ChecksumMismatch](crate::Warning::ChecksumMismatch) if the
+//! parsed value doesn't match it:
+//!
+//! ```rust
+//! use kvon_rs::{encode_string, object, parse_string_output, EncodeOptions, ParserOptions, Warning};
+//!
+//! let encoded = encode_string(&object! { port: 80 }, &EncodeOptions::new().checksum(true));
+//! let output = parse_string_output(&encoded, &ParserOptions::new()).unwrap();
+//! assert!(output.warnings.is_empty());
+//!
+//! let edited = encoded.replace("80", "8080");
+//! let output = parse_string_output(&edited, &ParserOptions::new()).unwrap();
+//! assert!(matches!(output.warnings[0], Warning::ChecksumMismatch { .. }));
+//! ```
+//!
+//! The checksum covers the value rather than its text, so reformatting a
+//! document or changing its comments keeps it valid.
+
+use sha2::{Digest, Sha256};
+
+use crate::value::Value;
+
+/// The start of the line holding the checksum.
+pub const CHECKSUM_PREFIX: &str = "# sha256:";
+
+/// The hex encoded SHA-256 of the canonical encoding of a value, see
+/// [Value::to_stable_string].
+pub fn checksum(value: &Value) -> String {
+	let hash = Sha256::digest(value.to_stable_string().as_bytes());
+	hash.iter().map(|byte| format!("{byte:02x}")).collect()
+}
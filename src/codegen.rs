@@ -0,0 +1,314 @@
+//! Generating Rust code from example documents, to turn an existing config
+//! into typed code. [generate] writes structs deriving `KvonDecode` and
+//! `KvonEncode` for the shape of a document, and a function returning the
+//! document as an [object!](crate::object) literal. The `kvon gen` command
+//! does the same for a file.
+//!
+//! ```rust
+//! use kvon_rs::{codegen::rust_structs, parse_string, schema::Schema};
+//!
+//! let value = parse_string("name: 'app'\nhttp-port: 8080").unwrap();
+//! let code = rust_structs("Config", &Schema::infer(&value));
+//! assert_eq!(
+//!     code,
+//!     "\
+//! #[derive(Debug, Clone, kvon_rs::KvonDecode, kvon_rs::KvonEncode)]
+//! pub struct Config {
+//!     #[kvon(rename = \"http-port\")]
+//!     pub http_port: i64,
+//!     pub name: String,
+//! }
+//! "
+//! );
+//! ```
+
+use std::collections::HashSet;
+
+use crate::{
+	encoder::encode_primitive_lines,
+	schema::{Constraint, Schema, SchemaRef},
+	value::{PrimitiveValue, Value},
+	EncodeOptions,
+};
+
+/// Writes the structs for the inferred schema of `value`, followed by a
+/// function returning `value`, named after the root struct, e.g.
+/// `example_config`.
+pub fn generate(name: &str, value: &Value) -> String {
+	let name = pascal_case(name);
+	let mut code = rust_structs(&name, &Schema::infer(value));
+	code.push_str(&format!(
+		"\npub fn example_{}() -> kvon_rs::value::Value {{\n    {}\n}}\n",
+		snake_case(&name),
+		object_literal(value).replace('\n', "\n    ")
+	));
+	code
+}
+
+/// Writes struct definitions for a schema, starting with the root struct
+/// named `name`. Nested objects are named after their keys, and keys that
+/// aren't valid field names are renamed.
+pub fn rust_structs(name: &str, schema: &Schema) -> String {
+	let mut generator = Generator::default();
+	let root = generator.rust_type(schema, &pascal_case(name));
+	if generator.structs.is_empty() {
+		// the root isn't an object, so there is nothing to derive for
+		return format!("pub type {} = {root};\n", pascal_case(name));
+	}
+	generator.structs.join("\n")
+}
+
+#[derive(Default)]
+struct Generator {
+	structs: Vec<String>,
+	names: HashSet<String>,
+	/// The functions of the schemas being written with the name of their
+	/// struct, to refer to recursive schemas by name.
+	stack: Vec<(usize, Option<String>)>,
+	/// The index in `stack` of the function whose schema is written next.
+	pending: Option<usize>,
+}
+
+impl Generator {
+	fn rust_type_ref(&mut self, schema: &SchemaRef, name: &str) -> String {
+		let SchemaRef::Fn(f) = schema else {
+			return self.rust_type(&schema.get(), name);
+		};
+		let address = *f as usize;
+		if let Some((_, name)) = self.stack.iter().find(|(a, _)| *a == address) {
+			return match name {
+				Some(name) => format!("Box<{name}>"),
+				None => "kvon_rs::value::Value".to_string(),
+			};
+		}
+		self.pending = Some(self.stack.len());
+		self.stack.push((address, None));
+		let rust_type = self.rust_type(&f(), name);
+		self.stack.pop();
+		rust_type
+	}
+
+	fn rust_type(&mut self, schema: &Schema, name: &str) -> String {
+		let pending = self.pending.take();
+		match schema {
+			Schema::Any => "kvon_rs::value::Value".to_string(),
+			Schema::Number => "f64".to_string(),
+			Schema::Boolean => "bool".to_string(),
+			Schema::String => "String".to_string(),
+			Schema::Constrained(schema, constraints) => match schema.as_ref() {
+				Schema::Number if is_integer(constraints) => "i64".to_string(),
+				schema => self.rust_type(schema, name),
+			},
+			Schema::Optional(schema) => format!("Option<{}>", self.rust_type_ref(schema, name)),
+			Schema::Array(schema) => format!(
+				"Vec<{}>",
+				self.rust_type_ref(schema, &format!("{name}Item"))
+			),
+			Schema::Map(schema) => format!(
+				"std::collections::HashMap<String, {}>",
+				self.rust_type_ref(schema, &format!("{name}Value"))
+			),
+			Schema::Object { fields, .. } => {
+				let name = self.unique_name(name);
+				if let Some(i) = pending {
+					self.stack[i].1 = Some(name.clone());
+				}
+				let index = self.structs.len();
+				self.structs.push(String::new());
+
+				let mut code = format!(
+					"#[derive(Debug, Clone, kvon_rs::KvonDecode, kvon_rs::KvonEncode)]\npub struct {name} {{\n"
+				);
+				// keys that are valid field names keep them
+				let mut idents: HashSet<String> = fields
+					.iter()
+					.map(|(key, _)| key.to_string())
+					.filter(|key| snake_case(key) == *key)
+					.collect();
+				for (key, schema) in fields {
+					let mut ident = snake_case(key);
+					if ident != key.as_ref() {
+						while !idents.insert(ident.clone()) {
+							ident.push('_');
+						}
+						code.push_str(&format!("    #[kvon(rename = {key:?})]\n"));
+					}
+					let rust_type = self.rust_type_ref(schema, &pascal_case(key));
+					code.push_str(&format!("    pub {ident}: {rust_type},\n"));
+				}
+				code.push_str("}\n");
+				self.structs[index] = code;
+				name
+			}
+		}
+	}
+
+	/// Returns `name`, or `name` with a number if a struct already uses it.
+	fn unique_name(&mut self, name: &str) -> String {
+		let mut unique = name.to_string();
+		let mut i = 2;
+		while self.names.contains(&unique) {
+			unique = format!("{name}{i}");
+			i += 1;
+		}
+		self.names.insert(unique.clone());
+		unique
+	}
+}
+
+/// Whether number constraints only allow whole numbers, e.g. a
+/// `MultipleOf(1.)` or the whole range of an inferred schema.
+fn is_integer(constraints: &[Constraint]) -> bool {
+	let whole = |n: &f32| n.fract() == 0.;
+	constraints
+		.iter()
+		.any(|c| matches!(c, Constraint::MultipleOf(n) if whole(n)))
+		|| constraints
+			.iter()
+			.filter_map(|c| match c {
+				Constraint::Minimum(n) | Constraint::Maximum(n) => Some(whole(n)),
+				_ => None,
+			})
+			.fold(None, |all, whole| Some(all.unwrap_or(true) && whole))
+			.unwrap_or(false)
+}
+
+/// Writes a value as an [object!](crate::object) or [array!](crate::array)
+/// literal. Primitives without a Rust literal, e.g. UUIDs, are written as
+/// strings.
+pub fn object_literal(value: &Value) -> String {
+	let mut code = String::new();
+	write_literal(value, 0, &mut code);
+	code
+}
+
+fn write_literal(value: &Value, depth: usize, code: &mut String) {
+	let indent = "    ".repeat(depth + 1);
+	match value {
+		Value::Primitive(p) => code.push_str(&primitive_literal(p)),
+		Value::Object(obj) if obj.is_empty() => code.push_str("kvon_rs::object! {}"),
+		Value::Object(obj) => {
+			code.push_str("kvon_rs::object! {\n");
+			let mut keys: Vec<_> = obj.keys().collect();
+			keys.sort();
+			for key in keys {
+				code.push_str(&indent);
+				match is_identifier(key) {
+					true => code.push_str(key),
+					false => code.push_str(&format!("{key:?}")),
+				}
+				code.push_str(": ");
+				write_nested(&obj[key], depth + 1, code);
+				code.push_str(",\n");
+			}
+			code.push_str(&"    ".repeat(depth));
+			code.push('}');
+		}
+		Value::Array(arr) => {
+			code.push_str("kvon_rs::array![");
+			for (i, item) in arr.iter().enumerate() {
+				if i > 0 {
+					code.push_str(", ");
+				}
+				write_nested(item, depth, code);
+			}
+			code.push(']');
+		}
+		Value::Raw(raw) => code.push_str(&format!("{:?}", raw.head().trim())),
+	}
+}
+
+/// Writes a value inside of a literal, where the macros accept nested objects
+/// and arrays without their names.
+fn write_nested(value: &Value, depth: usize, code: &mut String) {
+	let mut nested = String::new();
+	write_literal(value, depth, &mut nested);
+	let nested = nested
+		.strip_prefix("kvon_rs::object! ")
+		.or_else(|| nested.strip_prefix("kvon_rs::array!"))
+		.unwrap_or(&nested);
+	code.push_str(nested);
+}
+
+fn primitive_literal(p: &PrimitiveValue) -> String {
+	match p {
+		PrimitiveValue::Null => "null".to_string(),
+		PrimitiveValue::Boolean(b) => b.to_string(),
+		PrimitiveValue::Number(n) if n.fract() == 0. && n.abs() <= i32::MAX as f32 => {
+			format!("{}", *n as i32)
+		}
+		PrimitiveValue::Number(n) => format!("{n:?}"),
+		PrimitiveValue::String(s) => format!("{s:?}"),
+		p => {
+			let literal = encode_primitive_lines(p, &EncodeOptions::new(), String::new(), 0);
+			format!("{:?}", literal.join("\n"))
+		}
+	}
+}
+
+const KEYWORDS: &[&str] = &[
+	"as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+	"false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+	"ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+	"unsafe", "use", "where", "while",
+];
+
+fn is_identifier(s: &str) -> bool {
+	let mut chars = s.chars();
+	chars
+		.next()
+		.is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+		&& chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+		&& s != "_"
+}
+
+/// Splits a key into lowercase words at separators and camel case humps.
+fn words(key: &str) -> Vec<String> {
+	let mut words = Vec::new();
+	let mut word = String::new();
+	let mut previous_lowercase = false;
+	for c in key.chars() {
+		if !c.is_ascii_alphanumeric() {
+			words.push(std::mem::take(&mut word));
+			previous_lowercase = false;
+			continue;
+		}
+		if c.is_ascii_uppercase() && previous_lowercase {
+			words.push(std::mem::take(&mut word));
+		}
+		previous_lowercase = c.is_ascii_lowercase() || c.is_ascii_digit();
+		word.push(c.to_ascii_lowercase());
+	}
+	words.push(word);
+	words.retain(|word| !word.is_empty());
+	words
+}
+
+fn snake_case(key: &str) -> String {
+	let mut ident = words(key).join("_");
+	// keys without letters or digits, e.g. empty ones
+	if ident.is_empty() {
+		ident = "field".to_string();
+	}
+	if !ident.starts_with(|c: char| c.is_ascii_alphabetic()) {
+		ident.insert(0, '_');
+	}
+	if KEYWORDS.contains(&ident.as_str()) {
+		ident.push('_');
+	}
+	ident
+}
+
+fn pascal_case(key: &str) -> String {
+	let mut name: String = words(key)
+		.iter()
+		.map(|word| word[..1].to_ascii_uppercase() + &word[1..])
+		.collect();
+	if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+		name.insert(0, 'T');
+	}
+	if KEYWORDS.contains(&name.as_str()) {
+		name.push('_');
+	}
+	name
+}
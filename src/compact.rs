@@ -0,0 +1,350 @@
+//! A [Value] representation for documents made of many small objects and
+//! arrays, e.g. lists of records. Objects with up to [SMALL_OBJECT] entries
+//! are lists sorted by key instead of hash maps, and arrays are boxed slices
+//! without spare capacity, so small values take a single allocation each
+//! and their entries are next to each other in memory.
+//!
+//! ```rust
+//! use kvon_rs::{compact::parse_string_compact, parse_string, ParserOptions};
+//!
+//! let source = "server:\n  port: 80\n  host: 'localhost'\nports: [80 443]\n";
+//! let value = parse_string_compact(source, &ParserOptions::new()).unwrap();
+//! let server = value.get("server").unwrap().get_objects().unwrap();
+//! let keys: Vec<&str> = server.iter().map(|(key, _)| key).collect();
+//! assert_eq!(keys, ["host", "port"]);
+//! assert_eq!(value.get("ports").and_then(|ports| ports.get_index(1)).unwrap().get_number(), Ok(443.));
+//! assert_eq!(value.to_value(), parse_string(source).unwrap());
+//! ```
+//!
+//! Small objects iterate over their entries in the order of their keys, and
+//! looking up a key is a binary search. Larger objects are hash maps like
+//! those of a [Value], which iterate in an unspecified order.
+
+use std::collections::HashMap;
+
+use crate::{
+	parser::{Entries, Tree},
+	value::{GetterResult, PrimitiveValue, RawValue, Value},
+	Parser, ParserOptions, ParserResult,
+};
+
+/// The most entries an object keeps as a sorted list.
+pub const SMALL_OBJECT: usize = 8;
+
+/// A [Value] whose small objects and arrays are compact, see the module
+/// documentation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompactValue {
+	Primitive(PrimitiveValue),
+	Object(CompactObject),
+	Array(Box<[CompactValue]>),
+	Raw(Box<RawValue>),
+}
+
+#[allow(clippy::result_unit_err)]
+impl CompactValue {
+	pub fn is_object(&self) -> bool {
+		matches!(self, Self::Object(_))
+	}
+
+	pub fn is_array(&self) -> bool {
+		matches!(self, Self::Array(_))
+	}
+
+	pub fn get_objects(&self) -> GetterResult<&CompactObject> {
+		match self {
+			Self::Object(obj) => Ok(obj),
+			_ => Err(()),
+		}
+	}
+
+	pub fn get_vector(&self) -> GetterResult<&[CompactValue]> {
+		match self {
+			Self::Array(arr) => Ok(arr),
+			_ => Err(()),
+		}
+	}
+
+	pub fn get_primitive(&self) -> GetterResult<&PrimitiveValue> {
+		match self {
+			Self::Primitive(primitive) => Ok(primitive),
+			_ => Err(()),
+		}
+	}
+
+	pub fn get_string(&self) -> GetterResult<&str> {
+		self.get_primitive()?.get_string()
+	}
+
+	pub fn get_number(&self) -> GetterResult<f32> {
+		self.get_primitive()?.get_number()
+	}
+
+	pub fn get_boolean(&self) -> GetterResult<bool> {
+		self.get_primitive()?.get_boolean()
+	}
+
+	/// Returns the value of a key of an object.
+	pub fn get(&self, key: &str) -> Option<&CompactValue> {
+		self.get_objects().ok()?.get(key)
+	}
+
+	/// Returns an element of an array.
+	pub fn get_index(&self, index: usize) -> Option<&CompactValue> {
+		self.get_vector().ok()?.get(index)
+	}
+
+	/// Copies the value into a [Value].
+	pub fn to_value(&self) -> Value {
+		match self {
+			Self::Primitive(p) => Value::Primitive(p.clone()),
+			Self::Object(obj) => Value::Object(
+				obj.iter()
+					.map(|(key, value)| (key.to_string(), value.to_value()))
+					.collect(),
+			),
+			Self::Array(arr) => Value::Array(arr.iter().map(CompactValue::to_value).collect()),
+			Self::Raw(raw) => Value::Raw(raw.as_ref().clone()),
+		}
+	}
+}
+
+impl From<Value> for CompactValue {
+	fn from(value: Value) -> Self {
+		match value {
+			Value::Primitive(p) => Self::Primitive(p),
+			Value::Object(obj) => {
+				let mut entries = CompactObject::with_capacity(obj.len());
+				for (key, value) in obj {
+					entries.insert(key, value.into());
+				}
+				Self::Object(entries.shrink())
+			}
+			Value::Array(arr) => Self::Array(arr.into_iter().map(Into::into).collect()),
+			Value::Raw(raw) => Self::Raw(Box::new(raw)),
+		}
+	}
+}
+
+impl From<CompactValue> for Value {
+	fn from(value: CompactValue) -> Self {
+		match value {
+			CompactValue::Primitive(p) => Value::Primitive(p),
+			CompactValue::Object(obj) => Value::Object(
+				obj.into_entries()
+					.map(|(key, value)| (key, value.into()))
+					.collect(),
+			),
+			CompactValue::Array(arr) => {
+				Value::Array(arr.into_vec().into_iter().map(Into::into).collect())
+			}
+			CompactValue::Raw(raw) => Value::Raw(*raw),
+		}
+	}
+}
+
+/// The entries of an object of a [CompactValue].
+#[derive(Debug, Clone)]
+pub struct CompactObject(ObjectRepr);
+
+#[derive(Debug, Clone)]
+enum ObjectRepr {
+	/// Sorted by key.
+	Small(Vec<(String, CompactValue)>),
+	/// Boxed so that objects are no larger than small ones.
+	#[allow(clippy::box_collection)]
+	Large(Box<HashMap<String, CompactValue>>),
+}
+
+impl CompactObject {
+	fn with_capacity(capacity: usize) -> Self {
+		Self(match capacity {
+			0..=SMALL_OBJECT => ObjectRepr::Small(Vec::with_capacity(capacity)),
+			_ => ObjectRepr::Large(Box::new(HashMap::with_capacity(capacity))),
+		})
+	}
+
+	/// Drops the spare capacity of a small object.
+	fn shrink(mut self) -> Self {
+		if let ObjectRepr::Small(entries) = &mut self.0 {
+			entries.shrink_to_fit();
+		}
+		self
+	}
+
+	pub fn len(&self) -> usize {
+		match &self.0 {
+			ObjectRepr::Small(entries) => entries.len(),
+			ObjectRepr::Large(entries) => entries.len(),
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	pub fn get(&self, key: &str) -> Option<&CompactValue> {
+		match &self.0 {
+			ObjectRepr::Small(entries) => entries
+				.binary_search_by(|(k, _)| k.as_str().cmp(key))
+				.ok()
+				.map(|i| &entries[i].1),
+			ObjectRepr::Large(entries) => entries.get(key),
+		}
+	}
+
+	/// The entries, in the order of their keys if the object is small.
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &CompactValue)> {
+		let (small, large) = match &self.0 {
+			ObjectRepr::Small(entries) => {
+				(Some(entries.iter().map(|(k, v)| (k.as_str(), v))), None)
+			}
+			ObjectRepr::Large(entries) => {
+				(None, Some(entries.iter().map(|(k, v)| (k.as_str(), v))))
+			}
+		};
+		small
+			.into_iter()
+			.flatten()
+			.chain(large.into_iter().flatten())
+	}
+
+	fn into_entries(self) -> impl Iterator<Item = (String, CompactValue)> {
+		let (small, large) = match self.0 {
+			ObjectRepr::Small(entries) => (Some(entries), None),
+			ObjectRepr::Large(entries) => (None, Some(*entries)),
+		};
+		small
+			.into_iter()
+			.flatten()
+			.chain(large.into_iter().flatten())
+	}
+}
+
+impl PartialEq for CompactObject {
+	fn eq(&self, other: &Self) -> bool {
+		self.len() == other.len()
+			&& self
+				.iter()
+				.all(|(key, value)| other.get(key) == Some(value))
+	}
+}
+
+impl Entries<CompactValue> for CompactObject {
+	fn len(&self) -> usize {
+		CompactObject::len(self)
+	}
+
+	fn get(&self, key: &str) -> Option<&CompactValue> {
+		CompactObject::get(self, key)
+	}
+
+	fn get_mut(&mut self, key: &str) -> Option<&mut CompactValue> {
+		match &mut self.0 {
+			ObjectRepr::Small(entries) => entries
+				.binary_search_by(|(k, _)| k.as_str().cmp(key))
+				.ok()
+				.map(|i| &mut entries[i].1),
+			ObjectRepr::Large(entries) => entries.get_mut(key),
+		}
+	}
+
+	/// Small objects become hash maps once they grow past [SMALL_OBJECT]
+	/// entries.
+	fn insert(&mut self, key: String, value: CompactValue) -> Option<CompactValue> {
+		let entries = match &mut self.0 {
+			ObjectRepr::Small(entries) => entries,
+			ObjectRepr::Large(entries) => return entries.insert(key, value),
+		};
+		match entries.binary_search_by(|(k, _)| k.as_str().cmp(&key)) {
+			Ok(i) => Some(std::mem::replace(&mut entries[i].1, value)),
+			Err(_) if entries.len() == SMALL_OBJECT => {
+				let mut large: HashMap<_, _> = std::mem::take(entries).into_iter().collect();
+				large.insert(key, value);
+				self.0 = ObjectRepr::Large(Box::new(large));
+				None
+			}
+			Err(i) => {
+				entries.insert(i, (key, value));
+				None
+			}
+		}
+	}
+
+	fn remove(&mut self, key: &str) -> Option<CompactValue> {
+		match &mut self.0 {
+			ObjectRepr::Small(entries) => entries
+				.binary_search_by(|(k, _)| k.as_str().cmp(key))
+				.ok()
+				.map(|i| entries.remove(i).1),
+			ObjectRepr::Large(entries) => entries.remove(key),
+		}
+	}
+}
+
+impl Tree for CompactValue {
+	type Builder = ();
+	type Map = CompactObject;
+	type List = Vec<CompactValue>;
+
+	fn primitive(_: &mut (), primitive: PrimitiveValue) -> Self {
+		Self::Primitive(primitive)
+	}
+
+	fn value(_: &mut (), value: Value) -> Self {
+		value.into()
+	}
+
+	fn raw(_: &mut (), raw: RawValue) -> Self {
+		Self::Raw(Box::new(raw))
+	}
+
+	fn new_object(_: &(), capacity: usize) -> CompactObject {
+		CompactObject::with_capacity(capacity)
+	}
+
+	fn new_array(_: &()) -> Vec<CompactValue> {
+		Vec::new()
+	}
+
+	fn object(entries: CompactObject) -> Self {
+		Self::Object(entries.shrink())
+	}
+
+	fn array(items: Vec<CompactValue>) -> Self {
+		Self::Array(items.into_boxed_slice())
+	}
+
+	fn into_object(self) -> Option<CompactObject> {
+		match self {
+			Self::Object(entries) => Some(entries),
+			_ => None,
+		}
+	}
+
+	fn into_array(self) -> Option<Vec<CompactValue>> {
+		match self {
+			Self::Array(items) => Some(items.into_vec()),
+			_ => None,
+		}
+	}
+
+	fn as_object(&self) -> Option<&CompactObject> {
+		self.get_objects().ok()
+	}
+
+	fn object_mut(&mut self) -> Option<&mut CompactObject> {
+		match self {
+			Self::Object(entries) => Some(entries),
+			_ => None,
+		}
+	}
+}
+
+/// Parses a string into a [CompactValue], building its compact objects and
+/// arrays directly.
+pub fn parse_string_compact(s: &str, options: &ParserOptions) -> ParserResult<CompactValue> {
+	let mut parser = Parser::with_builder(options.clone(), ());
+	parser.parse_lines(s)?;
+	parser.finish_tree()
+}
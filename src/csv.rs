@@ -0,0 +1,159 @@
+//! Converting between CSV files and arrays of flat objects, e.g. to edit a
+//! list of records in a spreadsheet.
+//!
+//! ```rust
+//! use kvon_rs::{array, csv::{from_csv, to_csv, CsvOptions}};
+//!
+//! let users = from_csv("name,age\nada,36\ngrace,\n".as_bytes(), &CsvOptions::new()).unwrap();
+//! assert_eq!(users, array![{ name: "ada", age: 36 }, { name: "grace", age: null }]);
+//! assert_eq!(to_csv(&users).unwrap(), "age,name\n36,ada\n,grace\n");
+//! ```
+//!
+//! The columns of a written file are the keys of all objects, sorted. Cells
+//! of missing keys and `null`s are left empty.
+
+use std::{collections::HashMap, fmt, io};
+
+use crate::{
+	env::{parse_var_value, var_value},
+	path::KvonPath,
+	value::Value,
+};
+
+/// Options for reading and writing CSV files.
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+	pub(crate) delimiter: u8,
+	pub(crate) parse_values: bool,
+}
+
+impl Default for CsvOptions {
+	fn default() -> Self {
+		Self {
+			delimiter: b',',
+			parse_values: true,
+		}
+	}
+}
+
+impl CsvOptions {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets the byte separating cells. Defaults to `,`.
+	pub fn delimiter(mut self, delimiter: u8) -> Self {
+		self.delimiter = delimiter;
+		self
+	}
+
+	/// When enabled, `true`, `false`, `null` and numbers are read as
+	/// primitives of that type and empty cells as `null`. Otherwise every
+	/// cell is a string. Enabled by default.
+	pub fn parse_values(mut self, enable: bool) -> Self {
+		self.parse_values = enable;
+		self
+	}
+}
+
+/// Errors that can happen while converting CSV files.
+#[derive(Debug)]
+pub enum CsvError {
+	Csv(::csv::Error),
+	/// The value isn't an array of objects holding primitives. The path
+	/// points at the first value that doesn't fit.
+	NotTabular(KvonPath),
+}
+
+impl fmt::Display for CsvError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Csv(e) => write!(f, "{e}"),
+			Self::NotTabular(path) => {
+				if !path.is_empty() {
+					write!(f, "{path}: ")?;
+				}
+				write!(f, "expected an array of objects holding primitives")
+			}
+		}
+	}
+}
+
+impl std::error::Error for CsvError {}
+
+impl From<::csv::Error> for CsvError {
+	fn from(e: ::csv::Error) -> Self {
+		Self::Csv(e)
+	}
+}
+
+/// Reads a CSV file with a header row into an array of objects keyed by the
+/// header.
+pub fn from_csv<R: io::Read>(reader: R, options: &CsvOptions) -> Result<Value, CsvError> {
+	let mut reader = ::csv::ReaderBuilder::new()
+		.delimiter(options.delimiter)
+		.from_reader(reader);
+	let headers = reader.headers()?.clone();
+
+	let mut records = Vec::new();
+	for record in reader.records() {
+		let record = record?;
+		let obj: HashMap<String, Value> = headers
+			.iter()
+			.zip(record.iter())
+			.map(|(key, cell)| {
+				let value = match options.parse_values {
+					true if cell.is_empty() => Value::null(),
+					true => parse_var_value(cell.to_string()),
+					false => Value::from(cell),
+				};
+				(key.to_string(), value)
+			})
+			.collect();
+		records.push(Value::Object(obj));
+	}
+	Ok(Value::Array(records))
+}
+
+/// Writes an array of flat objects as a CSV file with a header row.
+pub fn to_csv(value: &Value) -> Result<String, CsvError> {
+	to_csv_with_options(value, &CsvOptions::new())
+}
+
+/// Writes an array of flat objects as a CSV file using the given options.
+pub fn to_csv_with_options(value: &Value, options: &CsvOptions) -> Result<String, CsvError> {
+	let Value::Array(records) = value else {
+		return Err(CsvError::NotTabular(KvonPath::root()));
+	};
+
+	let mut keys: Vec<&String> = Vec::new();
+	for (i, record) in records.iter().enumerate() {
+		let obj = record
+			.get_objects()
+			.map_err(|_| CsvError::NotTabular(KvonPath::root().index(i)))?;
+		for (key, value) in obj {
+			if matches!(value, Value::Object(_) | Value::Array(_)) {
+				return Err(CsvError::NotTabular(KvonPath::root().index(i).key(key)));
+			}
+			keys.push(key);
+		}
+	}
+	keys.sort();
+	keys.dedup();
+
+	let mut writer = ::csv::WriterBuilder::new()
+		.delimiter(options.delimiter)
+		.from_writer(Vec::new());
+	writer.write_record(&keys)?;
+	for record in records {
+		writer.write_record(keys.iter().map(|key| match record.get(key) {
+			Some(Value::Primitive(p)) if !p.is_null() => var_value(p),
+			Some(Value::Raw(raw)) => raw.head().trim().to_string(),
+			_ => String::new(),
+		}))?;
+	}
+	let bytes = writer
+		.into_inner()
+		.map_err(|e| ::csv::Error::from(e.into_error()))?;
+	Ok(String::from_utf8(bytes).expect("the cells are strings"))
+}
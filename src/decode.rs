@@ -0,0 +1,457 @@
+//! Typed decoding of [Value]s without serde. Implement [Decode] for your
+//! types, or derive it for structs with `#[derive(KvonDecode)]` when the
+//! `derive` feature is enabled:
+//!
+//! ```rust
+//! use kvon_rs::{
+//!     decode::{expect_object, field, field_or_else, Decode, DecodeError},
+//!     parse_string,
+//!     value::Value,
+//! };
+//!
+//! // equivalent to `#[derive(KvonDecode)]` with `#[kvon(default)]` on `port`
+//! struct Server {
+//!     host: String,
+//!     port: u16,
+//! }
+//!
+//! impl Decode for Server {
+//!     fn decode(value: &Value) -> Result<Self, DecodeError> {
+//!         let obj = expect_object(value)?;
+//!         Ok(Self {
+//!             host: field(obj, "host")?,
+//!             port: field_or_else(obj, "port", Default::default)?,
+//!         })
+//!     }
+//! }
+//!
+//! let server = Server::decode(&parse_string("host: 'localhost'").unwrap()).unwrap();
+//! assert_eq!(server.port, 0);
+//! ```
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	fmt,
+};
+
+use crate::{
+	path::{KvonPath, PathSegment},
+	schema::{Schema, SchemaRef},
+	value::{PrimitiveValue, Value},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeErrorKind {
+	/// A required key is missing from an object.
+	MissingField(String),
+	/// The value has a different type than expected.
+	InvalidType {
+		expected: &'static str,
+		found: &'static str,
+	},
+	/// An enum variant that doesn't exist.
+	UnknownVariant {
+		found: String,
+		expected: &'static [&'static str],
+	},
+	/// The value has the right type, but can't be converted, e.g. a number
+	/// that doesn't fit into a `u8`.
+	InvalidValue(String),
+}
+
+/// Errors that can happen while decoding a [Value].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+	pub kind: DecodeErrorKind,
+	/// The location of the value that failed to decode.
+	pub path: KvonPath,
+}
+
+impl DecodeError {
+	pub fn new(kind: DecodeErrorKind) -> Self {
+		Self {
+			kind,
+			path: KvonPath::root(),
+		}
+	}
+
+	pub fn invalid_type(expected: &'static str, found: &Value) -> Self {
+		Self::new(DecodeErrorKind::InvalidType {
+			expected,
+			found: type_name(found),
+		})
+	}
+
+	pub fn unknown_variant(found: &str, expected: &'static [&'static str]) -> Self {
+		Self::new(DecodeErrorKind::UnknownVariant {
+			found: found.to_string(),
+			expected,
+		})
+	}
+
+	pub fn invalid_value(message: impl ToString) -> Self {
+		Self::new(DecodeErrorKind::InvalidValue(message.to_string()))
+	}
+
+	/// Prepends a segment to the path, used while unwinding out of nested
+	/// values.
+	pub fn within(mut self, segment: PathSegment) -> Self {
+		self.path.prepend(segment);
+		self
+	}
+}
+
+impl fmt::Display for DecodeError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if !self.path.is_empty() {
+			write!(f, "{}: ", self.path)?;
+		}
+
+		match &self.kind {
+			DecodeErrorKind::MissingField(key) => write!(f, "missing field '{key}'"),
+			DecodeErrorKind::InvalidType { expected, found } => {
+				write!(f, "expected {expected}, found {found}")
+			}
+			DecodeErrorKind::UnknownVariant { found, expected } => write!(
+				f,
+				"unknown variant '{found}', expected one of: {}",
+				expected.join(", ")
+			),
+			DecodeErrorKind::InvalidValue(message) => write!(f, "{message}"),
+		}
+	}
+}
+
+impl std::error::Error for DecodeError {}
+
+/// The name of a value's type, as used in error messages.
+fn type_name(value: &Value) -> &'static str {
+	match value {
+		Value::Primitive(p) => match p {
+			PrimitiveValue::Number(_) => "number",
+			PrimitiveValue::String(_) => "string",
+			PrimitiveValue::Boolean(_) => "boolean",
+			PrimitiveValue::Null => "null",
+			#[cfg(feature = "quantities")]
+			PrimitiveValue::Duration(_) => "duration",
+			#[cfg(feature = "quantities")]
+			PrimitiveValue::ByteSize(_) => "byte size",
+			#[cfg(feature = "uuid")]
+			PrimitiveValue::Uuid(_) => "UUID",
+			#[cfg(feature = "ipaddr")]
+			PrimitiveValue::Ip(_) => "IP address",
+			#[cfg(feature = "semver")]
+			PrimitiveValue::Version(_) => "version",
+			PrimitiveValue::Custom(_) => "custom scalar",
+		},
+		Value::Object(_) => "object",
+		Value::Array(_) => "array",
+		Value::Raw(_) => "raw value",
+	}
+}
+
+/// Types that can be constructed from a [Value].
+pub trait Decode: Sized {
+	fn decode(value: &Value) -> Result<Self, DecodeError>;
+
+	/// The value used when the key of a struct field is missing. Returns
+	/// `None` for required fields, which is the default.
+	fn decode_missing() -> Option<Self> {
+		None
+	}
+
+	/// The shape of the values this type decodes, used to report unknown
+	/// keys. Accepts anything by default.
+	fn schema() -> Schema {
+		Schema::Any
+	}
+}
+
+/// Returns the entries of an object, for implementing [Decode] on structs.
+pub fn expect_object(value: &Value) -> Result<&HashMap<String, Value>, DecodeError> {
+	match value {
+		Value::Object(obj) => Ok(obj),
+		_ => Err(DecodeError::invalid_type("object", value)),
+	}
+}
+
+/// Splits an externally tagged enum into the variant's name and content.
+/// Unit variants are plain strings and have no content, other variants are
+/// objects with the name as their only key.
+pub fn external_variant(value: &Value) -> Result<(&str, Option<&Value>), DecodeError> {
+	match value {
+		Value::Primitive(PrimitiveValue::String(name)) => Ok((name, None)),
+		Value::Object(obj) if obj.len() == 1 => {
+			let (name, content) = obj.iter().next().unwrap();
+			Ok((name, Some(content)))
+		}
+		_ => Err(DecodeError::invalid_type("enum variant", value)),
+	}
+}
+
+/// Decodes the value of a key, which is required unless
+/// [Decode::decode_missing] provides a value.
+pub fn field<T: Decode>(obj: &HashMap<String, Value>, key: &str) -> Result<T, DecodeError> {
+	match obj.get(key) {
+		Some(value) => T::decode(value).map_err(|e| e.within(PathSegment::Key(key.to_string()))),
+		None => T::decode_missing()
+			.ok_or_else(|| DecodeError::new(DecodeErrorKind::MissingField(key.to_string()))),
+	}
+}
+
+/// Decodes the value of a key, calling `default` if it is missing.
+pub fn field_or_else<T: Decode>(
+	obj: &HashMap<String, Value>,
+	key: &str,
+	default: impl FnOnce() -> T,
+) -> Result<T, DecodeError> {
+	match obj.get(key) {
+		Some(value) => T::decode(value).map_err(|e| e.within(PathSegment::Key(key.to_string()))),
+		None => Ok(default()),
+	}
+}
+
+impl Decode for Value {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		Ok(value.clone())
+	}
+}
+
+impl Decode for bool {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		match value {
+			Value::Primitive(PrimitiveValue::Boolean(b)) => Ok(*b),
+			_ => Err(DecodeError::invalid_type("boolean", value)),
+		}
+	}
+
+	fn schema() -> Schema {
+		Schema::Boolean
+	}
+}
+
+impl Decode for String {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		match value {
+			Value::Primitive(PrimitiveValue::String(s)) => Ok(s.clone()),
+			_ => Err(DecodeError::invalid_type("string", value)),
+		}
+	}
+
+	fn schema() -> Schema {
+		Schema::String
+	}
+}
+
+impl Decode for f32 {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		match value {
+			Value::Primitive(PrimitiveValue::Number(n)) => Ok(*n),
+			_ => Err(DecodeError::invalid_type("number", value)),
+		}
+	}
+
+	fn schema() -> Schema {
+		Schema::Number
+	}
+}
+
+impl Decode for f64 {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		f32::decode(value).map(f64::from)
+	}
+
+	fn schema() -> Schema {
+		Schema::Number
+	}
+}
+
+macro_rules! impl_decode_integer {
+	($($t:ty),*) => {
+		$(
+			impl Decode for $t {
+				fn decode(value: &Value) -> Result<Self, DecodeError> {
+					// `MAX` rounds up when converted into a float, so the upper
+					// bound is exclusive
+					let n = f64::from(f32::decode(value)?);
+					if n.fract() != 0.0 || n < <$t>::MIN as f64 || n >= <$t>::MAX as f64 + 1.0 {
+						return Err(DecodeError::invalid_value(format!(
+							"{n} is not a valid {}",
+							stringify!($t)
+						)));
+					}
+					Ok(n as $t)
+				}
+
+				fn schema() -> Schema {
+					Schema::Number
+				}
+			}
+		)*
+	};
+}
+
+impl_decode_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+#[cfg(feature = "quantities")]
+impl Decode for std::time::Duration {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		match value {
+			Value::Primitive(PrimitiveValue::Duration(d)) => Ok(*d),
+			_ => Err(DecodeError::invalid_type("duration", value)),
+		}
+	}
+}
+
+#[cfg(feature = "uuid")]
+impl Decode for uuid::Uuid {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		match value {
+			Value::Primitive(PrimitiveValue::Uuid(uuid)) => Ok(*uuid),
+			_ => Err(DecodeError::invalid_type("UUID", value)),
+		}
+	}
+}
+
+#[cfg(feature = "ipaddr")]
+impl Decode for std::net::IpAddr {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		match value {
+			Value::Primitive(PrimitiveValue::Ip(ip)) => Ok(*ip),
+			_ => Err(DecodeError::invalid_type("IP address", value)),
+		}
+	}
+}
+
+#[cfg(feature = "semver")]
+impl Decode for semver::Version {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		match value {
+			Value::Primitive(PrimitiveValue::Version(version)) => Ok(version.clone()),
+			_ => Err(DecodeError::invalid_type("version", value)),
+		}
+	}
+}
+
+impl<T: Decode> Decode for Option<T> {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		match value {
+			Value::Primitive(PrimitiveValue::Null) => Ok(None),
+			value => T::decode(value).map(Some),
+		}
+	}
+
+	/// Optional fields may be missing.
+	fn decode_missing() -> Option<Self> {
+		Some(None)
+	}
+
+	fn schema() -> Schema {
+		Schema::Optional(SchemaRef::Fn(T::schema))
+	}
+}
+
+impl<T: Decode> Decode for Box<T> {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		T::decode(value).map(Box::new)
+	}
+
+	fn schema() -> Schema {
+		T::schema()
+	}
+}
+
+impl<T: Decode> Decode for Vec<T> {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		match value {
+			Value::Array(arr) => arr
+				.iter()
+				.enumerate()
+				.map(|(i, value)| T::decode(value).map_err(|e| e.within(PathSegment::Index(i))))
+				.collect(),
+			_ => Err(DecodeError::invalid_type("array", value)),
+		}
+	}
+
+	fn schema() -> Schema {
+		Schema::Array(SchemaRef::Fn(T::schema))
+	}
+}
+
+/// Returns the elements of an array with exactly `len` elements, for
+/// implementing [Decode] on fixed-size types.
+pub fn expect_array_of_len(value: &Value, len: usize) -> Result<&[Value], DecodeError> {
+	match value {
+		Value::Array(arr) if arr.len() == len => Ok(arr),
+		Value::Array(arr) => Err(DecodeError::invalid_value(format!(
+			"expected {len} elements, found {}",
+			arr.len()
+		))),
+		_ => Err(DecodeError::invalid_type("array", value)),
+	}
+}
+
+fn element<T: Decode>(arr: &[Value], index: usize) -> Result<T, DecodeError> {
+	T::decode(&arr[index]).map_err(|e| e.within(PathSegment::Index(index)))
+}
+
+impl<T: Decode, const N: usize> Decode for [T; N] {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		let arr = expect_array_of_len(value, N)?;
+		let elements = (0..N)
+			.map(|i| element(arr, i))
+			.collect::<Result<Vec<T>, _>>()?;
+		Ok(elements
+			.try_into()
+			.unwrap_or_else(|_| unreachable!("the length was checked")))
+	}
+
+	fn schema() -> Schema {
+		Schema::Array(SchemaRef::Fn(T::schema))
+	}
+}
+
+macro_rules! impl_decode_tuple {
+	($len:literal: $($t:ident $i:tt),*) => {
+		impl<$($t: Decode),*> Decode for ($($t,)*) {
+			fn decode(value: &Value) -> Result<Self, DecodeError> {
+				let arr = expect_array_of_len(value, $len)?;
+				Ok(($(element::<$t>(arr, $i)?,)*))
+			}
+
+			fn schema() -> Schema {
+				Schema::Array(Schema::Any.into())
+			}
+		}
+	};
+}
+
+impl_decode_tuple!(2: A 0, B 1);
+impl_decode_tuple!(3: A 0, B 1, C 2);
+impl_decode_tuple!(4: A 0, B 1, C 2, D 3);
+
+impl<T: Decode> Decode for HashMap<String, T> {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		let obj = expect_object(value)?;
+		obj.keys()
+			.map(|key| Ok((key.clone(), field(obj, key)?)))
+			.collect()
+	}
+
+	fn schema() -> Schema {
+		Schema::Map(SchemaRef::Fn(T::schema))
+	}
+}
+
+impl<T: Decode> Decode for BTreeMap<String, T> {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		let obj = expect_object(value)?;
+		obj.keys()
+			.map(|key| Ok((key.clone(), field(obj, key)?)))
+			.collect()
+	}
+
+	fn schema() -> Schema {
+		Schema::Map(SchemaRef::Fn(T::schema))
+	}
+}
@@ -0,0 +1,213 @@
+//! Structural differences between two [Value]s.
+//!
+//! ```rust
+//! use kvon_rs::{diff::diff, object};
+//!
+//! let old = object! { name: "app", port: 80 };
+//! let new = object! { name: "app", port: 8080, debug: true };
+//!
+//! let changes: Vec<String> = diff(&old, &new).iter().map(ToString::to_string).collect();
+//! assert_eq!(changes, ["+ debug: true", "~ port: 80 -> 8080"]);
+//! ```
+//!
+//! In tests, [assert_kvon_eq](crate::assert_kvon_eq) compares values and
+//! prints these changes on failure.
+
+use std::{borrow::Cow, fmt};
+
+use crate::{
+	encode_string,
+	encoder::encode_primitive_lines,
+	parse_string,
+	path::{KvonPath, PathSegment},
+	value::{PrimitiveValue, Value},
+	EncodeOptions,
+};
+
+/// A difference between two values at a path.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+	/// The key or array item only exists in the new value.
+	Added { path: KvonPath, value: Value },
+	/// The key or array item only exists in the old value.
+	Removed { path: KvonPath, value: Value },
+	/// The value was replaced by a different one. Objects and arrays are
+	/// compared by their items instead, so this only happens if one of the
+	/// values is a primitive or their types differ.
+	Changed {
+		path: KvonPath,
+		from: Value,
+		to: Value,
+	},
+}
+
+impl Change {
+	pub fn path(&self) -> &KvonPath {
+		match self {
+			Self::Added { path, .. } | Self::Removed { path, .. } | Self::Changed { path, .. } => {
+				path
+			}
+		}
+	}
+}
+
+impl fmt::Display for Change {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let path = self.path();
+		match self {
+			Self::Added { value, .. } => write!(f, "+ {path}: {}", summary(value)),
+			Self::Removed { value, .. } => write!(f, "- {path}: {}", summary(value)),
+			Self::Changed { from, to, .. } => {
+				write!(f, "~ {path}: {} -> {}", summary(from), summary(to))
+			}
+		}
+	}
+}
+
+/// Writes a value on a single line. Objects and arrays are abbreviated.
+pub(crate) fn summary(value: &Value) -> String {
+	match value {
+		Value::Primitive(PrimitiveValue::String(s)) if s.contains('\n') => format!("{s:?}"),
+		Value::Primitive(p) => {
+			encode_primitive_lines(p, &EncodeOptions::new(), String::new(), 0).join("\n")
+		}
+		Value::Object(obj) if obj.is_empty() => "{}".to_string(),
+		Value::Object(_) => "{...}".to_string(),
+		Value::Array(arr) if arr.is_empty() => "[]".to_string(),
+		Value::Array(_) => "[...]".to_string(),
+		Value::Raw(raw) => raw.head().trim().to_string(),
+	}
+}
+
+/// Returns the changes turning `old` into `new`, ordered by path with object
+/// keys sorted.
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+	let mut changes = Vec::new();
+	diff_helper(&mut KvonPath::root(), old, new, &mut changes);
+	changes
+}
+
+fn diff_helper(path: &mut KvonPath, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+	match (old, new) {
+		(Value::Object(old), Value::Object(new)) => {
+			let mut keys: Vec<_> = old
+				.keys()
+				.chain(new.keys().filter(|key| !old.contains_key(*key)))
+				.collect();
+			keys.sort();
+			for key in keys {
+				path.push(PathSegment::Key(key.clone()));
+				match (old.get(key), new.get(key)) {
+					(Some(old), Some(new)) => diff_helper(path, old, new, changes),
+					(Some(old), None) => changes.push(Change::Removed {
+						path: path.clone(),
+						value: old.clone(),
+					}),
+					(None, Some(new)) => changes.push(Change::Added {
+						path: path.clone(),
+						value: new.clone(),
+					}),
+					(None, None) => unreachable!(),
+				}
+				path.pop();
+			}
+		}
+		(Value::Array(old), Value::Array(new)) => {
+			for i in 0..old.len().max(new.len()) {
+				path.push(PathSegment::Index(i));
+				match (old.get(i), new.get(i)) {
+					(Some(old), Some(new)) => diff_helper(path, old, new, changes),
+					(Some(old), None) => changes.push(Change::Removed {
+						path: path.clone(),
+						value: old.clone(),
+					}),
+					(None, Some(new)) => changes.push(Change::Added {
+						path: path.clone(),
+						value: new.clone(),
+					}),
+					(None, None) => unreachable!(),
+				}
+				path.pop();
+			}
+		}
+		(old, new) if old != new => changes.push(Change::Changed {
+			path: path.clone(),
+			from: old.clone(),
+			to: new.clone(),
+		}),
+		_ => {}
+	}
+}
+
+/// Values and documents that [assert_kvon_eq](crate::assert_kvon_eq) can
+/// compare. Strings are parsed as documents.
+pub trait AsValue {
+	fn as_value(&self) -> Cow<'_, Value>;
+}
+
+impl AsValue for Value {
+	fn as_value(&self) -> Cow<'_, Value> {
+		Cow::Borrowed(self)
+	}
+}
+
+impl AsValue for str {
+	fn as_value(&self) -> Cow<'_, Value> {
+		match parse_string(self) {
+			Ok(value) => Cow::Owned(value),
+			Err(e) => panic!("invalid document: {e}"),
+		}
+	}
+}
+
+impl AsValue for String {
+	fn as_value(&self) -> Cow<'_, Value> {
+		self.as_str().as_value()
+	}
+}
+
+impl<T: AsValue + ?Sized> AsValue for &T {
+	fn as_value(&self) -> Cow<'_, Value> {
+		(**self).as_value()
+	}
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_kvon_eq(actual: &Value, expected: &Value) {
+	let changes = diff(expected, actual);
+	if changes.is_empty() {
+		return;
+	}
+
+	let mut message =
+		String::from("assertion `actual == expected` failed\nchanges from expected to actual:\n");
+	for change in changes {
+		message.push_str(&format!("  {change}\n"));
+	}
+	let options = EncodeOptions::new();
+	message.push_str(&format!("actual:{}\n", encode_string(actual, &options)));
+	message.push_str(&format!("expected:{}", encode_string(expected, &options)));
+	panic!("{message}");
+}
+
+/// Asserts that two values are equal, where either side may also be a
+/// document to parse. On failure, the panic message lists the changes
+/// between the values and both values as documents, instead of their debug
+/// output.
+///
+/// ```rust
+/// use kvon_rs::{assert_kvon_eq, object};
+///
+/// let value = object! { name: "app", ports: [80, 443] };
+/// assert_kvon_eq!(value, "name: 'app'\nports: [80 443]");
+/// ```
+#[macro_export]
+macro_rules! assert_kvon_eq {
+	($actual:expr, $expected:expr $(,)?) => {
+		$crate::diff::assert_kvon_eq(
+			&$crate::diff::AsValue::as_value(&$actual),
+			&$crate::diff::AsValue::as_value(&$expected),
+		)
+	};
+}
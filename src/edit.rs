@@ -0,0 +1,266 @@
+//! Setting values from text input, e.g. `kvon set server.port 9090` or
+//! config editors in a terminal UI.
+//!
+//! ```rust
+//! use kvon_rs::{
+//!     edit::set_input,
+//!     parse_string,
+//!     path::KvonPath,
+//!     schema::{Constraint, Schema},
+//! };
+//!
+//! fn port() -> Schema {
+//!     Schema::Number.constrain(Constraint::Maximum(65535.))
+//! }
+//! fn server() -> Schema {
+//!     Schema::object([("host", (|| Schema::String) as fn() -> Schema), ("port", port)])
+//! }
+//! let schema = Schema::object([("server", server as fn() -> Schema)]);
+//!
+//! let mut config = parse_string("server:\n  host: 'localhost'\n  port: 80").unwrap();
+//! let path: KvonPath = "server.port".parse().unwrap();
+//! let previous = set_input(&mut config, &path, "9090", Some(&schema)).unwrap();
+//! assert_eq!(previous, Some(80.into()));
+//! assert_eq!(config.get_path(&path), Some(&9090.into()));
+//!
+//! let error = set_input(&mut config, &path, "http", Some(&schema)).unwrap_err();
+//! assert_eq!(error.to_string(), "server.port: expected number, found string");
+//! let error = set_input(&mut config, &path, "70000", Some(&schema)).unwrap_err();
+//! assert_eq!(error.to_string(), "server.port: 70000 is greater than 65535");
+//! ```
+//!
+//! Inputs are read as inline KVON values, e.g. `true`, `[80 443]` or
+//! `'quoted'`, and any other text is a string, so `localhost` doesn't need
+//! quotes. With a schema, inputs are converted to the type it expects like
+//! [Value::coerce], e.g. `8080` for a string becomes `'8080'`.
+
+use std::fmt;
+
+use crate::{
+	decode::DecodeError,
+	error::ParserError,
+	path::{KvonPath, Path, PathSegment},
+	scanner::Scanner,
+	schema::{Schema, Violation},
+	value::{PrimitiveValue, Value},
+	ParserOptions, ParserResult,
+};
+
+/// Why an input can't be set, see [set_input].
+#[derive(Debug)]
+pub enum EditError {
+	/// The input isn't a valid value, e.g. an unclosed inline array.
+	Parse(ParserError),
+	/// The value at the path can't hold the new value, e.g. because it is a
+	/// primitive or the index is past the end of an array. The path points
+	/// at that value.
+	Unreachable(KvonPath),
+	/// The schema has no place for the path, e.g. a misspelled key of a
+	/// closed object.
+	NotInSchema(KvonPath),
+	/// The input has a different type than the schema expects.
+	Decode(DecodeError),
+	/// The input violates constraints of its schema.
+	Violations(Vec<Violation>),
+}
+
+impl fmt::Display for EditError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Parse(e) => write!(f, "{e}"),
+			Self::Unreachable(path) => {
+				if !path.is_empty() {
+					write!(f, "{path}: ")?;
+				}
+				write!(f, "can't contain the new value")
+			}
+			Self::NotInSchema(path) => write!(f, "{path}: not part of the schema"),
+			Self::Decode(e) => write!(f, "{e}"),
+			Self::Violations(violations) => {
+				let violations: Vec<_> = violations.iter().map(ToString::to_string).collect();
+				write!(f, "{}", violations.join("\n"))
+			}
+		}
+	}
+}
+
+impl std::error::Error for EditError {}
+
+impl From<ParserError> for EditError {
+	fn from(e: ParserError) -> Self {
+		Self::Parse(e)
+	}
+}
+
+impl From<DecodeError> for EditError {
+	fn from(e: DecodeError) -> Self {
+		Self::Decode(e)
+	}
+}
+
+/// Reads an input as an inline value, falling back to a string for text
+/// that isn't a single value, see the [module documentation](self). Inputs
+/// starting with `[` are always arrays, so an unclosed array is an error.
+pub fn parse_input(input: &str) -> ParserResult<Value> {
+	let options = ParserOptions::new();
+	let input = input.trim();
+	let mut scanner = Scanner::new(0, input, &options);
+
+	if let Some(value) = scanner.parse_inline_array()? {
+		scanner.consume_whitespaces();
+		return match scanner.reached_end() {
+			true => Ok(value),
+			false => Err(scanner.generate_trailing_content_error()),
+		};
+	}
+	// text only starting with a literal, e.g. `1.2.3` or `trueish`, is a
+	// string as well
+	match scanner.parse_primitive() {
+		Ok(Some(primitive)) if scanner.rest().trim().is_empty() => Ok(Value::Primitive(primitive)),
+		_ => Ok(Value::from(input)),
+	}
+}
+
+/// Reads an input for the value at `path` of documents following
+/// `schema`, converting it to the expected type and checking its
+/// constraints.
+pub fn typed_input(input: &str, path: &Path, schema: &Schema) -> Result<Value, EditError> {
+	let schema = schema
+		.at(path)
+		.ok_or_else(|| EditError::NotInSchema(path.into()))?;
+	let mut value = parse_input(input)?;
+	value.coerce(&schema);
+
+	check_type(&schema, &value).map_err(|mut e| {
+		e.path = path.iter().cloned().chain(e.path).collect();
+		e
+	})?;
+	let violations: Vec<_> = schema
+		.validate(&value)
+		.into_iter()
+		.map(|mut violation| {
+			violation.path = path.iter().cloned().chain(violation.path).collect();
+			violation
+		})
+		.collect();
+	match violations.is_empty() {
+		true => Ok(value),
+		false => Err(EditError::Violations(violations)),
+	}
+}
+
+/// Sets the value at `path`, returning the previous one. Missing keys
+/// along the way are added as objects and an index one past the end of an
+/// array appends to it. The document is left unchanged on errors.
+pub fn set_path(
+	document: &mut Value,
+	path: &Path,
+	value: Value,
+) -> Result<Option<Value>, EditError> {
+	// checked first, as objects added before reaching an error would stay
+	check_path(document, path)?;
+
+	let mut target = document;
+	for (i, segment) in path.iter().enumerate() {
+		let unreachable = || EditError::Unreachable(path[..i].into());
+		let is_last = i + 1 == path.len();
+		target = match (target, segment) {
+			(Value::Object(obj), PathSegment::Key(key)) if is_last => {
+				return Ok(obj.insert(key.clone(), value));
+			}
+			(Value::Object(obj), PathSegment::Key(key)) => obj
+				.entry(key.clone())
+				.or_insert_with(|| Value::Object(Default::default())),
+			(Value::Array(arr), PathSegment::Index(index)) if *index == arr.len() && is_last => {
+				arr.push(value);
+				return Ok(None);
+			}
+			(Value::Array(arr), PathSegment::Index(index)) => {
+				arr.get_mut(*index).ok_or_else(unreachable)?
+			}
+			_ => return Err(unreachable()),
+		};
+	}
+	Ok(Some(std::mem::replace(target, value)))
+}
+
+/// Checks that [set_path] can reach `path`.
+fn check_path(document: &Value, path: &Path) -> Result<(), EditError> {
+	let mut target = Some(document);
+	for (i, segment) in path.iter().enumerate() {
+		let is_last = i + 1 == path.len();
+		target = match (target, segment) {
+			// below a missing key, where only objects are added
+			(None, PathSegment::Key(_)) => None,
+			(Some(Value::Object(obj)), PathSegment::Key(key)) => obj.get(key),
+			(Some(Value::Array(arr)), PathSegment::Index(index))
+				if *index == arr.len() && is_last =>
+			{
+				None
+			}
+			(Some(Value::Array(arr)), PathSegment::Index(index)) if *index < arr.len() => {
+				arr.get(*index)
+			}
+			_ => return Err(EditError::Unreachable(path[..i].into())),
+		};
+	}
+	Ok(())
+}
+
+/// Reads an input and sets it at `path`, returning the previous value. The
+/// input is checked against `schema` if given, see [typed_input].
+pub fn set_input(
+	document: &mut Value,
+	path: &Path,
+	input: &str,
+	schema: Option<&Schema>,
+) -> Result<Option<Value>, EditError> {
+	let value = match schema {
+		Some(schema) => typed_input(input, path, schema)?,
+		None => parse_input(input)?,
+	};
+	set_path(document, path, value)
+}
+
+/// Checks that a value has the types its schema expects, like decoding it
+/// would.
+fn check_type(schema: &Schema, value: &Value) -> Result<(), DecodeError> {
+	let expected = match (schema, value) {
+		(Schema::Constrained(schema, _), value) => return check_type(schema, value),
+		(Schema::Optional(_), Value::Primitive(PrimitiveValue::Null)) => return Ok(()),
+		(Schema::Optional(schema), value) => return check_type(&schema.get(), value),
+		(Schema::Any, _)
+		| (Schema::Number, Value::Primitive(PrimitiveValue::Number(_)))
+		| (Schema::Boolean, Value::Primitive(PrimitiveValue::Boolean(_)))
+		| (Schema::String, Value::Primitive(PrimitiveValue::String(_))) => return Ok(()),
+		(Schema::Object { fields, .. }, Value::Object(obj)) => {
+			for (key, schema) in fields {
+				if let Some(value) = obj.get(key.as_ref()) {
+					check_type(&schema.get(), value)
+						.map_err(|e| e.within(PathSegment::Key(key.to_string())))?;
+				}
+			}
+			return Ok(());
+		}
+		(Schema::Map(schema), Value::Object(obj)) => {
+			let schema = schema.get();
+			for (key, value) in obj {
+				check_type(&schema, value).map_err(|e| e.within(PathSegment::Key(key.clone())))?;
+			}
+			return Ok(());
+		}
+		(Schema::Array(schema), Value::Array(arr)) => {
+			let schema = schema.get();
+			for (i, value) in arr.iter().enumerate() {
+				check_type(&schema, value).map_err(|e| e.within(PathSegment::Index(i)))?;
+			}
+			return Ok(());
+		}
+		(Schema::Number, _) => "number",
+		(Schema::Boolean, _) => "boolean",
+		(Schema::String, _) => "string",
+		(Schema::Object { .. } | Schema::Map(_), _) => "object",
+		(Schema::Array(_), _) => "array",
+	};
+	Err(DecodeError::invalid_type(expected, value))
+}
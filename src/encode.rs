@@ -0,0 +1,263 @@
+//! Conversion of typed data into [Value]s, the counterpart of
+//! [crate::decode]. Implement [Encode], or derive it for structs with
+//! `#[derive(KvonEncode)]` when the `derive` feature is enabled.
+//!
+//! Besides building a [Value], encodable types can be written straight to a
+//! [KvonWrite] sink, which avoids building the whole document in memory:
+//!
+//! ```rust
+//! use std::collections::BTreeMap;
+//!
+//! use kvon_rs::{encode::Encode, writer::KvonWriter};
+//!
+//! let mut ports = BTreeMap::new();
+//! ports.insert("http".to_string(), vec![80, 8080]);
+//! ports.insert("https".to_string(), vec![443]);
+//!
+//! let mut writer = KvonWriter::new(Vec::new()).unwrap();
+//! ports.write_entries(&mut writer).unwrap();
+//! let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+//! assert_eq!(output, "http:--\n\t- 80\n\t- 8080\nhttps:--\n\t- 443\n");
+//! ```
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	io,
+};
+
+use crate::{
+	value::{PrimitiveValue, Value},
+	writer::KvonWrite,
+};
+
+/// Types that can be converted into a [Value].
+pub trait Encode {
+	fn encode(&self) -> Value;
+
+	/// Writes the value to a sink. The default implementation encodes the
+	/// value first.
+	fn write<W: KvonWrite + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+		w.value_tree(&self.encode())
+	}
+
+	/// Writes the entries of an object-like value without opening an object,
+	/// e.g. to write them as the root of a document. Returns an error for
+	/// values that don't encode into objects.
+	fn write_entries<W: KvonWrite + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+		let Value::Object(obj) = self.encode() else {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"only objects can be written as entries",
+			));
+		};
+
+		let mut keys = obj.keys().collect::<Vec<_>>();
+		keys.sort_unstable();
+		for key in keys {
+			w.key(key)?;
+			w.value_tree(&obj[key])?;
+		}
+		Ok(())
+	}
+
+	/// Returns true for values that `#[kvon(skip_if_none)]` omits.
+	fn is_none(&self) -> bool {
+		false
+	}
+}
+
+impl Encode for Value {
+	fn encode(&self) -> Value {
+		self.clone()
+	}
+}
+
+impl Encode for PrimitiveValue {
+	fn encode(&self) -> Value {
+		Value::Primitive(self.clone())
+	}
+
+	fn write<W: KvonWrite + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+		w.value(self)
+	}
+}
+
+macro_rules! impl_encode_primitive {
+	($($t:ty),*) => {
+		$(
+			impl Encode for $t {
+				fn encode(&self) -> Value {
+					Value::from(self.clone())
+				}
+
+				fn write<W: KvonWrite + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+					w.value(&PrimitiveValue::from(self.clone()))
+				}
+			}
+		)*
+	};
+}
+
+impl_encode_primitive!(bool, f32, String, char);
+
+impl Encode for str {
+	fn encode(&self) -> Value {
+		Value::from(self)
+	}
+}
+
+macro_rules! impl_encode_number {
+	($($t:ty),*) => {
+		$(
+			impl Encode for $t {
+				fn encode(&self) -> Value {
+					Value::from(*self as f32)
+				}
+			}
+		)*
+	};
+}
+
+impl_encode_number!(f64, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+#[cfg(feature = "quantities")]
+impl Encode for std::time::Duration {
+	fn encode(&self) -> Value {
+		Value::from(*self)
+	}
+}
+
+#[cfg(feature = "uuid")]
+impl Encode for uuid::Uuid {
+	fn encode(&self) -> Value {
+		Value::Primitive(PrimitiveValue::Uuid(*self))
+	}
+}
+
+#[cfg(feature = "ipaddr")]
+impl Encode for std::net::IpAddr {
+	fn encode(&self) -> Value {
+		Value::Primitive(PrimitiveValue::Ip(*self))
+	}
+}
+
+#[cfg(feature = "semver")]
+impl Encode for semver::Version {
+	fn encode(&self) -> Value {
+		Value::Primitive(PrimitiveValue::Version(self.clone()))
+	}
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+	fn encode(&self) -> Value {
+		(**self).encode()
+	}
+
+	fn write<W: KvonWrite + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+		(**self).write(w)
+	}
+}
+
+impl<T: Encode + ?Sized> Encode for Box<T> {
+	fn encode(&self) -> Value {
+		(**self).encode()
+	}
+
+	fn write<W: KvonWrite + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+		(**self).write(w)
+	}
+}
+
+impl<T: Encode> Encode for Option<T> {
+	fn encode(&self) -> Value {
+		match self {
+			Some(value) => value.encode(),
+			None => Value::null(),
+		}
+	}
+
+	fn write<W: KvonWrite + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+		match self {
+			Some(value) => value.write(w),
+			None => w.value(&PrimitiveValue::Null),
+		}
+	}
+
+	fn is_none(&self) -> bool {
+		self.is_none()
+	}
+}
+
+impl<T: Encode> Encode for [T] {
+	fn encode(&self) -> Value {
+		Value::Array(self.iter().map(Encode::encode).collect())
+	}
+
+	fn write<W: KvonWrite + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+		w.begin_array()?;
+		for value in self {
+			value.write(w)?;
+		}
+		w.end()
+	}
+}
+
+impl<T: Encode> Encode for Vec<T> {
+	fn encode(&self) -> Value {
+		self.as_slice().encode()
+	}
+
+	fn write<W: KvonWrite + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+		self.as_slice().write(w)
+	}
+}
+
+impl<T: Encode, const N: usize> Encode for [T; N] {
+	fn encode(&self) -> Value {
+		self.as_slice().encode()
+	}
+
+	fn write<W: KvonWrite + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+		self.as_slice().write(w)
+	}
+}
+
+macro_rules! impl_encode_tuple {
+	($($t:ident $i:tt),*) => {
+		impl<$($t: Encode),*> Encode for ($($t,)*) {
+			fn encode(&self) -> Value {
+				Value::Array(vec![$(self.$i.encode()),*])
+			}
+		}
+	};
+}
+
+impl_encode_tuple!(A 0, B 1);
+impl_encode_tuple!(A 0, B 1, C 2);
+impl_encode_tuple!(A 0, B 1, C 2, D 3);
+
+impl<T: Encode> Encode for HashMap<String, T> {
+	fn encode(&self) -> Value {
+		Value::Object(self.iter().map(|(k, v)| (k.clone(), v.encode())).collect())
+	}
+}
+
+impl<T: Encode> Encode for BTreeMap<String, T> {
+	fn encode(&self) -> Value {
+		Value::Object(self.iter().map(|(k, v)| (k.clone(), v.encode())).collect())
+	}
+
+	fn write<W: KvonWrite + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+		w.begin_object()?;
+		self.write_entries(w)?;
+		w.end()
+	}
+
+	fn write_entries<W: KvonWrite + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+		for (key, value) in self {
+			w.key(key)?;
+			value.write(w)?;
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,1045 @@
+use std::{fmt::Debug, sync::Arc};
+
+use crate::{
+	indention::Indention,
+	parser::Comment,
+	path::{KvonPath, Path, PathSegment},
+	value::{PrimitiveValue, RawValue, Value},
+};
+
+/// A hook reordering the keys of the object at the given path. The keys are
+/// passed in the default order.
+pub type KeyOrder = dyn Fn(&Path, &mut Vec<&str>) + Send + Sync;
+
+#[derive(Clone)]
+struct KeyOrderHook(Arc<KeyOrder>);
+
+impl Debug for KeyOrderHook {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str("KeyOrderHook")
+	}
+}
+
+/// Options controlling how [Value]s are encoded.
+#[derive(Debug, Clone)]
+pub struct EncodeOptions {
+	pub(crate) indention: Indention,
+	pub(crate) redact_keys: Vec<String>,
+	pub(crate) redaction_placeholder: String,
+	pub(crate) max_line_width: Option<usize>,
+	pub(crate) array_items_per_line: Option<usize>,
+	pub(crate) align_matrices: bool,
+	pub(crate) tables: bool,
+	pub(crate) quote_style: QuoteStyle,
+	pub(crate) multi_line_strings: MultiLineStrings,
+	pub(crate) trailing_newlines: TrailingNewlines,
+	pub(crate) quote_all_keys: bool,
+	pub(crate) dotted_keys: bool,
+	pub(crate) float_format: FloatFormat,
+	pub(crate) always_include_fraction: bool,
+	pub(crate) blank_lines_between_keys: bool,
+	pub(crate) blank_lines_between_objects: bool,
+	pub(crate) header: Option<String>,
+	#[cfg(feature = "checksum")]
+	pub(crate) checksum: bool,
+	pub(crate) key_priority: Vec<String>,
+	pub(crate) comments: Vec<(KvonPath, String)>,
+	key_order: Option<KeyOrderHook>,
+}
+
+/// How numbers are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FloatFormat {
+	/// The shortest representation that parses back into the same number,
+	/// e.g. `0.1` or `1234.5`.
+	#[default]
+	Shortest,
+	/// A fixed number of digits after the decimal point, e.g. `0.10` for
+	/// `Fixed(2)`.
+	Fixed(usize),
+}
+
+/// The quote character preferred for inline strings. The other one is used
+/// when the string contains the preferred one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuoteStyle {
+	#[default]
+	Single,
+	Double,
+}
+
+/// Which strings are encoded as multi-line (`|`) blocks instead of inline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MultiLineStrings {
+	/// Only strings that can't be written inline, i.e. ones containing line
+	/// breaks.
+	#[default]
+	WhenNeeded,
+	/// Strings containing line breaks or any quote character, so that inline
+	/// strings never need a longer quote sequence like `''`.
+	WhenQuoted,
+}
+
+/// How line breaks at the end of multi-line strings are encoded. A block's
+/// lines are joined with `\n` when parsed, so a string ending with a line
+/// break is written with a last line holding only the indention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailingNewlines {
+	/// Writes the line breaks at the end as they are, so the string parses
+	/// back with the same number of them. Like all lines of a block, they
+	/// are read back as `\n`, so `\r\n` becomes `\n`.
+	#[default]
+	Keep,
+	/// Removes all line breaks at the end of the string.
+	Strip,
+	/// Adds a line break to non-empty strings not ending with one, e.g. for
+	/// scripts and files that require a final newline.
+	Ensure,
+}
+
+impl Default for EncodeOptions {
+	fn default() -> Self {
+		Self {
+			indention: Indention::default(),
+			redact_keys: Vec::new(),
+			redaction_placeholder: "<redacted>".to_string(),
+			max_line_width: None,
+			array_items_per_line: None,
+			align_matrices: false,
+			tables: false,
+			quote_style: QuoteStyle::default(),
+			multi_line_strings: MultiLineStrings::default(),
+			trailing_newlines: TrailingNewlines::default(),
+			quote_all_keys: false,
+			dotted_keys: false,
+			float_format: FloatFormat::default(),
+			always_include_fraction: false,
+			blank_lines_between_keys: false,
+			blank_lines_between_objects: false,
+			header: None,
+			#[cfg(feature = "checksum")]
+			checksum: false,
+			key_priority: Vec::new(),
+			comments: Vec::new(),
+			key_order: None,
+		}
+	}
+}
+
+impl EncodeOptions {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets the indention used for nested blocks.
+	pub fn indention(mut self, indention: Indention) -> Self {
+		self.indention = indention;
+		self
+	}
+
+	/// Replaces the values of keys matching any of the patterns with the
+	/// redaction placeholder. Patterns are matched case insensitively and may
+	/// contain `*` wildcards, e.g. `password`, `token` or `*_secret`.
+	pub fn redact_keys<S: ToString>(mut self, patterns: impl IntoIterator<Item = S>) -> Self {
+		self.redact_keys = patterns.into_iter().map(|p| p.to_string()).collect();
+		self
+	}
+
+	/// Sets the string that redacted values are replaced with. Defaults to
+	/// `<redacted>`.
+	pub fn redaction_placeholder(mut self, placeholder: impl ToString) -> Self {
+		self.redaction_placeholder = placeholder.to_string();
+		self
+	}
+
+	/// Inline arrays longer than `width` are expanded, with their values
+	/// split into `- ...` lines of at most `width` characters each. The
+	/// width doesn't account for the key and indention before the array.
+	pub fn max_line_width(mut self, width: usize) -> Self {
+		self.max_line_width = Some(width);
+		self
+	}
+
+	/// Inline arrays with more than `count` values are expanded, with at most
+	/// `count` values on each `- ...` line.
+	pub fn array_items_per_line(mut self, count: usize) -> Self {
+		self.array_items_per_line = Some(count.max(1));
+		self
+	}
+
+	/// When enabled, the elements of arrays of inline arrays with the same
+	/// length, e.g. matrices, are right-aligned into columns:
+	///
+	/// ```text
+	/// m:--
+	///     - [1  0 -2.5]
+	///     - [0 10    4]
+	/// ```
+	pub fn align_matrices(mut self, enable: bool) -> Self {
+		self.align_matrices = enable;
+		self
+	}
+
+	/// When enabled, arrays of objects that share the same keys and only hold
+	/// inline values are written as tables, with a comment naming the keys
+	/// and one inline array of values per object:
+	///
+	/// ```text
+	/// servers:--
+	///     #  host        port tls
+	///     - ['a.example' 80   false]
+	///     - ['b.example' 8443 true]
+	/// ```
+	///
+	/// The keys are only kept in the comment, so tables parse back into
+	/// arrays of arrays rather than objects. Meant for documents read by
+	/// people, such as reports.
+	pub fn tables(mut self, enable: bool) -> Self {
+		self.tables = enable;
+		self
+	}
+
+	/// Sets the quote character preferred for inline strings. Defaults to
+	/// [QuoteStyle::Single].
+	pub fn quote_style(mut self, style: QuoteStyle) -> Self {
+		self.quote_style = style;
+		self
+	}
+
+	/// Sets which strings are encoded as multi-line blocks. Defaults to
+	/// [MultiLineStrings::WhenNeeded].
+	pub fn multi_line_strings(mut self, multi_line_strings: MultiLineStrings) -> Self {
+		self.multi_line_strings = multi_line_strings;
+		self
+	}
+
+	/// Sets how line breaks at the end of multi-line strings are written.
+	/// Defaults to [TrailingNewlines::Keep].
+	pub fn trailing_newlines(mut self, trailing_newlines: TrailingNewlines) -> Self {
+		self.trailing_newlines = trailing_newlines;
+		self
+	}
+
+	/// When enabled, all keys are quoted. Otherwise only keys that would be
+	/// parsed differently without quotes are. String values are always
+	/// quoted, as unquoted words aren't valid values.
+	pub fn quote_all_keys(mut self, enable: bool) -> Self {
+		self.quote_all_keys = enable;
+		self
+	}
+
+	/// When enabled, chains of objects with a single key are written as
+	/// dotted keys, e.g. `server.tls.enabled: true` instead of three nested
+	/// lines, and keys containing dots are quoted. Parse the output with
+	/// [ParserOptions::dotted_keys](crate::ParserOptions::dotted_keys).
+	pub fn dotted_keys(mut self, enable: bool) -> Self {
+		self.dotted_keys = enable;
+		self
+	}
+
+	/// Sets how numbers are written. Defaults to [FloatFormat::Shortest].
+	pub fn float_format(mut self, format: FloatFormat) -> Self {
+		self.float_format = format;
+		self
+	}
+
+	/// When enabled, integral numbers are written with a fraction, e.g. `1.0`
+	/// instead of `1`.
+	pub fn always_include_fraction(mut self, enable: bool) -> Self {
+		self.always_include_fraction = enable;
+		self
+	}
+
+	/// When enabled, top-level keys are separated by a blank line.
+	pub fn blank_lines_between_keys(mut self, enable: bool) -> Self {
+		self.blank_lines_between_keys = enable;
+		self
+	}
+
+	/// When enabled, consecutive objects in expanded arrays are separated by
+	/// a blank line.
+	pub fn blank_lines_between_objects(mut self, enable: bool) -> Self {
+		self.blank_lines_between_objects = enable;
+		self
+	}
+
+	/// Writes `header` as a comment at the top of the document, e.g. to mark
+	/// it as generated. Each line of the header becomes a `# ...` line.
+	pub fn header(mut self, header: impl ToString) -> Self {
+		self.header = Some(header.to_string());
+		self
+	}
+
+	/// When enabled, a `# sha256: ...` line with the checksum of the value is
+	/// written at the top of the document, before the header. See
+	/// [crate::checksum]. The checksum is taken before encoding, so it won't
+	/// match documents whose values are changed by [EncodeOptions::redact_keys]
+	/// or rounded by [FloatFormat::Fixed].
+	#[cfg(feature = "checksum")]
+	pub fn checksum(mut self, enable: bool) -> Self {
+		self.checksum = enable;
+		self
+	}
+
+	/// Writes `text` as a comment after the key at the dotted path, e.g.
+	/// `server.port`. Replaces any previous comment of the key.
+	pub fn comment(mut self, path: &str, text: impl ToString) -> Self {
+		let path: KvonPath = path
+			.split('.')
+			.map(|key| PathSegment::Key(key.to_string()))
+			.collect();
+		self.comments.retain(|(p, _)| *p != path);
+		self.comments.push((path, text.to_string()));
+		self
+	}
+
+	/// Writes comments recorded by [crate::Parser::with_comments] after the
+	/// keys they trail, so that they are kept when the parsed value is edited
+	/// and encoded again. Comments on their own lines or trailing array items
+	/// are skipped.
+	///
+	/// ```rust
+	/// use kvon_rs::{encode_string, value::Value, EncodeOptions, Parser};
+	///
+	/// let mut parser = Parser::new().with_comments();
+	/// parser.next_line("port: 80 # set by installer").unwrap();
+	/// let comments = parser.comments().to_vec();
+	///
+	/// let mut value = parser.finish().unwrap();
+	/// if let Value::Object(obj) = &mut value {
+	///     obj.insert("port".to_string(), Value::from(8080.));
+	/// }
+	///
+	/// let options = EncodeOptions::new().comments(comments);
+	/// assert_eq!(encode_string(&value, &options), "\nport: 8080 # set by installer");
+	/// ```
+	pub fn comments(mut self, comments: impl IntoIterator<Item = Comment>) -> Self {
+		for comment in comments {
+			if let Some(path) = comment.path {
+				if let Some(PathSegment::Key(_)) = path.last() {
+					self.comments.retain(|(p, _)| *p != path);
+					self.comments.push((path, comment.text));
+				}
+			}
+		}
+		self
+	}
+
+	/// The comment written after the key at the path.
+	fn comment_at(&self, path: &Path) -> Option<String> {
+		self.comments
+			.iter()
+			.find(|(p, _)| **p == *path)
+			.map(|(_, text)| text.clone())
+	}
+
+	/// Writes the given keys first, in the given order, in every object.
+	/// The remaining keys follow sorted alphabetically, which is also the
+	/// default order.
+	pub fn key_priority<S: ToString>(mut self, keys: impl IntoIterator<Item = S>) -> Self {
+		self.key_priority = keys.into_iter().map(|k| k.to_string()).collect();
+		self
+	}
+
+	/// Sets a hook that reorders the keys of every object, e.g.
+	/// `key_order(|path, keys| keys.reverse())`. The hook receives the
+	/// object's path and its keys in the order given by
+	/// [EncodeOptions::key_priority].
+	pub fn key_order(
+		mut self,
+		key_order: impl Fn(&Path, &mut Vec<&str>) + Send + Sync + 'static,
+	) -> Self {
+		self.key_order = Some(KeyOrderHook(Arc::new(key_order)));
+		self
+	}
+
+	/// Returns the keys of an object in the order they are written.
+	fn ordered_keys<'a>(
+		&self,
+		path: &Path,
+		keys: impl Iterator<Item = &'a String>,
+	) -> Vec<&'a str> {
+		let mut keys = keys.map(String::as_str).collect::<Vec<_>>();
+		keys.sort_unstable();
+		keys.sort_by_key(|key| {
+			self.key_priority
+				.iter()
+				.position(|k| k == key)
+				.unwrap_or(usize::MAX)
+		});
+		if let Some(KeyOrderHook(key_order)) = &self.key_order {
+			key_order(path, &mut keys);
+		}
+		keys
+	}
+
+	fn format_number(&self, n: f32) -> String {
+		let mut s = match self.float_format {
+			FloatFormat::Shortest => n.to_string(),
+			FloatFormat::Fixed(precision) => format!("{n:.precision$}"),
+		};
+		if self.always_include_fraction && !s.contains('.') {
+			s.push_str(".0");
+		}
+		s
+	}
+
+	/// Splits the values of an inline array into lines, or returns `None` if
+	/// the array fits on a single line.
+	fn wrap_array_items(&self, items: &[String]) -> Option<Vec<String>> {
+		wrap_array_items(items, self.max_line_width, self.array_items_per_line)
+	}
+
+	pub(crate) fn should_redact(&self, key: &str) -> bool {
+		self.redact_keys
+			.iter()
+			.any(|pattern| matches_pattern(pattern, key))
+	}
+}
+
+/// Case insensitive glob matching where `*` matches any sequence of
+/// characters.
+fn matches_pattern(pattern: &str, key: &str) -> bool {
+	let pattern = pattern.to_lowercase();
+	let key = key.to_lowercase();
+
+	let mut parts = pattern.split('*');
+	let first = parts.next().unwrap_or_default();
+	let Some(mut rest) = key.strip_prefix(first) else {
+		return false;
+	};
+
+	let parts = parts.collect::<Vec<_>>();
+	let Some((last, middle)) = parts.split_last() else {
+		// no wildcards - the whole key must match
+		return rest.is_empty();
+	};
+
+	for part in middle {
+		match rest.find(part) {
+			Some(i) => rest = &rest[i + part.len()..],
+			None => return false,
+		}
+	}
+
+	rest.ends_with(last)
+}
+
+/// Splits the values of an inline array into lines of at most `max_width`
+/// characters and `max_count` values, or returns `None` if the array fits
+/// on a single line.
+pub(crate) fn wrap_array_items(
+	items: &[String],
+	max_width: Option<usize>,
+	max_count: Option<usize>,
+) -> Option<Vec<String>> {
+	let max_width = max_width.unwrap_or(usize::MAX);
+	let max_count = max_count.unwrap_or(usize::MAX);
+
+	// `[` and `]` plus a space between every two values
+	let width = items.iter().map(|item| item.len() + 1).sum::<usize>() + 1;
+	if width <= max_width && items.len() <= max_count {
+		return None;
+	}
+
+	let mut lines: Vec<String> = Vec::new();
+	let mut count = 0;
+	for item in items {
+		match lines.last_mut() {
+			Some(line) if count < max_count && line.len() + 1 + item.len() <= max_width => {
+				line.push(' ');
+				line.push_str(item);
+				count += 1;
+			}
+			_ => {
+				lines.push(item.clone());
+				count = 1;
+			}
+		}
+	}
+	Some(lines)
+}
+
+/// Quotes a string so that it can be parsed back on a single line, preferring
+/// the quote character of `style`. Returns `None` if no quote sequence can
+/// delimit the string.
+pub(crate) fn quote_inline(s: &str, style: QuoteStyle) -> Option<String> {
+	if s.is_empty() || s.contains('\n') {
+		return None;
+	}
+
+	let quotes = match style {
+		QuoteStyle::Single => ['\'', '"'],
+		QuoteStyle::Double => ['"', '\''],
+	};
+
+	// the opening quote sequence is as long as the run of quotes, and the
+	// closing one is the first occurrence of it, so the string must neither
+	// start nor end with the quote character
+	for count in 1..=3 {
+		for quote in quotes {
+			let delimiter = quote.to_string().repeat(count);
+			if !s.starts_with(quote) && !s.ends_with(quote) && !s.contains(&delimiter) {
+				return Some(format!("{delimiter}{s}{delimiter}"));
+			}
+		}
+	}
+
+	None
+}
+
+/// Keys are written unquoted unless the parser would read them differently,
+/// e.g. because they contain whitespace or a `:`. Keys with leading or
+/// trailing whitespace are therefore always quoted. Returns `None` for keys
+/// that can't be quoted either, e.g. ones containing a line break.
+pub(crate) fn encode_key(key: &str, options: &EncodeOptions) -> Option<String> {
+	let needs_quotes = key.starts_with(['\'', '"'])
+		|| key.contains(|c: char| c.is_whitespace() || matches!(c, ':' | '#' | ';'))
+		|| (options.dotted_keys && key.contains('.'));
+
+	match quote_inline(key, options.quote_style) {
+		Some(quoted) if needs_quotes || options.quote_all_keys => Some(quoted),
+		None if needs_quotes => None,
+		_ => Some(key.to_string()),
+	}
+}
+
+/// Returns the first key inside of `v` that [encode_key] can't write.
+fn unencodable_key<'a>(v: &'a Value, options: &EncodeOptions) -> Option<&'a str> {
+	match v {
+		Value::Object(obj) => obj
+			.iter()
+			.find_map(|(key, value)| match encode_key(key, options) {
+				Some(_) => unencodable_key(value, options),
+				None => Some(key.as_str()),
+			}),
+		Value::Array(arr) => arr.iter().find_map(|value| unencodable_key(value, options)),
+		_ => None,
+	}
+}
+
+/// A key that can't be written in a document, e.g. because it contains a
+/// line break. See [try_encode_string].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnencodableKey(pub String);
+
+impl std::fmt::Display for UnencodableKey {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "key {:?} can't be written in a document", self.0)
+	}
+}
+
+impl std::error::Error for UnencodableKey {}
+
+#[derive(Debug)]
+enum EncodedValue {
+	Inlined(String),
+	MultiLineString(Vec<String>),
+	/// Encoded keys with their values and trailing comments.
+	Object(Vec<(String, EncodedValue, Option<String>)>),
+	InlinedArray(Vec<EncodedValue>),
+	MultiLineArray(Vec<EncodedValue>),
+	/// An array of objects written as a comment with the keys, followed by
+	/// the values of every object as an inline array.
+	Table {
+		header: String,
+		rows: Vec<String>,
+	},
+	Raw(RawValue),
+}
+
+impl EncodedValue {
+	fn mls_from_str(s: &str, trailing_newlines: TrailingNewlines) -> Self {
+		if s.is_empty() {
+			return Self::MultiLineString(Vec::new());
+		}
+		let s = match trailing_newlines {
+			TrailingNewlines::Strip => s.trim_end_matches(['\n', '\r']),
+			_ => s,
+		};
+		// unlike `lines`, splitting keeps the empty line after a final line
+		// break
+		let mut lines: Vec<String> = s
+			.split('\n')
+			.map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+			.collect();
+		if trailing_newlines == TrailingNewlines::Ensure && !s.ends_with('\n') {
+			lines.push(String::new());
+		}
+		Self::MultiLineString(lines)
+	}
+
+	fn inlined(s: impl ToString) -> Self {
+		Self::Inlined(s.to_string())
+	}
+
+	/// Renders an inlined value or array into a string.
+	fn into_inline_string(self, options: &EncodeOptions) -> String {
+		let mut lines = vec![String::new()];
+		encoded_to_lines(options, "", &mut lines, 0, self);
+		lines.swap_remove(0)
+	}
+
+	fn is_inlined(&self) -> bool {
+		matches!(self, Self::Inlined(..))
+	}
+
+	/// Returns true if the value is written directly after the key's colon,
+	/// without a separating space.
+	fn is_attached_to_colon(&self) -> bool {
+		match self {
+			Self::MultiLineArray(..) | Self::Table { .. } => true,
+			// objects start on the next line, so an empty object is just the
+			// key and its colon, `key:`, which is distinct from `key: null`
+			Self::Object(..) => true,
+			Self::Raw(raw) => {
+				raw.head().is_empty() || raw.head().starts_with(|c: char| !c.is_whitespace())
+			}
+			_ => false,
+		}
+	}
+
+	fn from_primitive(p: &PrimitiveValue, options: &EncodeOptions) -> Self {
+		match p {
+			PrimitiveValue::Number(p) => Self::Inlined(options.format_number(*p)),
+			PrimitiveValue::Boolean(p) => Self::Inlined(p.to_string()),
+			PrimitiveValue::String(s) => {
+				let prefer_multi_line = match options.multi_line_strings {
+					MultiLineStrings::WhenNeeded => false,
+					MultiLineStrings::WhenQuoted => s.contains(['\'', '"']),
+				};
+				match quote_inline(s, options.quote_style) {
+					Some(quoted) if !prefer_multi_line => Self::Inlined(quoted),
+					_ => Self::mls_from_str(s, options.trailing_newlines),
+				}
+			}
+			PrimitiveValue::Null => Self::inlined("null"),
+			#[cfg(feature = "quantities")]
+			PrimitiveValue::Duration(d) => Self::Inlined(crate::quantity::format_duration(*d)),
+			#[cfg(feature = "quantities")]
+			PrimitiveValue::ByteSize(b) => Self::Inlined(crate::quantity::format_byte_size(*b)),
+			#[cfg(feature = "uuid")]
+			PrimitiveValue::Uuid(uuid) => Self::Inlined(uuid.to_string()),
+			#[cfg(feature = "ipaddr")]
+			PrimitiveValue::Ip(ip) => Self::Inlined(ip.to_string()),
+			#[cfg(feature = "semver")]
+			PrimitiveValue::Version(version) => Self::Inlined(version.to_string()),
+			PrimitiveValue::Custom(custom) => {
+				// `CustomValue::new` only accepts literals that can be inlined
+				let literal = match custom.literal() {
+					PrimitiveValue::String(s) => {
+						quote_inline(s, options.quote_style).unwrap_or_default()
+					}
+					literal => Self::from_primitive(literal, options).into_inline_string(options),
+				};
+				Self::Inlined(format!("!{} {literal}", custom.tag()))
+			}
+		}
+	}
+
+	/// Joins the keys of an entry whose value is an object with a single key
+	/// into a dotted key, as long as the keys are unquoted and the outer keys
+	/// have no comments.
+	fn collapse_dotted(
+		(mut key, mut value, mut comment): (String, Self, Option<String>),
+	) -> (String, Self, Option<String>) {
+		let dottable = |key: &str| !key.is_empty() && !key.starts_with(['\'', '"']);
+		loop {
+			let collapsible = match &value {
+				Self::Object(entries) => {
+					entries.len() == 1
+						&& dottable(&entries[0].0)
+						&& dottable(&key) && comment.is_none()
+				}
+				_ => false,
+			};
+			if !collapsible {
+				return (key, value, comment);
+			}
+			let Self::Object(mut entries) = value else {
+				unreachable!()
+			};
+			let (inner_key, inner_value, inner_comment) = entries.pop().unwrap();
+			key = format!("{key}.{inner_key}");
+			value = inner_value;
+			comment = inner_comment;
+		}
+	}
+
+	/// Writes the rows of a matrix with their elements padded into columns,
+	/// or returns `None` if the rows aren't inline arrays of the same length.
+	fn align_rows(rows: &[Self]) -> Option<Vec<String>> {
+		let mut cells = Vec::with_capacity(rows.len());
+		for row in rows {
+			match row {
+				Self::InlinedArray(row) if row.iter().all(Self::is_inlined) => cells.push(
+					row.iter()
+						.map(|cell| match cell {
+							Self::Inlined(s) => s.as_str(),
+							_ => unreachable!(),
+						})
+						.collect::<Vec<_>>(),
+				),
+				_ => return None,
+			}
+		}
+		let cols = cells.first()?.len();
+		if cells.iter().any(|row| row.len() != cols) {
+			return None;
+		}
+		let rows = pad_columns(&cells, true)
+			.into_iter()
+			.map(|row| format!("[{row}]"))
+			.collect();
+		Some(rows)
+	}
+
+	/// Writes an array of objects with the same keys as a table, or returns
+	/// `None` if the objects differ in their keys or have values that aren't
+	/// inlined.
+	fn table(records: &[Self]) -> Option<Self> {
+		let mut keys = None;
+		let mut cells = Vec::with_capacity(records.len() + 1);
+		for record in records {
+			let Self::Object(entries) = record else {
+				return None;
+			};
+			let record_keys: Vec<&str> = entries.iter().map(|(key, ..)| key.as_str()).collect();
+			if record_keys.is_empty() || keys.get_or_insert(record_keys.clone()) != &record_keys {
+				return None;
+			}
+			let mut row = Vec::with_capacity(entries.len());
+			for (_, value, comment) in entries {
+				match (value, comment) {
+					(Self::Inlined(s), None) => row.push(s.as_str()),
+					_ => return None,
+				}
+			}
+			cells.push(row);
+		}
+		cells.insert(0, keys?);
+
+		let mut rows = pad_columns(&cells, false).into_iter();
+		let header = rows.next().unwrap();
+		Some(Self::Table {
+			header,
+			rows: rows.map(|row| format!("[{row}]")).collect(),
+		})
+	}
+
+	fn from_value(v: &Value, options: &EncodeOptions, path: &mut KvonPath) -> Self {
+		match v {
+			Value::Primitive(p) => Self::from_primitive(p, options),
+			Value::Raw(raw) => Self::Raw(raw.clone()),
+			Value::Array(arr) => {
+				// encode all values
+				let encoded = arr
+					.iter()
+					.enumerate()
+					.map(|(i, value)| {
+						path.push(PathSegment::Index(i));
+						let encoded = Self::from_value(value, options, path);
+						path.pop();
+						encoded
+					})
+					.collect::<Vec<_>>();
+
+				// check if at least one of the variables is not inlined
+				let has_non_inlined = encoded.iter().any(|v| !v.is_inlined());
+
+				if options.tables {
+					if let Some(table) = Self::table(&encoded) {
+						return table;
+					}
+				}
+				if options.align_matrices {
+					if let Some(rows) = Self::align_rows(&encoded) {
+						return Self::MultiLineArray(rows.into_iter().map(Self::Inlined).collect());
+					}
+				}
+
+				// if there is a non inlined variable, then create a multi
+				// line array, otherwise create an inlined array
+				if has_non_inlined {
+					return Self::MultiLineArray(encoded);
+				}
+				if options.max_line_width.is_none() && options.array_items_per_line.is_none() {
+					return Self::InlinedArray(encoded);
+				}
+
+				// long arrays are expanded, with several values on each line
+				let items = encoded
+					.into_iter()
+					.map(|v| v.into_inline_string(options))
+					.collect::<Vec<_>>();
+				match options.wrap_array_items(&items) {
+					Some(lines) => {
+						Self::MultiLineArray(lines.into_iter().map(Self::Inlined).collect())
+					}
+					None => Self::InlinedArray(items.into_iter().map(Self::Inlined).collect()),
+				}
+			}
+			Value::Object(obj) => {
+				// encode all values in order, hiding the ones that should be
+				// redacted
+				let encoded = options
+					.ordered_keys(path, obj.keys())
+					.into_iter()
+					.map(|key| {
+						path.push(PathSegment::Key(key.to_string()));
+						let encoded = if options.should_redact(key) {
+							Self::from_primitive(
+								&PrimitiveValue::from(options.redaction_placeholder.as_str()),
+								options,
+							)
+						} else {
+							Self::from_value(&obj[key], options, path)
+						};
+						let comment = options.comment_at(path);
+						path.pop();
+						// checked by `try_encode_string`
+						let key = encode_key(key, options).unwrap();
+						let entry = (key, encoded, comment);
+						match options.dotted_keys {
+							true => Self::collapse_dotted(entry),
+							false => entry,
+						}
+					})
+					.collect();
+
+				Self::Object(encoded)
+			}
+		}
+	}
+}
+
+/// Pads the cells of rows with the same length into columns, separated by a
+/// space. The last column of left-aligned cells isn't padded.
+fn pad_columns(cells: &[Vec<&str>], right: bool) -> Vec<String> {
+	let cols = cells.first().map_or(0, Vec::len);
+	let widths: Vec<usize> = (0..cols)
+		.map(|col| {
+			cells
+				.iter()
+				.map(|row| row[col].chars().count())
+				.max()
+				.unwrap()
+		})
+		.collect();
+	cells
+		.iter()
+		.map(|row| {
+			let row: Vec<String> = row
+				.iter()
+				.zip(&widths)
+				.map(|(cell, width)| match right {
+					true => format!("{cell:>width$}"),
+					false => format!("{cell:width$}"),
+				})
+				.collect();
+			row.join(" ").trim_end().to_string()
+		})
+		.collect()
+}
+
+fn encode_indent(lines: &mut [String], indent_str: &str, indent: i32) {
+	for _ in 0..indent {
+		lines.last_mut().unwrap().push_str(indent_str);
+	}
+}
+
+fn encoded_to_lines(
+	options: &EncodeOptions,
+	indent_str: &str,
+	lines: &mut Vec<String>,
+	indent: i32,
+	v: EncodedValue,
+) {
+	match v {
+		EncodedValue::Inlined(s) => {
+			lines.last_mut().unwrap().push_str(&s);
+		}
+		EncodedValue::MultiLineString(s) => {
+			lines.last_mut().unwrap().push('|');
+			for line in s {
+				lines.push(String::new());
+				encode_indent(lines, indent_str, indent);
+				lines.last_mut().unwrap().push_str(&line);
+			}
+		}
+		EncodedValue::Object(v) => {
+			for (i, (key, value, comment)) in v.into_iter().enumerate() {
+				if i > 0 && indent == 0 && options.blank_lines_between_keys {
+					lines.push(String::new());
+				}
+				lines.push(String::new());
+
+				encode_indent(lines, indent_str, indent);
+
+				// for readability, if the next value is a multi line array,
+				// don't add a space after the colon
+				if value.is_attached_to_colon() {
+					lines.last_mut().unwrap().push_str(&format!("{key}:"));
+				} else {
+					lines.last_mut().unwrap().push_str(&format!("{key}: "));
+				}
+
+				// encode the value
+				let key_line = lines.len() - 1;
+				encoded_to_lines(options, indent_str, lines, indent + 1, value);
+
+				// the comment trails the line of the key
+				if let Some(comment) = comment {
+					let line = &mut lines[key_line];
+					if !line.ends_with(' ') {
+						line.push(' ');
+					}
+					line.push_str(&format!("# {comment}"));
+				}
+			}
+		}
+		EncodedValue::InlinedArray(arr) => {
+			lines.last_mut().unwrap().push('[');
+			if !arr.is_empty() {
+				let mut it = arr.into_iter();
+				encoded_to_lines(options, indent_str, lines, indent, it.next().unwrap());
+				for v in it {
+					lines.last_mut().unwrap().push(' ');
+					encoded_to_lines(options, indent_str, lines, indent, v);
+				}
+			}
+			lines.last_mut().unwrap().push(']');
+		}
+		EncodedValue::Raw(raw) => {
+			let last = lines.last_mut().unwrap();
+			if last.ends_with(' ') {
+				last.push_str(raw.head().trim_start());
+			} else {
+				last.push_str(raw.head());
+			}
+			for line in raw.reindented_body(indent_str) {
+				lines.push(String::new());
+				encode_indent(lines, indent_str, indent);
+				lines.last_mut().unwrap().push_str(&line);
+			}
+		}
+		EncodedValue::Table { header, rows } => {
+			lines.last_mut().unwrap().push_str("--");
+			lines.push(String::new());
+			encode_indent(lines, indent_str, indent);
+			// aligns the keys with the values after `- [`
+			lines.last_mut().unwrap().push_str(&format!("#  {header}"));
+			for row in rows {
+				lines.push(String::new());
+				encode_indent(lines, indent_str, indent);
+				lines.last_mut().unwrap().push_str(&format!("- {row}"));
+			}
+		}
+		EncodedValue::MultiLineArray(arr) => {
+			lines.last_mut().unwrap().push_str("--");
+
+			let mut previous_was_object = false;
+			for v in arr {
+				let is_object = matches!(v, EncodedValue::Object(..));
+				if is_object && previous_was_object && options.blank_lines_between_objects {
+					lines.push(String::new());
+				}
+				previous_was_object = is_object;
+
+				lines.push(String::new());
+				encode_indent(lines, indent_str, indent);
+
+				match &v {
+					EncodedValue::MultiLineArray(..) => {}
+					// objects start on the next line, after a lone `-`
+					EncodedValue::Object(..) => lines.last_mut().unwrap().push('-'),
+					_ => lines.last_mut().unwrap().push_str("- "),
+				}
+
+				encoded_to_lines(options, indent_str, lines, indent + 1, v);
+			}
+		}
+	}
+}
+
+/// Encodes a primitive placed after `prefix`, the already indented text on
+/// its first line. Multi-line strings are indented by `indent` levels.
+pub(crate) fn encode_primitive_lines(
+	p: &PrimitiveValue,
+	options: &EncodeOptions,
+	prefix: String,
+	indent: i32,
+) -> Vec<String> {
+	let indention = options.indention.to_string_unit();
+	let mut lines = vec![prefix];
+	encoded_to_lines(
+		options,
+		&indention,
+		&mut lines,
+		indent,
+		EncodedValue::from_primitive(p, options),
+	);
+	lines
+}
+
+/// Encodes a [Value] into a string using the given options.
+///
+/// Empty objects are encoded as a key without a value (`key:`) or as a lone
+/// `-` in expanded arrays, and empty arrays as `[]`. Both parse back into the
+/// same empty value, while `null` is always written explicitly.
+///
+/// # Panics
+///
+/// Panics if `v` contains a key that can't be written, see
+/// [try_encode_string].
+pub fn encode_string(v: &Value, options: &EncodeOptions) -> String {
+	try_encode_string(v, options).unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Encodes a [Value] like [encode_string], but returns an error instead of
+/// panicking if it contains a key that can't be written, e.g. one with a
+/// line break.
+pub fn try_encode_string(v: &Value, options: &EncodeOptions) -> Result<String, UnencodableKey> {
+	if let Some(key) = unencodable_key(v, options) {
+		return Err(UnencodableKey(key.to_string()));
+	}
+
+	// convert indention to string
+	let indention = options.indention.to_string_unit();
+
+	// encode value
+	let encoded = EncodedValue::from_value(v, options, &mut KvonPath::root());
+
+	// convert to lines, starting with the header comment
+	let mut lines: Vec<String> = match &options.header {
+		Some(header) => header.lines().map(|line| format!("# {line}")).collect(),
+		None => vec![String::new()],
+	};
+	#[cfg(feature = "checksum")]
+	if options.checksum {
+		let checksum = format!(
+			"{} {}",
+			crate::checksum::CHECKSUM_PREFIX,
+			crate::checksum::checksum(v)
+		);
+		match options.header {
+			Some(_) => lines.insert(0, checksum),
+			None => lines[0] = checksum,
+		}
+	}
+	// values other than objects continue the last line, which mustn't be
+	// a comment
+	if !matches!(encoded, EncodedValue::Object(_)) && lines.last().is_none_or(|l| !l.is_empty()) {
+		lines.push(String::new());
+	}
+	encoded_to_lines(options, &indention, &mut lines, 0, encoded);
+
+	// join lines
+	Ok(lines.join("\n"))
+}
+
+/// Encodes a [Value] into a string. This implementation will prefer to
+/// expand arrays and strings to multiple lines to improve readability.
+pub fn encode_string_expanded(v: &Value, indention: Indention) -> String {
+	encode_string(v, &EncodeOptions::new().indention(indention))
+}
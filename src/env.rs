@@ -0,0 +1,170 @@
+//! Converting values to and from environment variables, e.g. to pass a
+//! config to a child process or to override it in a container.
+//!
+//! ```rust
+//! use kvon_rs::{env::to_env_vars, object};
+//!
+//! let config = object! { server: { port: 8080, max_connections: 100 } };
+//! assert_eq!(
+//!     to_env_vars(&config, "app"),
+//!     [
+//!         ("APP_SERVER_MAX__CONNECTIONS".to_string(), "100".to_string()),
+//!         ("APP_SERVER_PORT".to_string(), "8080".to_string()),
+//!     ]
+//! );
+//! ```
+//!
+//! Names are built as follows:
+//! - The prefix and the keys along the path are joined by `_`, and array
+//!   items use their index.
+//! - Letters are uppercased, so keys differing in case share a name.
+//! - `_` and characters other than ASCII letters and digits are written as
+//!   `__`, which is read back as `_`. Keys starting or ending with `_` next to
+//!   another key can't be told apart, e.g. `a_.b` and `a._b`.
+//!
+//! Values are written without quotes. When reading them back, `true`,
+//! `false`, `null` and numbers become primitives of that type, and anything
+//! else a string. Empty objects and arrays are skipped.
+
+use std::collections::HashMap;
+
+use crate::{
+	encoder::encode_primitive_lines,
+	value::{PrimitiveValue, Value},
+	EncodeOptions,
+};
+
+/// Returns the leaves of `value` as environment variables, sorted by name.
+pub fn to_env_vars(value: &Value, prefix: &str) -> Vec<(String, String)> {
+	let mut vars = Vec::new();
+	collect(value, &mut vec![prefix.to_string()], &mut vars);
+	vars.sort();
+	vars
+}
+
+fn collect(value: &Value, path: &mut Vec<String>, vars: &mut Vec<(String, String)>) {
+	match value {
+		Value::Object(obj) => {
+			for (key, value) in obj {
+				path.push(key.clone());
+				collect(value, path, vars);
+				path.pop();
+			}
+		}
+		Value::Array(arr) => {
+			for (i, value) in arr.iter().enumerate() {
+				path.push(i.to_string());
+				collect(value, path, vars);
+				path.pop();
+			}
+		}
+		Value::Primitive(p) => vars.push((var_name(path), var_value(p))),
+		Value::Raw(raw) => vars.push((var_name(path), raw.head().trim().to_string())),
+	}
+}
+
+fn var_name(path: &[String]) -> String {
+	let segments: Vec<String> = path
+		.iter()
+		.filter(|segment| !segment.is_empty())
+		.map(|segment| {
+			segment
+				.chars()
+				.map(|c| match c.is_ascii_alphanumeric() {
+					true => c.to_ascii_uppercase().to_string(),
+					false => "__".to_string(),
+				})
+				.collect()
+		})
+		.collect();
+	segments.join("_")
+}
+
+pub(crate) fn var_value(p: &PrimitiveValue) -> String {
+	match p {
+		PrimitiveValue::String(s) => s.clone(),
+		p => encode_primitive_lines(p, &EncodeOptions::new(), String::new(), 0).join(""),
+	}
+}
+
+/// Builds an object from the environment variables starting with `prefix`,
+/// see [from_vars]. Variables whose name or value isn't valid Unicode are
+/// skipped.
+pub fn from_env_vars(prefix: &str) -> Value {
+	let vars = std::env::vars_os()
+		.filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+	from_vars(prefix, vars)
+}
+
+/// Builds an object from the variables whose names start with `prefix`
+/// followed by `_`, or from all variables if the prefix is empty. Keys are
+/// lowercased, and objects whose keys are indices become arrays.
+pub fn from_vars(prefix: &str, vars: impl IntoIterator<Item = (String, String)>) -> Value {
+	flat_vars(prefix, vars).unflatten("\0")
+}
+
+/// Like [from_vars], but returns an error if a variable holds the value of
+/// a key that other variables nest keys in, e.g. `A` and `A_B`.
+#[cfg(feature = "properties")]
+pub(crate) fn try_from_vars(
+	prefix: &str,
+	vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<Value, crate::value::UnflattenConflict> {
+	flat_vars(prefix, vars)
+		.try_unflatten("\0")
+		.map_err(|e| crate::value::UnflattenConflict {
+			key: e.key.replace('\0', "."),
+			prefix: e.prefix.replace('\0', "."),
+		})
+}
+
+/// The values of the variables starting with `prefix`, by their keys joined
+/// by `\0`.
+fn flat_vars(prefix: &str, vars: impl IntoIterator<Item = (String, String)>) -> Value {
+	let prefix = match prefix {
+		"" => String::new(),
+		prefix => format!("{}_", var_name(&[prefix.to_string()])),
+	};
+
+	let mut flat = HashMap::new();
+	for (name, value) in vars {
+		let Some(name) = name.strip_prefix(&prefix) else {
+			continue;
+		};
+		if name.is_empty() {
+			continue;
+		}
+		flat.insert(split_name(name).join("\0"), parse_var_value(value));
+	}
+	Value::Object(flat)
+}
+
+/// Splits a variable name at single `_`s into lowercase keys, where `__`
+/// stands for a `_` inside of a key.
+fn split_name(name: &str) -> Vec<String> {
+	let mut keys = vec![String::new()];
+	let mut chars = name.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'_' if chars.peek() == Some(&'_') => {
+				chars.next();
+				keys.last_mut().unwrap().push('_');
+			}
+			'_' => keys.push(String::new()),
+			c => keys.last_mut().unwrap().push(c.to_ascii_lowercase()),
+		}
+	}
+	keys
+}
+
+pub(crate) fn parse_var_value(value: String) -> Value {
+	match value.as_str() {
+		"true" => Value::from(true),
+		"false" => Value::from(false),
+		"null" => Value::null(),
+		s => match s.parse::<f32>() {
+			Ok(n) if n.is_finite() && s.trim() == s => Value::from(n),
+			_ => Value::from(value),
+		},
+	}
+}
@@ -1,41 +1,344 @@
-use crate::indention::Indention;
+use crate::{indention::Indention, FormatVersion};
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParserErrorKind {
-	UnexpectedCharacter,
+	/// Content that doesn't fit the grammar at this point of the line.
+	Unexpected {
+		/// The offending token, or `end of line` if the line ended early.
+		found: String,
+		/// What could have appeared instead.
+		expected: &'static [&'static str],
+	},
 	UnclosedString,
 	Expected(String),
+	/// The input ended inside of a block that has no content yet. Only
+	/// produced when enabled with
+	/// [crate::ParserOptions::error_on_unclosed_blocks].
+	UnexpectedEof {
+		expected: String,
+	},
+	/// A bare word resembling a literal, e.g. `True` instead of `true`. Only
+	/// produced in strict literal mode.
+	NearMissLiteral {
+		found: String,
+		expected: String,
+	},
+	/// A word shaped like a literal of an enabled feature that isn't valid,
+	/// e.g. `10.0.0.300` with the `ipaddr` feature.
+	InvalidLiteral {
+		found: String,
+		expected: &'static str,
+	},
+	// tags
+	UnknownTag(String),
+	InvalidTaggedValue {
+		tag: String,
+		message: String,
+	},
+	/// Parsing was aborted through a [crate::CancelToken].
+	Cancelled,
+	/// The input couldn't be read, e.g. because a reader returned invalid
+	/// UTF-8. Holds the message of the [std::io::Error].
+	Read(String),
+	/// The document declares a version newer than
+	/// [FormatVersion::LATEST].
+	UnsupportedVersion(FormatVersion),
+	/// A dotted key and another key assign to overlapping paths, e.g. `a: 1`
+	/// followed by `a.b: 2`. Only produced with
+	/// [crate::ParserOptions::dotted_keys].
+	KeyConflict {
+		path: String,
+		/// The line of the value the key conflicts with.
+		other_line_number: usize,
+	},
+	/// Content after a marker that opens a block, e.g. `arr:-- a`. The
+	/// marker is `:--`, `--` or `|`.
+	TrailingAfterMarker {
+		marker: &'static str,
+	},
+	/// An array line mixing values with an entry that has to stand alone,
+	/// e.g. `- 1 2 x: 3`.
+	MixedArrayEntries {
+		/// The kind of the entry, `key-value pair` or `multi-line string`.
+		entry: &'static str,
+	},
+	/// Syntax of an extension that the KVON spec doesn't define, e.g. a
+	/// tagged literal. Only produced with [crate::Profile::Strict].
+	NotInSpec {
+		/// The extension, e.g. `tagged literals`.
+		extension: &'static str,
+	},
 	// indention
 	InconsistentIndention(Indention, Indention),
 	InvalidIndention,
 	MultipleTabIndent,
 	MixedTabsAndSpaces,
-	SpacesNotMultipleOfIndent,
+	/// A line indented to a width between two block levels, e.g. 3 spaces in
+	/// a document indented by 2, or 3 tabs in a document indented by 2 tabs
+	/// per level, see [crate::ParserOptions::lenient_tab_indention]. Widths
+	/// count the spaces or tabs of `indention`.
+	MisalignedIndention {
+		indention: Indention,
+		found: usize,
+		/// The width of the block level below the line.
+		shallower: usize,
+		/// The width of the block level above the line.
+		deeper: usize,
+	},
+}
+
+/// What the widths of indention are counted in.
+fn indent_unit(indention: &Indention) -> &'static str {
+	match indention {
+		Indention::Tabs => "tabs",
+		Indention::Spaces(_) => "spaces",
+	}
+}
+
+/// Broad groups of [ParserErrorKind]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+	/// The line doesn't follow the grammar.
+	Syntax,
+	/// The line's leading whitespace is invalid.
+	Indentation,
+	/// The line is well formed, but a literal's content is invalid.
+	Value,
+	/// Parsing was aborted through a [crate::CancelToken].
+	Cancelled,
+	/// The input couldn't be read.
+	Io,
 }
 
 impl ParserErrorKind {
 	pub fn expected(s: impl ToString) -> Self {
 		Self::Expected(s.to_string())
 	}
+
+	pub fn unexpected(found: impl ToString, expected: &'static [&'static str]) -> Self {
+		Self::Unexpected {
+			found: found.to_string(),
+			expected,
+		}
+	}
+
+	/// A stable identifier of the error kind. Codes never change meaning
+	/// between releases, so they can be used to link to documentation or to
+	/// assert on in tests.
+	pub fn code(&self) -> &'static str {
+		match self {
+			Self::Unexpected { .. } => "E100_UNEXPECTED_CHARACTER",
+			Self::UnclosedString => "E101_UNCLOSED_STRING",
+			Self::Expected(_) => "E102_EXPECTED",
+			Self::NearMissLiteral { .. } => "E103_NEAR_MISS_LITERAL",
+			Self::UnexpectedEof { .. } => "E104_UNEXPECTED_EOF",
+			Self::UnsupportedVersion(_) => "E105_UNSUPPORTED_VERSION",
+			Self::KeyConflict { .. } => "E106_KEY_CONFLICT",
+			Self::TrailingAfterMarker { .. } => "E107_TRAILING_AFTER_MARKER",
+			Self::MixedArrayEntries { .. } => "E108_MIXED_ARRAY_ENTRIES",
+			Self::NotInSpec { .. } => "E109_NOT_IN_SPEC",
+			// tags
+			Self::UnknownTag(_) => "E200_UNKNOWN_TAG",
+			Self::InvalidTaggedValue { .. } => "E201_INVALID_TAGGED_VALUE",
+			Self::InvalidLiteral { .. } => "E202_INVALID_LITERAL",
+			Self::Cancelled => "E300_CANCELLED",
+			Self::Read(_) => "E301_READ_FAILED",
+			// indention
+			Self::MixedTabsAndSpaces => "E001_MIXED_INDENT",
+			Self::InconsistentIndention(..) => "E002_INCONSISTENT_INDENT",
+			Self::InvalidIndention => "E003_INVALID_INDENT",
+			Self::MultipleTabIndent => "E004_MULTIPLE_TAB_INDENT",
+			Self::MisalignedIndention { .. } => "E005_MISALIGNED_INDENT",
+		}
+	}
+
+	/// A suggestion for fixing the error that doesn't depend on the rest of
+	/// the line.
+	pub(crate) fn default_hint(&self) -> Option<String> {
+		match self {
+			Self::MixedTabsAndSpaces => {
+				Some("indent using either tabs or spaces, but not both".to_string())
+			}
+			Self::InconsistentIndention(Indention::Tabs, _) => Some(
+				"this document is indented with tabs, replace the spaces with tabs".to_string(),
+			),
+			Self::InconsistentIndention(Indention::Spaces(spaces), _) => Some(format!(
+				"this document is indented with {spaces} spaces, replace the tabs with spaces"
+			)),
+			Self::MultipleTabIndent => {
+				Some("nested blocks are indented by one tab more than their parent".to_string())
+			}
+			Self::InvalidIndention => Some(
+				"a line can be indented at most one level deeper than the line before it"
+					.to_string(),
+			),
+			Self::UnsupportedVersion(_) => Some(format!(
+				"this parser supports KVON up to version {}, update it to read this document",
+				FormatVersion::LATEST
+			)),
+			Self::MisalignedIndention {
+				indention,
+				shallower,
+				deeper,
+				..
+			} => Some(format!(
+				"indent by {shallower} {} to continue the outer block, or by {deeper} to nest",
+				indent_unit(indention)
+			)),
+			Self::TrailingAfterMarker { .. } => {
+				Some("the block's content goes on the indented lines below".to_string())
+			}
+			Self::MixedArrayEntries { entry } => Some(format!(
+				"start a new entry with `- ` on the next line for the {entry}"
+			)),
+			_ => None,
+		}
+	}
+
+	pub fn category(&self) -> ErrorCategory {
+		match self {
+			Self::Unexpected { .. }
+			| Self::UnclosedString
+			| Self::Expected(_)
+			| Self::NearMissLiteral { .. }
+			| Self::UnexpectedEof { .. }
+			| Self::UnsupportedVersion(_)
+			| Self::KeyConflict { .. }
+			| Self::TrailingAfterMarker { .. }
+			| Self::MixedArrayEntries { .. }
+			| Self::NotInSpec { .. }
+			| Self::UnknownTag(_) => ErrorCategory::Syntax,
+			Self::InvalidTaggedValue { .. } | Self::InvalidLiteral { .. } => ErrorCategory::Value,
+			Self::Cancelled => ErrorCategory::Cancelled,
+			Self::Read(_) => ErrorCategory::Io,
+			Self::InconsistentIndention(..)
+			| Self::InvalidIndention
+			| Self::MultipleTabIndent
+			| Self::MixedTabsAndSpaces
+			| Self::MisalignedIndention { .. } => ErrorCategory::Indentation,
+		}
+	}
 }
 
 /// Errors that can happen during parsing.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ParserError {
 	pub kind: ParserErrorKind,
 	pub line_number: usize,
+	/// The byte offset of the error in `line`. See [ParserError::char_column]
+	/// and [ParserError::utf16_column] for columns suitable for displaying.
 	pub column_number: usize,
 	pub line: String,
+	/// A suggestion for fixing common mistakes.
+	pub hint: Option<String>,
+}
+
+impl ParserError {
+	pub fn with_hint(mut self, hint: impl ToString) -> Self {
+		self.hint = Some(hint.to_string());
+		self
+	}
+
+	/// The part of the line before the error.
+	fn line_prefix(&self) -> &str {
+		let mut column = self.column_number.min(self.line.len());
+		while !self.line.is_char_boundary(column) {
+			column -= 1;
+		}
+		&self.line[..column]
+	}
+
+	/// The column of the error counted in characters.
+	pub fn char_column(&self) -> usize {
+		self.line_prefix().chars().count()
+	}
+
+	/// The column of the error counted in UTF-16 code units, as used by
+	/// editors following the language server protocol.
+	pub fn utf16_column(&self) -> usize {
+		self.line_prefix().encode_utf16().count()
+	}
+
+	/// See [ParserErrorKind::code].
+	pub fn code(&self) -> &'static str {
+		self.kind.code()
+	}
+
+	pub fn category(&self) -> ErrorCategory {
+		self.kind.category()
+	}
+
+	pub fn is_indentation_error(&self) -> bool {
+		self.category() == ErrorCategory::Indentation
+	}
+
+	pub fn is_syntax_error(&self) -> bool {
+		self.category() == ErrorCategory::Syntax
+	}
+
+	pub fn is_value_error(&self) -> bool {
+		self.category() == ErrorCategory::Value
+	}
 }
 
 impl std::fmt::Display for ParserError {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		write!(f, "{}:{}: ", self.line_number, self.column_number)?;
+		write!(f, "{}:{}: ", self.line_number, self.char_column())?;
 
 		match &self.kind {
-			ParserErrorKind::UnexpectedCharacter => write!(f, "unexpected character"),
+			ParserErrorKind::Unexpected { found, expected } => {
+				write!(f, "unexpected '{found}'")?;
+				if let Some((last, rest)) = expected.split_last() {
+					write!(f, ", expected ")?;
+					if !rest.is_empty() {
+						write!(f, "{} or ", rest.join(", "))?;
+					}
+					write!(f, "{last}")?;
+				}
+				Ok(())
+			}
 			ParserErrorKind::UnclosedString => write!(f, "string not closed"),
 			ParserErrorKind::Expected(s) => write!(f, "expected '{s}'"),
+			ParserErrorKind::UnexpectedEof { expected } => {
+				write!(f, "unexpected end of input, expected {expected}")
+			}
+			ParserErrorKind::NearMissLiteral { found, expected } => {
+				write!(f, "'{found}' is not a literal, did you mean '{expected}'?")
+			}
+			ParserErrorKind::InvalidLiteral { found, expected } => {
+				write!(f, "invalid {expected} '{found}'")
+			}
+			// tags
+			ParserErrorKind::UnknownTag(tag) => write!(f, "unknown tag '!{tag}'"),
+			ParserErrorKind::InvalidTaggedValue { tag, message } => {
+				write!(f, "invalid value for tag '!{tag}': {message}")
+			}
+			ParserErrorKind::Cancelled => write!(f, "parsing was cancelled"),
+			ParserErrorKind::Read(message) => write!(f, "failed to read the input: {message}"),
+			ParserErrorKind::UnsupportedVersion(version) => {
+				write!(f, "unsupported KVON version {version}")
+			}
+			ParserErrorKind::KeyConflict {
+				path,
+				other_line_number,
+			} => write!(
+				f,
+				"key '{path}' conflicts with the value on line {other_line_number}"
+			),
+			ParserErrorKind::TrailingAfterMarker { marker } => {
+				let construct = match *marker {
+					":--" => "expanded array",
+					"--" => "nested array",
+					_ => "multi-line string",
+				};
+				write!(f, "{construct} marker '{marker}' must end the line")
+			}
+			ParserErrorKind::MixedArrayEntries { entry } => {
+				write!(f, "a {entry} can't share an array line with other values")
+			}
+			ParserErrorKind::NotInSpec { extension } => {
+				write!(f, "{extension} are not part of the KVON spec")
+			}
 			// indention
 			ParserErrorKind::InconsistentIndention(expected, found) => write!(
 				f,
@@ -46,12 +349,22 @@ impl std::fmt::Display for ParserError {
 			ParserErrorKind::MixedTabsAndSpaces => {
 				write!(f, "indention of mixed tabs and spaces is not allowed")
 			}
-			ParserErrorKind::SpacesNotMultipleOfIndent => {
-				write!(
-					f,
-					"amount of spaces is not a multiple of the indention spaces"
-				)
-			}
+			ParserErrorKind::MisalignedIndention {
+				indention,
+				found,
+				shallower,
+				deeper,
+			} => write!(
+				f,
+				"indented by {found} {}, expected {shallower} or {deeper}",
+				indent_unit(indention)
+			),
+		}?;
+
+		if let Some(hint) = &self.hint {
+			write!(f, " (hint: {hint})")?;
 		}
+
+		Ok(())
 	}
 }
@@ -0,0 +1,209 @@
+//! Reports explaining where the effective values of a [LayeredConfig] come
+//! from and which values of other layers they override, for debugging
+//! precedence issues.
+//!
+//! ```rust
+//! use kvon_rs::{explain::explain, layers::LayeredConfig};
+//!
+//! let mut config = LayeredConfig::new().with_spans();
+//! config
+//!     .add_str("defaults.kvon", "server:\n\thost: 'localhost'\n\tport: 80\n")
+//!     .unwrap();
+//! config.add_str("site.kvon", "server:\n\tport: 8080\n").unwrap();
+//!
+//! assert_eq!(
+//!     explain(&config).to_table(),
+//!     "\
+//! path         value        source
+//! server.host  'localhost'  defaults.kvon:2
+//! server.port  8080         site.kvon:2
+//!              80           defaults.kvon:3
+//! "
+//! );
+//! ```
+//!
+//! Each value that isn't an object is explained, followed by the values it
+//! overrides, the one with the highest precedence first. Objects are only
+//! explained if they replace values of other types.
+
+use std::collections::HashMap;
+
+use crate::{
+	diff::summary,
+	encode::Encode,
+	encode_string,
+	layers::{LayeredConfig, Origin},
+	overlay::OverlayValue,
+	path::{KvonPath, Path},
+	value::Value,
+	EncodeOptions,
+};
+
+/// The effective value at a path, see [explain].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+	pub path: KvonPath,
+	pub value: Value,
+	pub origin: Origin,
+	/// The values of other layers at the path, the one with the highest
+	/// precedence first.
+	pub overridden: Vec<Candidate>,
+}
+
+/// A value overridden by a layer with higher precedence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+	pub value: Value,
+	pub origin: Origin,
+}
+
+/// `path`, `value` and `source`, with `overridden` candidates if there are
+/// any.
+impl Encode for Explanation {
+	fn encode(&self) -> Value {
+		let mut obj = HashMap::new();
+		obj.insert("path".to_string(), Value::from(self.path.to_string()));
+		obj.insert("value".to_string(), self.value.clone());
+		obj.insert("source".to_string(), Value::from(self.origin.to_string()));
+		if !self.overridden.is_empty() {
+			obj.insert("overridden".to_string(), self.overridden.encode());
+		}
+		Value::Object(obj)
+	}
+}
+
+/// `value` and `source`.
+impl Encode for Candidate {
+	fn encode(&self) -> Value {
+		let mut obj = HashMap::new();
+		obj.insert("value".to_string(), self.value.clone());
+		obj.insert("source".to_string(), Value::from(self.origin.to_string()));
+		Value::Object(obj)
+	}
+}
+
+/// The explanations of the values of a config, see [explain].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+	entries: Vec<Explanation>,
+}
+
+impl Report {
+	/// The explanations ordered by path, with object keys sorted.
+	pub fn entries(&self) -> &[Explanation] {
+		&self.entries
+	}
+
+	pub fn get(&self, path: &Path) -> Option<&Explanation> {
+		self.entries.iter().find(|entry| *entry.path == *path)
+	}
+
+	/// Keeps the explanations of the value at `path` and the values inside
+	/// of it, e.g. all keys of `server`.
+	pub fn within(mut self, path: &Path) -> Self {
+		self.entries.retain(|entry| entry.path.starts_with(path));
+		self
+	}
+
+	/// Writes the report as a KVON document listing the explanations as
+	/// `entries`.
+	pub fn to_kvon(&self) -> String {
+		let mut report = HashMap::new();
+		report.insert("entries".to_string(), self.entries.encode());
+		let encoded = encode_string(&Value::Object(report), &EncodeOptions::new());
+		format!("{}\n", encoded.trim_matches('\n'))
+	}
+
+	/// Writes the report as a table with a row for each value. Overridden
+	/// values follow in rows without a path.
+	pub fn to_table(&self) -> String {
+		self.table(false)
+	}
+
+	/// Writes the report as a table like [Report::to_table], with ANSI
+	/// escape codes for terminals that make the header bold and strike
+	/// through overridden values.
+	pub fn to_ansi(&self) -> String {
+		self.table(true)
+	}
+
+	fn table(&self, ansi: bool) -> String {
+		let mut rows = vec![(
+			"1",
+			[
+				"path".to_string(),
+				"value".to_string(),
+				"source".to_string(),
+			],
+		)];
+		for entry in &self.entries {
+			let row = [
+				entry.path.to_string(),
+				summary(&entry.value),
+				entry.origin.to_string(),
+			];
+			rows.push(("", row));
+			for candidate in &entry.overridden {
+				let row = [
+					String::new(),
+					summary(&candidate.value),
+					candidate.origin.to_string(),
+				];
+				rows.push(("2;9", row));
+			}
+		}
+
+		let width = |column: usize| {
+			rows.iter()
+				.map(|(_, row)| row[column].chars().count())
+				.max()
+				.unwrap_or(0)
+		};
+		let widths = [width(0), width(1)];
+		let mut table = String::new();
+		for (style, [path, value, source]) in rows {
+			let style = |text: &str| match ansi && !style.is_empty() && !text.is_empty() {
+				true => format!("\x1b[{style}m{text}\x1b[0m"),
+				false => text.to_string(),
+			};
+			let pad = |text: &str, width: usize| " ".repeat(width - text.chars().count() + 2);
+			table.push_str(&path);
+			table.push_str(&pad(&path, widths[0]));
+			table.push_str(&style(&value));
+			table.push_str(&pad(&value, widths[1]));
+			table.push_str(&source);
+			table.push('\n');
+		}
+		table
+	}
+}
+
+/// Explains the effective values of a config, see the
+/// [module documentation](self).
+pub fn explain(config: &LayeredConfig) -> Report {
+	let mut entries = Vec::new();
+	config.merge_with(|path, layers, replaced| {
+		let &(top, value) = layers.last().unwrap();
+		if value.is_object() && replaced.is_empty() {
+			return;
+		}
+		let value = match value.is_object() {
+			true => OverlayValue::new(layers.iter().map(|&(_, value)| value)).to_value(),
+			false => value.clone(),
+		};
+		let overridden = replaced
+			.into_iter()
+			.map(|(layer, value)| Candidate {
+				value: value.clone(),
+				origin: config.origin(layer, path),
+			})
+			.collect();
+		entries.push(Explanation {
+			path: path.clone(),
+			value,
+			origin: config.origin(top, path),
+			overridden,
+		});
+	});
+	Report { entries }
+}
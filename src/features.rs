@@ -0,0 +1,168 @@
+//! What this implementation supports beyond the KVON spec, so that tools
+//! working with several implementations can agree on the syntax documents
+//! may use.
+//!
+//! ```rust
+//! use kvon_rs::{features::Feature, supports, FormatVersion, SUPPORTED_FEATURES};
+//!
+//! assert_eq!(SUPPORTED_FEATURES.spec_version, FormatVersion::LATEST);
+//! assert!(SUPPORTED_FEATURES.extensions.contains(&Feature::DottedKeys));
+//! assert!(supports("dotted-keys"));
+//! assert!(!supports("inline-objects"));
+//! ```
+//!
+//! Extensions are named like `dotted-keys`, which never changes, so that the
+//! names can be exchanged with other implementations, e.g. through
+//! [SupportedFeatures]' [Encode] implementation. Documents only using the
+//! spec can be checked with [crate::Profile::Strict].
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crate::{encode::Encode, value::Value, FormatVersion};
+
+/// A grammar extension, see the [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+	/// `yes`, `on`, `no` and `off`, see
+	/// [crate::ParserOptions::boolean_aliases].
+	BooleanAliases,
+	/// `~`, see [crate::ParserOptions::null_alias].
+	NullAlias,
+	/// See [crate::ParserOptions::dotted_keys].
+	DottedKeys,
+	/// See [crate::ParserOptions::comment_prefixes].
+	CommentPrefixes,
+	/// See [crate::ParserOptions::lenient_tab_indention].
+	LenientTabIndention,
+	/// See [crate::ParserOptions::blank_lines_in_strings].
+	BlankLinesInStrings,
+	/// `!tag literal`, see [crate::ParserOptions::tags].
+	TaggedLiterals,
+	/// See [crate::ParserOptions::primitive_parser].
+	PrimitiveParsers,
+	/// Requires the `uuid` feature.
+	UuidLiterals,
+	/// Requires the `ipaddr` feature.
+	IpAddressLiterals,
+	/// Requires the `semver` feature.
+	VersionLiterals,
+	/// Durations and byte sizes, requires the `quantities` feature.
+	QuantityLiterals,
+}
+
+impl Feature {
+	/// All extensions, whether supported by this build or not.
+	pub const ALL: &'static [Self] = &[
+		Self::BooleanAliases,
+		Self::NullAlias,
+		Self::DottedKeys,
+		Self::CommentPrefixes,
+		Self::LenientTabIndention,
+		Self::BlankLinesInStrings,
+		Self::TaggedLiterals,
+		Self::PrimitiveParsers,
+		Self::UuidLiterals,
+		Self::IpAddressLiterals,
+		Self::VersionLiterals,
+		Self::QuantityLiterals,
+	];
+
+	/// The stable name of the extension, e.g. `dotted-keys`.
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::BooleanAliases => "boolean-aliases",
+			Self::NullAlias => "null-alias",
+			Self::DottedKeys => "dotted-keys",
+			Self::CommentPrefixes => "comment-prefixes",
+			Self::LenientTabIndention => "lenient-tab-indention",
+			Self::BlankLinesInStrings => "blank-lines-in-strings",
+			Self::TaggedLiterals => "tagged-literals",
+			Self::PrimitiveParsers => "primitive-parsers",
+			Self::UuidLiterals => "uuid-literals",
+			Self::IpAddressLiterals => "ip-address-literals",
+			Self::VersionLiterals => "version-literals",
+			Self::QuantityLiterals => "quantity-literals",
+		}
+	}
+}
+
+impl fmt::Display for Feature {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.name())
+	}
+}
+
+/// Parses the name of an extension.
+impl FromStr for Feature {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::ALL
+			.iter()
+			.copied()
+			.find(|feature| feature.name() == s)
+			.ok_or(())
+	}
+}
+
+/// The spec version and extensions of this build, see
+/// [SUPPORTED_FEATURES].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SupportedFeatures {
+	/// The newest version of the spec documents can declare, see
+	/// [FormatVersion::LATEST].
+	pub spec_version: FormatVersion,
+	pub extensions: &'static [Feature],
+}
+
+/// `spec_version` as a string like `'1.0'` and the names of the
+/// `extensions`.
+impl Encode for SupportedFeatures {
+	fn encode(&self) -> Value {
+		let extensions = self
+			.extensions
+			.iter()
+			.map(|feature| Value::from(feature.name()))
+			.collect();
+		let mut obj = HashMap::new();
+		obj.insert(
+			"spec_version".to_string(),
+			Value::from(self.spec_version.to_string()),
+		);
+		obj.insert("extensions".to_string(), Value::Array(extensions));
+		Value::Object(obj)
+	}
+}
+
+/// What this build of the crate supports. Literals of optional features are
+/// only listed if they are enabled.
+pub const SUPPORTED_FEATURES: SupportedFeatures = SupportedFeatures {
+	spec_version: FormatVersion::LATEST,
+	extensions: &[
+		Feature::BooleanAliases,
+		Feature::NullAlias,
+		Feature::DottedKeys,
+		Feature::CommentPrefixes,
+		Feature::LenientTabIndention,
+		Feature::BlankLinesInStrings,
+		Feature::TaggedLiterals,
+		Feature::PrimitiveParsers,
+		#[cfg(feature = "uuid")]
+		Feature::UuidLiterals,
+		#[cfg(feature = "ipaddr")]
+		Feature::IpAddressLiterals,
+		#[cfg(feature = "semver")]
+		Feature::VersionLiterals,
+		#[cfg(feature = "quantities")]
+		Feature::QuantityLiterals,
+	],
+};
+
+/// Whether this build supports the extension with the given name, e.g.
+/// `dotted-keys`. Unknown names aren't supported.
+pub fn supports(feature: &str) -> bool {
+	SUPPORTED_FEATURES
+		.extensions
+		.iter()
+		.any(|supported| supported.name() == feature)
+}
@@ -0,0 +1,818 @@
+//! Formatting documents in place of their source text, keeping everything
+//! the encoder would drop.
+//!
+//! ```rust
+//! use kvon_rs::{format::{format_str, FormatOptions}, indention::Indention};
+//!
+//! let source = "# servers\nb:\n    port:   80 # http\n\na:  [1   \"x\"]\n";
+//! assert_eq!(
+//!     format_str(source, &FormatOptions::new().indention(Indention::Tabs)).unwrap(),
+//!     "# servers\nb:\n\tport: 80 # http\n\na: [1 'x']\n"
+//! );
+//! ```
+//!
+//! Lines are indented with the configured indention and the whitespace
+//! between tokens is normalized. Inline strings are quoted in the preferred
+//! style where possible. Comments, blank lines, the order of keys and the
+//! text of keys, numbers and the lines of multi-line strings are kept as
+//! they are. Formatting a formatted document doesn't change it.
+//!
+//! Arrays can also be sorted and deduplicated, see
+//! [FormatOptions::sort_arrays] and [FormatOptions::dedup_arrays]. Only the
+//! lines of the entries holding arrays that change are re-encoded, so the
+//! rest of the document keeps its layout. The comment trailing an entry's
+//! first line is kept, while comments on the entry's other lines can't be
+//! and produce a [FormatError::CommentsInArray].
+//!
+//! Projects configure the formatter with a [CONFIG_FILE_NAME] file, which
+//! [FormatOptions::discover] finds for a document:
+//!
+//! ```text
+//! indention: 2 # spaces, or 'tabs'
+//! max_line_width: 80
+//! array_items_per_line: 8
+//! quote_style: 'double'
+//! sort_arrays: ['allowed_ips' 'servers.*.tags']
+//! dedup_arrays: [['plugins' 'name']] # pairs of a path and a key
+//! ```
+
+use std::{
+	fmt, fs, io,
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	decode::{expect_object, field, Decode, DecodeError},
+	encode_string,
+	encoder::{encode_key, quote_inline, wrap_array_items},
+	error::ParserError,
+	indention::Indention,
+	parse_string, parse_string_output,
+	path::{KvonPath, PathSegment},
+	scanner::Scanner,
+	value::{PrimitiveValue, Value},
+	EncodeOptions, ParseOutput, Parser, ParserOptions, QuoteStyle,
+};
+
+/// The name of the files configuring the formatter.
+pub const CONFIG_FILE_NAME: &str = ".kvonfmt";
+
+/// Options for [format_str].
+#[derive(Debug, Clone, Default)]
+pub struct FormatOptions {
+	pub(crate) indention: Option<Indention>,
+	pub(crate) quote_style: QuoteStyle,
+	pub(crate) max_line_width: Option<usize>,
+	pub(crate) array_items_per_line: Option<usize>,
+	pub(crate) sort_arrays: Vec<String>,
+	/// Pairs of a path and a key, see [Value::dedup_arrays].
+	pub(crate) dedup_arrays: Vec<(String, String)>,
+}
+
+impl FormatOptions {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets the indention of the formatted document. By default, documents
+	/// keep their own indention.
+	pub fn indention(mut self, indention: Indention) -> Self {
+		self.indention = Some(indention);
+		self
+	}
+
+	/// Sets the quote character preferred for inline strings. Defaults to
+	/// [QuoteStyle::Single].
+	pub fn quote_style(mut self, style: QuoteStyle) -> Self {
+		self.quote_style = style;
+		self
+	}
+
+	/// Inline arrays longer than `width` are split, like
+	/// [EncodeOptions::max_line_width](crate::EncodeOptions::max_line_width).
+	/// The value of a key becomes an expanded array, and the values of an
+	/// expanded array's line are spread over several lines.
+	pub fn max_line_width(mut self, width: usize) -> Self {
+		self.max_line_width = Some(width);
+		self
+	}
+
+	/// Inline arrays with more than `count` values are split like with
+	/// [FormatOptions::max_line_width].
+	pub fn array_items_per_line(mut self, count: usize) -> Self {
+		self.array_items_per_line = Some(count.max(1));
+		self
+	}
+
+	/// Sorts the arrays at a dotted path like the one of
+	/// [Value::redact_path] in the order of their values.
+	pub fn sort_arrays(mut self, path: impl Into<String>) -> Self {
+		self.sort_arrays.push(path.into());
+		self
+	}
+
+	/// Removes repeated values from the arrays at a dotted path, see
+	/// [Value::dedup_arrays]. Deduplicating happens after sorting.
+	pub fn dedup_arrays(mut self, path: impl Into<String>, key: impl Into<String>) -> Self {
+		self.dedup_arrays.push((path.into(), key.into()));
+		self
+	}
+
+	/// Sorts and deduplicates the arrays of a value. Returns whether that
+	/// changed it.
+	fn canonicalize(&self, value: &mut Value) -> bool {
+		let before = value.clone();
+		for path in &self.sort_arrays {
+			value.sort_arrays_by(path, Ord::cmp);
+		}
+		for (path, key) in &self.dedup_arrays {
+			value.dedup_arrays(path, key);
+		}
+		*value != before
+	}
+
+	/// Reads the options of a [CONFIG_FILE_NAME] document.
+	pub fn from_config(source: &str) -> Result<Self, FormatConfigError> {
+		Ok(Self::decode(&parse_string(source)?)?)
+	}
+
+	/// Reads the [CONFIG_FILE_NAME] file closest to `path`, looking in its
+	/// directory and then in the directories above it. Returns the default
+	/// options if there is none.
+	pub fn discover(path: impl AsRef<Path>) -> Result<Self, FormatConfigError> {
+		match find_config(path.as_ref())? {
+			Some(config) => Self::from_config(&fs::read_to_string(config)?),
+			None => Ok(Self::default()),
+		}
+	}
+}
+
+/// Finds the [CONFIG_FILE_NAME] file for a document or directory.
+pub fn find_config(path: &Path) -> io::Result<Option<PathBuf>> {
+	let path = std::path::absolute(path)?;
+	let start = match path.is_dir() {
+		true => path.as_path(),
+		false => path.parent().unwrap_or(&path),
+	};
+	Ok(start
+		.ancestors()
+		.map(|dir| dir.join(CONFIG_FILE_NAME))
+		.find(|config| config.is_file()))
+}
+
+impl Decode for FormatOptions {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		const KEYS: &[&str] = &[
+			"indention",
+			"quote_style",
+			"max_line_width",
+			"array_items_per_line",
+			"sort_arrays",
+			"dedup_arrays",
+		];
+
+		let obj = expect_object(value)?;
+		if let Some(key) = obj.keys().filter(|key| !KEYS.contains(&key.as_str())).min() {
+			return Err(
+				DecodeError::invalid_value("unknown option").within(PathSegment::Key(key.clone()))
+			);
+		}
+
+		Ok(Self {
+			indention: field(obj, "indention")?,
+			quote_style: field::<Option<QuoteStyle>>(obj, "quote_style")?.unwrap_or_default(),
+			max_line_width: field(obj, "max_line_width")?,
+			array_items_per_line: field::<Option<usize>>(obj, "array_items_per_line")?
+				.map(|count| count.max(1)),
+			sort_arrays: field::<Option<_>>(obj, "sort_arrays")?.unwrap_or_default(),
+			dedup_arrays: field::<Option<_>>(obj, "dedup_arrays")?.unwrap_or_default(),
+		})
+	}
+}
+
+/// `'tabs'` or the number of spaces.
+impl Decode for Indention {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		match value {
+			Value::Primitive(PrimitiveValue::String(s)) if s == "tabs" => Ok(Self::Tabs),
+			Value::Primitive(PrimitiveValue::Number(_)) => match usize::decode(value)? {
+				// lines without indention would move out of their blocks
+				0 => Err(DecodeError::invalid_value("expected at least 1 space")),
+				spaces => Ok(Self::Spaces(spaces)),
+			},
+			_ => Err(DecodeError::invalid_type("'tabs' or a number", value)),
+		}
+	}
+}
+
+/// `'single'` or `'double'`.
+impl Decode for QuoteStyle {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		match String::decode(value)?.as_str() {
+			"single" => Ok(Self::Single),
+			"double" => Ok(Self::Double),
+			other => Err(DecodeError::unknown_variant(other, &["single", "double"])),
+		}
+	}
+}
+
+/// Errors that can happen while reading a [CONFIG_FILE_NAME] file.
+#[derive(Debug)]
+pub enum FormatConfigError {
+	Io(io::Error),
+	Parser(ParserError),
+	Decode(DecodeError),
+}
+
+impl fmt::Display for FormatConfigError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Io(e) => write!(f, "{e}"),
+			Self::Parser(e) => write!(f, "{e}"),
+			Self::Decode(e) => write!(f, "{e}"),
+		}
+	}
+}
+
+impl std::error::Error for FormatConfigError {}
+
+impl From<io::Error> for FormatConfigError {
+	fn from(e: io::Error) -> Self {
+		Self::Io(e)
+	}
+}
+
+impl From<ParserError> for FormatConfigError {
+	fn from(e: ParserError) -> Self {
+		Self::Parser(e)
+	}
+}
+
+impl From<DecodeError> for FormatConfigError {
+	fn from(e: DecodeError) -> Self {
+		Self::Decode(e)
+	}
+}
+
+/// Errors that can happen while formatting a document.
+#[derive(Debug)]
+pub enum FormatError {
+	Parser(ParserError),
+	/// An array that is sorted or deduplicated spans lines with comments,
+	/// which would be lost when its entry is re-encoded.
+	CommentsInArray {
+		/// The entry holding the array.
+		path: KvonPath,
+		/// The line of the first comment.
+		line_number: usize,
+	},
+	/// An entry replaced by [rewrite_entry] spans lines with comments.
+	CommentsInEntry {
+		path: KvonPath,
+		/// The line of the first comment.
+		line_number: usize,
+	},
+}
+
+impl fmt::Display for FormatError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Parser(e) => write!(f, "{e}"),
+			Self::CommentsInArray { path, line_number } => write!(
+				f,
+				"{line_number}: can't sort or deduplicate '{path}' without losing its comments"
+			),
+			Self::CommentsInEntry { path, line_number } => write!(
+				f,
+				"{line_number}: can't replace '{path}' without losing its comments"
+			),
+		}
+	}
+}
+
+impl std::error::Error for FormatError {}
+
+impl From<ParserError> for FormatError {
+	fn from(e: ParserError) -> Self {
+		Self::Parser(e)
+	}
+}
+
+/// What the lines below a line belong to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+	Object,
+	Array,
+	/// A multi-line string, whose lines are kept as they are.
+	String,
+}
+
+/// The formatted tokens of a line.
+struct Formatted {
+	line: String,
+	opened: Option<Block>,
+	/// The `- ...` lines the rest of a split inline array continues on.
+	/// They belong to the array opened by the line, or follow it in the same
+	/// array.
+	continued: Vec<String>,
+}
+
+impl Formatted {
+	fn new(line: String, opened: Option<Block>) -> Self {
+		Self {
+			line,
+			opened,
+			continued: Vec::new(),
+		}
+	}
+}
+
+/// Formats a document. Fails if the document doesn't parse.
+pub fn format_str(source: &str, options: &FormatOptions) -> Result<String, FormatError> {
+	format_str_with_value(source, options).map(|(formatted, _)| formatted)
+}
+
+/// Formats a document like [format_str], also returning the value of the
+/// formatted document. Sorting and deduplicating arrays make it differ from
+/// the value of `source`.
+pub fn format_str_with_value(
+	source: &str,
+	options: &FormatOptions,
+) -> Result<(String, Value), FormatError> {
+	let parser_options = ParserOptions::new();
+	let output = parse_string_output(source, &parser_options)?;
+	let mut value = output.value.clone();
+	if options.canonicalize(&mut value) {
+		let source = rewrite_changed_arrays(source, &output, &value)?;
+		return format_str_with_value(&source, options);
+	}
+	let indention = output.detected_indention.unwrap_or_default();
+	let unit = options.indention.unwrap_or(indention).to_string_unit();
+
+	let mut lines: Vec<String> = Vec::new();
+	// the kind of every block level of the current line, the root first
+	let mut blocks = vec![Block::Object];
+	for (line_number, line) in source.lines().enumerate() {
+		// lines of multi-line strings only get their indention replaced
+		if let Some(level) = blocks.iter().position(|block| *block == Block::String) {
+			let mut scanner = Scanner::new(line_number, line, &parser_options);
+			if scanner.have_indentions(indention, level) {
+				lines.push(unit.repeat(level) + scanner.rest());
+				continue;
+			}
+			blocks.truncate(level);
+		}
+
+		let content = line.trim_start();
+		let level = indent_level(&line[..line.len() - content.len()], indention);
+		if content.is_empty() {
+			lines.push(String::new());
+			continue;
+		}
+
+		let mut scanner = Scanner::new(line_number, content, &parser_options);
+		if scanner.see_end_or_comment() {
+			lines.push(unit.repeat(level) + content.trim_end());
+			continue;
+		}
+
+		blocks.truncate(level + 1);
+		let formatted = match blocks.last() {
+			Some(Block::Array) => format_array_line(&mut scanner, options),
+			_ => format_object_line(&mut scanner, options),
+		};
+		let mut line = unit.repeat(level) + &formatted.line;
+		scanner.consume_whitespaces();
+		if !scanner.reached_end() {
+			line.push(' ');
+			line.push_str(scanner.rest().trim_end());
+		}
+		lines.push(line);
+
+		let continued_level = match formatted.opened {
+			Some(_) => level + 1,
+			None => level,
+		};
+		for continued in formatted.continued {
+			lines.push(unit.repeat(continued_level) + "- " + &continued);
+		}
+		if let Some(block) = formatted.opened {
+			blocks.push(block);
+		}
+	}
+
+	while lines.last().is_some_and(String::is_empty) {
+		lines.pop();
+	}
+	let formatted = match lines.is_empty() {
+		true => String::new(),
+		false => lines.join("\n") + "\n",
+	};
+	Ok((formatted, output.value))
+}
+
+/// The block level of a line's leading whitespace. Comments may be indented
+/// between levels, which rounds down.
+fn indent_level(whitespace: &str, indention: Indention) -> usize {
+	match indention {
+		Indention::Tabs => whitespace.chars().take_while(|c| *c == '\t').count(),
+		Indention::Spaces(spaces) => whitespace.len() / spaces.max(1),
+	}
+}
+
+/// Formats the tokens of a line in an object.
+fn format_object_line(scanner: &mut Scanner, options: &FormatOptions) -> Formatted {
+	let start = scanner.rest();
+	let _ = scanner.parse_key();
+	let mut line = start[..start.len() - scanner.rest().len()].to_string();
+	scanner.consume_whitespaces();
+
+	if scanner.have(":--") {
+		line.push_str(":--");
+		return Formatted::new(line, Some(Block::Array));
+	}
+	if !scanner.have(":") {
+		return Formatted::new(line, None);
+	}
+	line.push(':');
+	format_value(scanner, options, line, true)
+}
+
+/// Formats the tokens of a line in an array.
+fn format_array_line(scanner: &mut Scanner, options: &FormatOptions) -> Formatted {
+	if scanner.have("--") {
+		return Formatted::new("--".to_string(), Some(Block::Array));
+	}
+	scanner.have("-");
+	scanner.consume_whitespaces();
+	let mut line = "-".to_string();
+	if scanner.see_end_or_comment() {
+		return Formatted::new(line, Some(Block::Object));
+	}
+
+	// an object with a single key, whose value can't be an expanded array
+	scanner.record();
+	let start = scanner.rest();
+	if matches!(scanner.parse_key_path_with_colon(), Ok(Some(_))) {
+		scanner.cancel_restore();
+		let key = &start[..start.len() - scanner.rest().len()];
+		// the key without the whitespace before its colon
+		line.push(' ');
+		line.push_str(key[..key.len() - 1].trim_end());
+		line.push(':');
+		return format_value(scanner, options, line, false);
+	}
+	scanner.restore();
+
+	if scanner.have("|") {
+		line.push_str(" |");
+		return Formatted::new(line, Some(Block::String));
+	}
+
+	let mut items = Vec::new();
+	loop {
+		scanner.consume_whitespaces();
+		if scanner.see_end_or_comment() {
+			break;
+		}
+		let mut item = String::new();
+		if !format_inline(scanner, options, &mut item) {
+			break;
+		}
+		items.push(item);
+	}
+	let mut continued =
+		wrap_array_items(&items, options.max_line_width, options.array_items_per_line)
+			.unwrap_or_else(|| vec![items.join(" ")]);
+	line.push(' ');
+	line.push_str(&continued.remove(0));
+	Formatted {
+		line,
+		opened: None,
+		continued,
+	}
+}
+
+/// Formats what follows the colon of a key. Long inline arrays are split
+/// into an expanded array if `may_expand` is set.
+fn format_value(
+	scanner: &mut Scanner,
+	options: &FormatOptions,
+	mut line: String,
+	may_expand: bool,
+) -> Formatted {
+	scanner.consume_whitespaces();
+	if scanner.see_end_or_comment() {
+		return Formatted::new(line, Some(Block::Object));
+	}
+	if scanner.have("|") {
+		line.push_str(" |");
+		return Formatted::new(line, Some(Block::String));
+	}
+
+	if may_expand && scanner.see("[") {
+		scanner.record();
+		let items = format_inline_array_items(scanner, options);
+		let wrapped =
+			wrap_array_items(&items, options.max_line_width, options.array_items_per_line);
+		if let Some(continued) = wrapped {
+			scanner.cancel_restore();
+			line.push_str("--");
+			return Formatted {
+				line,
+				opened: Some(Block::Array),
+				continued,
+			};
+		}
+		scanner.restore();
+	}
+	line.push(' ');
+	format_inline(scanner, options, &mut line);
+	Formatted::new(line, None)
+}
+
+/// Formats the values of an inline array separately.
+fn format_inline_array_items(scanner: &mut Scanner, options: &FormatOptions) -> Vec<String> {
+	scanner.have("[");
+	let mut items = Vec::new();
+	loop {
+		scanner.consume_whitespaces();
+		if scanner.have("]") || scanner.see_end_or_comment() {
+			break;
+		}
+		let mut item = String::new();
+		if !format_inline(scanner, options, &mut item) {
+			break;
+		}
+		items.push(item);
+	}
+	items
+}
+
+/// Formats an inline array or a literal. Returns false if there is none.
+fn format_inline(scanner: &mut Scanner, options: &FormatOptions, line: &mut String) -> bool {
+	if scanner.see("[") {
+		line.push('[');
+		line.push_str(&format_inline_array_items(scanner, options).join(" "));
+		line.push(']');
+		return true;
+	}
+
+	let start = scanner.rest();
+	let Ok(Some(primitive)) = scanner.parse_primitive() else {
+		return false;
+	};
+	let text = &start[..start.len() - scanner.rest().len()];
+	match primitive {
+		PrimitiveValue::String(s) if text.starts_with(['\'', '"']) => {
+			match quote_inline(&s, options.quote_style) {
+				Some(quoted) => line.push_str(&quoted),
+				None => line.push_str(text),
+			}
+		}
+		_ => line.push_str(text),
+	}
+	true
+}
+
+/// Re-encodes the entries of a document holding the arrays that differ
+/// between its value and `canonical`, keeping all other lines.
+fn rewrite_changed_arrays(
+	source: &str,
+	output: &ParseOutput,
+	canonical: &Value,
+) -> Result<String, FormatError> {
+	let mut changed = Vec::new();
+	changed_arrays(
+		&output.value,
+		canonical,
+		&mut KvonPath::root(),
+		0,
+		&mut changed,
+	);
+	rewrite_entries(source, output, canonical, changed).map_err(|e| match e {
+		FormatError::CommentsInEntry { path, line_number } => {
+			FormatError::CommentsInArray { path, line_number }
+		}
+		e => e,
+	})
+}
+
+/// Replaces the entry at `path` in a document with its value in `value`,
+/// keeping all other lines, e.g. to write back a value changed with
+/// [crate::edit::set_input]. Keys missing from the document are added
+/// after the other keys of their object. Paths without an entry of their
+/// own, such as items of inline arrays, replace the innermost entry holding
+/// them, which fails if it spans lines with comments.
+pub fn rewrite_entry(source: &str, value: &Value, path: &KvonPath) -> Result<String, FormatError> {
+	let output = parse_string_output(source, &ParserOptions::new())?;
+	rewrite_entries(source, &output, value, vec![path.clone()])
+}
+
+/// Re-encodes the entries of a document at `paths` with their values in
+/// `value`, keeping all other lines, see [rewrite_entry].
+fn rewrite_entries(
+	source: &str,
+	output: &ParseOutput,
+	value: &Value,
+	paths: Vec<KvonPath>,
+) -> Result<String, FormatError> {
+	let parser_options = ParserOptions::new();
+	let mut parser = Parser::with_options(parser_options.clone()).with_spans();
+	for line in source.lines() {
+		parser.next_line(line)?;
+	}
+	let (_, spans) = parser.finish_with_spans()?;
+
+	let mut lines: Vec<String> = source.lines().map(str::to_string).collect();
+	let indention = output.detected_indention.unwrap_or_default();
+	let encode_options = EncodeOptions::new().indention(indention);
+	let indent = |line: &str| line.len() - line.trim_start().len();
+	// the entry's first line and the more indented lines below it
+	let last_line = |lines: &[String], first: usize| {
+		let mut last = first;
+		for (i, line) in lines.iter().enumerate().skip(first + 1) {
+			if !line.trim().is_empty() && indent(line) <= indent(&lines[first]) {
+				break;
+			}
+			if !line.trim().is_empty() {
+				last = i;
+			}
+		}
+		last
+	};
+	// the encoded lines of a key and its value, the first one without `key:`
+	let encode_entry = |value: &Value| {
+		let encoded = encode_string(&Value::key_value_pair("v", value.clone()), &encode_options);
+		let mut encoded: Vec<String> = encoded
+			.trim_start_matches('\n')
+			.lines()
+			.map(str::to_string)
+			.collect();
+		encoded[0].replace_range(..2, "");
+		encoded
+	};
+	let mut replacements = Vec::new();
+
+	// keys missing from their object are added below its last line, along
+	// with the keys nested in them
+	let mut entries: Vec<KvonPath> = Vec::new();
+	for path in paths {
+		let missing = (1..=path.len()).find(|len| output.value.get_path(&path[..*len]).is_none());
+		let added = missing.map_or(&path[..], |len| &path[..len]);
+		let (Some(new), Some((PathSegment::Key(key), parent))) =
+			(value.get_path(added), added.split_last())
+		else {
+			entries.push(path);
+			continue;
+		};
+		let after = match spans.get(parent) {
+			Some(span) => Some(last_line(&lines, span.line_number) + 1),
+			None if parent.is_empty() => Some(lines.len()),
+			None => None,
+		};
+		let (Some(after), Some(_), Some(Value::Object(siblings))) =
+			(after, missing, output.value.get_path(parent))
+		else {
+			entries.push(path);
+			continue;
+		};
+		// indented like the other keys, or one level deeper than the object
+		let prefix = match siblings.keys().find_map(|key| {
+			let mut sibling = KvonPath::from(parent);
+			sibling.push(PathSegment::Key(key.clone()));
+			spans.get(&sibling)
+		}) {
+			Some(span) => lines[span.line_number][..indent(&lines[span.line_number])].to_string(),
+			None if parent.is_empty() => String::new(),
+			None => {
+				let line = &lines[spans[parent].line_number];
+				line[..indent(line)].to_string() + &indention.to_string_unit()
+			}
+		};
+		let Some(key) = encode_key(key, &encode_options) else {
+			entries.push(path);
+			continue;
+		};
+		let mut added = encode_entry(new);
+		added[0] = format!("{key}:{}", added[0]);
+		let added = added
+			.into_iter()
+			.map(|line| prefix.clone() + &line)
+			.collect();
+		replacements.push((after..after, added));
+	}
+
+	// the entries holding the paths, which are the values starting at a key,
+	// outer entries first
+	for path in std::mem::take(&mut entries) {
+		let len = (0..=path.len())
+			.rev()
+			.find(|len| {
+				*len == 0
+					|| matches!(path[len - 1], PathSegment::Key(_))
+						&& spans.contains_key(&path[..*len])
+			})
+			.unwrap_or_default();
+		entries.push(KvonPath::from(&path[..len]));
+	}
+	entries.sort();
+	entries.dedup();
+	entries.dedup_by(|inner, outer| inner.starts_with(outer));
+
+	// the line of the first comment between two lines
+	let comments_in = |first: usize, last: usize| {
+		output
+			.comments
+			.iter()
+			.map(|comment| comment.span.line_number)
+			.find(|line_number| (first..=last).contains(line_number))
+	};
+	for path in entries {
+		let Some(span) = spans.get(&path) else {
+			// the root, which is only replaced as a whole
+			if let Some(line_number) = comments_in(0, lines.len()) {
+				return Err(FormatError::CommentsInEntry { path, line_number });
+			}
+			let encoded = encode_string(value, &encode_options);
+			return Ok(encoded.trim_start_matches('\n').to_string() + "\n");
+		};
+
+		let first = span.line_number;
+		let last = last_line(&lines, first);
+		if let Some(line_number) = comments_in(first + 1, last) {
+			return Err(FormatError::CommentsInEntry { path, line_number });
+		}
+
+		// the line up to the key's colon is kept, and so is its comment
+		let line = &lines[first];
+		let mut scanner = Scanner::new(span.line_number, line, &parser_options);
+		scanner.advance_by(span.column_number);
+		let _ = scanner.parse_key_path_with_colon();
+		let head = &line[..line.len() - scanner.rest().len()];
+		let comment = output
+			.comments
+			.iter()
+			.find(|comment| comment.span.line_number == span.line_number)
+			.map(|comment| &line[comment.span.column_number..]);
+
+		let Some(value) = value.get_path(&path) else {
+			continue;
+		};
+		let mut replaced = encode_entry(value);
+		replaced[0] = head.to_string() + &replaced[0];
+		if let Some(comment) = comment {
+			replaced[0].push(' ');
+			replaced[0].push_str(comment);
+		}
+		let prefix = &line[..indent(line)];
+		for line in &mut replaced[1..] {
+			line.insert_str(0, prefix);
+		}
+		replacements.push((first..last + 1, replaced));
+	}
+
+	// replaced from the bottom, so that the line numbers of the entries
+	// above stay valid
+	replacements.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+	for (range, replaced) in replacements {
+		lines.splice(range, replaced);
+	}
+	Ok(lines.join("\n") + "\n")
+}
+
+/// Collects the paths of the innermost arrays holding the differences
+/// between two values. `array` is the length of the path of the innermost
+/// array around the values.
+fn changed_arrays(
+	before: &Value,
+	after: &Value,
+	path: &mut KvonPath,
+	array: usize,
+	changed: &mut Vec<KvonPath>,
+) {
+	if before == after {
+		return;
+	}
+	match (before, after) {
+		(Value::Object(a), Value::Object(b))
+			if a.len() == b.len() && a.keys().all(|key| b.contains_key(key)) =>
+		{
+			for (key, value) in a {
+				path.push(PathSegment::Key(key.clone()));
+				changed_arrays(value, &b[key], path, array, changed);
+				path.pop();
+			}
+		}
+		(Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+			let array = path.len();
+			for (i, (a, b)) in a.iter().zip(b).enumerate() {
+				path.push(PathSegment::Index(i));
+				changed_arrays(a, b, path, array, changed);
+				path.pop();
+			}
+		}
+		(Value::Array(_), Value::Array(_)) => changed.push(path.clone()),
+		_ => changed.push(KvonPath::from(&path[..array])),
+	}
+}
@@ -1,11 +1,22 @@
 /// The indents used in the encoded string. Can be either a constant amount of
 /// spaces, or a tab.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
 pub enum Indention {
+	#[default]
 	Tabs,
 	Spaces(usize),
 }
 
+impl Indention {
+	/// The string making up a single level of indention.
+	pub fn to_string_unit(&self) -> String {
+		match self {
+			Self::Tabs => "\t".to_string(),
+			Self::Spaces(spaces) => " ".repeat(*spaces),
+		}
+	}
+}
+
 impl std::fmt::Display for Indention {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
@@ -14,9 +25,3 @@ impl std::fmt::Display for Indention {
 		}
 	}
 }
-
-impl std::default::Default for Indention {
-	fn default() -> Self {
-		Indention::Tabs
-	}
-}
@@ -0,0 +1,389 @@
+//! Best-effort conversion between INI files and values, to migrate old
+//! configs to KVON.
+//!
+//! ```rust
+//! use kvon_rs::{ini::{from_ini, to_ini}, object};
+//!
+//! let import = from_ini(
+//!     "name = app\n\n[server]\nport = 8080 ; default is 80\n\n[server.tls]\nenabled = true\n",
+//! );
+//! assert!(import.warnings.is_empty());
+//! let config = object! { name: "app", server: { port: 8080, tls: { enabled: true } } };
+//! assert_eq!(import.value, config);
+//!
+//! assert_eq!(
+//!     to_ini(&config).unwrap(),
+//!     "name = app\n\n[server]\nport = 8080\n\n[server.tls]\nenabled = true\n"
+//! );
+//! ```
+//!
+//! Sections become nested objects, where `[a.b]` and `[a "b"]` are both
+//! the object at `a.b`. Keys before the first section belong to the root
+//! object, and `key[] = value` lines add items to the array at `key`.
+//! Unquoted values are typed like [crate::env] variables, so `true`, `false`,
+//! `null` and numbers become primitives of that type, while values in `"` or
+//! `'` are strings. Comments starting with `;` or `#` are dropped.
+//!
+//! Constructs that don't map cleanly are reported as [IniWarning]s instead
+//! of failing the import, so that they can be fixed by hand afterwards.
+
+use std::{collections::HashMap, fmt};
+
+use crate::{
+	env::{parse_var_value, var_value},
+	path::{KvonPath, PathSegment},
+	value::{PrimitiveValue, Value},
+};
+
+/// A construct of an INI file that was imported with losses, see
+/// [from_ini].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IniWarning {
+	/// A line that is neither a section, a key-value pair nor a comment,
+	/// e.g. a block of an nginx config. It is skipped.
+	UnreadableLine { line_number: usize },
+	/// A key set more than once in the same section. The last value is used.
+	DuplicateKey { path: KvonPath, line_number: usize },
+	/// A key without `=`, e.g. the `skip-networking` flags of MySQL. It is
+	/// read as `true`.
+	KeyWithoutValue { path: KvonPath, line_number: usize },
+	/// A section or key replacing a value of another type, e.g. `[a]` after
+	/// `a = 1`, or `a[] = 1` after `a = 1`.
+	Conflict { path: KvonPath, line_number: usize },
+}
+
+impl fmt::Display for IniWarning {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::UnreadableLine { line_number } => {
+				write!(f, "{line_number}: unreadable line, skipping it")
+			}
+			Self::DuplicateKey { path, line_number } => {
+				write!(
+					f,
+					"{line_number}: duplicate key {path}, replacing the previous value"
+				)
+			}
+			Self::KeyWithoutValue { path, line_number } => {
+				write!(f, "{line_number}: key {path} has no value, using true")
+			}
+			Self::Conflict { path, line_number } => {
+				write!(f, "{line_number}: {path} replaces a value of another type")
+			}
+		}
+	}
+}
+
+/// The value of an INI file and what couldn't be imported cleanly, see
+/// [from_ini].
+#[derive(Debug, Clone, PartialEq)]
+pub struct IniImport {
+	pub value: Value,
+	pub warnings: Vec<IniWarning>,
+}
+
+/// Reads an INI file, see the [module documentation](self).
+pub fn from_ini(source: &str) -> IniImport {
+	let mut root = Value::empty_object();
+	let mut warnings = Vec::new();
+	let mut section = KvonPath::root();
+	// the keys set in each section so far, to detect duplicates
+	let mut seen: HashMap<KvonPath, usize> = HashMap::new();
+
+	for (line_number, line) in source.lines().enumerate() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with([';', '#']) {
+			continue;
+		}
+
+		if let Some(name) = line.strip_prefix('[') {
+			let Some(name) = section_name(name) else {
+				warnings.push(IniWarning::UnreadableLine { line_number });
+				continue;
+			};
+			section = name;
+			if object_at(&mut root, &section) {
+				warnings.push(IniWarning::Conflict {
+					path: section.clone(),
+					line_number,
+				});
+			}
+			continue;
+		}
+
+		let (key, value) = match line.split_once('=') {
+			Some((key, value)) => (key.trim(), Some(value.trim())),
+			None => (strip_comment(line), None),
+		};
+		let (key, append) = match key.strip_suffix("[]") {
+			Some(key) => (key.trim_end(), true),
+			None => (key, false),
+		};
+		// keys without values are words, unlike e.g. the `}` of a block
+		let is_word = key
+			.chars()
+			.all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'));
+		if key.is_empty() || (value.is_none() && !is_word) {
+			warnings.push(IniWarning::UnreadableLine { line_number });
+			continue;
+		}
+		let path = section.clone().key(key);
+		let value = match value {
+			Some(value) => ini_value(value),
+			None => {
+				warnings.push(IniWarning::KeyWithoutValue {
+					path: path.clone(),
+					line_number,
+				});
+				Value::from(true)
+			}
+		};
+
+		// the section exists, as it was created by its header
+		let Some(Value::Object(obj)) = get_mut(&mut root, &section) else {
+			unreachable!()
+		};
+		let previous = obj.get_mut(key);
+		match (append, previous) {
+			(true, Some(Value::Array(arr))) => arr.push(value),
+			(true, previous) => {
+				if previous.is_some() {
+					warnings.push(IniWarning::Conflict { path, line_number });
+				}
+				obj.insert(key.to_string(), Value::Array(vec![value]));
+			}
+			(false, previous) => {
+				if previous.is_some() {
+					let warning = match seen.contains_key(&path) {
+						true => IniWarning::DuplicateKey {
+							path: path.clone(),
+							line_number,
+						},
+						false => IniWarning::Conflict {
+							path: path.clone(),
+							line_number,
+						},
+					};
+					warnings.push(warning);
+				}
+				obj.insert(key.to_string(), value);
+				seen.insert(path, line_number);
+			}
+		}
+	}
+
+	IniImport {
+		value: root,
+		warnings,
+	}
+}
+
+/// Reads the path of a `[a.b]` or `[a "b"]` section header after the `[`.
+fn section_name(name: &str) -> Option<KvonPath> {
+	let name = strip_comment(name).strip_suffix(']')?.trim();
+	let (name, subsection) = match name.split_once(char::is_whitespace) {
+		Some((name, subsection)) => {
+			let subsection = subsection.trim();
+			let subsection = subsection.strip_prefix('"')?.strip_suffix('"')?;
+			(name, Some(subsection))
+		}
+		None => (name, None),
+	};
+	if name.is_empty() || name.split('.').any(str::is_empty) {
+		return None;
+	}
+	let mut path: KvonPath = name
+		.split('.')
+		.map(|key| PathSegment::Key(key.to_string()))
+		.collect();
+	if let Some(subsection) = subsection {
+		path.push(PathSegment::Key(subsection.to_string()));
+	}
+	Some(path)
+}
+
+/// Makes the value at `path` an object, creating missing ones. Returns true
+/// if a value of another type was replaced.
+fn object_at(root: &mut Value, path: &[PathSegment]) -> bool {
+	let mut replaced = false;
+	let mut target = root;
+	for segment in path {
+		let PathSegment::Key(key) = segment else {
+			unreachable!("sections only have keys")
+		};
+		let Value::Object(obj) = target else {
+			unreachable!()
+		};
+		let entry = obj.entry(key.clone()).or_insert_with(Value::empty_object);
+		if !entry.is_object() {
+			*entry = Value::empty_object();
+			replaced = true;
+		}
+		target = entry;
+	}
+	replaced
+}
+
+fn get_mut<'a>(root: &'a mut Value, path: &[PathSegment]) -> Option<&'a mut Value> {
+	path.iter()
+		.try_fold(root, |value, segment| match (value, segment) {
+			(Value::Object(obj), PathSegment::Key(key)) => obj.get_mut(key),
+			_ => None,
+		})
+}
+
+/// Removes a `;` or `#` comment following whitespace.
+fn strip_comment(s: &str) -> &str {
+	let end = s
+		.char_indices()
+		.find(|&(i, c)| matches!(c, ';' | '#') && s[..i].ends_with(char::is_whitespace))
+		.map_or(s.len(), |(i, _)| i);
+	s[..end].trim_end()
+}
+
+fn ini_value(value: &str) -> Value {
+	if let Some(quote @ ('"' | '\'')) = value.chars().next() {
+		if let Some(end) = value[1..].find(quote) {
+			let rest = value[end + 2..].trim_start();
+			if rest.is_empty() || rest.starts_with([';', '#']) {
+				return Value::from(&value[1..end + 1]);
+			}
+		}
+	}
+	parse_var_value(strip_comment(value).to_string())
+}
+
+/// A value that an INI file can't hold, see [to_ini].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IniError {
+	/// The location of the value.
+	pub path: KvonPath,
+	pub message: &'static str,
+}
+
+impl fmt::Display for IniError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if !self.path.is_empty() {
+			write!(f, "{}: ", self.path)?;
+		}
+		write!(f, "{}", self.message)
+	}
+}
+
+impl std::error::Error for IniError {}
+
+/// Writes an object as an INI file that [from_ini] reads back. Nested
+/// objects become sections named by their path, e.g. `[server.tls]`, and
+/// arrays of primitives `key[] = value` lines. Keys are sorted.
+///
+/// Fails for values that INI files can't hold, e.g. arrays of objects,
+/// multi-line strings or keys containing `=`.
+pub fn to_ini(value: &Value) -> Result<String, IniError> {
+	let mut sections = Vec::new();
+	collect_sections(value, &mut KvonPath::root(), &mut sections)?;
+	let sections: Vec<String> = sections
+		.into_iter()
+		.map(|(path, lines)| match path.is_empty() {
+			true => lines,
+			false => format!("[{path}]\n{lines}"),
+		})
+		.collect();
+	Ok(sections.join("\n"))
+}
+
+/// Collects the lines of the section of an object and its nested objects.
+fn collect_sections(
+	value: &Value,
+	path: &mut KvonPath,
+	sections: &mut Vec<(KvonPath, String)>,
+) -> Result<(), IniError> {
+	let error = |path: &KvonPath, message| IniError {
+		path: path.clone(),
+		message,
+	};
+	let Value::Object(obj) = value else {
+		return Err(error(path, "expected an object"));
+	};
+	let mut keys: Vec<&String> = obj.keys().collect();
+	keys.sort();
+
+	let mut lines = String::new();
+	let mut nested = Vec::new();
+	for key in keys {
+		path.push(PathSegment::Key(key.clone()));
+		let is_section_name = !key.is_empty()
+			&& !key.contains(['.', '[', ']', '"'])
+			&& !key.contains(char::is_whitespace);
+		let is_key = !key.is_empty()
+			&& !key.starts_with(['[', ';', '#'])
+			&& !key.contains(['=', '\n'])
+			&& key.trim() == key;
+		match &obj[key] {
+			Value::Object(_) if !is_section_name => {
+				return Err(error(
+					path,
+					"section names can't contain '.', brackets, quotes or whitespace",
+				));
+			}
+			Value::Object(_) => nested.push(key),
+			_ if !is_key => {
+				return Err(error(path, "keys can't contain '=' or line breaks"));
+			}
+			Value::Array(arr) => {
+				for (i, item) in arr.iter().enumerate() {
+					path.push(PathSegment::Index(i));
+					let item = match item {
+						Value::Primitive(p) => {
+							write_value(p).map_err(|message| error(path, message))?
+						}
+						_ => return Err(error(path, "arrays can only hold primitives")),
+					};
+					path.pop();
+					lines.push_str(&format!("{key}[] = {item}\n"));
+				}
+			}
+			Value::Primitive(p) => {
+				let value = write_value(p).map_err(|message| error(path, message))?;
+				lines.push_str(&format!("{key} = {value}\n"));
+			}
+			Value::Raw(_) => return Err(error(path, "raw values can't be written")),
+		}
+		path.pop();
+	}
+
+	// objects holding only objects don't need a header of their own, but
+	// empty ones do
+	let is_empty = lines.is_empty() && nested.is_empty();
+	if !lines.is_empty() || (is_empty && !path.is_empty()) {
+		sections.push((path.clone(), lines));
+	}
+	for key in nested {
+		path.push(PathSegment::Key(key.clone()));
+		collect_sections(&obj[key], path, sections)?;
+		path.pop();
+	}
+	Ok(())
+}
+
+/// Writes a primitive, quoting strings that would otherwise be read as
+/// something else.
+fn write_value(p: &PrimitiveValue) -> Result<String, &'static str> {
+	let value = var_value(p);
+	if value.contains(['\n', '\r']) {
+		return Err("multi-line strings can't be written");
+	}
+	let PrimitiveValue::String(s) = p else {
+		return Ok(value);
+	};
+	let is_plain = !s.is_empty()
+		&& s.trim() == s
+		&& !s.starts_with(['"', '\''])
+		&& !s.contains([';', '#'])
+		&& parse_var_value(s.clone()) == Value::from(s.as_str());
+	match is_plain {
+		true => Ok(value),
+		false if !s.contains('"') => Ok(format!("\"{s}\"")),
+		false if !s.contains('\'') => Ok(format!("'{s}'")),
+		false => Err("strings containing both kinds of quotes can't be written"),
+	}
+}
@@ -0,0 +1,486 @@
+//! Converting [Schema]s to and from a subset of
+//! [JSON Schema](https://json-schema.org/) (draft 2020-12). JSON Schemas are
+//! represented as [Value]s, which the `serde` feature converts to and from
+//! JSON, e.g. with `serde_json`.
+//!
+//! ```rust
+//! use kvon_rs::{
+//!     json_schema::{from_json_schema, to_json_schema},
+//!     parse_string,
+//! };
+//!
+//! let json_schema = parse_string(
+//!     "
+//! type: 'object'
+//! properties:
+//!     port:
+//!         type: 'integer'
+//!         maximum: 65535
+//! required: ['port']
+//! additionalProperties: false
+//! ",
+//! )
+//! .unwrap();
+//!
+//! let schema = from_json_schema(&json_schema).unwrap();
+//! let violations = schema.validate(&parse_string("port: 70000").unwrap());
+//! assert_eq!(violations[0].to_string(), "port: 70000 is greater than 65535");
+//!
+//! let exported = to_json_schema(&schema);
+//! assert_eq!(
+//!     exported.get_objects().unwrap()["properties"],
+//!     json_schema.get_objects().unwrap()["properties"]
+//! );
+//! ```
+//!
+//! Keywords without a KVON equivalent, e.g. `allOf` or `format`, are
+//! ignored when importing, so the imported schema may accept more values than
+//! the original.
+
+use std::{borrow::Cow, collections::HashMap, fmt};
+
+use crate::{
+	schema::{Constraint, Schema, SchemaRef},
+	value::{PrimitiveValue, Value},
+};
+
+/// A JSON Schema that can't be converted, see [from_json_schema].
+#[derive(Debug, Clone)]
+pub struct JsonSchemaError {
+	/// The JSON pointer of the schema containing the error, e.g.
+	/// `/properties/port`.
+	pub pointer: String,
+	pub kind: JsonSchemaErrorKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum JsonSchemaErrorKind {
+	/// A keyword has a value of the wrong type.
+	InvalidKeyword {
+		keyword: String,
+		expected: &'static str,
+	},
+	/// The schema is `false`, which doesn't accept any value.
+	Never,
+	UnknownType(String),
+	InvalidPattern(regex::Error),
+	/// A `$ref` that doesn't point into the same document.
+	UnresolvedRef(String),
+}
+
+impl fmt::Display for JsonSchemaError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if !self.pointer.is_empty() {
+			write!(f, "{}: ", self.pointer)?;
+		}
+		match &self.kind {
+			JsonSchemaErrorKind::InvalidKeyword { keyword, expected } => {
+				write!(f, "expected {expected} for '{keyword}'")
+			}
+			JsonSchemaErrorKind::Never => {
+				write!(f, "schemas rejecting every value aren't supported")
+			}
+			JsonSchemaErrorKind::UnknownType(name) => write!(f, "unknown type '{name}'"),
+			JsonSchemaErrorKind::InvalidPattern(e) => write!(f, "invalid pattern: {e}"),
+			JsonSchemaErrorKind::UnresolvedRef(reference) => {
+				write!(f, "can't resolve reference '{reference}'")
+			}
+		}
+	}
+}
+
+impl std::error::Error for JsonSchemaError {}
+
+/// Converts a schema to a JSON Schema. Recursive schemas accept any value
+/// where they repeat.
+pub fn to_json_schema(schema: &Schema) -> Value {
+	let mut json = export(schema, &mut Vec::new());
+	if let Value::Object(obj) = &mut json {
+		obj.insert(
+			"$schema".to_string(),
+			Value::from("https://json-schema.org/draft/2020-12/schema"),
+		);
+	}
+	json
+}
+
+/// Converts a nested schema, where `stack` holds the functions of the
+/// schemas containing it.
+fn export_ref(schema: &SchemaRef, stack: &mut Vec<usize>) -> Value {
+	match schema {
+		SchemaRef::Fn(f) => {
+			let address = *f as usize;
+			if stack.contains(&address) {
+				return Value::empty_object();
+			}
+			stack.push(address);
+			let json = export(&f(), stack);
+			stack.pop();
+			json
+		}
+		SchemaRef::Owned(schema) => export(schema, stack),
+	}
+}
+
+fn export(schema: &Schema, stack: &mut Vec<usize>) -> Value {
+	let mut obj = HashMap::new();
+	let mut typed = |name: &str| {
+		obj.insert("type".to_string(), Value::from(name));
+	};
+	match schema {
+		Schema::Any => {}
+		Schema::Number => typed("number"),
+		Schema::Boolean => typed("boolean"),
+		Schema::String => typed("string"),
+		Schema::Object { fields, open } => {
+			typed("object");
+			let mut properties = HashMap::new();
+			let mut required = Vec::new();
+			for (key, schema) in fields {
+				if !matches!(schema.get().as_ref(), Schema::Optional(_)) {
+					required.push(Value::from(key.to_string()));
+				}
+				properties.insert(key.to_string(), export_ref(schema, stack));
+			}
+			obj.insert("properties".to_string(), Value::Object(properties));
+			if !required.is_empty() {
+				obj.insert("required".to_string(), Value::Array(required));
+			}
+			if !open {
+				obj.insert("additionalProperties".to_string(), Value::from(false));
+			}
+		}
+		Schema::Map(schema) => {
+			typed("object");
+			obj.insert(
+				"additionalProperties".to_string(),
+				export_ref(schema, stack),
+			);
+		}
+		Schema::Array(schema) => {
+			typed("array");
+			obj.insert("items".to_string(), export_ref(schema, stack));
+		}
+		Schema::Optional(schema) => {
+			let null = Value::Object(HashMap::from([("type".to_string(), Value::from("null"))]));
+			obj.insert(
+				"anyOf".to_string(),
+				Value::Array(vec![export_ref(schema, stack), null]),
+			);
+		}
+		Schema::Constrained(schema, constraints) => {
+			let Value::Object(mut obj) = export(schema, stack) else {
+				unreachable!()
+			};
+			for constraint in constraints {
+				let (keyword, value) = match constraint {
+					Constraint::Pattern(regex) => ("pattern", Value::from(regex.as_str())),
+					Constraint::MinLength(n) => ("minLength", Value::from(*n as f32)),
+					Constraint::MaxLength(n) => ("maxLength", Value::from(*n as f32)),
+					Constraint::OneOf(values) => (
+						"enum",
+						Value::Array(values.iter().map(|v| Value::from(v.as_str())).collect()),
+					),
+					Constraint::Minimum(n) => ("minimum", Value::from(*n)),
+					Constraint::Maximum(n) => ("maximum", Value::from(*n)),
+					Constraint::ExclusiveMinimum(n) => ("exclusiveMinimum", Value::from(*n)),
+					Constraint::ExclusiveMaximum(n) => ("exclusiveMaximum", Value::from(*n)),
+					// whole numbers are exported as integers below
+					Constraint::MultipleOf(n) if *n == 1. => continue,
+					Constraint::MultipleOf(n) => ("multipleOf", Value::from(*n)),
+				};
+				obj.insert(keyword.to_string(), value);
+			}
+			let whole = constraints
+				.iter()
+				.any(|c| matches!(c, Constraint::MultipleOf(n) if *n == 1.));
+			if whole && matches!(schema.as_ref(), Schema::Number) {
+				obj.insert("type".to_string(), Value::from("integer"));
+			}
+			return Value::Object(obj);
+		}
+	}
+	Value::Object(obj)
+}
+
+/// Converts a JSON Schema to a schema. Supports the keywords `type`,
+/// `properties`, `required`, `additionalProperties`, `items`, `anyOf` and
+/// `oneOf` with `null`, `enum` of strings, the string and number
+/// constraints, and `$ref`s to `$defs` in the same document.
+pub fn from_json_schema(json: &Value) -> Result<Schema, JsonSchemaError> {
+	Importer {
+		root: json,
+		refs: Vec::new(),
+	}
+	.import(json, &mut String::new())
+}
+
+struct Importer<'a> {
+	root: &'a Value,
+	/// The references being imported, to stop at recursive references.
+	refs: Vec<String>,
+}
+
+impl Importer<'_> {
+	fn import(&mut self, json: &Value, pointer: &mut String) -> Result<Schema, JsonSchemaError> {
+		let error = |pointer: &str, kind| JsonSchemaError {
+			pointer: pointer.to_string(),
+			kind,
+		};
+		let invalid = |pointer: &str, keyword: &str, expected| {
+			error(
+				pointer,
+				JsonSchemaErrorKind::InvalidKeyword {
+					keyword: keyword.to_string(),
+					expected,
+				},
+			)
+		};
+
+		let obj = match json {
+			Value::Primitive(PrimitiveValue::Boolean(true)) => return Ok(Schema::Any),
+			Value::Primitive(PrimitiveValue::Boolean(false)) => {
+				return Err(error(pointer, JsonSchemaErrorKind::Never))
+			}
+			Value::Object(obj) => obj,
+			_ => return Err(invalid(pointer, "", "an object or a boolean")),
+		};
+
+		if let Some(reference) = obj.get("$ref") {
+			let Some(reference) = reference
+				.get_primitive()
+				.ok()
+				.and_then(|p| p.get_string().ok())
+			else {
+				return Err(invalid(pointer, "$ref", "a string"));
+			};
+			let reference = reference.to_string();
+			if self.refs.contains(&reference) {
+				return Ok(Schema::Any);
+			}
+			let target = resolve(self.root, &reference).ok_or_else(|| {
+				error(
+					pointer,
+					JsonSchemaErrorKind::UnresolvedRef(reference.clone()),
+				)
+			})?;
+			self.refs.push(reference.clone());
+			let mut target_pointer = reference.trim_start_matches('#').to_string();
+			let schema = self.import(target, &mut target_pointer);
+			self.refs.pop();
+			return schema;
+		}
+
+		for keyword in ["anyOf", "oneOf"] {
+			let Some(alternatives) = obj.get(keyword) else {
+				continue;
+			};
+			let Value::Array(alternatives) = alternatives else {
+				return Err(invalid(pointer, keyword, "an array"));
+			};
+			let (nulls, others): (Vec<_>, Vec<_>) =
+				alternatives
+					.iter()
+					.enumerate()
+					.partition(|(_, alternative)| {
+						alternative.get_objects().is_ok_and(|obj| {
+							obj.len() == 1
+								&& obj
+									.get("type")
+									.and_then(|t| t.get_primitive().ok())
+									.is_some_and(|t| t.get_string().is_ok_and(|t| t == "null"))
+						})
+					});
+			return match others.as_slice() {
+				[(i, alternative)] => {
+					let len = pointer.len();
+					pointer.push_str(&format!("/{keyword}/{i}"));
+					let schema = self.import(alternative, pointer)?;
+					pointer.truncate(len);
+					Ok(match nulls.is_empty() {
+						true => schema,
+						false => Schema::Optional(schema.into()),
+					})
+				}
+				_ => Ok(Schema::Any),
+			};
+		}
+
+		let mut types = Vec::new();
+		match obj.get("type") {
+			None => {}
+			Some(Value::Primitive(PrimitiveValue::String(name))) => types.push(name.as_str()),
+			Some(Value::Array(names)) => {
+				for name in names {
+					match name {
+						Value::Primitive(PrimitiveValue::String(name)) => types.push(name),
+						_ => {
+							return Err(invalid(pointer, "type", "a string or an array of strings"))
+						}
+					}
+				}
+			}
+			Some(_) => return Err(invalid(pointer, "type", "a string or an array of strings")),
+		}
+		let nullable = types.contains(&"null");
+		types.retain(|name| *name != "null");
+		if types.is_empty() && obj.contains_key("enum") {
+			types.push("string");
+		}
+
+		let mut constraints = Vec::new();
+		let mut schema = match types.as_slice() {
+			["number"] => Schema::Number,
+			["integer"] => {
+				constraints.push(Constraint::MultipleOf(1.));
+				Schema::Number
+			}
+			["boolean"] => Schema::Boolean,
+			["string"] => Schema::String,
+			["array"] => match obj.get("items") {
+				Some(items) => {
+					let len = pointer.len();
+					pointer.push_str("/items");
+					let items = self.import(items, pointer)?;
+					pointer.truncate(len);
+					Schema::Array(items.into())
+				}
+				None => Schema::Array(Schema::Any.into()),
+			},
+			["object"] => self.import_object(obj, pointer)?,
+			[name] => {
+				return Err(error(
+					pointer,
+					JsonSchemaErrorKind::UnknownType(name.to_string()),
+				))
+			}
+			_ => Schema::Any,
+		};
+
+		for (keyword, value) in obj {
+			let number = || {
+				value
+					.get_primitive()
+					.ok()
+					.and_then(|p| p.get_number().ok())
+					.ok_or_else(|| invalid(pointer, keyword, "a number"))
+			};
+			let constraint = match keyword.as_str() {
+				"pattern" => {
+					let Some(pattern) =
+						value.get_primitive().ok().and_then(|p| p.get_string().ok())
+					else {
+						return Err(invalid(pointer, keyword, "a string"));
+					};
+					Constraint::pattern(pattern)
+						.map_err(|e| error(pointer, JsonSchemaErrorKind::InvalidPattern(e)))?
+				}
+				"minLength" => Constraint::MinLength(number()? as usize),
+				"maxLength" => Constraint::MaxLength(number()? as usize),
+				"minimum" => Constraint::Minimum(number()?),
+				"maximum" => Constraint::Maximum(number()?),
+				"exclusiveMinimum" => Constraint::ExclusiveMinimum(number()?),
+				"exclusiveMaximum" => Constraint::ExclusiveMaximum(number()?),
+				"multipleOf" => Constraint::MultipleOf(number()?),
+				"enum" => {
+					let values: Option<Vec<String>> = value.get_vector().ok().and_then(|values| {
+						values
+							.iter()
+							.map(|v| Some(v.get_primitive().ok()?.get_string().ok()?.to_string()))
+							.collect()
+					});
+					match values {
+						Some(values) => Constraint::OneOf(values),
+						None => return Err(invalid(pointer, keyword, "an array of strings")),
+					}
+				}
+				_ => continue,
+			};
+			constraints.push(constraint);
+		}
+		// keywords are unordered, so sort the constraints for stable output
+		constraints.sort_by_key(|c| c.to_string());
+		for constraint in constraints {
+			schema = schema.constrain(constraint);
+		}
+
+		Ok(match nullable {
+			true => Schema::Optional(schema.into()),
+			false => schema,
+		})
+	}
+
+	fn import_object(
+		&mut self,
+		obj: &HashMap<String, Value>,
+		pointer: &mut String,
+	) -> Result<Schema, JsonSchemaError> {
+		let len = pointer.len();
+		// `None` if no additional properties are allowed
+		let additional = match obj.get("additionalProperties") {
+			None => Some(Schema::Any),
+			Some(Value::Primitive(PrimitiveValue::Boolean(false))) => None,
+			Some(additional) => {
+				pointer.push_str("/additionalProperties");
+				let additional = self.import(additional, pointer)?;
+				pointer.truncate(len);
+				Some(additional)
+			}
+		};
+
+		let Some(properties) = obj.get("properties") else {
+			return Ok(match additional {
+				Some(additional) => Schema::Map(additional.into()),
+				None => Schema::object::<&str, Schema>([]),
+			});
+		};
+		let Value::Object(properties) = properties else {
+			return Err(JsonSchemaError {
+				pointer: pointer.clone(),
+				kind: JsonSchemaErrorKind::InvalidKeyword {
+					keyword: "properties".to_string(),
+					expected: "an object",
+				},
+			});
+		};
+		let required: Vec<&str> = obj
+			.get("required")
+			.and_then(|required| required.get_vector().ok())
+			.map(|required| {
+				required
+					.iter()
+					.filter_map(|key| key.get_primitive().ok()?.get_string().ok())
+					.collect()
+			})
+			.unwrap_or_default();
+
+		let mut keys: Vec<_> = properties.keys().collect();
+		keys.sort();
+		let mut fields = Vec::new();
+		for key in keys {
+			pointer.push_str(&format!("/properties/{key}"));
+			let mut schema = self.import(&properties[key], pointer)?;
+			pointer.truncate(len);
+			if !required.contains(&key.as_str()) && !matches!(schema, Schema::Optional(_)) {
+				schema = Schema::Optional(schema.into());
+			}
+			fields.push((Cow::Owned(key.clone()), SchemaRef::from(schema)));
+		}
+		Ok(Schema::Object {
+			fields,
+			// objects can't restrict additional properties to a schema
+			open: additional.is_some(),
+		})
+	}
+}
+
+/// Returns the schema a `$ref` like `#/$defs/server` points to.
+fn resolve<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+	let pointer = reference.strip_prefix('#')?;
+	let mut target = root;
+	for segment in pointer.split('/').skip(1) {
+		let segment = segment.replace("~1", "/").replace("~0", "~");
+		target = target.get_objects().ok()?.get(&segment)?;
+	}
+	Some(target)
+}
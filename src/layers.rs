@@ -0,0 +1,325 @@
+//! Configs merged from layers of documents, e.g. defaults overridden by a
+//! site config and then by the user's config, remembering which layer each
+//! value comes from.
+//!
+//! ```rust
+//! use kvon_rs::{layers::LayeredConfig, object, path::KvonPath};
+//!
+//! let mut config = LayeredConfig::new().with_spans();
+//! config
+//!     .add_str("defaults.kvon", "server:\n\thost: 'localhost'\n\tport: 80\n")
+//!     .unwrap();
+//! config.add_str("site.kvon", "server:\n\tport: 8080\n").unwrap();
+//!
+//! let merged = config.merge();
+//! assert_eq!(
+//!     merged.value(),
+//!     &object! { server: { host: "localhost", port: 8080 } }
+//! );
+//! let port: KvonPath = "server.port".parse().unwrap();
+//! let origin = merged.origin(&port).unwrap();
+//! assert_eq!((origin.layer, origin.to_string()), (1, "site.kvon:2".to_string()));
+//! ```
+//!
+//! Layers are merged like [OverlayValue]: objects key by key, with later
+//! layers taking precedence, while any other value replaces the values of
+//! earlier layers. There is no way to remove a key set by an earlier layer.
+
+use std::{collections::HashMap, fmt, fs, io, path::Path as FilePath, sync::Arc};
+
+use crate::{
+	error::ParserError,
+	overlay::OverlayValue,
+	parser::Parser,
+	path::{KvonPath, Path, PathSegment, Span},
+	value::Value,
+	ParserOptions,
+};
+
+/// Errors that can happen while adding a layer. Both name the layer.
+#[derive(Debug)]
+pub enum LayerError {
+	Io {
+		name: String,
+		error: io::Error,
+	},
+	Parser {
+		name: String,
+		error: Box<ParserError>,
+	},
+}
+
+impl fmt::Display for LayerError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Io { name, error } => write!(f, "{name}: {error}"),
+			Self::Parser { name, error } => write!(f, "{name}: {error}"),
+		}
+	}
+}
+
+impl std::error::Error for LayerError {}
+
+/// A document contributing to a [LayeredConfig].
+#[derive(Debug, Clone)]
+pub struct Layer {
+	name: Arc<str>,
+	value: Value,
+	spans: HashMap<KvonPath, Span>,
+}
+
+impl Layer {
+	/// The name the layer was added with, e.g. its file.
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn value(&self) -> &Value {
+		&self.value
+	}
+
+	/// Where the value at `path` starts in the layer's source. Always
+	/// `None` unless spans are enabled with [LayeredConfig::with_spans].
+	pub fn span(&self, path: &Path) -> Option<Span> {
+		self.spans.get(path).copied()
+	}
+}
+
+/// Where the effective value at a path comes from, see
+/// [ValueWithOrigin::origin].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+	/// The index of the layer, in the order the layers were added.
+	pub layer: usize,
+	/// The name of the layer.
+	pub name: Arc<str>,
+	/// Where the value starts in the source of the layer, if known.
+	pub span: Option<Span>,
+}
+
+/// `name:line` with the line counted from 1 like in editors, or just the
+/// name of the layer if the line isn't known.
+impl fmt::Display for Origin {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.name)?;
+		if let Some(span) = self.span {
+			write!(f, ":{}", span.line_number + 1)?;
+		}
+		Ok(())
+	}
+}
+
+/// Documents merged in order, see the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct LayeredConfig {
+	layers: Vec<Layer>,
+	options: ParserOptions,
+	spans: bool,
+}
+
+impl LayeredConfig {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets the options layers are parsed with.
+	pub fn options(mut self, options: ParserOptions) -> Self {
+		self.options = options;
+		self
+	}
+
+	/// Records where values start in the sources of the layers added
+	/// afterwards, so that [Origin]s include their lines.
+	pub fn with_spans(mut self) -> Self {
+		self.spans = true;
+		self
+	}
+
+	/// Parses a document and adds it as the layer with the highest
+	/// precedence so far.
+	pub fn add_str(&mut self, name: &str, source: &str) -> Result<(), LayerError> {
+		let parse = || {
+			let mut parser = Parser::with_options(self.options.clone());
+			if self.spans {
+				parser = parser.with_spans();
+			}
+			parser.parse_lines(source)?;
+			parser.finish_with_spans()
+		};
+		let (value, spans) = parse().map_err(|error| LayerError::Parser {
+			name: name.to_string(),
+			error: Box::new(error),
+		})?;
+		self.layers.push(Layer {
+			name: name.into(),
+			value,
+			spans,
+		});
+		Ok(())
+	}
+
+	/// Reads a file and adds it as a layer named by its path, see
+	/// [LayeredConfig::add_str].
+	pub fn add_file(&mut self, path: impl AsRef<FilePath>) -> Result<(), LayerError> {
+		let name = path.as_ref().display().to_string();
+		match fs::read_to_string(&path) {
+			Ok(source) => self.add_str(&name, &source),
+			Err(error) => Err(LayerError::Io { name, error }),
+		}
+	}
+
+	/// Adds a value that wasn't parsed, e.g. built from environment
+	/// variables, as a layer.
+	pub fn add_value(&mut self, name: &str, value: Value) {
+		self.layers.push(Layer {
+			name: name.into(),
+			value,
+			spans: HashMap::new(),
+		});
+	}
+
+	/// The layers, the one with the lowest precedence first.
+	pub fn layers(&self) -> &[Layer] {
+		&self.layers
+	}
+
+	/// Merges the layers into a single value.
+	pub fn value(&self) -> Value {
+		OverlayValue::new(self.layers.iter().map(|layer| &layer.value)).to_value()
+	}
+
+	/// Merges the layers into a single value, recording the origin of each
+	/// of its values.
+	pub fn merge(&self) -> ValueWithOrigin {
+		self.merge_with(|_, _, _| {})
+	}
+
+	/// Merges the layers like [LayeredConfig::merge], calling `visit` for
+	/// each path before merging the values inside of it. `visit` gets the
+	/// layers merged at the path, the one with the highest precedence last,
+	/// and the values they replace, the one with the highest precedence
+	/// first.
+	pub(crate) fn merge_with<'a>(
+		&'a self,
+		mut visit: impl FnMut(&KvonPath, &[(usize, &'a Value)], Vec<(usize, &'a Value)>),
+	) -> ValueWithOrigin {
+		let layers: Vec<(usize, &Value)> = self
+			.layers
+			.iter()
+			.map(|layer| &layer.value)
+			.enumerate()
+			.collect();
+		let mut origins = HashMap::new();
+		let value = match layers.is_empty() {
+			true => Value::Object(HashMap::new()),
+			false => self.merge_layers(layers, &mut KvonPath::root(), &mut origins, &mut visit),
+		};
+		ValueWithOrigin { value, origins }
+	}
+
+	pub(crate) fn origin(&self, layer: usize, path: &Path) -> Origin {
+		Origin {
+			layer,
+			name: Arc::clone(&self.layers[layer].name),
+			span: self.layers[layer].span(path),
+		}
+	}
+
+	/// Merges the values of the layers at `path`. There is at least one.
+	fn merge_layers<'a>(
+		&self,
+		mut layers: Vec<(usize, &'a Value)>,
+		path: &mut KvonPath,
+		origins: &mut HashMap<KvonPath, Origin>,
+		visit: &mut impl FnMut(&KvonPath, &[(usize, &'a Value)], Vec<(usize, &'a Value)>),
+	) -> Value {
+		let &(top, value) = layers.last().unwrap();
+		origins.insert(path.clone(), self.origin(top, path));
+
+		// objects are merged with the objects of the layers before them,
+		// any other value replaces all of them
+		let replaced = match value.is_object() {
+			true => layers
+				.iter()
+				.rposition(|(_, value)| !value.is_object())
+				.map_or(0, |i| i + 1),
+			false => layers.len() - 1,
+		};
+		let replaced = layers.drain(..replaced).rev().collect();
+		visit(path, &layers, replaced);
+		if !value.is_object() {
+			self.record_origins(top, value, path, origins);
+			return value.clone();
+		}
+
+		let mut keys: Vec<&String> = layers.iter().flat_map(|(_, value)| value.keys()).collect();
+		keys.sort_unstable();
+		keys.dedup();
+
+		let mut merged = HashMap::with_capacity(keys.len());
+		for key in keys {
+			let values = layers
+				.iter()
+				.filter_map(|&(layer, value)| Some((layer, value.get(key)?)))
+				.collect();
+			path.push(PathSegment::Key(key.clone()));
+			merged.insert(key.clone(), self.merge_layers(values, path, origins, visit));
+			path.pop();
+		}
+		Value::Object(merged)
+	}
+
+	/// Records that the values inside of `value` come from `layer`.
+	fn record_origins(
+		&self,
+		layer: usize,
+		value: &Value,
+		path: &mut KvonPath,
+		origins: &mut HashMap<KvonPath, Origin>,
+	) {
+		let mut record = |segment, value| {
+			path.push(segment);
+			origins.insert(path.clone(), self.origin(layer, path));
+			self.record_origins(layer, value, path, origins);
+			path.pop();
+		};
+		match value {
+			Value::Object(obj) => {
+				for (key, value) in obj {
+					record(PathSegment::Key(key.clone()), value);
+				}
+			}
+			Value::Array(arr) => {
+				for (i, value) in arr.iter().enumerate() {
+					record(PathSegment::Index(i), value);
+				}
+			}
+			_ => {}
+		}
+	}
+}
+
+/// A merged value with the origins of its values, see
+/// [LayeredConfig::merge].
+#[derive(Debug, Clone)]
+pub struct ValueWithOrigin {
+	value: Value,
+	origins: HashMap<KvonPath, Origin>,
+}
+
+impl ValueWithOrigin {
+	pub fn value(&self) -> &Value {
+		&self.value
+	}
+
+	pub fn into_value(self) -> Value {
+		self.value
+	}
+
+	/// The layer that set the value at `path`, or `None` if there is no
+	/// value at the path. Objects merged from several layers have the
+	/// origin of the last of them.
+	pub fn origin(&self, path: &Path) -> Option<&Origin> {
+		self.origins.get(path)
+	}
+}
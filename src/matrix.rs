@@ -0,0 +1,94 @@
+//! Rectangular arrays of arrays, e.g. calibration or transformation matrices.
+//! Matrices are stored as an expanded array with one inline array per row:
+//!
+//! ```rust
+//! use kvon_rs::{encode_string, parse_string, EncodeOptions};
+//!
+//! let value = parse_string("m:--\n\t- [1 0 -2.5]\n\t- [0 10 4]").unwrap();
+//! let m = value.get("m").unwrap().get_matrix::<f64>().unwrap();
+//! assert_eq!((m.rows, m.cols), (2, 3));
+//! assert_eq!(m.get(1, 2), Some(&4.));
+//!
+//! let options = EncodeOptions::new().align_matrices(true);
+//! assert_eq!(
+//!     encode_string(&value, &options),
+//!     "\nm:--\n\t- [1  0 -2.5]\n\t- [0 10    4]"
+//! );
+//! ```
+
+use crate::{
+	decode::{expect_array_of_len, Decode, DecodeError},
+	encode::Encode,
+	path::PathSegment,
+	schema::{Schema, SchemaRef},
+	value::Value,
+};
+
+/// A matrix with its elements stored row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix<T> {
+	pub rows: usize,
+	pub cols: usize,
+	/// The `rows * cols` elements, the first row first.
+	pub data: Vec<T>,
+}
+
+impl<T> Matrix<T> {
+	/// Creates a matrix from its elements in row order. Returns `None` if
+	/// there are not exactly `rows * cols` elements.
+	pub fn new(rows: usize, cols: usize, data: Vec<T>) -> Option<Self> {
+		(data.len() == rows * cols).then_some(Self { rows, cols, data })
+	}
+
+	pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+		match row < self.rows && col < self.cols {
+			true => self.data.get(row * self.cols + col),
+			false => None,
+		}
+	}
+
+	/// Iterates over the rows as slices.
+	pub fn iter_rows(&self) -> impl Iterator<Item = &[T]> {
+		// `chunks` panics on a size of 0, which only empty rows have
+		(0..self.rows).map(|row| &self.data[row * self.cols..(row + 1) * self.cols])
+	}
+}
+
+impl<T: Decode> Decode for Matrix<T> {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		let rows = value
+			.get_vector()
+			.map_err(|_| DecodeError::invalid_type("array", value))?;
+		let cols = match rows.first() {
+			Some(Value::Array(row)) => row.len(),
+			_ => 0,
+		};
+
+		let mut data = Vec::with_capacity(rows.len() * cols);
+		for (i, row) in rows.iter().enumerate() {
+			let within = |e: DecodeError| e.within(PathSegment::Index(i));
+			for (j, element) in expect_array_of_len(row, cols)
+				.map_err(within)?
+				.iter()
+				.enumerate()
+			{
+				data.push(T::decode(element).map_err(|e| within(e.within(PathSegment::Index(j))))?);
+			}
+		}
+		Ok(Self {
+			rows: rows.len(),
+			cols,
+			data,
+		})
+	}
+
+	fn schema() -> Schema {
+		Schema::Array(Schema::Array(SchemaRef::Fn(T::schema)).into())
+	}
+}
+
+impl<T: Encode> Encode for Matrix<T> {
+	fn encode(&self) -> Value {
+		Value::Array(self.iter_rows().map(|row| row.encode()).collect())
+	}
+}
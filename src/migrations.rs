@@ -0,0 +1,231 @@
+//! Upgrading documents written by older versions of an application, such as
+//! configs and state files. Each document stores its version under a key,
+//! and registered migrations are applied in order until the document
+//! reaches the latest version:
+//!
+//! ```rust
+//! use kvon_rs::{migrations::{rename, Migrations}, object};
+//!
+//! let migrations = Migrations::new("version")
+//!     // version 1 to 2: `addr` was renamed to `server.host`
+//!     .step(|value| {
+//!         rename(value, "addr", "server.host");
+//!         Ok(())
+//!     })
+//!     // version 2 to 3: `port` moved into `server`
+//!     .step(|value| {
+//!         rename(value, "port", "server.port");
+//!         Ok(())
+//!     });
+//!
+//! let mut config = object! { version: 1, addr: "localhost", port: 80 };
+//! migrations.migrate(&mut config).unwrap();
+//! assert_eq!(
+//!     config,
+//!     object! { version: 3, server: { host: "localhost", port: 80 } }
+//! );
+//! ```
+
+use std::{fmt, sync::Arc};
+
+use crate::{
+	diff::{diff, Change},
+	value::{PrimitiveValue, Value},
+};
+
+/// A transform upgrading a document by one version.
+pub type Migration = dyn Fn(&mut Value) -> Result<(), String> + Send + Sync;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationError {
+	/// The document isn't an object, so it can't store a version.
+	NotAnObject,
+	/// The version key doesn't hold a positive integer.
+	InvalidVersion(Value),
+	/// The document was written by a newer version of the application.
+	UnsupportedVersion { found: u32, latest: u32 },
+	/// A migration returned an error.
+	Failed { from: u32, message: String },
+}
+
+impl fmt::Display for MigrationError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::NotAnObject => write!(f, "only objects can be migrated"),
+			Self::InvalidVersion(value) => write!(f, "invalid version {value:?}"),
+			Self::UnsupportedVersion { found, latest } => write!(
+				f,
+				"version {found} is newer than the latest known version {latest}"
+			),
+			Self::Failed { from, message } => write!(
+				f,
+				"migrating from version {from} to {} failed: {message}",
+				from + 1
+			),
+		}
+	}
+}
+
+impl std::error::Error for MigrationError {}
+
+/// An ordered list of migrations. Documents without the version key are
+/// treated as version 1, the version before the first migration.
+#[derive(Clone)]
+pub struct Migrations {
+	version_key: String,
+	steps: Vec<Arc<Migration>>,
+}
+
+impl fmt::Debug for Migrations {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Migrations")
+			.field("version_key", &self.version_key)
+			.field("latest_version", &self.latest_version())
+			.finish()
+	}
+}
+
+impl Migrations {
+	/// Creates an empty list of migrations reading the version from the given
+	/// key of the root object.
+	pub fn new(version_key: impl ToString) -> Self {
+		Self {
+			version_key: version_key.to_string(),
+			steps: Vec::new(),
+		}
+	}
+
+	/// Adds a migration from the current latest version to the next one.
+	pub fn step(
+		mut self,
+		migration: impl Fn(&mut Value) -> Result<(), String> + Send + Sync + 'static,
+	) -> Self {
+		self.steps.push(Arc::new(migration));
+		self
+	}
+
+	/// The version documents have after being migrated.
+	pub fn latest_version(&self) -> u32 {
+		self.steps.len() as u32 + 1
+	}
+
+	/// Reads the version of a document.
+	pub fn version(&self, value: &Value) -> Result<u32, MigrationError> {
+		let Value::Object(obj) = value else {
+			return Err(MigrationError::NotAnObject);
+		};
+		match obj.get(&self.version_key) {
+			None => Ok(1),
+			Some(Value::Primitive(PrimitiveValue::Number(n)))
+				if *n >= 1. && n.fract() == 0. && *n <= u32::MAX as f32 =>
+			{
+				Ok(*n as u32)
+			}
+			Some(other) => Err(MigrationError::InvalidVersion(other.clone())),
+		}
+	}
+
+	/// Applies all migrations the document needs and sets its version to the
+	/// latest one. Returns the version the document had before. On error,
+	/// the document may be partially migrated.
+	pub fn migrate(&self, value: &mut Value) -> Result<u32, MigrationError> {
+		let version = self.version(value)?;
+		let latest = self.latest_version();
+		if version > latest {
+			return Err(MigrationError::UnsupportedVersion {
+				found: version,
+				latest,
+			});
+		}
+
+		for (from, step) in (version..).zip(&self.steps[version as usize - 1..]) {
+			step(value).map_err(|message| MigrationError::Failed { from, message })?;
+		}
+
+		if let Value::Object(obj) = value {
+			obj.insert(self.version_key.clone(), Value::from(latest as f32));
+		}
+		Ok(version)
+	}
+
+	/// Returns the changes [Migrations::migrate] would make, without
+	/// modifying the document.
+	pub fn dry_run(&self, value: &Value) -> Result<Vec<Change>, MigrationError> {
+		let mut migrated = value.clone();
+		self.migrate(&mut migrated)?;
+		Ok(diff(value, &migrated))
+	}
+}
+
+/// Moves the value at the dotted path `from`, e.g. `server.addr`, to the
+/// dotted path `to`, creating missing objects along the way. Returns false
+/// and leaves the value unchanged if there is no value at `from` or `to`
+/// can't be created.
+pub fn rename(value: &mut Value, from: &str, to: &str) -> bool {
+	let Some(moved) = remove(value, from) else {
+		return false;
+	};
+	match insert(value, to, moved) {
+		Ok(()) => true,
+		Err(moved) => {
+			// the object `from` was removed from is still there
+			let _ = insert(value, from, moved);
+			false
+		}
+	}
+}
+
+/// Inserts a value at the dotted path, creating missing objects along the
+/// way. Gives the value back if a value on the way isn't an object, without
+/// adding any objects.
+fn insert(value: &mut Value, path: &str, inserted: Value) -> Result<(), Value> {
+	let mut keys: Vec<&str> = path.split('.').collect();
+	let last = keys.pop().unwrap();
+
+	let mut existing = Some(&*value);
+	for key in &keys {
+		existing = match existing {
+			Some(Value::Object(obj)) => obj.get(*key),
+			Some(_) => return Err(inserted),
+			None => None,
+		};
+	}
+	if existing.is_some_and(|existing| !existing.is_object()) {
+		return Err(inserted);
+	}
+
+	let mut target = value;
+	for key in keys {
+		let Value::Object(obj) = target else {
+			return Err(inserted);
+		};
+		target = obj
+			.entry(key.to_string())
+			.or_insert_with(Value::empty_object);
+	}
+	match target {
+		Value::Object(obj) => {
+			obj.insert(last.to_string(), inserted);
+			Ok(())
+		}
+		_ => Err(inserted),
+	}
+}
+
+/// Removes and returns the value at the dotted path.
+pub fn remove(value: &mut Value, path: &str) -> Option<Value> {
+	let mut keys: Vec<&str> = path.split('.').collect();
+	let last = keys.pop().unwrap();
+	let mut target = value;
+	for key in keys {
+		match target {
+			Value::Object(obj) => target = obj.get_mut(key)?,
+			_ => return None,
+		}
+	}
+
+	match target {
+		Value::Object(obj) => obj.remove(last),
+		_ => None,
+	}
+}
@@ -0,0 +1,126 @@
+//! Lookups through layers of values without merging them, e.g. defaults
+//! overridden by site and user configs shared between many tenants.
+//!
+//! ```rust
+//! use kvon_rs::{object, overlay::OverlayValue};
+//!
+//! let defaults = object! { server: { host: "localhost", port: 80 }, debug: false };
+//! let user = object! { server: { port: 8080 } };
+//!
+//! let config = OverlayValue::new([&defaults, &user]);
+//! let server = config.get("server").unwrap();
+//! assert_eq!(server.get("port").unwrap().top(), &8080.into());
+//! assert_eq!(server.get("host").unwrap().top(), &"localhost".into());
+//! assert_eq!(config.keys(), ["debug", "server"]);
+//! ```
+//!
+//! Objects are merged key by key, with later layers taking precedence. Any
+//! other value, including arrays, replaces the values of earlier layers.
+
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+
+use crate::{
+	path::{Path, PathSegment},
+	value::Value,
+};
+
+/// A read-only view of a value made of layers, see the
+/// [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct OverlayValue<'a> {
+	/// The values at this location that contribute to it, the last taking
+	/// precedence. Only the last one can be something other than an object.
+	layers: Vec<&'a Value>,
+}
+
+impl<'a> OverlayValue<'a> {
+	/// Layers values, with later values taking precedence over earlier ones.
+	pub fn new(layers: impl IntoIterator<Item = &'a Value>) -> Self {
+		Self::from_layers(layers.into_iter().collect())
+	}
+
+	/// Drops the layers hidden by a later value that isn't an object.
+	fn from_layers(mut layers: Vec<&'a Value>) -> Self {
+		if let Some(top) = layers.last() {
+			if !top.is_object() {
+				layers.drain(..layers.len() - 1);
+			} else if let Some(i) = layers.iter().rposition(|value| !value.is_object()) {
+				layers.drain(..=i);
+			}
+		}
+		Self { layers }
+	}
+
+	/// Returns the value of a key of an object.
+	pub fn get(&self, key: &str) -> Option<OverlayValue<'a>> {
+		let layers: Vec<&'a Value> = self.layers.iter().filter_map(|v| v.get(key)).collect();
+		match layers.is_empty() {
+			true => None,
+			false => Some(Self::from_layers(layers)),
+		}
+	}
+
+	/// Returns the value at the given path. Indices look into the array of
+	/// the top layer, as arrays aren't merged.
+	pub fn get_path(&self, path: &Path) -> Option<OverlayValue<'a>> {
+		path.iter()
+			.try_fold(self.clone(), |overlay, segment| match segment {
+				PathSegment::Key(key) => overlay.get(key),
+				PathSegment::Index(index) => overlay
+					.top()
+					.get_index(*index)
+					.map(|value| Self::from_layers(vec![value])),
+			})
+	}
+
+	/// The value of the layer with the highest precedence. It is the
+	/// effective value, unless it is an object that is merged with the
+	/// objects of earlier layers.
+	pub fn top(&self) -> &'a Value {
+		lazy_static! {
+			static ref EMPTY: Value = Value::Object(HashMap::new());
+		}
+		self.layers.last().copied().unwrap_or(&EMPTY)
+	}
+
+	/// The effective value if only a single layer contributes to it, which
+	/// avoids merging.
+	pub fn as_single(&self) -> Option<&'a Value> {
+		match self.layers.as_slice() {
+			[value] => Some(value),
+			_ => None,
+		}
+	}
+
+	pub fn is_object(&self) -> bool {
+		self.top().is_object()
+	}
+
+	/// The keys of all layers of an object, sorted.
+	pub fn keys(&self) -> Vec<&'a str> {
+		let mut keys: Vec<&'a str> = self
+			.layers
+			.iter()
+			.flat_map(|value| value.keys())
+			.map(String::as_str)
+			.collect();
+		keys.sort_unstable();
+		keys.dedup();
+		keys
+	}
+
+	/// Merges the layers into a single value.
+	pub fn to_value(&self) -> Value {
+		if let Some(value) = self.as_single() {
+			return value.clone();
+		}
+		Value::Object(
+			self.keys()
+				.into_iter()
+				.map(|key| (key.to_string(), self.get(key).unwrap().to_value()))
+				.collect(),
+		)
+	}
+}
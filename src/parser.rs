@@ -0,0 +1,2293 @@
+use std::{
+	collections::HashMap,
+	io::{BufRead, BufReader, Read},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+};
+
+use crate::{
+	error::{ParserError, ParserErrorKind},
+	indention::Indention,
+	path::{KvonPath, PathSegment, Span},
+	scanner::{PrimitiveParser, Scanner, END_OF_LINE},
+	tags::TagRegistry,
+	value::{PrimitiveValue, RawValue, Value},
+	ParserResult,
+};
+
+/// A flag for aborting parsing from another thread. Clones share the same
+/// flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Makes parsers using this token return a [ParserErrorKind::Cancelled]
+	/// error before their next line.
+	pub fn cancel(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.0.load(Ordering::Relaxed)
+	}
+}
+
+/// How much of the input has been parsed, see [ParserOptions::progress].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+	/// The amount of bytes read, including line breaks.
+	pub bytes: usize,
+	pub lines: usize,
+}
+
+/// The line without its `\n` or `\r\n`, like `str::lines` yields it.
+fn strip_line_break(line: &str) -> &str {
+	line.strip_suffix('\n')
+		.map_or(line, |line| line.strip_suffix('\r').unwrap_or(line))
+}
+
+/// A hook notified after every parsed line.
+type ProgressCallback = dyn Fn(Progress) + Send + Sync;
+
+#[derive(Clone)]
+pub(crate) struct ProgressHook(pub(crate) Arc<ProgressCallback>);
+
+impl std::fmt::Debug for ProgressHook {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str("ProgressHook")
+	}
+}
+
+/// A `#` comment, see [Parser::with_comments].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+	/// Where the `#` is.
+	pub span: Span,
+	/// The text after the `#`, without surrounding whitespace.
+	pub text: String,
+	/// The path of the value on the same line, if the comment trails one.
+	/// The last one is used for lines with several array items.
+	pub path: Option<KvonPath>,
+}
+
+/// A `#kvon:` line at the top of a document, e.g. `#kvon: schema app.kvon`,
+/// see [Parser::directives].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Directive {
+	pub name: String,
+	/// The text after the name, without surrounding whitespace. Empty if the
+	/// directive only has a name.
+	pub value: String,
+	pub line_number: usize,
+}
+
+/// The version of the KVON format a document declares with a `#kvon 1.0`
+/// line at its top, see [Parser::version].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FormatVersion {
+	pub major: u32,
+	pub minor: u32,
+}
+
+impl FormatVersion {
+	/// The newest version this crate can parse.
+	pub const LATEST: Self = Self { major: 1, minor: 0 };
+}
+
+impl std::fmt::Display for FormatVersion {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}.{}", self.major, self.minor)
+	}
+}
+
+impl std::str::FromStr for FormatVersion {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (major, minor) = s.split_once('.').ok_or(())?;
+		let digits = |s: &str| {
+			if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+				s.parse().map_err(|_| ())
+			} else {
+				Err(())
+			}
+		};
+		Ok(Self {
+			major: digits(major)?,
+			minor: digits(minor)?,
+		})
+	}
+}
+
+/// A non-fatal issue found while parsing, see [Parser::warnings].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+	/// A key appearing more than once in the same object. The last value is
+	/// used.
+	DuplicateKey { path: KvonPath, line_number: usize },
+	/// The document's `# sha256: ...` line doesn't match its value, i.e. it
+	/// was changed after it was written. See [crate::checksum].
+	#[cfg(feature = "checksum")]
+	ChecksumMismatch {
+		line_number: usize,
+		expected: String,
+		found: String,
+	},
+}
+
+impl std::fmt::Display for Warning {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::DuplicateKey { path, line_number } => write!(
+				f,
+				"{line_number}: duplicate key {path}, replacing the previous value"
+			),
+			#[cfg(feature = "checksum")]
+			Self::ChecksumMismatch { line_number, .. } => write!(
+				f,
+				"{line_number}: checksum mismatch, the document was changed after it was written"
+			),
+		}
+	}
+}
+
+/// A parsed document together with what was learned about its source, see
+/// [crate::parse_string_output].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseOutput {
+	pub value: Value,
+	/// The indention of the document, or `None` if no line is indented.
+	pub detected_indention: Option<Indention>,
+	pub line_count: usize,
+	pub comments: Vec<Comment>,
+	pub warnings: Vec<Warning>,
+	pub directives: Vec<Directive>,
+	pub shebang: Option<String>,
+	version: Option<FormatVersion>,
+	profile: Option<Profile>,
+}
+
+impl ParseOutput {
+	/// The version the document declares, see [Parser::version].
+	pub fn version(&self) -> Option<FormatVersion> {
+		self.version
+	}
+
+	/// The profile the document was parsed with, see
+	/// [ParserOptions::profile].
+	pub fn profile(&self) -> Option<Profile> {
+		self.profile
+	}
+
+	/// Options encoding values in the style of the document, with its
+	/// indention and comments.
+	pub fn encode_options(&self) -> crate::EncodeOptions {
+		crate::EncodeOptions::new()
+			.indention(self.detected_indention.unwrap_or_default())
+			.comments(self.comments.clone())
+	}
+}
+
+#[derive(Clone)]
+pub(crate) struct PrimitiveParserHook(pub(crate) Arc<dyn PrimitiveParser>);
+
+impl std::fmt::Debug for PrimitiveParserHook {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str("PrimitiveParserHook")
+	}
+}
+
+/// A preset of [ParserOptions] for how closely documents have to follow the
+/// KVON spec, see [ParserOptions::profile].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Profile {
+	/// Rejects everything the spec doesn't define, so that accepted
+	/// documents can be read by other implementations:
+	///
+	/// - tagged literals and the literals of the `uuid`, `ipaddr`, `semver`
+	///   and `quantities` features produce a [ParserErrorKind::NotInSpec]
+	///   error, which no other profile produces;
+	/// - misspelled literals like `True` produce an error, see
+	///   [ParserOptions::strict_literals];
+	/// - the options of [Profile::Permissive] and
+	///   [ParserOptions::comment_prefixes] are disabled, so e.g. `yes` isn't
+	///   a boolean and `//` doesn't start a comment;
+	/// - documents declaring a version newer than [FormatVersion::LATEST]
+	///   produce an error.
+	///
+	/// [ParserOptions::primitive_parser] hooks aren't called.
+	Strict,
+	/// Enables the extensions that make hand-written documents more
+	/// convenient: [ParserOptions::boolean_aliases],
+	/// [ParserOptions::null_alias], [ParserOptions::dotted_keys],
+	/// [ParserOptions::lenient_tab_indention],
+	/// [ParserOptions::blank_lines_in_strings] and
+	/// [ParserOptions::allow_unknown_tags].
+	Permissive,
+}
+
+/// Options controlling how documents are parsed.
+#[derive(Debug, Clone, Default)]
+pub struct ParserOptions {
+	pub(crate) profile: Option<Profile>,
+	pub(crate) tags: TagRegistry,
+	pub(crate) allow_unknown_tags: bool,
+	pub(crate) boolean_aliases: bool,
+	pub(crate) null_alias: bool,
+	pub(crate) strict_literals: bool,
+	pub(crate) raw_paths: Vec<Vec<String>>,
+	pub(crate) error_on_unclosed_blocks: bool,
+	pub(crate) lenient_tab_indention: bool,
+	pub(crate) comment_prefixes: Vec<String>,
+	pub(crate) allow_newer_versions: bool,
+	pub(crate) dotted_keys: bool,
+	pub(crate) blank_lines_in_strings: bool,
+	pub(crate) primitive_parsers: Vec<PrimitiveParserHook>,
+	#[cfg(feature = "unicode-normalization")]
+	pub(crate) normalize_keys: bool,
+	pub(crate) cancel_token: Option<CancelToken>,
+	pub(crate) progress: Option<ProgressHook>,
+}
+
+impl ParserOptions {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets the options of a profile. Options set afterwards override the
+	/// ones of the profile.
+	///
+	/// ```rust
+	/// use kvon_rs::{error::ParserErrorKind, parse_string_with_options, ParserOptions, Profile};
+	///
+	/// let permissive = ParserOptions::new().profile(Profile::Permissive);
+	/// assert!(parse_string_with_options("debug: yes", &permissive).is_ok());
+	///
+	/// let strict = ParserOptions::new().profile(Profile::Strict);
+	/// let error = parse_string_with_options("timeout: !duration '5s'", &strict).unwrap_err();
+	/// assert_eq!(error.kind, ParserErrorKind::NotInSpec { extension: "tagged literals" });
+	/// ```
+	pub fn profile(mut self, profile: Profile) -> Self {
+		let permissive = profile == Profile::Permissive;
+		self.profile = Some(profile);
+		self.strict_literals = !permissive;
+		self.boolean_aliases = permissive;
+		self.null_alias = permissive;
+		self.dotted_keys = permissive;
+		self.lenient_tab_indention = permissive;
+		self.blank_lines_in_strings = permissive;
+		self.allow_unknown_tags = permissive;
+		if !permissive {
+			self.comment_prefixes.clear();
+			self.allow_newer_versions = false;
+		}
+		self
+	}
+
+	/// Sets the tags recognized in tagged literals such as `!duration '5s'`.
+	pub fn tags(mut self, tags: TagRegistry) -> Self {
+		self.tags = tags;
+		self
+	}
+
+	/// When enabled, literals with unregistered tags are parsed as if they
+	/// weren't tagged instead of producing an error.
+	pub fn allow_unknown_tags(mut self, allow: bool) -> Self {
+		self.allow_unknown_tags = allow;
+		self
+	}
+
+	/// When enabled, `yes`/`on` are parsed as `true` and `no`/`off` as
+	/// `false`. Disabled by default, as the spec only defines `true` and
+	/// `false`.
+	pub fn boolean_aliases(mut self, enable: bool) -> Self {
+		self.boolean_aliases = enable;
+		self
+	}
+
+	/// When enabled, `~` is parsed as `null`. Disabled by default.
+	pub fn null_alias(mut self, enable: bool) -> Self {
+		self.null_alias = enable;
+		self
+	}
+
+	/// When enabled, bare words that look like a misspelled literal, such as
+	/// `True` or `YES`, produce a [ParserErrorKind::NearMissLiteral] error
+	/// suggesting the correct spelling.
+	pub fn strict_literals(mut self, enable: bool) -> Self {
+		self.strict_literals = enable;
+		self
+	}
+
+	/// When enabled, [Parser::finish] returns a [ParserErrorKind::UnexpectedEof]
+	/// error if the input ends right after a multi-line string or an expanded
+	/// array was opened, instead of producing an empty value. This detects
+	/// truncated inputs.
+	pub fn error_on_unclosed_blocks(mut self, enable: bool) -> Self {
+		self.error_on_unclosed_blocks = enable;
+		self
+	}
+
+	/// When enabled, a first indented line using several tabs defines how
+	/// many tabs make up one level of indention, instead of producing a
+	/// [ParserErrorKind::MultipleTabIndent] error. This accepts documents from
+	/// formatters that indent with e.g. two tabs per level.
+	pub fn lenient_tab_indention(mut self, enable: bool) -> Self {
+		self.lenient_tab_indention = enable;
+		self
+	}
+
+	/// Additionally starts comments with any of the given prefixes, e.g. `//`
+	/// or `;` for files migrated from other formats. `#` always starts a
+	/// comment.
+	pub fn comment_prefixes<S: ToString>(mut self, prefixes: impl IntoIterator<Item = S>) -> Self {
+		self.comment_prefixes = prefixes.into_iter().map(|p| p.to_string()).collect();
+		self
+	}
+
+	/// When enabled, unquoted keys containing dots are paths of nested
+	/// objects, e.g. `server.port: 8080` is parsed like `server:` followed by
+	/// an indented `port: 8080`. Keys with the same prefix share the nested
+	/// object. Quoted keys such as `'a.b'` are kept as they are.
+	///
+	/// Keys that would replace a value of another line instead of extending
+	/// it, e.g. `a: 1` followed by `a.b: 2`, are a
+	/// [ParserErrorKind::KeyConflict] error.
+	pub fn dotted_keys(mut self, enable: bool) -> Self {
+		self.dotted_keys = enable;
+		self
+	}
+
+	/// When enabled, lines that are empty or only hold whitespace don't end a
+	/// multi-line string or raw value, even if they aren't indented. They are
+	/// kept as empty lines if the block continues below them, e.g. for
+	/// Markdown or shell scripts whose editor strips trailing whitespace.
+	///
+	/// ```rust
+	/// use kvon_rs::{object, parse_string_with_options, ParserOptions};
+	///
+	/// let options = ParserOptions::new().blank_lines_in_strings(true);
+	/// let value = parse_string_with_options("s: |\n\tone\n\n\ttwo\n\nb: 1", &options).unwrap();
+	/// assert_eq!(value, object! { s: "one\n\ntwo", b: 1 });
+	/// ```
+	pub fn blank_lines_in_strings(mut self, enable: bool) -> Self {
+		self.blank_lines_in_strings = enable;
+		self
+	}
+
+	/// Adds a hook that gets the first chance at parsing every literal, e.g.
+	/// to support domain-specific literals without quotes. Hooks are tried in
+	/// the order they were added, before the standard literals.
+	///
+	/// ```rust
+	/// use kvon_rs::{object, parse_string_with_options, scanner::Scanner, value::PrimitiveValue, ParserOptions};
+	///
+	/// // bare IPv4 addresses, parsed as strings
+	/// let options = ParserOptions::new().primitive_parser(|scanner: &mut Scanner| {
+	///     let word = scanner.rest().split([' ', ']', '#']).next().unwrap();
+	///     if word.parse::<std::net::Ipv4Addr>().is_err() {
+	///         return Ok(None);
+	///     }
+	///     scanner.advance_by(word.len());
+	///     Ok(Some(PrimitiveValue::from(word)))
+	/// });
+	///
+	/// let value = parse_string_with_options("hosts: [10.0.0.1 10.0.0.2]\nport: 80", &options).unwrap();
+	/// assert_eq!(value, object! { hosts: ["10.0.0.1", "10.0.0.2"], port: 80 });
+	/// ```
+	pub fn primitive_parser(mut self, parser: impl PrimitiveParser + 'static) -> Self {
+		self.primitive_parsers
+			.push(PrimitiveParserHook(Arc::new(parser)));
+		self
+	}
+
+	/// When enabled, documents declaring a version newer than
+	/// [FormatVersion::LATEST] are parsed as if they were of the latest
+	/// version. Otherwise they produce a
+	/// [ParserErrorKind::UnsupportedVersion] error, as they may use syntax
+	/// that would be misparsed.
+	pub fn allow_newer_versions(mut self, allow: bool) -> Self {
+		self.allow_newer_versions = allow;
+		self
+	}
+
+	/// When enabled, keys are converted to Unicode normalization form C, so
+	/// that keys which look the same, e.g. a precomposed `é` and an `e`
+	/// followed by a combining accent, are treated as the same key.
+	#[cfg(feature = "unicode-normalization")]
+	pub fn normalize_keys(mut self, enable: bool) -> Self {
+		self.normalize_keys = enable;
+		self
+	}
+
+	/// Checks the token before every line, returning a
+	/// [ParserErrorKind::Cancelled] error once it was cancelled.
+	pub fn with_cancel_token(mut self, token: CancelToken) -> Self {
+		self.cancel_token = Some(token);
+		self
+	}
+
+	/// Sets a hook that the `parse_string` and `parse_reader` functions
+	/// call after every line, e.g. to show a progress bar while loading
+	/// large files. The hook is called often, so it should be cheap or
+	/// throttle itself. A [Parser] fed with [Parser::next_line] doesn't call
+	/// it, as it doesn't know how much input is left.
+	pub fn progress(mut self, progress: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+		self.progress = Some(ProgressHook(Arc::new(progress)));
+		self
+	}
+
+	/// Values at the given dotted key paths, e.g. `payload` or `meta.body`,
+	/// are not parsed. Instead, their source text is captured into a
+	/// [Value::Raw] which can be inspected or encoded back verbatim.
+	pub fn raw_paths<S: AsRef<str>>(mut self, paths: impl IntoIterator<Item = S>) -> Self {
+		self.raw_paths = paths
+			.into_iter()
+			.map(|path| path.as_ref().split('.').map(ToString::to_string).collect())
+			.collect();
+		self
+	}
+}
+
+/// The values a [Parser] builds documents from, [Value] unless parsed into
+/// another representation, e.g. by
+/// [crate::shared::parse_string_interned].
+pub trait Tree: Sized + Clone {
+	/// The state needed to build values, e.g. a string interner.
+	type Builder: Clone;
+	/// The entries of an object.
+	type Map: Entries<Self>;
+	/// The items of an array.
+	type List: Items<Self>;
+
+	fn primitive(builder: &mut Self::Builder, primitive: PrimitiveValue) -> Self;
+
+	/// Converts a value parsed as a whole, i.e. an inline array.
+	fn value(builder: &mut Self::Builder, value: Value) -> Self;
+
+	fn raw(builder: &mut Self::Builder, raw: RawValue) -> Self;
+
+	fn new_object(builder: &Self::Builder, capacity: usize) -> Self::Map;
+
+	fn new_array(builder: &Self::Builder) -> Self::List;
+
+	fn object(entries: Self::Map) -> Self;
+
+	fn array(items: Self::List) -> Self;
+
+	fn into_object(self) -> Option<Self::Map>;
+
+	fn into_array(self) -> Option<Self::List>;
+
+	fn as_object(&self) -> Option<&Self::Map>;
+
+	fn object_mut(&mut self) -> Option<&mut Self::Map>;
+
+	fn is_object(&self) -> bool {
+		self.as_object().is_some()
+	}
+
+	/// Returns the value of a key of an object.
+	fn get(&self, key: &str) -> Option<&Self> {
+		self.as_object()?.get(key)
+	}
+}
+
+/// The entries of an object while a [Parser] builds it.
+pub trait Entries<T>: Clone {
+	fn len(&self) -> usize;
+
+	fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	fn get(&self, key: &str) -> Option<&T>;
+
+	fn get_mut(&mut self, key: &str) -> Option<&mut T>;
+
+	/// Sets the value of a key, returning the value it replaced.
+	fn insert(&mut self, key: String, value: T) -> Option<T>;
+
+	fn remove(&mut self, key: &str) -> Option<T>;
+}
+
+/// The items of an array while a [Parser] builds it.
+pub trait Items<T>: Clone {
+	fn len(&self) -> usize;
+
+	fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	fn push(&mut self, value: T);
+
+	fn pop(&mut self) -> Option<T>;
+}
+
+impl<T: Clone> Entries<T> for HashMap<String, T> {
+	fn len(&self) -> usize {
+		HashMap::len(self)
+	}
+
+	fn get(&self, key: &str) -> Option<&T> {
+		HashMap::get(self, key)
+	}
+
+	fn get_mut(&mut self, key: &str) -> Option<&mut T> {
+		HashMap::get_mut(self, key)
+	}
+
+	fn insert(&mut self, key: String, value: T) -> Option<T> {
+		HashMap::insert(self, key, value)
+	}
+
+	fn remove(&mut self, key: &str) -> Option<T> {
+		HashMap::remove(self, key)
+	}
+}
+
+impl<T: Clone> Items<T> for Vec<T> {
+	fn len(&self) -> usize {
+		Vec::len(self)
+	}
+
+	fn push(&mut self, value: T) {
+		Vec::push(self, value)
+	}
+
+	fn pop(&mut self) -> Option<T> {
+		Vec::pop(self)
+	}
+}
+
+impl Tree for Value {
+	type Builder = ();
+	type Map = HashMap<String, Value>;
+	type List = Vec<Value>;
+
+	fn primitive(_: &mut (), primitive: PrimitiveValue) -> Self {
+		Value::Primitive(primitive)
+	}
+
+	fn value(_: &mut (), value: Value) -> Self {
+		value
+	}
+
+	fn raw(_: &mut (), raw: RawValue) -> Self {
+		Value::Raw(raw)
+	}
+
+	fn new_object(_: &(), capacity: usize) -> HashMap<String, Value> {
+		HashMap::with_capacity(capacity)
+	}
+
+	fn new_array(_: &()) -> Vec<Value> {
+		Vec::new()
+	}
+
+	fn object(entries: HashMap<String, Value>) -> Self {
+		Value::Object(entries)
+	}
+
+	fn array(items: Vec<Value>) -> Self {
+		Value::Array(items)
+	}
+
+	fn into_object(self) -> Option<HashMap<String, Value>> {
+		match self {
+			Value::Object(entries) => Some(entries),
+			_ => None,
+		}
+	}
+
+	fn into_array(self) -> Option<Vec<Value>> {
+		match self {
+			Value::Array(items) => Some(items),
+			_ => None,
+		}
+	}
+
+	fn object_mut(&mut self) -> Option<&mut HashMap<String, Value>> {
+		match self {
+			Value::Object(entries) => Some(entries),
+			_ => None,
+		}
+	}
+
+	fn as_object(&self) -> Option<&HashMap<String, Value>> {
+		self.get_objects().ok()
+	}
+}
+
+#[derive(Clone)]
+struct ObjectContent<T: Tree> {
+	pending_key: String,
+	/// The keys below `pending_key` of a dotted key, e.g. `port` for
+	/// `server.port`.
+	pending_path: Vec<String>,
+	values: T::Map,
+}
+
+impl<T: Tree> ObjectContent<T> {
+	/// Inserts a value at the pending key, creating the objects of a dotted
+	/// key. Values that aren't objects are replaced by them. Returns the
+	/// level of the first key of the path whose value changed, and the value
+	/// it replaced.
+	fn insert_pending(&mut self, value: T, builder: &T::Builder) -> (usize, Option<T>) {
+		let key = std::mem::take(&mut self.pending_key);
+		let path = std::mem::take(&mut self.pending_path);
+		let depth = path.len();
+
+		let mut target = &mut self.values;
+		let mut key = key;
+		let mut changed = None;
+		for (level, next) in path.into_iter().enumerate() {
+			if !target.get(&key).is_some_and(T::is_object) {
+				let object = T::object(T::new_object(builder, 0));
+				let replaced = target.insert(key.clone(), object);
+				changed.get_or_insert((level, replaced));
+			}
+			target = target.get_mut(&key).and_then(T::object_mut).unwrap();
+			key = next;
+		}
+		let replaced = target.insert(key, value);
+		changed.unwrap_or((depth, replaced))
+	}
+
+	/// Undoes [ObjectContent::insert_pending] of the value at `path`, making
+	/// it the pending key again. Returns the inserted value.
+	fn remove_inserted(&mut self, path: &[String], level: usize, replaced: Option<T>) -> T {
+		let mut target = &mut self.values;
+		for key in &path[..level] {
+			target = target
+				.get_mut(key)
+				.and_then(T::object_mut)
+				.expect("the objects above the changed key existed before");
+		}
+		let mut value = match replaced {
+			Some(replaced) => target.insert(path[level].clone(), replaced),
+			None => target.remove(&path[level]),
+		}
+		.unwrap();
+		for key in &path[level + 1..] {
+			let mut obj = value
+				.into_object()
+				.expect("the objects below the changed key were created by the insert");
+			value = obj.remove(key).unwrap();
+		}
+
+		self.pending_key = path[0].clone();
+		self.pending_path = path[1..].to_vec();
+		value
+	}
+}
+
+#[derive(Clone)]
+struct ArrayContent<T: Tree> {
+	values: T::List,
+}
+
+#[derive(Clone)]
+struct MultiLineStringContent {
+	lines: Vec<String>,
+}
+
+#[derive(Clone)]
+struct RawContent {
+	head: String,
+	lines: Vec<String>,
+	indention: Indention,
+}
+
+#[derive(Clone)]
+enum ContextContent<T: Tree> {
+	Object(ObjectContent<T>),
+	Array(ArrayContent<T>),
+	MultiLineString(MultiLineStringContent),
+	Raw(RawContent),
+}
+
+/// Parsing is a recursive process. `Context` is a struct that holds the data
+/// associated with a recursive step in that process.
+#[derive(Clone)]
+struct Context<T: Tree> {
+	indent: usize,
+	content: ContextContent<T>,
+}
+
+impl<T: Tree> Context<T> {
+	fn object_context(indent: usize, pending_key: String, values: T::Map) -> Self {
+		Self {
+			indent,
+			content: ContextContent::Object(ObjectContent {
+				pending_key,
+				pending_path: Vec::new(),
+				values,
+			}),
+		}
+	}
+
+	fn array_context(indent: usize, builder: &T::Builder) -> Self {
+		Self {
+			indent,
+			content: ContextContent::Array(ArrayContent {
+				values: T::new_array(builder),
+			}),
+		}
+	}
+
+	fn multi_line_string_context(indent: usize) -> Self {
+		Self {
+			indent,
+			content: ContextContent::MultiLineString(MultiLineStringContent { lines: vec![] }),
+		}
+	}
+
+	fn raw_context(indent: usize, head: String) -> Self {
+		Self {
+			indent,
+			content: ContextContent::Raw(RawContent {
+				head,
+				lines: vec![],
+				indention: Indention::default(),
+			}),
+		}
+	}
+
+	fn is_object_context(&self) -> bool {
+		matches!(self.content, ContextContent::Object(_))
+	}
+
+	fn is_array_context(&self) -> bool {
+		matches!(self.content, ContextContent::Array(_))
+	}
+
+	fn get_indent(&self) -> usize {
+		self.indent
+	}
+
+	fn get_objects(self) -> Result<T::Map, ()> {
+		match self.content {
+			ContextContent::Object(obj) => Ok(obj.values),
+			_ => Err(()),
+		}
+	}
+
+	/// Sets the key the next value is added under, given as the path of a
+	/// possibly dotted key.
+	fn set_pending_key(&mut self, mut path: Vec<String>) {
+		match &mut self.content {
+			ContextContent::Object(obj) => {
+				obj.pending_key = path.remove(0);
+				obj.pending_path = path;
+			}
+			_ => panic!(),
+		}
+	}
+
+	fn push_v(&mut self, value: T, builder: &T::Builder) {
+		match &mut self.content {
+			ContextContent::Object(obj) => {
+				obj.insert_pending(value, builder);
+			}
+			ContextContent::Array(arr) => {
+				arr.values.push(value);
+			}
+			_ => panic!(),
+		}
+	}
+
+	fn into_value(self, builder: &mut T::Builder) -> T {
+		match self.content {
+			ContextContent::Object(obj) => T::object(obj.values),
+			ContextContent::Array(arr) => T::array(arr.values),
+			ContextContent::MultiLineString(mls) => {
+				T::primitive(builder, PrimitiveValue::String(mls.lines.join("\n")))
+			}
+			ContextContent::Raw(raw) => {
+				T::raw(builder, RawValue::new(raw.head, raw.lines, raw.indention))
+			}
+		}
+	}
+}
+
+/// Parses a `#kvon <version>` line at the top of a document. Returns `None`
+/// for other lines.
+pub(crate) fn parse_version_line(
+	scanner: &mut Scanner,
+	line: &str,
+	options: &ParserOptions,
+) -> ParserResult<Option<FormatVersion>> {
+	let Some(version) = line.strip_prefix("#kvon ") else {
+		return Ok(None);
+	};
+	let version = version.trim();
+	let Ok(version) = version.parse::<FormatVersion>() else {
+		scanner.advance_by("#kvon ".len());
+		scanner.consume_whitespaces();
+		return Err(scanner
+			.generate_error(ParserErrorKind::unexpected(version, &["version"]))
+			.with_hint(format!(
+				"declare the version as major and minor number, e.g. `#kvon {}`",
+				FormatVersion::LATEST
+			)));
+	};
+	if version > FormatVersion::LATEST && !options.allow_newer_versions {
+		return Err(scanner.generate_error(ParserErrorKind::UnsupportedVersion(version)));
+	}
+	Ok(Some(version))
+}
+
+/// Explains why a multi-line string ended at a blank or comment line, for
+/// the error when its lines continue below.
+pub(crate) fn block_end_hint(scanner: &Scanner) -> Option<&'static str> {
+	if scanner.rest().trim().is_empty() {
+		Some("blank lines inside a multi-line string must be indented like its other lines")
+	} else if scanner.see_end_or_comment() {
+		Some("a comment that isn't indented like a multi-line string's lines ends it")
+	} else {
+		None
+	}
+}
+
+/// The indention of a document, detected from its first indented line.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DocumentIndention {
+	pub(crate) indention: Option<Indention>,
+	/// The amount of tabs making up one level when indented with tabs, see
+	/// [ParserOptions::lenient_tab_indention].
+	tabs_per_level: usize,
+}
+
+impl DocumentIndention {
+	pub(crate) fn new() -> Self {
+		Self {
+			indention: None,
+			tabs_per_level: 1,
+		}
+	}
+
+	/// Defines the indention of the document from the whitespace of its first
+	/// indented line, which is one level deep.
+	fn detect(
+		&mut self,
+		scanner: &Scanner,
+		options: &ParserOptions,
+		tabs_count: usize,
+		spaces_count: usize,
+	) -> ParserResult<()> {
+		// mixed tabs and spaces are not allowed
+		if tabs_count > 0 && spaces_count > 0 {
+			return Err(scanner.generate_error(ParserErrorKind::MixedTabsAndSpaces));
+		}
+
+		// initial indention of more than one tabs is not allowed, unless it
+		// defines the width of a level
+		if tabs_count > 1 {
+			if !options.lenient_tab_indention {
+				return Err(scanner
+					.generate_error(ParserErrorKind::MultipleTabIndent)
+					.with_hint(format!(
+						"this line is indented by {tabs_count} tabs, but nested blocks are indented by one tab more than their parent"
+					)));
+			}
+			self.tabs_per_level = tabs_count;
+		}
+
+		self.indention = Some(if spaces_count > 0 {
+			Indention::Spaces(spaces_count)
+		} else {
+			Indention::Tabs
+		});
+		Ok(())
+	}
+
+	/// Calculates the indent level of a line's leading whitespace and auto
+	/// detects the indention if it has not been set yet.
+	pub(crate) fn level(
+		&mut self,
+		scanner: &Scanner,
+		options: &ParserOptions,
+		tabs_count: usize,
+		spaces_count: usize,
+	) -> ParserResult<usize> {
+		if tabs_count > 0 || spaces_count > 0 {
+			// mixed tabs and spaces are not allowed
+			if tabs_count > 0 && spaces_count > 0 {
+				return Err(scanner.generate_error(ParserErrorKind::MixedTabsAndSpaces));
+			}
+
+			// calculate the indent level
+			if let Some(indention) = &self.indention {
+				// check that the space and tab count makes a valid indention
+				// and return the indent level
+				match indention {
+					Indention::Tabs => {
+						if spaces_count > 0 {
+							Err(
+								scanner.generate_error(ParserErrorKind::InconsistentIndention(
+									*indention,
+									Indention::Spaces(spaces_count),
+								)),
+							)
+						} else if tabs_count.is_multiple_of(self.tabs_per_level) {
+							Ok(tabs_count / self.tabs_per_level)
+						} else {
+							let shallower = tabs_count / self.tabs_per_level * self.tabs_per_level;
+							Err(
+								scanner.generate_error(ParserErrorKind::MisalignedIndention {
+									indention: Indention::Tabs,
+									found: tabs_count,
+									shallower,
+									deeper: shallower + self.tabs_per_level,
+								}),
+							)
+						}
+					}
+					Indention::Spaces(spaces) => {
+						if spaces_count > 0 {
+							if spaces_count.is_multiple_of(*spaces) {
+								Ok(spaces_count / spaces)
+							} else {
+								// integer division would silently move the
+								// line into the shallower block
+								let shallower = spaces_count / spaces * spaces;
+								Err(
+									scanner.generate_error(ParserErrorKind::MisalignedIndention {
+										indention: *indention,
+										found: spaces_count,
+										shallower,
+										deeper: shallower + spaces,
+									}),
+								)
+							}
+						} else {
+							Err(
+								scanner.generate_error(ParserErrorKind::InconsistentIndention(
+									*indention,
+									Indention::Tabs,
+								)),
+							)
+						}
+					}
+				}
+			} else {
+				self.detect(scanner, options, tabs_count, spaces_count)?;
+				Ok(1)
+			}
+		} else {
+			Ok(0)
+		}
+	}
+
+	/// Consumes the indention of a line of a multi-line string or raw value
+	/// whose content is `indent` levels deep. Returns false if the line isn't
+	/// indented that deep.
+	pub(crate) fn consume_block_indention(
+		&mut self,
+		scanner: &mut Scanner,
+		options: &ParserOptions,
+		indent: usize,
+	) -> ParserResult<bool> {
+		// the first indented line of the file may be inside the block. Its
+		// indention is the first tab or all leading spaces, as anything
+		// after that is content, e.g. tabs in a spaces-indented file.
+		if self.indention.is_none() {
+			let (tabs_count, spaces_count) = if scanner.see("\t") {
+				(1, 0)
+			} else {
+				(0, scanner.leading_spaces())
+			};
+
+			// no indentions
+			if spaces_count == 0 && tabs_count == 0 {
+				return Ok(false);
+			}
+			self.detect(scanner, options, tabs_count, spaces_count)?;
+		}
+
+		// consume the leading indention
+		let indention = self.indention.unwrap();
+		let amount = match indention {
+			Indention::Tabs => indent * self.tabs_per_level,
+			Indention::Spaces(_) => indent,
+		};
+		Ok(scanner.have_indentions(indention, amount))
+	}
+}
+
+/// A change made to the parser while processing a line, recorded with error
+/// recovery to undo the changes of a line that fails.
+#[derive(Clone)]
+enum Change<T: Tree> {
+	/// The top context was popped and its value added to the context below
+	/// it. Multi-line strings and raw values are kept, as their lines can't
+	/// be recovered from the value.
+	Popped {
+		indent: usize,
+		content: Option<ContextContent<T>>,
+		pending_key: String,
+		pending_path: Vec<String>,
+	},
+	/// The pending key of the object at `depth` of the stack was set.
+	PendingKey {
+		depth: usize,
+		key: String,
+		path: Vec<String>,
+	},
+	/// A value was inserted into the object at `depth` of the stack, see
+	/// [ObjectContent::insert_pending].
+	Inserted {
+		depth: usize,
+		path: Vec<String>,
+		level: usize,
+		replaced: Option<T>,
+	},
+	/// A value was pushed to the array at `depth` of the stack.
+	Pushed { depth: usize },
+	Span {
+		path: KvonPath,
+		replaced: Option<Span>,
+	},
+	KeyLine {
+		path: KvonPath,
+		replaced: Option<(usize, bool)>,
+	},
+}
+
+/// The state of a parser before a line, besides the changes recorded as
+/// [Change]s. Restored when the line fails with error recovery.
+struct LineSnapshot {
+	indention: DocumentIndention,
+	stack_len: usize,
+	in_header: bool,
+	shebang: Option<String>,
+	version: Option<FormatVersion>,
+	directives: usize,
+	warnings: usize,
+	comments: usize,
+	#[cfg(feature = "checksum")]
+	checksum: Option<(usize, String)>,
+	block_ended_by: Option<&'static str>,
+	blank_lines: usize,
+	object_sizes: Vec<usize>,
+}
+
+/// A struct that processes lines one by one, decoding them and building
+/// [value::Value]s.
+#[derive(Clone)]
+pub struct Parser<T: Tree = Value> {
+	line_number: usize,
+	indention: DocumentIndention,
+	context_stack: Vec<Context<T>>,
+	builder: T::Builder,
+	options: Arc<ParserOptions>,
+	/// The start of every value, only recorded when enabled.
+	spans: Option<HashMap<KvonPath, Span>>,
+	/// The comments seen so far, only recorded when enabled.
+	comments: Option<Vec<Comment>>,
+	/// The last value started on the current line, only recorded with
+	/// comments.
+	line_path: Option<KvonPath>,
+	/// Whether only blank and comment lines have been parsed so far.
+	in_header: bool,
+	shebang: Option<String>,
+	directives: Vec<Directive>,
+	version: Option<FormatVersion>,
+	warnings: Vec<Warning>,
+	line_count: usize,
+	/// The `# sha256: ...` line at the top of the document.
+	#[cfg(feature = "checksum")]
+	checksum: Option<(usize, String)>,
+	/// The line every key was assigned on, and whether the object was only
+	/// created by a dotted key. Only recorded with
+	/// [ParserOptions::dotted_keys].
+	key_lines: HashMap<KvonPath, (usize, bool)>,
+	/// Why the last multi-line string or raw value ended, if it was a blank
+	/// or comment line. Explains the error when the block's lines continue
+	/// below that line.
+	block_ended_by: Option<&'static str>,
+	/// The blank lines seen since the last line of the current multi-line
+	/// string or raw value, see [ParserOptions::blank_lines_in_strings].
+	blank_lines: usize,
+	/// The number of entries of the object that ended last at each depth of
+	/// the stack. Objects are usually shaped like their siblings, e.g. the
+	/// items of an array, so their maps start with that capacity.
+	object_sizes: Vec<usize>,
+	/// The errors of the lines discarded so far, only recorded when
+	/// enabled with [Parser::with_error_recovery].
+	errors: Option<Vec<ParserError>>,
+	/// The changes made by the current line, only recorded with error
+	/// recovery.
+	changes: Option<Vec<Change<T>>>,
+}
+
+impl Default for Parser {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Parser {
+	pub fn new() -> Self {
+		Self::with_options(ParserOptions::default())
+	}
+
+	pub fn with_options(options: ParserOptions) -> Self {
+		Self::with_builder(options, ())
+	}
+}
+
+impl<T: Tree> Parser<T> {
+	/// A parser building its document as `T`, with the state `builder`.
+	pub(crate) fn with_builder(options: ParserOptions, builder: T::Builder) -> Self {
+		let root_context = Context::object_context(0, String::new(), T::new_object(&builder, 0));
+		Self {
+			line_number: 0,
+			indention: DocumentIndention::new(),
+			context_stack: vec![root_context],
+			builder,
+			options: Arc::new(options),
+			spans: None,
+			comments: None,
+			line_path: None,
+			in_header: true,
+			shebang: None,
+			directives: Vec::new(),
+			version: None,
+			warnings: Vec::new(),
+			line_count: 0,
+			#[cfg(feature = "checksum")]
+			checksum: None,
+			key_lines: HashMap::new(),
+			block_ended_by: None,
+			blank_lines: 0,
+			object_sizes: Vec::new(),
+			errors: None,
+			changes: None,
+		}
+	}
+
+	/// Sets the number of the first line, for documents embedded in a larger
+	/// file.
+	pub(crate) fn with_first_line_number(mut self, line_number: usize) -> Self {
+		self.line_number = line_number;
+		self
+	}
+
+	/// Records where every value starts, e.g. to point at the source of a
+	/// value that failed validation. See [Parser::finish_with_spans].
+	pub fn with_spans(mut self) -> Self {
+		self.spans = Some(HashMap::new());
+		self
+	}
+
+	/// Records the comments of the document, which are otherwise discarded.
+	/// Comments inside multi-line strings and raw values are part of their
+	/// content instead.
+	///
+	/// ```rust
+	/// use kvon_rs::Parser;
+	///
+	/// let mut parser = Parser::new().with_comments();
+	/// parser.next_line("# 2024-05-01: raised the limit").unwrap();
+	/// parser.next_line("limit: 10 # requests per second").unwrap();
+	///
+	/// let comments: Vec<_> = parser.comments().iter().map(|c| c.text.as_str()).collect();
+	/// assert_eq!(comments, ["2024-05-01: raised the limit", "requests per second"]);
+	/// ```
+	pub fn with_comments(mut self) -> Self {
+		self.comments = Some(Vec::new());
+		self
+	}
+
+	/// Keeps accepting lines after one fails, e.g. for an interactive shell.
+	/// [Parser::next_line] still returns the error, but the line is
+	/// discarded as if it hadn't been given, and the next lines continue the
+	/// document. The changes of every line are recorded to undo them, which
+	/// makes parsing a bit slower.
+	///
+	/// ```rust
+	/// use kvon_rs::{object, Parser};
+	///
+	/// let mut parser = Parser::new().with_error_recovery();
+	/// parser.next_line("server:").unwrap();
+	/// parser.next_line("  port: 80 80").unwrap_err();
+	/// parser.next_line("  port: 80").unwrap();
+	/// assert_eq!(parser.current_value(), object! { server: { port: 80 } });
+	/// assert_eq!(parser.errors()[0].line_number, 1);
+	/// ```
+	pub fn with_error_recovery(mut self) -> Self {
+		self.errors = Some(Vec::new());
+		self.changes = Some(Vec::new());
+		self
+	}
+
+	/// The errors of the lines discarded so far, in order. Empty unless
+	/// enabled with [Parser::with_error_recovery].
+	pub fn errors(&self) -> &[ParserError] {
+		self.errors.as_deref().unwrap_or_default()
+	}
+
+	/// The document parsed so far, with its open blocks ended like by
+	/// [Parser::finish], e.g. to inspect a partially entered document.
+	pub fn current_value(&self) -> T {
+		let mut builder = self.builder.clone();
+		let mut stack = self.context_stack.clone();
+		while stack.len() > 1 {
+			let context = stack.pop().unwrap();
+			let value = context.into_value(&mut builder);
+			stack.last_mut().unwrap().push_v(value, &builder);
+		}
+		stack.pop().unwrap().into_value(&mut builder)
+	}
+
+	/// The comments of the lines parsed so far, in order. Empty unless
+	/// enabled with [Parser::with_comments].
+	pub fn comments(&self) -> &[Comment] {
+		self.comments.as_deref().unwrap_or_default()
+	}
+
+	/// The `#!` line the document starts with, without the `#!`, e.g.
+	/// `/usr/bin/env app`.
+	pub fn shebang(&self) -> Option<&str> {
+		self.shebang.as_deref()
+	}
+
+	/// The `#kvon:` directives at the top of the document, before its first
+	/// key. These lines aren't reported as comments.
+	///
+	/// ```rust
+	/// use kvon_rs::Parser;
+	///
+	/// let mut parser = Parser::new();
+	/// parser.next_line("#!/usr/bin/env app").unwrap();
+	/// parser.next_line("#kvon: schema app.kvon").unwrap();
+	/// parser.next_line("port: 80").unwrap();
+	///
+	/// assert_eq!(parser.shebang(), Some("/usr/bin/env app"));
+	/// assert_eq!(parser.directives()[0].name, "schema");
+	/// assert_eq!(parser.directives()[0].value, "app.kvon");
+	/// ```
+	pub fn directives(&self) -> &[Directive] {
+		&self.directives
+	}
+
+	/// The version the document declares with a `#kvon 1.0` line before its
+	/// first key, if any.
+	///
+	/// ```rust
+	/// use kvon_rs::{FormatVersion, Parser};
+	///
+	/// let mut parser = Parser::new();
+	/// parser.next_line("#kvon 1.0").unwrap();
+	/// assert_eq!(parser.version(), Some(FormatVersion { major: 1, minor: 0 }));
+	///
+	/// // newer versions may contain syntax this parser doesn't know about
+	/// assert!(Parser::new().next_line("#kvon 2.0").is_err());
+	/// ```
+	pub fn version(&self) -> Option<FormatVersion> {
+		self.version
+	}
+
+	/// The non-fatal issues found in the lines parsed so far.
+	pub fn warnings(&self) -> &[Warning] {
+		&self.warnings
+	}
+
+	/// Handles shebang, version, directive and checksum lines at the top of
+	/// the document. Returns false for other lines.
+	fn process_header_line(&mut self, scanner: &mut Scanner, line: &str) -> ParserResult<bool> {
+		#[cfg(feature = "checksum")]
+		if let Some(checksum) = line.strip_prefix(crate::checksum::CHECKSUM_PREFIX) {
+			self.checksum = Some((self.line_number, checksum.trim().to_string()));
+			return Ok(true);
+		}
+
+		if let Some(shebang) = line.strip_prefix("#!") {
+			// only the first line can be a shebang
+			if self.line_number == 0 {
+				self.shebang = Some(shebang.trim().to_string());
+				return Ok(true);
+			}
+		}
+
+		if let Some(version) = parse_version_line(scanner, line, &self.options)? {
+			self.version = Some(version);
+			return Ok(true);
+		}
+
+		let Some(directive) = line.strip_prefix("#kvon:") else {
+			return Ok(false);
+		};
+		let directive = directive.trim();
+		let (name, value) = directive
+			.split_once(char::is_whitespace)
+			.unwrap_or((directive, ""));
+		self.directives.push(Directive {
+			name: name.to_string(),
+			value: value.trim().to_string(),
+			line_number: self.line_number,
+		});
+		Ok(true)
+	}
+
+	/// Records the comment ending the line, if there is one.
+	fn record_comment(&mut self, scanner: &mut Scanner) {
+		if let Some(comments) = &mut self.comments {
+			scanner.consume_whitespaces();
+			let column_number = scanner.column();
+			if let Some(text) = scanner.parse_comment() {
+				comments.push(Comment {
+					span: Span {
+						line_number: self.line_number,
+						column_number,
+					},
+					text: text.trim().to_string(),
+					path: self.line_path.take(),
+				});
+			}
+		}
+	}
+
+	/// The path of the context on top of the stack.
+	fn current_path(&self) -> KvonPath {
+		let mut path = KvonPath::root();
+		for context in &self.context_stack[..self.context_stack.len() - 1] {
+			match &context.content {
+				ContextContent::Object(obj) => {
+					path.push(PathSegment::Key(obj.pending_key.clone()));
+					path.extend(obj.pending_path.iter().cloned().map(PathSegment::Key));
+				}
+				ContextContent::Array(arr) => path.push(PathSegment::Index(arr.values.len())),
+				_ => unreachable!("only objects and arrays have children"),
+			}
+		}
+		path
+	}
+
+	/// Records the start of the value that is added next to the top context
+	/// under `segment`.
+	fn record_span(&mut self, segment: PathSegment, column_number: usize) {
+		if self.records_paths() {
+			let mut path = self.current_path();
+			path.push(segment);
+			self.insert_span(path, column_number);
+		}
+	}
+
+	fn insert_span(&mut self, path: KvonPath, column_number: usize) {
+		let span = Span {
+			line_number: self.line_number,
+			column_number,
+		};
+		if self.comments.is_some() {
+			self.line_path = Some(path.clone());
+		}
+		if let Some(spans) = &mut self.spans {
+			match &mut self.changes {
+				Some(changes) => {
+					let replaced = spans.insert(path.clone(), span);
+					changes.push(Change::Span { path, replaced });
+				}
+				None => {
+					spans.insert(path, span);
+				}
+			}
+		}
+	}
+
+	/// Whether the paths of values are needed, for spans or comments.
+	fn records_paths(&self) -> bool {
+		self.spans.is_some() || self.comments.is_some()
+	}
+
+	/// The index the next item of the array on top of the stack will have.
+	fn next_index(&self) -> usize {
+		match &self.context_stack.last().unwrap().content {
+			ContextContent::Array(arr) => arr.values.len(),
+			_ => 0,
+		}
+	}
+
+	/// A context for an object pushed onto the stack next, sized like the
+	/// last object at its depth.
+	fn object_context(&self, indent: usize, pending_key: String) -> Context<T> {
+		let capacity = self
+			.object_sizes
+			.get(self.context_stack.len())
+			.copied()
+			.unwrap_or_default();
+		Context::object_context(indent, pending_key, T::new_object(&self.builder, capacity))
+	}
+
+	/// The object holding `value` under a possibly dotted key.
+	fn nest(&self, key: &[String], value: T) -> T {
+		key.iter().rev().fold(value, |value, key| {
+			let mut entries = T::new_object(&self.builder, 1);
+			entries.insert(key.clone(), value);
+			T::object(entries)
+		})
+	}
+
+	/// Removes the top context from the stack and merges it to the context
+	/// below it.
+	fn pop_stack(&mut self) {
+		// remove the top context
+		let context = self.context_stack.pop().unwrap();
+		if let ContextContent::Object(obj) = &context.content {
+			let depth = self.context_stack.len();
+			if self.object_sizes.len() <= depth {
+				self.object_sizes.resize(depth + 1, 0);
+			}
+			self.object_sizes[depth] = obj.values.len();
+		}
+		if let Some(changes) = &mut self.changes {
+			let (content, pending_key, pending_path) = match &context.content {
+				ContextContent::Object(obj) => {
+					(None, obj.pending_key.clone(), obj.pending_path.clone())
+				}
+				ContextContent::Array(_) => (None, String::new(), Vec::new()),
+				content => (Some(content.clone()), String::new(), Vec::new()),
+			};
+			changes.push(Change::Popped {
+				indent: context.indent,
+				content,
+				pending_key,
+				pending_path,
+			});
+		}
+
+		// add it to the context underneath
+		let value = context.into_value(&mut self.builder);
+		self.push_value(value);
+	}
+
+	/// Adds a value to the context on top of the stack, under its pending key
+	/// if it's an object.
+	fn push_value(&mut self, value: T) {
+		let depth = self.context_stack.len() - 1;
+		match &mut self.context_stack[depth].content {
+			ContextContent::Object(obj) => {
+				let path = self.changes.as_ref().map(|_| {
+					std::iter::once(obj.pending_key.clone())
+						.chain(obj.pending_path.iter().cloned())
+						.collect()
+				});
+				let (level, replaced) = obj.insert_pending(value, &self.builder);
+				if let (Some(changes), Some(path)) = (&mut self.changes, path) {
+					changes.push(Change::Inserted {
+						depth,
+						path,
+						level,
+						replaced,
+					});
+				}
+			}
+			ContextContent::Array(arr) => {
+				arr.values.push(value);
+				if let Some(changes) = &mut self.changes {
+					changes.push(Change::Pushed { depth });
+				}
+			}
+			_ => panic!(),
+		}
+	}
+
+	/// Sets the key the next value of the object on top of the stack is added
+	/// under, given as the path of a possibly dotted key.
+	fn set_pending_key(&mut self, key: Vec<String>) {
+		let depth = self.context_stack.len() - 1;
+		let top = &mut self.context_stack[depth];
+		if let (Some(changes), ContextContent::Object(obj)) = (&mut self.changes, &top.content) {
+			changes.push(Change::PendingKey {
+				depth,
+				key: obj.pending_key.clone(),
+				path: obj.pending_path.clone(),
+			});
+		}
+		top.set_pending_key(key);
+	}
+
+	// Collapses context from the top of the stack until the indent of the top
+	// context doesn't exceed the given indent.
+	fn collapse_context_to_indent(&mut self, indent: usize) {
+		while self
+			.context_stack
+			.last()
+			.map(|ctx| ctx.get_indent())
+			.unwrap() > indent
+		{
+			self.pop_stack();
+		}
+	}
+
+	/// Collapses all contexts from the stack until only one remains - the root
+	/// object context.
+	pub fn collapse_context(&mut self) {
+		self.collapse_context_to_indent(0);
+	}
+
+	/// Returns true if the value of `key` in the top context should be
+	/// captured as a raw value. Only keys nested in objects can be raw.
+	fn is_raw_key(&self, key: &[String]) -> bool {
+		if self.options.raw_paths.is_empty() {
+			return false;
+		}
+
+		let mut path = Vec::with_capacity(self.context_stack.len());
+		for (i, context) in self.context_stack.iter().enumerate() {
+			match &context.content {
+				ContextContent::Object(_) if i + 1 == self.context_stack.len() => {}
+				ContextContent::Object(obj) => {
+					path.push(obj.pending_key.as_str());
+					path.extend(obj.pending_path.iter().map(String::as_str));
+				}
+				_ => return false,
+			}
+		}
+		path.extend(key.iter().map(String::as_str));
+
+		self.options
+			.raw_paths
+			.iter()
+			.any(|raw_path| raw_path.iter().eq(path.iter()))
+	}
+
+	/// Returns an error if `key` would replace a value of another line instead
+	/// of extending it, i.e. if a dotted key runs into a value that isn't an
+	/// object, or a key replaces an object created by dotted keys. Keys
+	/// assigned twice only produce a [Warning::DuplicateKey]. The error points
+	/// at `column`, the start of the key.
+	fn check_key_conflict(
+		&mut self,
+		scanner: &Scanner,
+		key: &[String],
+		column: usize,
+	) -> ParserResult<()> {
+		let ContextContent::Object(obj) = &self.context_stack.last().unwrap().content else {
+			return Ok(());
+		};
+		let mut path = self.current_path();
+		let mut existing: Option<&T> = None;
+		for (i, segment) in key.iter().enumerate() {
+			existing = match i {
+				0 => obj.values.get(segment),
+				_ => existing.and_then(|value| value.get(segment)),
+			};
+			let Some(value) = existing else {
+				break;
+			};
+			path.push(PathSegment::Key(segment.clone()));
+			let Some(&(other_line_number, implicit)) = self.key_lines.get(&path) else {
+				continue;
+			};
+			let last = i + 1 == key.len();
+			let prefix = path.to_string();
+			let hint = match (last, value.is_object()) {
+				(false, false) => format!("'{prefix}' holds a value, so it can't also hold keys"),
+				(true, _) if implicit => {
+					format!("this would replace the keys nested under '{prefix}' by dotted keys")
+				}
+				_ => continue,
+			};
+			let mut path = self.current_path();
+			path.extend(key.iter().cloned().map(PathSegment::Key));
+			let error = scanner.generate_error(ParserErrorKind::KeyConflict {
+				path: path.to_string(),
+				other_line_number,
+			});
+			return Err(ParserError {
+				column_number: column,
+				..error
+			}
+			.with_hint(hint));
+		}
+
+		let mut path = self.current_path();
+		for (i, segment) in key.iter().enumerate() {
+			path.push(PathSegment::Key(segment.clone()));
+			let key_line = match self.key_lines.get(&path) {
+				Some(&key_line) if i + 1 < key.len() => key_line,
+				_ => (self.line_number, i + 1 < key.len()),
+			};
+			let replaced = self.key_lines.insert(path.clone(), key_line);
+			if let Some(changes) = &mut self.changes {
+				changes.push(Change::KeyLine {
+					path: path.clone(),
+					replaced,
+				});
+			}
+		}
+		Ok(())
+	}
+
+	/// Processes a line whose indention has been consumed in the context of an
+	/// object.
+	fn process_post_indent_object(
+		&mut self,
+		scanner: &mut Scanner,
+		indent: usize,
+	) -> ParserResult<()> {
+		// key
+		let column = scanner.column();
+		let key = scanner.parse_key_path()?;
+		if self.options.dotted_keys {
+			self.check_key_conflict(scanner, &key, column)?;
+		}
+		if self.records_paths() {
+			let mut path = self.current_path();
+			path.extend(key.iter().cloned().map(PathSegment::Key));
+			self.insert_span(path, column);
+		}
+		if let ContextContent::Object(obj) = &self.context_stack.last().unwrap().content {
+			let mut existing = obj.values.get(&key[0]);
+			for key in &key[1..] {
+				existing = existing.and_then(|value| value.get(key));
+			}
+			if existing.is_some() {
+				let mut path = self.current_path();
+				path.extend(key.iter().cloned().map(PathSegment::Key));
+				self.warnings.push(Warning::DuplicateKey {
+					path,
+					line_number: self.line_number,
+				});
+			}
+		}
+
+		// whitespace
+		scanner.consume_whitespaces();
+
+		// raw value - capture the rest of the line and the indented block
+		// below it without parsing
+		if scanner.see(":") && self.is_raw_key(&key) {
+			scanner.have(":");
+			let head = scanner.consume_rest().trim_end().to_string();
+
+			self.set_pending_key(key);
+			self.context_stack
+				.push(Context::raw_context(indent + 1, head));
+			return Ok(());
+		}
+
+		// array
+		if scanner.have(":--") {
+			scanner.expect_end_after_marker(":--")?;
+
+			// set the key to the current context
+			self.set_pending_key(key);
+
+			// push the array context
+			self.context_stack
+				.push(Context::array_context(indent + 1, &self.builder));
+			return Ok(());
+		}
+
+		// object or value
+		if scanner.have(":") {
+			scanner.consume_whitespaces();
+
+			self.set_pending_key(key);
+
+			// object - push a new context
+			if scanner.see_end_or_comment() {
+				let context = self.object_context(indent + 1, String::new());
+				self.context_stack.push(context);
+				return Ok(());
+			}
+
+			if let Some(value) = scanner.parse_inline_array()? {
+				// inlined array
+				let value = T::value(&mut self.builder, value);
+				self.push_value(value);
+			} else if let Some(primitive) = scanner.parse_primitive()? {
+				// value
+				let value = T::primitive(&mut self.builder, primitive);
+				self.push_value(value);
+			} else if scanner.have("|") {
+				// multi-line string
+				scanner.expect_end_after_marker("|")?;
+				self.context_stack
+					.push(Context::multi_line_string_context(indent + 1));
+			} else {
+				let error = scanner.generate_unexpected_error(&["value", "end of line"]);
+				return Err(if scanner.see(":") {
+					error.with_hint("remove the duplicate colon")
+				} else {
+					error
+				});
+			}
+
+			// expected to reach end of line
+			if scanner.see_end_or_comment() {
+				return Ok(());
+			} else {
+				return Err(scanner.generate_trailing_content_error());
+			}
+		}
+
+		// if found something other than the end of line or a comment,
+		// return an error
+		if !scanner.see_end_or_comment() {
+			return Err(scanner
+				.generate_unexpected_error(&[":", "end of line", "comment"])
+				.with_hint("separate keys from values with a colon, e.g. `key: value`"));
+		}
+
+		self.set_pending_key(key);
+		let value = T::primitive(&mut self.builder, PrimitiveValue::Null);
+		self.push_value(value);
+
+		Ok(())
+	}
+
+	/// Processes a line whose indention has been consumed in the context of an
+	/// array.
+	fn process_post_indent_array(
+		&mut self,
+		scanner: &mut Scanner,
+		indent: usize,
+	) -> ParserResult<()> {
+		// sub array
+		let column = scanner.column();
+		if scanner.have("--") {
+			scanner.expect_end_after_marker("--")?;
+			self.record_span(PathSegment::Index(self.next_index()), column);
+			self.context_stack
+				.push(Context::array_context(indent + 1, &self.builder));
+			return Ok(());
+		}
+
+		// array entries must start with `-`
+		if !scanner.have("-") {
+			return Err(scanner.generate_error(ParserErrorKind::expected("-")));
+		}
+		scanner.consume_whitespaces();
+
+		// object with more than one key
+		if scanner.see_end_or_comment() {
+			self.record_span(PathSegment::Index(self.next_index()), column);
+			let context = self.object_context(indent + 1, String::new());
+			self.context_stack.push(context);
+			return Ok(());
+		}
+
+		// object with one key
+		let key_column = scanner.column();
+		if let Some(key) = scanner.parse_key_path_with_colon()? {
+			scanner.consume_whitespaces();
+
+			if self.records_paths() {
+				let mut path = self.current_path();
+				path.push(PathSegment::Index(self.next_index()));
+				self.insert_span(path.clone(), column);
+				path.extend(key.iter().cloned().map(PathSegment::Key));
+				self.insert_span(path, key_column);
+			}
+
+			let mut single_root = self.object_context(indent + 1, String::new());
+			single_root.set_pending_key(key.clone());
+
+			// object context with single root
+			if scanner.see_end_or_comment() {
+				self.context_stack.push(single_root);
+				let context = self.object_context(indent + 1, String::new());
+				self.context_stack.push(context);
+				return Ok(());
+			}
+
+			if let Some(value) = scanner.parse_inline_array()? {
+				// inlined array
+				let value = T::value(&mut self.builder, value);
+				let value = self.nest(&key, value);
+				self.push_value(value);
+			} else if let Some(primitive) = scanner.parse_primitive()? {
+				// primitive
+				let value = T::primitive(&mut self.builder, primitive);
+				let value = self.nest(&key, value);
+				self.push_value(value);
+			} else if scanner.have("|") {
+				// object context with single root and multi line string value
+				scanner.expect_end_after_marker("|")?;
+				self.context_stack.push(single_root);
+				self.context_stack
+					.push(Context::multi_line_string_context(indent + 1));
+			}
+
+			// expected to reach end of line
+			if scanner.see_end_or_comment() {
+				return Ok(());
+			} else {
+				return Err(scanner.generate_trailing_content_error());
+			}
+		}
+
+		// multi-line string
+		if scanner.have("|") {
+			scanner.expect_end_after_marker("|")?;
+			self.record_span(PathSegment::Index(self.next_index()), column);
+			self.context_stack
+				.push(Context::multi_line_string_context(indent + 1));
+			return Ok(());
+		}
+
+		// iterate over all the values on the line
+		loop {
+			scanner.consume_whitespaces();
+			if scanner.see_end_or_comment() {
+				break;
+			}
+			self.record_span(PathSegment::Index(self.next_index()), scanner.column());
+
+			// inlined array
+			if let Some(value) = scanner.parse_inline_array()? {
+				let value = T::value(&mut self.builder, value);
+				self.push_value(value);
+				continue;
+			}
+
+			// value
+			if let Some(primitive) = scanner.parse_primitive()? {
+				let value = T::primitive(&mut self.builder, primitive);
+				self.push_value(value);
+				continue;
+			}
+
+			// entries that have to be alone on their line
+			let entry = if scanner.see("|") {
+				Some("multi-line string")
+			} else {
+				scanner.record();
+				let is_pair = matches!(scanner.parse_key_path_with_colon(), Ok(Some(_)));
+				scanner.restore();
+				is_pair.then_some("key-value pair")
+			};
+			return Err(match entry {
+				Some(entry) => scanner.generate_error(ParserErrorKind::MixedArrayEntries { entry }),
+				None => scanner.generate_unexpected_error(&["value"]),
+			});
+		}
+
+		// if found something other than the end of line or a comment,
+		// return an error
+		if !scanner.see_end_or_comment() {
+			return Err(scanner.generate_unexpected_error(END_OF_LINE));
+		}
+
+		Ok(())
+	}
+
+	/// Returns true if the line belongs to the multi-line string or raw value.
+	/// Returns false if it doesn't and the context has been popped or the top
+	/// context isn't a multi-line string or a raw value.
+	fn process_multi_line_string_line(&mut self, scanner: &mut Scanner) -> ParserResult<bool> {
+		let last = self.context_stack.last_mut().unwrap();
+		let indent = last.get_indent();
+		if matches!(
+			last.content,
+			ContextContent::MultiLineString(_) | ContextContent::Raw(_)
+		) {
+			let options = Arc::clone(&self.options);
+			if !self
+				.indention
+				.consume_block_indention(scanner, &options, indent)?
+			{
+				// there weren't enough leading indents - the multi line
+				// string ended.
+				if self.skip_blank_line(scanner) {
+					return Ok(true);
+				}
+				self.end_block(scanner);
+				return Ok(false);
+			}
+
+			// the rest of the line belongs to the string or raw value
+			let rest = scanner.consume_rest().to_string();
+			let blank_lines = std::mem::take(&mut self.blank_lines);
+			let blank_lines = std::iter::repeat_n(String::new(), blank_lines);
+			match &mut self.context_stack.last_mut().unwrap().content {
+				ContextContent::MultiLineString(mls) => {
+					mls.lines.extend(blank_lines);
+					mls.lines.push(rest);
+				}
+				ContextContent::Raw(raw) => {
+					raw.indention = self.indention.indention.unwrap_or_default();
+					raw.lines.extend(blank_lines);
+					raw.lines.push(rest);
+				}
+				_ => unreachable!(),
+			}
+			Ok(true)
+		} else {
+			Ok(false)
+		}
+	}
+
+	/// Skips a blank line inside a multi-line string or raw value if enabled
+	/// with [ParserOptions::blank_lines_in_strings]. Whether it belongs to
+	/// the block is only known once the block continues.
+	fn skip_blank_line(&mut self, scanner: &Scanner) -> bool {
+		let skip = self.options.blank_lines_in_strings && scanner.rest().trim().is_empty();
+		if skip {
+			self.blank_lines += 1;
+		}
+		skip
+	}
+
+	/// Ends a multi-line string or raw value at a line that isn't indented
+	/// like its content. Blank lines before it don't belong to the block.
+	fn end_block(&mut self, scanner: &Scanner) {
+		self.blank_lines = 0;
+		self.block_ended_by = block_end_hint(scanner);
+		self.pop_stack();
+	}
+
+	/// Calculates indention and then calls any of the `process_post_indent`
+	/// methods.
+	fn process_line(&mut self, line: &str) -> ParserResult<()> {
+		// wrap the line in a scanner
+		let options = Arc::clone(&self.options);
+		let mut scanner = Scanner::new(self.line_number, line, &options);
+
+		self.line_path = None;
+
+		// handle multi-line strings
+		if self.process_multi_line_string_line(&mut scanner)? {
+			return Ok(());
+		}
+
+		// check if line has no content
+		if scanner.see_end_or_comment() {
+			if !(self.in_header && self.process_header_line(&mut scanner, line)?) {
+				self.record_comment(&mut scanner);
+			}
+			return Ok(());
+		}
+		self.in_header = false;
+
+		// parse whitespaces
+		let (tabs_count, spaces_count) = scanner.next_whitespaces();
+
+		// calculate indent level
+		let indent = self
+			.indention
+			.level(&scanner, &options, tabs_count, spaces_count)?;
+
+		// calculate the maximum indent the next item is allowed to be in
+		let max_indent = match self.context_stack.last() {
+			Some(ctx) => ctx.get_indent(),
+			None => 0,
+		};
+
+		// if the indent is invalid, return an error
+		let block_ended_by = self.block_ended_by.take();
+		if indent > max_indent {
+			let error = scanner.generate_error(ParserErrorKind::InvalidIndention);
+			return Err(match block_ended_by {
+				Some(hint) => error.with_hint(hint),
+				None => error,
+			});
+		}
+
+		// pop contexts to match the indent
+		self.collapse_context_to_indent(indent);
+
+		let top = self.context_stack.last().unwrap();
+		if top.is_object_context() {
+			// handle the rest of the line as an object's line
+			self.process_post_indent_object(&mut scanner, indent)?;
+		} else if top.is_array_context() {
+			// handle the rest of the line as an array's line
+			self.process_post_indent_array(&mut scanner, indent)?;
+		}
+
+		self.record_comment(&mut scanner);
+		Ok(())
+	}
+
+	/// The state to restore if the next line fails, see
+	/// [Parser::with_error_recovery].
+	fn snapshot(&mut self) -> LineSnapshot {
+		self.changes.as_mut().unwrap().clear();
+		LineSnapshot {
+			indention: self.indention,
+			stack_len: self.context_stack.len(),
+			in_header: self.in_header,
+			shebang: self.shebang.clone(),
+			version: self.version,
+			directives: self.directives.len(),
+			warnings: self.warnings.len(),
+			comments: self.comments().len(),
+			#[cfg(feature = "checksum")]
+			checksum: self.checksum.clone(),
+			block_ended_by: self.block_ended_by,
+			blank_lines: self.blank_lines,
+			object_sizes: self.object_sizes.clone(),
+		}
+	}
+
+	/// Undoes the changes of a line that failed, in reverse order.
+	fn undo_line(&mut self, snapshot: LineSnapshot) {
+		let changes = std::mem::take(self.changes.as_mut().unwrap());
+
+		// contexts pushed by the line are dropped, popped ones pushed again
+		let popped = changes
+			.iter()
+			.filter(|change| matches!(change, Change::Popped { .. }))
+			.count();
+		self.context_stack.truncate(snapshot.stack_len - popped);
+		let mut removed = None;
+		for change in changes.into_iter().rev() {
+			match change {
+				Change::Popped {
+					indent,
+					content,
+					pending_key,
+					pending_path,
+				} => {
+					let value: T = removed.take().unwrap();
+					let content = match content {
+						Some(content) => content,
+						None if value.is_object() => ContextContent::Object(ObjectContent {
+							pending_key,
+							pending_path,
+							values: value.into_object().unwrap(),
+						}),
+						None => ContextContent::Array(ArrayContent {
+							values: value.into_array().unwrap(),
+						}),
+					};
+					self.context_stack.push(Context { indent, content });
+				}
+				Change::PendingKey { depth, key, path } => {
+					if let ContextContent::Object(obj) = &mut self.context_stack[depth].content {
+						obj.pending_key = key;
+						obj.pending_path = path;
+					}
+				}
+				Change::Inserted {
+					depth,
+					path,
+					level,
+					replaced,
+				} => {
+					if let ContextContent::Object(obj) = &mut self.context_stack[depth].content {
+						removed = Some(obj.remove_inserted(&path, level, replaced));
+					}
+				}
+				Change::Pushed { depth } => {
+					if let ContextContent::Array(arr) = &mut self.context_stack[depth].content {
+						removed = arr.values.pop();
+					}
+				}
+				Change::Span { path, replaced } => {
+					let spans = self.spans.as_mut().unwrap();
+					match replaced {
+						Some(span) => spans.insert(path, span),
+						None => spans.remove(&path),
+					};
+				}
+				Change::KeyLine { path, replaced } => {
+					match replaced {
+						Some(key_line) => self.key_lines.insert(path, key_line),
+						None => self.key_lines.remove(&path),
+					};
+				}
+			}
+		}
+
+		self.indention = snapshot.indention;
+		self.in_header = snapshot.in_header;
+		self.shebang = snapshot.shebang;
+		self.version = snapshot.version;
+		self.directives.truncate(snapshot.directives);
+		self.warnings.truncate(snapshot.warnings);
+		if let Some(comments) = &mut self.comments {
+			comments.truncate(snapshot.comments);
+		}
+		#[cfg(feature = "checksum")]
+		{
+			self.checksum = snapshot.checksum;
+		}
+		self.block_ended_by = snapshot.block_ended_by;
+		self.blank_lines = snapshot.blank_lines;
+		self.object_sizes = snapshot.object_sizes;
+	}
+
+	/// Parses another line.
+	pub fn next_line(&mut self, line: &str) -> ParserResult<()> {
+		if let Some(token) = &self.options.cancel_token {
+			if token.is_cancelled() {
+				return Err(ParserError {
+					kind: ParserErrorKind::Cancelled,
+					line_number: self.line_number,
+					column_number: 0,
+					line: line.to_string(),
+					hint: None,
+				});
+			}
+		}
+
+		if self.errors.is_some() {
+			let snapshot = self.snapshot();
+			if let Err(error) = self.process_line(line) {
+				// discard the line, but keep its number for the next ones
+				self.undo_line(snapshot);
+				self.errors.as_mut().unwrap().push(error.clone());
+				self.line_number += 1;
+				self.line_count += 1;
+				return Err(error);
+			}
+		} else {
+			self.process_line(line)?;
+		}
+		self.line_number += 1;
+		self.line_count += 1;
+		Ok(())
+	}
+
+	/// Parses the lines of a string, calling the [ParserOptions::progress]
+	/// hook after each of them.
+	pub(crate) fn parse_lines(&mut self, s: &str) -> ParserResult<()> {
+		let mut progress = Progress { bytes: 0, lines: 0 };
+		for line in s.split_inclusive('\n') {
+			progress.bytes += line.len();
+			progress.lines += 1;
+			self.next_line(strip_line_break(line))?;
+			self.report_progress(progress);
+		}
+		Ok(())
+	}
+
+	/// Parses the lines of a reader like [Parser::parse_lines]. Failing to
+	/// read, e.g. invalid UTF-8, produces a [ParserErrorKind::Read] error.
+	pub(crate) fn parse_reader(&mut self, r: impl Read) -> ParserResult<()> {
+		let mut reader = BufReader::new(r);
+		let mut line = String::new();
+		let mut progress = Progress { bytes: 0, lines: 0 };
+		loop {
+			let amount = reader.read_line(&mut line).map_err(|error| ParserError {
+				kind: ParserErrorKind::Read(error.to_string()),
+				line_number: self.line_number,
+				column_number: 0,
+				line: String::new(),
+				hint: None,
+			})?;
+			if amount == 0 {
+				return Ok(());
+			}
+			self.next_line(strip_line_break(&line))?;
+			line.clear();
+
+			progress.bytes += amount;
+			progress.lines += 1;
+			self.report_progress(progress);
+		}
+	}
+
+	fn report_progress(&self, progress: Progress) {
+		if let Some(ProgressHook(hook)) = &self.options.progress {
+			hook(progress);
+		}
+	}
+
+	/// Collapses all remaining contexts and returns the parsed root object,
+	/// like [Parser::finish]. The parser is left without a document.
+	pub(crate) fn finish_tree(&mut self) -> ParserResult<T> {
+		if self.options.error_on_unclosed_blocks {
+			let expected = match &self.context_stack.last().unwrap().content {
+				ContextContent::MultiLineString(mls) if mls.lines.is_empty() => {
+					Some("multi-line string content")
+				}
+				ContextContent::Array(arr) if arr.values.is_empty() => Some("array elements"),
+				_ => None,
+			};
+
+			if let Some(expected) = expected {
+				return Err(ParserError {
+					kind: ParserErrorKind::UnexpectedEof {
+						expected: expected.to_string(),
+					},
+					line_number: self.line_number,
+					column_number: 0,
+					line: String::new(),
+					hint: Some("the input may have been truncated".to_string()),
+				});
+			}
+		}
+
+		self.collapse_context();
+
+		Ok(T::object(
+			self.context_stack.pop().unwrap().get_objects().unwrap(),
+		))
+	}
+
+	/// The state the document was built with, e.g. a string interner.
+	pub(crate) fn builder_mut(&mut self) -> &mut T::Builder {
+		&mut self.builder
+	}
+}
+
+impl Parser {
+	/// Like [Parser::finish], but also returns where each value starts, keyed
+	/// by its path. Empty unless enabled with [Parser::with_spans].
+	pub fn finish_with_spans(mut self) -> ParserResult<(Value, HashMap<KvonPath, Span>)> {
+		let spans = self.spans.take().unwrap_or_default();
+		Ok((self.finish()?, spans))
+	}
+
+	/// Like [Parser::finish], but also returns the comments, warnings and
+	/// style of the document. Comments are only recorded if enabled with
+	/// [Parser::with_comments].
+	pub fn finish_output(mut self) -> ParserResult<ParseOutput> {
+		let comments = self.comments.take().unwrap_or_default();
+		let detected_indention = self.indention.indention;
+		let line_count = self.line_count;
+		let warnings = std::mem::take(&mut self.warnings);
+		let directives = std::mem::take(&mut self.directives);
+		let shebang = self.shebang.take();
+		let version = self.version;
+		let profile = self.options.profile;
+		#[cfg(feature = "checksum")]
+		let checksum = self.checksum.take();
+		let value = self.finish()?;
+		#[cfg(feature = "checksum")]
+		let warnings = {
+			let mut warnings = warnings;
+			if let Some((line_number, expected)) = checksum {
+				let found = crate::checksum::checksum(&value);
+				if found != expected {
+					warnings.push(Warning::ChecksumMismatch {
+						line_number,
+						expected,
+						found,
+					});
+				}
+			}
+			warnings
+		};
+		Ok(ParseOutput {
+			value,
+			detected_indention,
+			line_count,
+			comments,
+			warnings,
+			directives,
+			shebang,
+			version,
+			profile,
+		})
+	}
+
+	/// Collapses all remaining contexts and returns the parsed root object.
+	pub fn finish(mut self) -> ParserResult<Value> {
+		self.finish_tree()
+	}
+}
@@ -0,0 +1,285 @@
+//! Patches are lists of [Change]s written as KVON documents, so that config
+//! changes can be reviewed and applied without sending whole files.
+//!
+//! ```rust
+//! use kvon_rs::{
+//!     diff::diff,
+//!     object,
+//!     patch::{apply_patch, encode_patch, parse_patch},
+//! };
+//!
+//! let old = object! { server: { port: 80 }, debug: true };
+//! let new = object! { server: { port: 8080, tls: true } };
+//!
+//! let patch = encode_patch(&diff(&old, &new));
+//! assert_eq!(
+//!     patch,
+//!     "changes:--
+//! \t-
+//! \t\tremove: 'debug'
+//! \t\tvalue: true
+//! \t-
+//! \t\tchange: 'server.port'
+//! \t\tfrom: 80
+//! \t\tto: 8080
+//! \t-
+//! \t\tadd: 'server.tls'
+//! \t\tvalue: true
+//! "
+//! );
+//!
+//! let mut config = old.clone();
+//! apply_patch(&mut config, &parse_patch(&patch).unwrap()).unwrap();
+//! assert_eq!(config, new);
+//!
+//! // the patch only applies to documents holding the old values
+//! assert_eq!(
+//!     apply_patch(&mut config, &parse_patch(&patch).unwrap())
+//!         .unwrap_err()
+//!         .to_string(),
+//!     "debug: expected true, found nothing"
+//! );
+//! ```
+//!
+//! Each change names its path with `add`, `remove` or `change`. Removals
+//! record the removed value and changes the value they replace, which has
+//! to match the document the patch is applied to.
+
+use std::{collections::HashMap, fmt, fs, io, path::Path as FilePath};
+
+use crate::{
+	decode::{expect_object, field, Decode, DecodeError},
+	diff::{summary, Change},
+	edit::{set_path, EditError},
+	encode::Encode,
+	encode_string,
+	error::ParserError,
+	parse_string,
+	path::{KvonPath, Path, PathSegment},
+	value::Value,
+	EncodeOptions,
+};
+
+/// Errors that can happen while reading or applying a patch.
+#[derive(Debug)]
+pub enum PatchError {
+	Io(io::Error),
+	Parser(ParserError),
+	Decode(DecodeError),
+	/// An added value has no place in the document, e.g. because its parent
+	/// is a primitive.
+	Edit(EditError),
+	/// The document doesn't hold the value the change expects, e.g.
+	/// because it was edited since the patch was made. `found` is `None` if
+	/// there is no value at the path.
+	Conflict {
+		change: Box<Change>,
+		found: Option<Value>,
+	},
+}
+
+impl fmt::Display for PatchError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Io(e) => write!(f, "{e}"),
+			Self::Parser(e) => write!(f, "{e}"),
+			Self::Decode(e) => write!(f, "{e}"),
+			Self::Edit(e) => write!(f, "{e}"),
+			Self::Conflict { change, found } => {
+				let path = change.path();
+				let expected = match &**change {
+					Change::Added { .. } => "nothing".to_string(),
+					Change::Removed { value, .. } => summary(value),
+					Change::Changed { from, .. } => summary(from),
+				};
+				let found = found.as_ref().map_or("nothing".to_string(), summary);
+				write!(f, "{path}: expected {expected}, found {found}")
+			}
+		}
+	}
+}
+
+impl std::error::Error for PatchError {}
+
+impl From<io::Error> for PatchError {
+	fn from(e: io::Error) -> Self {
+		Self::Io(e)
+	}
+}
+
+impl From<ParserError> for PatchError {
+	fn from(e: ParserError) -> Self {
+		Self::Parser(e)
+	}
+}
+
+impl From<DecodeError> for PatchError {
+	fn from(e: DecodeError) -> Self {
+		Self::Decode(e)
+	}
+}
+
+impl From<EditError> for PatchError {
+	fn from(e: EditError) -> Self {
+		Self::Edit(e)
+	}
+}
+
+/// `add: path` with `value`, `remove: path` with `value`, or
+/// `change: path` with `from` and `to`.
+impl Encode for Change {
+	fn encode(&self) -> Value {
+		let path = Value::from(self.path().to_string());
+		let entries = match self {
+			Self::Added { value, .. } => [("add", path), ("value", value.clone())].to_vec(),
+			Self::Removed { value, .. } => [("remove", path), ("value", value.clone())].to_vec(),
+			Self::Changed { from, to, .. } => {
+				[("change", path), ("from", from.clone()), ("to", to.clone())].to_vec()
+			}
+		};
+		Value::Object(
+			entries
+				.into_iter()
+				.map(|(key, value)| (key.to_string(), value))
+				.collect(),
+		)
+	}
+}
+
+impl Decode for Change {
+	fn decode(value: &Value) -> Result<Self, DecodeError> {
+		let obj = expect_object(value)?;
+		let (kind, keys): (_, &[&str]) = if obj.contains_key("add") {
+			("add", &["add", "value"])
+		} else if obj.contains_key("remove") {
+			("remove", &["remove", "value"])
+		} else if obj.contains_key("change") {
+			("change", &["change", "from", "to"])
+		} else {
+			return Err(DecodeError::invalid_value(
+				"expected one of 'add', 'remove' or 'change'",
+			));
+		};
+		if let Some(key) = obj.keys().filter(|key| !keys.contains(&key.as_str())).min() {
+			return Err(
+				DecodeError::invalid_value(format!("unexpected key in '{kind}' change"))
+					.within(PathSegment::Key(key.clone())),
+			);
+		}
+
+		let path: String = field(obj, kind)?;
+		let path: KvonPath = path.parse().map_err(|e| {
+			DecodeError::invalid_value(e).within(PathSegment::Key(kind.to_string()))
+		})?;
+		Ok(match kind {
+			"add" => Self::Added {
+				path,
+				value: field(obj, "value")?,
+			},
+			"remove" => Self::Removed {
+				path,
+				value: field(obj, "value")?,
+			},
+			_ => Self::Changed {
+				path,
+				from: field(obj, "from")?,
+				to: field(obj, "to")?,
+			},
+		})
+	}
+}
+
+/// Writes changes as a patch document, see the
+/// [module documentation](self).
+pub fn encode_patch(changes: &[Change]) -> String {
+	let mut patch = HashMap::new();
+	patch.insert("changes".to_string(), changes.encode());
+	let encoded = encode_string(&Value::Object(patch), &EncodeOptions::new());
+	format!("{}\n", encoded.trim_matches('\n'))
+}
+
+/// Reads the changes of a patch document.
+pub fn parse_patch(source: &str) -> Result<Vec<Change>, PatchError> {
+	let patch = parse_string(source)?;
+	let obj = expect_object(&patch)?;
+	if let Some(key) = obj.keys().find(|key| *key != "changes") {
+		let error = DecodeError::invalid_value("unknown key").within(PathSegment::Key(key.clone()));
+		return Err(error.into());
+	}
+	Ok(field::<Option<_>>(obj, "changes")?.unwrap_or_default())
+}
+
+/// Applies changes to a value. Either all changes apply or the value is
+/// left as it was.
+///
+/// Removed array items are applied last and from the end of their array,
+/// so that removing an item doesn't shift the indices of the ones after it.
+pub fn apply_patch(value: &mut Value, changes: &[Change]) -> Result<(), PatchError> {
+	let mut patched = value.clone();
+	let (removed_items, changes): (Vec<&Change>, Vec<&Change>) =
+		changes.iter().partition(|change| {
+			matches!(change, Change::Removed { .. })
+				&& matches!(change.path().last(), Some(PathSegment::Index(_)))
+		});
+	for change in changes.into_iter().chain(removed_items.into_iter().rev()) {
+		apply_change(&mut patched, change)?;
+	}
+	*value = patched;
+	Ok(())
+}
+
+/// Reads a patch file and applies it to a value, see [apply_patch].
+pub fn apply_patch_file(value: &mut Value, patch: impl AsRef<FilePath>) -> Result<(), PatchError> {
+	let changes = parse_patch(&fs::read_to_string(patch)?)?;
+	apply_patch(value, &changes)
+}
+
+fn apply_change(value: &mut Value, change: &Change) -> Result<(), PatchError> {
+	let found = value.get_path(change.path());
+	let expected = match change {
+		Change::Added { .. } => None,
+		Change::Removed { value, .. } => Some(value),
+		Change::Changed { from, .. } => Some(from),
+	};
+	if found != expected {
+		return Err(PatchError::Conflict {
+			change: Box::new(change.clone()),
+			found: found.cloned(),
+		});
+	}
+
+	match change {
+		Change::Added { path, value: new } => {
+			set_path(value, path, new.clone())?;
+		}
+		Change::Removed { path, .. } => remove_path(value, path),
+		Change::Changed { path, to, .. } => {
+			set_path(value, path, to.clone())?;
+		}
+	}
+	Ok(())
+}
+
+/// Removes the value at a path that exists.
+fn remove_path(value: &mut Value, path: &Path) {
+	let Some((last, parent)) = path.split_last() else {
+		*value = Value::null();
+		return;
+	};
+	let parent = parent
+		.iter()
+		.fold(value, |value, segment| match (value, segment) {
+			(Value::Object(obj), PathSegment::Key(key)) => obj.get_mut(key).unwrap(),
+			(Value::Array(arr), PathSegment::Index(index)) => &mut arr[*index],
+			_ => unreachable!("the path exists"),
+		});
+	match (parent, last) {
+		(Value::Object(obj), PathSegment::Key(key)) => {
+			obj.remove(key);
+		}
+		(Value::Array(arr), PathSegment::Index(index)) => {
+			arr.remove(*index);
+		}
+		_ => unreachable!("the path exists"),
+	}
+}
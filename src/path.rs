@@ -0,0 +1,278 @@
+use std::{fmt, str::FromStr};
+
+/// A single step from a value into one of its children.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PathSegment {
+	/// An object's key.
+	Key(String),
+	/// An array's index.
+	Index(usize),
+}
+
+/// The location of a value inside of a document, as the list of segments
+/// leading to it from the root. The root itself has an empty path.
+pub type Path = [PathSegment];
+
+/// Where a value starts in the source: at its key for object entries, and at
+/// the value itself or its `-` for array items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+	pub line_number: usize,
+	/// The byte offset in the line, like [crate::error::ParserError::column_number].
+	pub column_number: usize,
+}
+
+/// An owned [Path], like [std::path::PathBuf] for [std::path::Path]. Paths
+/// are written and parsed as keys separated by `.` and indices in brackets,
+/// e.g. `servers[0].host`. Keys that are empty or contain `.`, `[`, `]` or
+/// quotes are quoted, e.g. `labels.'app.kubernetes.io/name'`.
+///
+/// ```rust
+/// use kvon_rs::{object, path::KvonPath};
+///
+/// let path = KvonPath::root().key("servers").index(0).key("host");
+/// assert_eq!(path.to_string(), "servers[0].host");
+/// assert_eq!("servers[0].host".parse::<KvonPath>(), Ok(path.clone()));
+///
+/// let value = object! { servers: [{ host: "localhost" }] };
+/// assert_eq!(value.get_path(&path), Some(&"localhost".into()));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KvonPath(Vec<PathSegment>);
+
+impl KvonPath {
+	/// The path of the root value, without segments.
+	pub fn root() -> Self {
+		Self::default()
+	}
+
+	/// Appends a key, for building paths.
+	pub fn key(mut self, key: impl Into<String>) -> Self {
+		self.push(PathSegment::Key(key.into()));
+		self
+	}
+
+	/// Appends an index, for building paths.
+	pub fn index(mut self, index: usize) -> Self {
+		self.push(PathSegment::Index(index));
+		self
+	}
+
+	pub fn push(&mut self, segment: PathSegment) {
+		self.0.push(segment);
+	}
+
+	pub fn pop(&mut self) -> Option<PathSegment> {
+		self.0.pop()
+	}
+
+	/// Inserts a segment at the start, e.g. while unwinding out of nested
+	/// values.
+	pub fn prepend(&mut self, segment: PathSegment) {
+		self.0.insert(0, segment);
+	}
+
+	/// The path without its last segment, or `None` for the root.
+	pub fn parent(&self) -> Option<&Path> {
+		self.0.split_last().map(|(_, parent)| parent)
+	}
+
+	pub fn into_vec(self) -> Vec<PathSegment> {
+		self.0
+	}
+}
+
+impl std::ops::Deref for KvonPath {
+	type Target = Path;
+
+	fn deref(&self) -> &Path {
+		&self.0
+	}
+}
+
+impl AsRef<Path> for KvonPath {
+	fn as_ref(&self) -> &Path {
+		&self.0
+	}
+}
+
+impl std::borrow::Borrow<Path> for KvonPath {
+	fn borrow(&self) -> &Path {
+		&self.0
+	}
+}
+
+impl From<Vec<PathSegment>> for KvonPath {
+	fn from(segments: Vec<PathSegment>) -> Self {
+		Self(segments)
+	}
+}
+
+impl From<&Path> for KvonPath {
+	fn from(segments: &Path) -> Self {
+		Self(segments.to_vec())
+	}
+}
+
+impl From<KvonPath> for Vec<PathSegment> {
+	fn from(path: KvonPath) -> Self {
+		path.0
+	}
+}
+
+impl FromIterator<PathSegment> for KvonPath {
+	fn from_iter<T: IntoIterator<Item = PathSegment>>(iter: T) -> Self {
+		Self(iter.into_iter().collect())
+	}
+}
+
+impl Extend<PathSegment> for KvonPath {
+	fn extend<I: IntoIterator<Item = PathSegment>>(&mut self, iter: I) {
+		self.0.extend(iter);
+	}
+}
+
+impl IntoIterator for KvonPath {
+	type Item = PathSegment;
+	type IntoIter = std::vec::IntoIter<PathSegment>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.0.into_iter()
+	}
+}
+
+impl<'a> IntoIterator for &'a KvonPath {
+	type Item = &'a PathSegment;
+	type IntoIter = std::slice::Iter<'a, PathSegment>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.0.iter()
+	}
+}
+
+impl PartialEq<Vec<PathSegment>> for KvonPath {
+	fn eq(&self, other: &Vec<PathSegment>) -> bool {
+		&self.0 == other
+	}
+}
+
+impl fmt::Display for KvonPath {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		use fmt::Write;
+
+		for (i, segment) in self.0.iter().enumerate() {
+			match segment {
+				PathSegment::Key(key) => {
+					if i > 0 {
+						f.write_char('.')?;
+					}
+					if key.is_empty() || key.contains(['.', '[', ']', '\'', '"', '\\']) {
+						f.write_char('\'')?;
+						for c in key.chars() {
+							if matches!(c, '\'' | '\\') {
+								f.write_char('\\')?;
+							}
+							f.write_char(c)?;
+						}
+						f.write_char('\'')?;
+					} else {
+						f.write_str(key)?;
+					}
+				}
+				PathSegment::Index(index) => write!(f, "[{index}]")?,
+			}
+		}
+		Ok(())
+	}
+}
+
+/// An invalid path string, see [KvonPath]'s [FromStr] implementation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePathError {
+	/// The byte offset of the error in the string.
+	pub position: usize,
+	pub message: &'static str,
+}
+
+impl fmt::Display for ParsePathError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "invalid path at {}: {}", self.position, self.message)
+	}
+}
+
+impl std::error::Error for ParsePathError {}
+
+impl FromStr for KvonPath {
+	type Err = ParsePathError;
+
+	/// Parses a path like `servers[0].host`. The empty string is the root.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let error = |position, message| ParsePathError { position, message };
+		let mut path = Self::root();
+		let mut chars = s.char_indices().peekable();
+		// whether a key is expected next, i.e. at the start or after a `.`
+		let mut expect_key = true;
+		while let Some(&(start, c)) = chars.peek() {
+			match c {
+				'[' if expect_key && !path.is_empty() => {
+					return Err(error(start, "expected a key"));
+				}
+				'[' => {
+					chars.next();
+					let digits: String =
+						std::iter::from_fn(|| chars.next_if(|(_, c)| c.is_ascii_digit()))
+							.map(|(_, c)| c)
+							.collect();
+					if chars.next().map(|(_, c)| c) != Some(']') {
+						return Err(error(start, "expected an index followed by ']'"));
+					}
+					let index = digits
+						.parse()
+						.map_err(|_| error(start + 1, "expected an index"))?;
+					path.push(PathSegment::Index(index));
+					expect_key = false;
+				}
+				'.' if !expect_key => {
+					chars.next();
+					expect_key = true;
+				}
+				'\'' | '"' if expect_key => {
+					chars.next();
+					let mut key = String::new();
+					loop {
+						match chars.next() {
+							Some((_, '\\')) => match chars.next() {
+								Some((_, c)) => key.push(c),
+								None => return Err(error(start, "unclosed quote")),
+							},
+							Some((_, q)) if q == c => break,
+							Some((_, c)) => key.push(c),
+							None => return Err(error(start, "unclosed quote")),
+						}
+					}
+					path.push(PathSegment::Key(key));
+					expect_key = false;
+				}
+				_ if expect_key => {
+					let mut key = String::new();
+					while let Some((_, c)) = chars.next_if(|(_, c)| !matches!(c, '.' | '[')) {
+						if matches!(c, ']' | '\'' | '"' | '\\') {
+							return Err(error(start, "quote keys containing ']', quotes or '\\'"));
+						}
+						key.push(c);
+					}
+					if key.is_empty() {
+						return Err(error(start, "expected a key"));
+					}
+					path.push(PathSegment::Key(key));
+					expect_key = false;
+				}
+				_ => return Err(error(start, "expected '.' or '['")),
+			}
+		}
+		if expect_key && !path.is_empty() {
+			return Err(error(s.len(), "expected a key"));
+		}
+		Ok(path)
+	}
+}
@@ -0,0 +1,314 @@
+//! Converting values to and from flat `key=value` files, i.e. Java
+//! properties and dotenv files, e.g. for services that can't read KVON.
+//!
+//! ```rust
+//! use kvon_rs::{
+//!     object,
+//!     properties::{from_dotenv, from_properties, to_dotenv, to_properties},
+//! };
+//!
+//! let config = object! { server: { host: "localhost", ports: [80, 443] } };
+//! let properties = to_properties(&config);
+//! assert_eq!(
+//!     properties,
+//!     "server.host=localhost\nserver.ports.0=80\nserver.ports.1=443\n"
+//! );
+//! assert_eq!(from_properties(&properties).unwrap(), config);
+//!
+//! let dotenv = to_dotenv(&config, "app");
+//! assert_eq!(
+//!     dotenv,
+//!     "APP_SERVER_HOST=localhost\nAPP_SERVER_PORTS_0=80\nAPP_SERVER_PORTS_1=443\n"
+//! );
+//! assert_eq!(from_dotenv(&dotenv, "app").unwrap(), config);
+//! ```
+//!
+//! Properties files use the keys of [Value::flatten] with `.` as the
+//! separator, so keys containing dots can't be read back as they were. Dotenv
+//! files use the names of [crate::env]. In both, values are written without
+//! their types, and `true`, `false`, `null` and numbers are read back as
+//! primitives of that type. Empty objects and arrays are skipped.
+
+use std::{collections::HashMap, fmt};
+
+use crate::{
+	env::{parse_var_value, to_env_vars, try_from_vars, var_value},
+	value::{UnflattenConflict, Value},
+};
+
+/// Errors that can happen while reading properties and dotenv files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertiesError {
+	/// A line that can't be read, e.g. an unclosed quote.
+	Syntax {
+		line_number: usize,
+		message: &'static str,
+	},
+	/// A key is both a value and the prefix of other keys, e.g. `a=1` and
+	/// `a.b=2`.
+	Conflict(UnflattenConflict),
+}
+
+impl fmt::Display for PropertiesError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Syntax {
+				line_number,
+				message,
+			} => write!(f, "{line_number}: {message}"),
+			Self::Conflict(e) => write!(f, "{e}"),
+		}
+	}
+}
+
+impl std::error::Error for PropertiesError {}
+
+impl From<UnflattenConflict> for PropertiesError {
+	fn from(e: UnflattenConflict) -> Self {
+		Self::Conflict(e)
+	}
+}
+
+/// Writes the leaves of a value as a properties file, sorted by key.
+/// Characters outside of ASCII are escaped as `\uXXXX`, so that the file can
+/// also be read as ISO 8859-1.
+pub fn to_properties(value: &Value) -> String {
+	let Value::Object(flat) = value.flatten(".") else {
+		return String::new();
+	};
+	let mut entries: Vec<(String, String)> = flat
+		.into_iter()
+		.filter_map(|(key, value)| {
+			let value = match value {
+				Value::Primitive(p) => var_value(&p),
+				Value::Raw(raw) => raw.head().trim().to_string(),
+				_ => return None,
+			};
+			Some((escape_property(&key, true), escape_property(&value, false)))
+		})
+		.collect();
+	entries.sort();
+	entries
+		.into_iter()
+		.map(|(key, value)| format!("{key}={value}\n"))
+		.collect()
+}
+
+fn escape_property(s: &str, is_key: bool) -> String {
+	let mut escaped = String::with_capacity(s.len());
+	for (i, c) in s.chars().enumerate() {
+		match c {
+			'\\' => escaped.push_str("\\\\"),
+			'\t' => escaped.push_str("\\t"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			'\x0c' => escaped.push_str("\\f"),
+			' ' if is_key || i == 0 => escaped.push_str("\\ "),
+			'=' | ':' | '#' | '!' if is_key || i == 0 => {
+				escaped.push('\\');
+				escaped.push(c);
+			}
+			c if !c.is_ascii() || c.is_ascii_control() => {
+				for unit in c.encode_utf16(&mut [0; 2]) {
+					escaped.push_str(&format!("\\u{unit:04X}"));
+				}
+			}
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+/// Reads a properties file, splitting keys at `.` into nested objects, see
+/// the [module documentation](self). Objects whose keys are indices become
+/// arrays.
+///
+/// Lines are read like Java's `Properties::load`: keys end at the first
+/// unescaped `=`, `:` or whitespace, lines ending with a `\` continue on the
+/// next line and lines starting with `#` or `!` are comments.
+pub fn from_properties(source: &str) -> Result<Value, PropertiesError> {
+	let mut flat = HashMap::new();
+	let mut lines = source.lines().enumerate();
+	while let Some((line_number, line)) = lines.next() {
+		let mut line = line.trim_start().to_string();
+		if line.is_empty() || line.starts_with(['#', '!']) {
+			continue;
+		}
+		while ends_with_continuation(&line) {
+			line.pop();
+			match lines.next() {
+				Some((_, next)) => line.push_str(next.trim_start()),
+				None => break,
+			}
+		}
+
+		let syntax = |message| PropertiesError::Syntax {
+			line_number,
+			message,
+		};
+		let mut chars = line.chars().peekable();
+		let mut key = String::new();
+		while let Some(c) = chars.next_if(|c| !matches!(c, '=' | ':') && !c.is_whitespace()) {
+			match c {
+				'\\' => key.push(unescape(&mut chars).map_err(syntax)?),
+				c => key.push(c),
+			}
+		}
+		while chars.next_if(|c| c.is_whitespace()).is_some() {}
+		if chars.next_if(|c| matches!(c, '=' | ':')).is_some() {
+			while chars.next_if(|c| c.is_whitespace()).is_some() {}
+		}
+		let mut value = String::new();
+		while let Some(c) = chars.next() {
+			match c {
+				'\\' => value.push(unescape(&mut chars).map_err(syntax)?),
+				c => value.push(c),
+			}
+		}
+		flat.insert(key, parse_var_value(value));
+	}
+	Ok(Value::Object(flat).try_unflatten(".")?)
+}
+
+/// Whether a line ends with an odd number of `\`, i.e. continues on the
+/// next line.
+fn ends_with_continuation(line: &str) -> bool {
+	line.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1
+}
+
+/// Reads the character escaped by a `\` that was just consumed.
+fn unescape(chars: &mut impl Iterator<Item = char>) -> Result<char, &'static str> {
+	Ok(match chars.next() {
+		Some('t') => '\t',
+		Some('n') => '\n',
+		Some('r') => '\r',
+		Some('f') => '\x0c',
+		Some('u') => {
+			let unit = hex_unit(chars)?;
+			match char::from_u32(unit.into()) {
+				Some(c) => c,
+				// characters outside of the BMP are a pair of escaped surrogates
+				None => {
+					let (Some('\\'), Some('u')) = (chars.next(), chars.next()) else {
+						return Err("expected the second half of a surrogate pair");
+					};
+					let low = hex_unit(chars)?;
+					char::decode_utf16([unit, low])
+						.next()
+						.and_then(Result::ok)
+						.ok_or("invalid surrogate pair")?
+				}
+			}
+		}
+		Some(c) => c,
+		None => '\\',
+	})
+}
+
+fn hex_unit(chars: &mut impl Iterator<Item = char>) -> Result<u16, &'static str> {
+	let hex: String = chars.by_ref().take(4).collect();
+	match hex.len() {
+		4 => u16::from_str_radix(&hex, 16).map_err(|_| "expected 4 hexadecimal digits after '\\u'"),
+		_ => Err("expected 4 hexadecimal digits after '\\u'"),
+	}
+}
+
+/// Writes the leaves of a value as a dotenv file with the names of
+/// [to_env_vars]. Values are quoted with `"` unless they only hold letters,
+/// digits and punctuation without special meaning in shells.
+pub fn to_dotenv(value: &Value, prefix: &str) -> String {
+	to_env_vars(value, prefix)
+		.into_iter()
+		.map(|(name, value)| format!("{name}={}\n", quote_dotenv(&value)))
+		.collect()
+}
+
+fn quote_dotenv(value: &str) -> String {
+	let is_plain = |c: char| c.is_ascii_alphanumeric() || "_-.,:/@+%".contains(c);
+	if !value.is_empty() && value.chars().all(is_plain) {
+		return value.to_string();
+	}
+	let mut quoted = String::from("\"");
+	for c in value.chars() {
+		match c {
+			'\\' | '"' | '$' | '`' => {
+				quoted.push('\\');
+				quoted.push(c);
+			}
+			'\n' => quoted.push_str("\\n"),
+			'\r' => quoted.push_str("\\r"),
+			c => quoted.push(c),
+		}
+	}
+	quoted.push('"');
+	quoted
+}
+
+/// Reads a dotenv file, building an object from the variables whose names
+/// start with `prefix` like [crate::env::from_vars]. Variables holding the
+/// value of a key that other variables nest keys in, e.g. `A` and `A_B`,
+/// are a [PropertiesError::Conflict].
+///
+/// Lines hold `NAME=value`, optionally preceded by `export`. Values in `"`
+/// can contain the escapes `\n`, `\r`, `\t`, `\"`, `\$` and `\\`, values in
+/// `'` are taken literally and unquoted values end at a ` #` comment.
+pub fn from_dotenv(source: &str, prefix: &str) -> Result<Value, PropertiesError> {
+	let mut vars = Vec::new();
+	for (line_number, line) in source.lines().enumerate() {
+		let syntax = |message| PropertiesError::Syntax {
+			line_number,
+			message,
+		};
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+		let line = line.strip_prefix("export ").unwrap_or(line);
+		let (name, value) = line
+			.split_once('=')
+			.ok_or(syntax("expected 'NAME=value'"))?;
+		let name = name.trim();
+		if name.is_empty() || name.contains(char::is_whitespace) {
+			return Err(syntax("expected a name without whitespace before '='"));
+		}
+		let value = dotenv_value(value.trim_start()).map_err(syntax)?;
+		vars.push((name.to_string(), value));
+	}
+	Ok(try_from_vars(prefix, vars)?)
+}
+
+fn dotenv_value(value: &str) -> Result<String, &'static str> {
+	let quote = match value.chars().next() {
+		Some(quote @ ('"' | '\'')) => quote,
+		_ => {
+			let end = value.find(" #").unwrap_or(value.len());
+			return Ok(value[..end].trim_end().to_string());
+		}
+	};
+
+	let mut unquoted = String::new();
+	let mut chars = value[1..].chars();
+	loop {
+		match chars.next() {
+			None => return Err("unclosed quote"),
+			Some(c) if c == quote => break,
+			Some('\\') if quote == '"' => match chars.next() {
+				Some('n') => unquoted.push('\n'),
+				Some('r') => unquoted.push('\r'),
+				Some('t') => unquoted.push('\t'),
+				Some(c @ ('"' | '$' | '`' | '\\')) => unquoted.push(c),
+				Some(c) => {
+					unquoted.push('\\');
+					unquoted.push(c);
+				}
+				None => return Err("unclosed quote"),
+			},
+			Some(c) => unquoted.push(c),
+		}
+	}
+	let rest = chars.as_str().trim_start();
+	if !rest.is_empty() && !rest.starts_with('#') {
+		return Err("unexpected content after the quoted value");
+	}
+	Ok(unquoted)
+}
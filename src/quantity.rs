@@ -0,0 +1,110 @@
+//! Duration (`5s`, `2h30m`) and byte size (`10MiB`, `4GB`) literals, enabled
+//! by the `quantities` feature.
+
+use std::time::Duration;
+
+const DURATION_UNITS: [(&str, u128); 7] = [
+	("d", 86_400_000_000_000),
+	("h", 3_600_000_000_000),
+	("m", 60_000_000_000),
+	("s", 1_000_000_000),
+	("ms", 1_000_000),
+	("us", 1_000),
+	("ns", 1),
+];
+
+const BYTE_SIZE_UNITS: [(&str, u64); 9] = [
+	("TiB", 1 << 40),
+	("GiB", 1 << 30),
+	("MiB", 1 << 20),
+	("KiB", 1 << 10),
+	("TB", 1_000_000_000_000),
+	("GB", 1_000_000_000),
+	("MB", 1_000_000),
+	("KB", 1_000),
+	("B", 1),
+];
+
+/// Splits a leading decimal number from `s`, returning the number and the
+/// rest of the string.
+fn split_number(s: &str) -> Option<(f64, &str)> {
+	let end = s
+		.find(|c: char| !c.is_ascii_digit() && c != '.')
+		.unwrap_or(s.len());
+	let number = &s[..end];
+	if number.is_empty() || number.starts_with('.') || number.ends_with('.') {
+		return None;
+	}
+	Some((number.parse().ok()?, &s[end..]))
+}
+
+/// Splits a leading unit from `s`, preferring the longest matching unit.
+fn split_unit<'a, T: Copy>(s: &'a str, units: &[(&str, T)]) -> Option<(T, &'a str)> {
+	let end = s
+		.find(|c: char| !c.is_ascii_alphabetic())
+		.unwrap_or(s.len());
+	let unit = &s[..end];
+	units
+		.iter()
+		.find(|(name, _)| *name == unit)
+		.map(|(_, factor)| (*factor, &s[end..]))
+}
+
+/// Parses a sequence of `<number><unit>` components, e.g. `2h30m` or `1.5s`.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+	let mut rest = s;
+	let mut nanos = 0.0;
+	while !rest.is_empty() {
+		let (number, after_number) = split_number(rest)?;
+		let (factor, after_unit) = split_unit(after_number, &DURATION_UNITS)?;
+		nanos += number * factor as f64;
+		rest = after_unit;
+	}
+
+	if s.is_empty() || !nanos.is_finite() || nanos > u64::MAX as f64 {
+		None
+	} else {
+		Some(Duration::from_nanos(nanos.round() as u64))
+	}
+}
+
+/// Parses a `<number><unit>` byte size, e.g. `10MiB` or `4GB`.
+pub fn parse_byte_size(s: &str) -> Option<u64> {
+	let (number, after_number) = split_number(s)?;
+	let (factor, rest) = split_unit(after_number, &BYTE_SIZE_UNITS)?;
+	let bytes = number * factor as f64;
+	if !rest.is_empty() || bytes.fract() != 0.0 || bytes > u64::MAX as f64 {
+		None
+	} else {
+		Some(bytes as u64)
+	}
+}
+
+/// Formats a duration as a sequence of integral components, largest unit
+/// first, e.g. `2h30m` or `1s500ms`. A zero duration is formatted as `0s`.
+pub fn format_duration(duration: Duration) -> String {
+	let mut nanos = duration.as_nanos();
+	if nanos == 0 {
+		return "0s".to_string();
+	}
+
+	let mut s = String::new();
+	for (unit, factor) in DURATION_UNITS {
+		if nanos >= factor {
+			s.push_str(&format!("{}{unit}", nanos / factor));
+			nanos %= factor;
+		}
+	}
+	s
+}
+
+/// Formats a byte size with the unit that represents it exactly with the
+/// smallest number, preferring binary units, e.g. `10MiB`, `4GB` or `123B`.
+pub fn format_byte_size(bytes: u64) -> String {
+	let (unit, factor) = BYTE_SIZE_UNITS
+		.iter()
+		.filter(|(_, factor)| bytes != 0 && bytes.is_multiple_of(*factor))
+		.min_by_key(|(_, factor)| bytes / factor)
+		.unwrap_or(&("B", 1));
+	format!("{}{unit}", bytes / factor)
+}
@@ -0,0 +1,167 @@
+//! Append-only record files. A record file is a sequence of independent
+//! documents, each starting with a `---` line:
+//!
+//! ```text
+//! ---
+//! level: 'info'
+//! message: 'started'
+//! ---
+//! level: 'error'
+//! message: 'failed'
+//! ```
+//!
+//! Records can be appended to a file without reading it, which makes the
+//! format suitable for structured logs.
+
+use std::{
+	fmt,
+	io::{self, BufRead},
+};
+
+use crate::{
+	encoder::encode_string, error::ParserError, value::Value, EncodeOptions, Parser, ParserOptions,
+};
+
+/// The line starting every record.
+pub const RECORD_SEPARATOR: &str = "---";
+
+/// Encodes a value into a record, ready to be appended to a record file.
+pub fn encode_record(v: &Value) -> String {
+	encode_record_with_options(v, &EncodeOptions::new())
+}
+
+/// Encodes a value into a record using the given options.
+pub fn encode_record_with_options(v: &Value, options: &EncodeOptions) -> String {
+	let encoded = encode_string(v, options);
+	format!("{RECORD_SEPARATOR}\n{}\n", encoded.trim_start_matches('\n'))
+}
+
+/// Errors that can happen while reading records.
+#[derive(Debug)]
+pub enum RecordError {
+	Io(io::Error),
+	Parser(ParserError),
+}
+
+impl fmt::Display for RecordError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Io(e) => write!(f, "{e}"),
+			Self::Parser(e) => write!(f, "{e}"),
+		}
+	}
+}
+
+impl std::error::Error for RecordError {}
+
+impl From<io::Error> for RecordError {
+	fn from(e: io::Error) -> Self {
+		Self::Io(e)
+	}
+}
+
+impl From<ParserError> for RecordError {
+	fn from(e: ParserError) -> Self {
+		Self::Parser(e)
+	}
+}
+
+/// Reads the records of a record file one by one. Only a single record is
+/// held in memory at a time.
+pub fn parse_records<R: BufRead>(r: R) -> Records<R> {
+	parse_records_with_options(r, &ParserOptions::default())
+}
+
+/// Reads the records of a record file using the given options.
+pub fn parse_records_with_options<R: BufRead>(r: R, options: &ParserOptions) -> Records<R> {
+	Records {
+		reader: r,
+		options: options.clone(),
+		parser: None,
+		line_number: 0,
+		skipping: false,
+		done: false,
+	}
+}
+
+/// An iterator over the records of a record file, see [parse_records].
+/// After a record that fails to parse, reading continues with the next
+/// record, so that one bad record doesn't hide the ones after it. Reading
+/// errors end the iteration.
+pub struct Records<R> {
+	reader: R,
+	options: ParserOptions,
+	/// The parser of the record currently being read.
+	parser: Option<Parser>,
+	line_number: usize,
+	/// Whether the rest of a record that failed to parse is skipped.
+	skipping: bool,
+	done: bool,
+}
+
+impl<R: BufRead> Records<R> {
+	/// Starts a new record at the given zero based line, returning the
+	/// parser of the previous one.
+	fn start_record(&mut self, first_line: usize) -> Option<Parser> {
+		let parser = Parser::with_options(self.options.clone()).with_first_line_number(first_line);
+		self.parser.replace(parser)
+	}
+
+	fn next_record(&mut self) -> Result<Option<Value>, RecordError> {
+		let mut line = String::new();
+
+		loop {
+			line.clear();
+			if self.reader.read_line(&mut line)? == 0 {
+				return match self.parser.take() {
+					Some(parser) => Ok(Some(parser.finish()?)),
+					None => Ok(None),
+				};
+			}
+			let content = line.trim_end_matches(['\n', '\r']);
+			self.line_number += 1;
+
+			if content == RECORD_SEPARATOR {
+				self.skipping = false;
+				if let Some(parser) = self.start_record(self.line_number) {
+					return Ok(Some(parser.finish()?));
+				}
+				continue;
+			}
+			if self.skipping {
+				continue;
+			}
+
+			// the separator of the first record is optional, but blank lines
+			// and comments before it don't form a record
+			if self.parser.is_none() {
+				let trimmed = content.trim_start();
+				if trimmed.is_empty() || trimmed.starts_with('#') {
+					continue;
+				}
+				self.start_record(self.line_number - 1);
+			}
+			if let Err(e) = self.parser.as_mut().unwrap().next_line(content) {
+				self.parser = None;
+				self.skipping = true;
+				return Err(e.into());
+			}
+		}
+	}
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+	type Item = Result<Value, RecordError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done {
+			return None;
+		}
+
+		let record = self.next_record();
+		if matches!(record, Ok(None) | Err(RecordError::Io(_))) {
+			self.done = true;
+		}
+		record.transpose()
+	}
+}
@@ -0,0 +1,256 @@
+//! Colorized views of values for terminals and web pages. Values are laid out
+//! like encoded documents, with keys, strings, numbers, booleans and `null`
+//! styled differently.
+//!
+//! ```rust
+//! use kvon_rs::{object, render::to_ansi};
+//!
+//! let value = object! { name: "app", port: 80 };
+//! assert_eq!(
+//!     to_ansi(&value),
+//!     "\x1b[34mname\x1b[0m: \x1b[32m'app'\x1b[0m\n\x1b[34mport\x1b[0m: \x1b[33m80\x1b[0m\n"
+//! );
+//! ```
+//!
+//! [to_html] nests objects and arrays in `<details>` elements, so they can be
+//! collapsed, and marks tokens with classes that [HTML_STYLE] colors.
+
+use crate::{
+	encoder::{encode_key, encode_primitive_lines},
+	value::{PrimitiveValue, Value},
+	EncodeOptions,
+};
+
+/// CSS for the output of [to_html].
+pub const HTML_STYLE: &str = "\
+.kvon { font-family: monospace; white-space: pre; }
+.kvon summary { cursor: pointer; list-style: none; }
+.kvon summary::-webkit-details-marker { display: none; }
+.kvon details:not([open]) > summary::after { content: ' ...'; opacity: 0.5; }
+.kvon-key { color: #0550ae; }
+.kvon-string { color: #116329; }
+.kvon-number { color: #953800; }
+.kvon-boolean { color: #8250df; }
+.kvon-null { color: #6e7781; }
+";
+
+/// Renders a value with ANSI escape codes for terminals.
+pub fn to_ansi(value: &Value) -> String {
+	let mut ansi = Ansi(String::new());
+	render(value, &mut ansi);
+	ansi.0
+}
+
+/// Renders a value as HTML, wrapped in a `<div class="kvon">`. Style it with
+/// [HTML_STYLE] or custom rules for the `kvon-*` classes.
+pub fn to_html(value: &Value) -> String {
+	let mut html = Html(String::from("<div class=\"kvon\">"));
+	render(value, &mut html);
+	html.0.push_str("</div>");
+	html.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+	Key,
+	String,
+	Number,
+	Boolean,
+	Null,
+	/// Punctuation and other primitives.
+	Plain,
+}
+
+type Line = Vec<(Token, String)>;
+
+/// The output format of [render].
+trait Sink {
+	/// Writes a line. The lines of the children of lines with `block` set
+	/// follow, until the matching [Sink::end_block].
+	fn line(&mut self, depth: usize, tokens: Line, block: bool);
+	fn end_block(&mut self);
+}
+
+struct Ansi(String);
+
+impl Sink for Ansi {
+	fn line(&mut self, depth: usize, tokens: Line, _block: bool) {
+		self.0.push_str(&"    ".repeat(depth));
+		for (token, text) in tokens {
+			let color = match token {
+				Token::Key => "34",
+				Token::String => "32",
+				Token::Number => "33",
+				Token::Boolean => "35",
+				Token::Null => "2",
+				Token::Plain => {
+					self.0.push_str(&text);
+					continue;
+				}
+			};
+			self.0.push_str(&format!("\x1b[{color}m{text}\x1b[0m"));
+		}
+		self.0.push('\n');
+	}
+
+	fn end_block(&mut self) {}
+}
+
+struct Html(String);
+
+impl Sink for Html {
+	fn line(&mut self, depth: usize, tokens: Line, block: bool) {
+		self.0.push_str(match block {
+			true => "<details open><summary>",
+			false => "<div>",
+		});
+		self.0.push_str(&"    ".repeat(depth));
+		for (token, text) in tokens {
+			let class = match token {
+				Token::Key => "kvon-key",
+				Token::String => "kvon-string",
+				Token::Number => "kvon-number",
+				Token::Boolean => "kvon-boolean",
+				Token::Null => "kvon-null",
+				Token::Plain => {
+					self.0.push_str(&escape_html(&text));
+					continue;
+				}
+			};
+			self.0.push_str(&format!(
+				"<span class=\"{class}\">{}</span>",
+				escape_html(&text)
+			));
+		}
+		self.0.push_str(match block {
+			true => "</summary>",
+			false => "</div>",
+		});
+	}
+
+	fn end_block(&mut self) {
+		self.0.push_str("</details>");
+	}
+}
+
+fn escape_html(text: &str) -> String {
+	text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
+
+fn render(value: &Value, sink: &mut impl Sink) {
+	match value {
+		Value::Object(obj) => render_entries(obj, 0, sink),
+		Value::Array(arr) if !is_inline(value) => render_items(arr, 0, sink),
+		value => render_nested(Vec::new(), value, 0, sink),
+	}
+}
+
+fn render_entries(
+	obj: &std::collections::HashMap<String, Value>,
+	depth: usize,
+	sink: &mut impl Sink,
+) {
+	let options = EncodeOptions::new();
+	let mut keys: Vec<_> = obj.keys().collect();
+	keys.sort();
+	for key in keys {
+		let line = vec![
+			(
+				Token::Key,
+				encode_key(key, &options).unwrap_or_else(|| key.clone()),
+			),
+			(Token::Plain, ":".to_string()),
+		];
+		render_nested(line, &obj[key], depth, sink);
+	}
+}
+
+fn render_items(arr: &[Value], depth: usize, sink: &mut impl Sink) {
+	for item in arr {
+		render_nested(vec![(Token::Plain, "-".to_string())], item, depth, sink);
+	}
+}
+
+/// Writes a value after the start of a line, i.e. after a key or the `-`
+/// of an array item.
+fn render_nested(mut line: Line, value: &Value, depth: usize, sink: &mut impl Sink) {
+	let space = |line: &mut Line| {
+		if !line.is_empty() {
+			line.push((Token::Plain, " ".to_string()));
+		}
+	};
+	match value {
+		Value::Object(obj) if obj.is_empty() => sink.line(depth, line, false),
+		Value::Object(obj) => {
+			sink.line(depth, line, true);
+			render_entries(obj, depth + 1, sink);
+			sink.end_block();
+		}
+		Value::Array(arr) if !is_inline(value) => {
+			line.push((Token::Plain, "--".to_string()));
+			sink.line(depth, line, true);
+			render_items(arr, depth + 1, sink);
+			sink.end_block();
+		}
+		Value::Array(arr) => {
+			space(&mut line);
+			line.push((Token::Plain, "[".to_string()));
+			for (i, item) in arr.iter().enumerate() {
+				if i > 0 {
+					line.push((Token::Plain, " ".to_string()));
+				}
+				if let Value::Primitive(p) = item {
+					line.push(primitive_token(p));
+				}
+			}
+			line.push((Token::Plain, "]".to_string()));
+			sink.line(depth, line, false);
+		}
+		Value::Primitive(PrimitiveValue::String(s)) if s.contains('\n') => {
+			space(&mut line);
+			line.push((Token::Plain, "|".to_string()));
+			sink.line(depth, line, true);
+			for text in s.split('\n') {
+				sink.line(depth + 1, vec![(Token::String, text.to_string())], false);
+			}
+			sink.end_block();
+		}
+		Value::Primitive(p) => {
+			space(&mut line);
+			line.push(primitive_token(p));
+			sink.line(depth, line, false);
+		}
+		Value::Raw(raw) => {
+			space(&mut line);
+			line.push((Token::Plain, raw.head().trim().to_string()));
+			sink.line(depth, line, false);
+		}
+	}
+}
+
+/// Whether an array is written on a single line, i.e. only holds single line
+/// primitives.
+fn is_inline(value: &Value) -> bool {
+	match value {
+		Value::Array(arr) => arr.iter().all(|item| match item {
+			Value::Primitive(PrimitiveValue::String(s)) => !s.contains('\n'),
+			item => item.is_primitive(),
+		}),
+		_ => false,
+	}
+}
+
+fn primitive_token(p: &PrimitiveValue) -> (Token, String) {
+	let token = match p {
+		PrimitiveValue::String(_) => Token::String,
+		PrimitiveValue::Number(_) => Token::Number,
+		PrimitiveValue::Boolean(_) => Token::Boolean,
+		PrimitiveValue::Null => Token::Null,
+		_ => Token::Plain,
+	};
+	let text = encode_primitive_lines(p, &EncodeOptions::new(), String::new(), 0).join("");
+	(token, text)
+}
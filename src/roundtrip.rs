@@ -0,0 +1,90 @@
+//! Checking that re-encoding a document keeps its value, e.g. before
+//! adopting the formatter on existing files.
+//!
+//! ```rust
+//! use kvon_rs::roundtrip::verify_roundtrip;
+//!
+//! assert!(verify_roundtrip("server:\n  port: 80 # http\nname: 'app'").is_ok());
+//! assert!(verify_roundtrip("a: [1").is_err());
+//! ```
+
+use std::fmt;
+
+use crate::{
+	diff::{diff, Change},
+	encode_string,
+	error::ParserError,
+	parse_string, parse_string_output,
+	value::Value,
+	ParserOptions,
+};
+
+/// Why a document doesn't survive being re-encoded, see [verify_roundtrip].
+#[derive(Debug)]
+pub enum RoundtripReport {
+	/// The document itself doesn't parse.
+	Invalid(ParserError),
+	/// The re-encoded document doesn't parse.
+	Unparsable {
+		encoded: String,
+		error: Box<ParserError>,
+	},
+	/// The re-encoded document parses into a different value. `change` is
+	/// the first difference, ordered by path.
+	Changed {
+		encoded: String,
+		change: Box<Change>,
+	},
+}
+
+impl fmt::Display for RoundtripReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Invalid(e) => write!(f, "{e}"),
+			Self::Unparsable { error, .. } => {
+				write!(f, "the re-encoded document doesn't parse: {error}")
+			}
+			Self::Changed { change, .. } => {
+				write!(f, "re-encoding changes the value: {change}")
+			}
+		}
+	}
+}
+
+impl std::error::Error for RoundtripReport {}
+
+/// Parses a document, encodes it in its own style and parses the result
+/// again. Fails if the value changed on the way.
+pub fn verify_roundtrip(source: &str) -> Result<(), RoundtripReport> {
+	let output =
+		parse_string_output(source, &ParserOptions::new()).map_err(RoundtripReport::Invalid)?;
+	let encoded = encode_string(&output.value, &output.encode_options());
+	verify_encoding(&output.value, encoded)
+}
+
+/// Checks that another encoding of a document, e.g. by
+/// [format_str](crate::format::format_str), parses into the same value.
+pub fn verify_reencoded(source: &str, encoded: String) -> Result<(), RoundtripReport> {
+	let value = parse_string(source).map_err(RoundtripReport::Invalid)?;
+	verify_encoding(&value, encoded)
+}
+
+/// Checks that an encoding of a value parses back into it.
+pub fn verify_encoding(value: &Value, encoded: String) -> Result<(), RoundtripReport> {
+	let reparsed = match parse_string(&encoded) {
+		Ok(reparsed) => reparsed,
+		Err(error) => {
+			return Err(RoundtripReport::Unparsable {
+				encoded,
+				error: Box::new(error),
+			})
+		}
+	};
+	match diff(value, &reparsed).into_iter().next() {
+		Some(change) => Err(RoundtripReport::Changed {
+			encoded,
+			change: Box::new(change),
+		}),
+		None => Ok(()),
+	}
+}
@@ -0,0 +1,824 @@
+//! The tokenizer the parser reads each line with. It can be used to build
+//! KVON dialects, e.g. with extra literal types, on top of the standard
+//! grammar.
+//!
+//! A [Scanner] holds a single line and a position in it. `see` methods look
+//! at the text after the position, `have` methods consume it if it matches,
+//! and `parse` methods consume and return a token, or return `None` without
+//! consuming anything. To look further ahead, save the position with
+//! [Scanner::record] and go back to it with [Scanner::restore]:
+//!
+//! ```rust
+//! use kvon_rs::{scanner::Scanner, value::PrimitiveValue, ParserOptions};
+//!
+//! let options = ParserOptions::new();
+//! let mut scanner = Scanner::new(0, "port: 80 # http", &options);
+//!
+//! assert_eq!(scanner.parse_key_with_colon().unwrap(), "port");
+//! scanner.consume_whitespaces();
+//!
+//! // an `@` literal of a dialect, falling back to the standard literals
+//! scanner.record();
+//! if scanner.have("@") {
+//!     scanner.cancel_restore();
+//! } else {
+//!     scanner.restore();
+//!     let value = scanner.parse_primitive().unwrap();
+//!     assert_eq!(value, Some(PrimitiveValue::Number(80.)));
+//! }
+//!
+//! assert!(scanner.see_end_or_comment());
+//! assert_eq!(scanner.consume_whitespaces_and_comment(), Some(" http"));
+//! ```
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::{
+	error::{ParserError, ParserErrorKind},
+	indention::Indention,
+	parser::PrimitiveParserHook,
+	value::{PrimitiveValue, Value},
+	ParserOptions, ParserResult, Profile,
+};
+
+/// A hook getting the first chance at parsing every literal, see
+/// [ParserOptions::primitive_parser]. Implemented for closures taking the
+/// scanner.
+pub trait PrimitiveParser: Send + Sync {
+	/// Parses the literal at the scanner's position, or returns `Ok(None)` to
+	/// fall back to the standard literals. Anything consumed before
+	/// returning `Ok(None)` is restored.
+	fn parse(&self, scanner: &mut Scanner) -> ParserResult<Option<PrimitiveValue>>;
+}
+
+impl<F> PrimitiveParser for F
+where
+	F: Fn(&mut Scanner) -> ParserResult<Option<PrimitiveValue>> + Send + Sync,
+{
+	fn parse(&self, scanner: &mut Scanner) -> ParserResult<Option<PrimitiveValue>> {
+		self(scanner)
+	}
+}
+
+/// Reads the tokens of a single line. See the [module documentation](self).
+pub struct Scanner<'a> {
+	line_number: usize,
+	line: &'a str,
+	left: &'a str,
+	i: usize,
+	recorded: Vec<(usize, &'a str)>,
+	options: &'a ParserOptions,
+}
+
+/// The only things that may follow a complete line.
+pub const END_OF_LINE: &[&str] = &["end of line", "comment"];
+
+impl<'a> Scanner<'a> {
+	/// Creates a scanner at the start of `line`. The line number is only
+	/// used for errors. Literals are parsed according to `options`.
+	pub fn new(line_number: usize, line: &'a str, options: &'a ParserOptions) -> Self {
+		Self {
+			line_number,
+			line,
+			left: line,
+			i: 0,
+			recorded: Vec::new(),
+			options,
+		}
+	}
+
+	/// Creates an error at the current position.
+	pub fn generate_error(&self, kind: ParserErrorKind) -> ParserError {
+		ParserError {
+			hint: kind.default_hint(),
+			kind,
+			line_number: self.line_number,
+			column_number: self.i,
+			line: self.line.to_string(),
+		}
+	}
+
+	/// With [Profile::Strict], returns a [ParserErrorKind::NotInSpec] error
+	/// for an extension starting at `start`.
+	fn check_extension(&self, start: usize, extension: &'static str) -> ParserResult<()> {
+		if self.options.profile != Some(Profile::Strict) {
+			return Ok(());
+		}
+		let mut error = self.generate_error(ParserErrorKind::NotInSpec { extension });
+		error.column_number = start;
+		Err(error)
+	}
+
+	/// Generates an [ParserErrorKind::Unexpected] error for the token at the
+	/// current position.
+	pub fn generate_unexpected_error(&self, expected: &'static [&'static str]) -> ParserError {
+		let token = self
+			.left
+			.trim_start()
+			.split(char::is_whitespace)
+			.next()
+			.unwrap_or_default();
+		let found = if token.is_empty() {
+			"end of line"
+		} else {
+			token
+		};
+
+		self.generate_error(ParserErrorKind::unexpected(found, expected))
+	}
+
+	/// Generates an error for content found after a complete value, with a
+	/// hint for the common mistakes that lead to it.
+	pub fn generate_trailing_content_error(&mut self) -> ParserError {
+		let error = self.generate_unexpected_error(END_OF_LINE);
+
+		self.record();
+		self.consume_whitespaces();
+		let hint = if self.see(":") {
+			Some("remove the duplicate colon")
+		} else if matches!(self.parse_primitive(), Ok(Some(_))) {
+			Some("multiple values must be wrapped in an inline array, e.g. `[1 2]`")
+		} else {
+			None
+		};
+		self.restore();
+
+		match hint {
+			Some(hint) => error.with_hint(hint),
+			None => error,
+		}
+	}
+
+	/// Checks that only a comment follows a marker opening a block, e.g.
+	/// `:--`.
+	pub fn expect_end_after_marker(&mut self, marker: &'static str) -> ParserResult<()> {
+		if self.see_end_or_comment() {
+			return Ok(());
+		}
+		self.consume_whitespaces();
+		Err(self.generate_error(ParserErrorKind::TrailingAfterMarker { marker }))
+	}
+
+	/// The rest of the line after the current position.
+	pub fn rest(&self) -> &'a str {
+		self.left
+	}
+
+	/// Consumes and returns the rest of the line.
+	pub fn consume_rest(&mut self) -> &'a str {
+		let ret = self.left;
+		self.i = self.line.len();
+		self.left = &self.line[self.line.len()..self.line.len()];
+
+		ret
+	}
+
+	/// Saves the current position. Every call must be followed by either
+	/// [Scanner::restore] or [Scanner::cancel_restore]. Calls can be nested.
+	pub fn record(&mut self) {
+		self.recorded.push((self.i, self.left));
+	}
+
+	/// Goes back to the last saved position.
+	pub fn restore(&mut self) {
+		(self.i, self.left) = self.recorded.pop().unwrap();
+	}
+
+	/// Discards the last saved position, keeping the current one.
+	pub fn cancel_restore(&mut self) {
+		self.recorded.pop().unwrap();
+	}
+
+	/// The byte offset of the current position in the line.
+	pub fn column(&self) -> usize {
+		self.i
+	}
+
+	/// Returns whether or not the end of the line has been reached.
+	pub fn reached_end(&self) -> bool {
+		self.left.is_empty()
+	}
+
+	/// Returns true if the remaining part of the line starts with `s`.
+	pub fn see(&self, s: &str) -> bool {
+		self.left.starts_with(s)
+	}
+
+	/// If sees `s` returns true and advances by the length of `s`.
+	/// Otherwise returns false.
+	pub fn have(&mut self, s: &str) -> bool {
+		if self.see(s) {
+			self.advance_by(s.len());
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Returns true if the remaining part of the line starts with any of
+	/// `ss`.
+	pub fn see_any(&self, ss: &[&str]) -> bool {
+		ss.iter().any(|s| self.see(s))
+	}
+
+	/// The comment marker `s` starts with, `#` or one of
+	/// [ParserOptions::comment_prefixes].
+	fn comment_marker<'s>(&'s self, s: &str) -> Option<&'s str> {
+		if s.starts_with('#') {
+			return Some("#");
+		}
+		self.options
+			.comment_prefixes
+			.iter()
+			.map(String::as_str)
+			.find(|prefix| s.starts_with(prefix))
+	}
+
+	/// Returns true if only whitespaces and a comment are left.
+	pub fn see_end_or_comment(&self) -> bool {
+		let left = self.left.trim_start();
+		left.is_empty() || self.comment_marker(left).is_some()
+	}
+
+	/// Consumes the comment at the current position, returning its text
+	/// without the marker.
+	pub fn parse_comment(&mut self) -> Option<&'a str> {
+		let marker_len = self.comment_marker(self.left)?.len();
+		Some(&self.consume_rest()[marker_len..])
+	}
+
+	/// Consumes whitespaces and the comment after them, returning its text
+	/// without the marker.
+	pub fn consume_whitespaces_and_comment(&mut self) -> Option<&'a str> {
+		self.consume_whitespaces();
+		self.parse_comment()
+	}
+
+	/// Consumes a single character, which may span multiple bytes.
+	pub fn advance(&mut self) {
+		if let Some(c) = self.left.chars().next() {
+			self.advance_by(c.len_utf8());
+		}
+	}
+
+	/// Consumes `amount` of bytes. `amount` must end on a character boundary,
+	/// which is the case when it is the length of a previously seen string.
+	pub fn advance_by(&mut self, amount: usize) {
+		debug_assert!(self.left.is_char_boundary(amount));
+		self.left = &self.left[amount..];
+		self.i += amount;
+	}
+
+	/// Consumes the whitespaces and returns the tuple
+	/// (tabs count, spaces count).
+	pub fn next_whitespaces(&mut self) -> (usize, usize) {
+		let mut tabs_count = 0;
+		let mut spaces_count = 0;
+
+		// counts how many tabs and spaces were seen until the next non
+		// whitespace character, or the end of the file
+		for c in self.left.chars() {
+			match c {
+				' ' => spaces_count += 1,
+				'\t' => tabs_count += 1,
+				_ => break,
+			}
+		}
+		self.advance_by(tabs_count + spaces_count);
+
+		(tabs_count, spaces_count)
+	}
+
+	/// Counts the spaces at the current position without consuming them.
+	pub fn leading_spaces(&self) -> usize {
+		self.left.len() - self.left.trim_start_matches(' ').len()
+	}
+
+	/// Advances past all the leading whitespaces.
+	pub fn consume_whitespaces(&mut self) {
+		let start_len = self.left.len();
+		self.left = self.left.trim_start();
+		self.i += start_len - self.left.len();
+	}
+
+	// helper function for `parse_string_literal`
+	fn parse_string_literal_with(&mut self, escape: &str) -> ParserResult<String> {
+		match self.left.find(escape) {
+			Some(end) => {
+				let s = self.left[..end].to_string();
+				self.advance_by(end + escape.len());
+				Ok(s)
+			}
+			None => {
+				self.advance_by(self.left.len());
+				Err(self
+					.generate_error(ParserErrorKind::UnclosedString)
+					.with_hint(format!("close the string with {escape}")))
+			}
+		}
+	}
+
+	/// Tries parsing a string literal, returns `None` if no literal found.
+	/// Returns and error if the string literal is invalid.
+	pub fn parse_string_literal(&mut self) -> ParserResult<Option<String>> {
+		if self.see("'") {
+			let start = self.i;
+			while self.have("'") {}
+			let escape = &self.line[start..self.i];
+
+			self.parse_string_literal_with(escape).map(Some)
+		} else if self.see("\"") {
+			let start = self.i;
+			while self.have("\"") {}
+			let escape = &self.line[start..self.i];
+
+			self.parse_string_literal_with(escape).map(Some)
+		} else {
+			Ok(None)
+		}
+	}
+
+	/// Parses a quoted or an unquoted key. Unquoted keys end at whitespace,
+	/// `:`, `#` or `;`, and may contain any other character, including
+	/// non-ASCII letters.
+	pub fn parse_key(&mut self) -> ParserResult<String> {
+		let key = if let Some(literal) = self.parse_string_literal()? {
+			literal
+		} else {
+			let end = self
+				.left
+				.find([' ', '\t', ':', '#', ';'])
+				.unwrap_or(self.left.len());
+			let key = self.left[..end].to_string();
+			self.advance_by(end);
+			key
+		};
+
+		#[cfg(feature = "unicode-normalization")]
+		if self.options.normalize_keys {
+			use unicode_normalization::UnicodeNormalization;
+			return Ok(key.nfc().collect());
+		}
+
+		Ok(key)
+	}
+
+	/// Parses a key like [Scanner::parse_key]. With
+	/// [ParserOptions::dotted_keys], unquoted keys are split at dots into the
+	/// path of nested keys, e.g. `server.port` into `server` and `port`.
+	pub fn parse_key_path(&mut self) -> ParserResult<Vec<String>> {
+		self.record();
+		let quoted = self.see("'") || self.see("\"");
+		let key = self.parse_recorded_key()?;
+		self.split_key(key, quoted)
+	}
+
+	/// Parses a key path like [Scanner::parse_key_path] followed by a colon.
+	/// Returns `None` without consuming anything if there is no colon after
+	/// the key.
+	pub fn parse_key_path_with_colon(&mut self) -> ParserResult<Option<Vec<String>>> {
+		self.record();
+		let quoted = self.see("'") || self.see("\"");
+		let key = self.parse_recorded_key()?;
+
+		self.consume_whitespaces();
+		if !self.have(":") {
+			self.restore();
+			return Ok(None);
+		}
+		self.split_key(key, quoted).map(Some)
+	}
+
+	/// Parses a key after a [Scanner::record], discarding the record if that
+	/// fails.
+	fn parse_recorded_key(&mut self) -> ParserResult<String> {
+		self.parse_key().inspect_err(|_| self.cancel_restore())
+	}
+
+	/// Splits a key that was parsed after a [Scanner::record] into its path,
+	/// consuming the record.
+	fn split_key(&mut self, key: String, quoted: bool) -> ParserResult<Vec<String>> {
+		if !self.options.dotted_keys || quoted || !key.contains('.') {
+			self.cancel_restore();
+			return Ok(vec![key]);
+		}
+
+		let path: Vec<String> = key.split('.').map(ToString::to_string).collect();
+		if path.iter().any(String::is_empty) {
+			self.restore();
+			return Err(self
+				.generate_error(ParserErrorKind::unexpected(key, &["key"]))
+				.with_hint("quote keys with empty parts, e.g. 'a..b' or '.a'"));
+		}
+		self.cancel_restore();
+		Ok(path)
+	}
+
+	/// Parses a key followed by a colon, e.g. `key:`. Returns an empty string
+	/// without consuming anything if there is no colon after the key.
+	pub fn parse_key_with_colon(&mut self) -> ParserResult<String> {
+		self.record();
+
+		let key = self.parse_recorded_key()?;
+
+		self.consume_whitespaces();
+		if self.have(":") {
+			self.cancel_restore();
+			Ok(key)
+		} else {
+			self.restore();
+			Ok(String::new())
+		}
+	}
+
+	/// Parses a number such as `-1.5`.
+	pub fn parse_numerical_literal(&mut self) -> Option<f32> {
+		lazy_static! {
+			static ref RE: Regex = Regex::new(r"^-?[0-9]*(?:\.[0-9]+)?").unwrap();
+		}
+
+		// if the regex captures, and the the value can be unwrapped, advance
+		// and return
+		if let Some(captures) = RE.captures(self.left) {
+			if let Some(m) = captures.get(0) {
+				let s = m.as_str();
+				if let Ok(value) = s.parse() {
+					self.advance_by(s.len());
+					return Some(value);
+				}
+			}
+		}
+
+		None
+	}
+
+	/// Parses a duration or a byte size literal such as `2h30m` or `10MiB`.
+	#[cfg(feature = "quantities")]
+	pub fn parse_quantity_literal(&mut self) -> Option<PrimitiveValue> {
+		if !self.left.starts_with(|c: char| c.is_ascii_digit()) {
+			return None;
+		}
+
+		let end = self
+			.left
+			.find(|c: char| c.is_whitespace() || c == ']' || c == '#')
+			.unwrap_or(self.left.len());
+		let token = &self.left[..end];
+
+		let value = if let Some(bytes) = crate::quantity::parse_byte_size(token) {
+			PrimitiveValue::ByteSize(bytes)
+		} else {
+			PrimitiveValue::Duration(crate::quantity::parse_duration(token)?)
+		};
+
+		self.advance_by(end);
+		Some(value)
+	}
+
+	/// Parses a UUID or an IP address literal such as
+	/// `67e55044-10b1-426f-9247-bb680e5fe0c8` or `10.0.0.1`. Returns an error
+	/// for words shaped like one of them that aren't valid, e.g. `10.0.0.300`.
+	#[cfg(any(feature = "uuid", feature = "ipaddr"))]
+	pub fn parse_address_literal(&mut self) -> ParserResult<Option<PrimitiveValue>> {
+		let end = self
+			.left
+			.find(|c: char| c.is_whitespace() || c == ']' || c == '#')
+			.unwrap_or(self.left.len());
+		let token = &self.left[..end];
+		let invalid = |expected| {
+			self.generate_error(ParserErrorKind::InvalidLiteral {
+				found: token.to_string(),
+				expected,
+			})
+		};
+
+		#[cfg(feature = "uuid")]
+		if token.len() == 36 && [8, 13, 18, 23].iter().all(|&i| token.as_bytes()[i] == b'-') {
+			let uuid = uuid::Uuid::try_parse(token).map_err(|_| invalid("UUID"))?;
+			self.advance_by(end);
+			return Ok(Some(PrimitiveValue::Uuid(uuid)));
+		}
+
+		#[cfg(feature = "ipaddr")]
+		{
+			let is_ipv4 = token.matches('.').count() == 3
+				&& token.chars().all(|c| c.is_ascii_digit() || c == '.');
+			let is_ipv6 = token.matches(':').count() >= 2
+				&& token
+					.chars()
+					.all(|c| c.is_ascii_hexdigit() || c == ':' || c == '.');
+			if is_ipv4 || is_ipv6 {
+				let ip = token.parse().map_err(|_| invalid("IP address"))?;
+				self.advance_by(end);
+				return Ok(Some(PrimitiveValue::Ip(ip)));
+			}
+		}
+
+		Ok(None)
+	}
+
+	/// Parses a semantic version literal such as `1.2.3-alpha+build`. Returns
+	/// an error for words shaped like a version that aren't valid, e.g.
+	/// `1.02.3`.
+	#[cfg(feature = "semver")]
+	pub fn parse_version_literal(&mut self) -> ParserResult<Option<PrimitiveValue>> {
+		lazy_static! {
+			static ref RE: Regex =
+				Regex::new(r"^[0-9]+\.[0-9]+\.[0-9]+(?:[-+][0-9A-Za-z.+-]*)?$").unwrap();
+		}
+
+		let end = self
+			.left
+			.find(|c: char| c.is_whitespace() || c == ']' || c == '#')
+			.unwrap_or(self.left.len());
+		let token = &self.left[..end];
+		if !RE.is_match(token) {
+			return Ok(None);
+		}
+
+		let version = semver::Version::parse(token).map_err(|_| {
+			self.generate_error(ParserErrorKind::InvalidLiteral {
+				found: token.to_string(),
+				expected: "version",
+			})
+		})?;
+		self.advance_by(end);
+		Ok(Some(PrimitiveValue::Version(version)))
+	}
+
+	/// Returns the bare word at the start of the remaining line, up to the next
+	/// whitespace, comment or inline array delimiter.
+	fn peek_word(&self) -> &'a str {
+		let end = self
+			.left
+			.find(|c: char| c.is_whitespace() || c == '#' || c == '[' || c == ']')
+			.unwrap_or(self.left.len());
+		&self.left[..end]
+	}
+
+	/// If the next bare word is exactly `word`, consumes it and returns true.
+	fn have_word(&mut self, word: &str) -> bool {
+		if self.peek_word() == word {
+			self.advance_by(word.len());
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Parses `true` or `false`, and their aliases if enabled with
+	/// [ParserOptions::boolean_aliases].
+	pub fn parse_boolean_literal(&mut self) -> Option<bool> {
+		if self.have("true") {
+			Some(true)
+		} else if self.have("false") {
+			Some(false)
+		} else if self.options.boolean_aliases && (self.have_word("yes") || self.have_word("on")) {
+			Some(true)
+		} else if self.options.boolean_aliases && (self.have_word("no") || self.have_word("off")) {
+			Some(false)
+		} else {
+			None
+		}
+	}
+
+	/// Consumes `null`, or `~` if enabled with [ParserOptions::null_alias].
+	pub fn parse_null_literal(&mut self) -> bool {
+		self.have("null") || (self.options.null_alias && self.have_word("~"))
+	}
+
+	/// In strict literal mode, returns an error for bare words that look like
+	/// a misspelled boolean or null, such as `True` or `YES`.
+	fn check_near_miss_literal(&self) -> ParserResult<()> {
+		if !self.options.strict_literals {
+			return Ok(());
+		}
+
+		let word = self.peek_word();
+		let lower = word.to_lowercase();
+		let expected = match lower.as_str() {
+			"true" | "false" | "null" => lower.as_str(),
+			"nil" | "none" => "null",
+			"yes" | "on" | "no" | "off" if self.options.boolean_aliases => lower.as_str(),
+			"yes" | "on" => "true",
+			"no" | "off" => "false",
+			_ => return Ok(()),
+		};
+
+		Err(self.generate_error(ParserErrorKind::NearMissLiteral {
+			found: word.to_string(),
+			expected: expected.to_string(),
+		}))
+	}
+
+	/// Helper for `parse_inline_array`
+	fn next_inline_array(&mut self) -> ParserResult<Value> {
+		let mut values = Vec::new();
+		loop {
+			self.consume_whitespaces();
+
+			// end of array
+			if self.have("]") {
+				break;
+			}
+
+			// new sub array
+			if self.have("[") {
+				values.push(self.next_inline_array()?);
+				continue;
+			}
+
+			// next value
+			if let Some(primitive) = self.parse_primitive()? {
+				values.push(Value::Primitive(primitive));
+				continue;
+			}
+
+			let error = self.generate_unexpected_error(&["value", "]"]);
+			return Err(if self.left.trim().is_empty() {
+				error.with_hint("close the array with `]`")
+			} else if self.see_end_or_comment() {
+				error.with_hint(
+					"inline arrays end on their line, so comments can only follow the closing `]`",
+				)
+			} else {
+				error
+			});
+		}
+
+		Ok(Value::Array(values))
+	}
+
+	/// Parses an inline array such as `[1 [2 3]]`.
+	pub fn parse_inline_array(&mut self) -> ParserResult<Option<Value>> {
+		if self.have("[") {
+			Ok(Some(self.next_inline_array()?))
+		} else {
+			Ok(None)
+		}
+	}
+
+	/// Parses a `!tag literal` pair, converting the literal with the tag's
+	/// registered hook.
+	fn parse_tagged_primitive(&mut self) -> ParserResult<Option<PrimitiveValue>> {
+		if !self.have("!") {
+			return Ok(None);
+		}
+		self.check_extension(self.i - 1, "tagged literals")?;
+
+		// tag name
+		let start = self.i;
+		while !self.reached_end() && !self.see_any(&[" ", "\t", "#", "]"]) {
+			self.advance();
+		}
+		let tag = &self.line[start..self.i];
+		if tag.is_empty() {
+			return Err(self.generate_error(ParserErrorKind::expected("tag name")));
+		}
+
+		// tagged literal
+		self.consume_whitespaces();
+		let literal_start = self.i;
+		let Some(literal) = self.parse_untagged_primitive()? else {
+			return Err(self.generate_error(ParserErrorKind::expected("literal")));
+		};
+
+		match self.options.tags.get(tag) {
+			Some(parser) => parser(literal).map(Some).map_err(|message| {
+				let mut error = self.generate_error(ParserErrorKind::InvalidTaggedValue {
+					tag: tag.to_string(),
+					message,
+				});
+				error.column_number = literal_start;
+				error
+			}),
+			None if self.options.allow_unknown_tags => Ok(Some(literal)),
+			None => {
+				let mut error = self.generate_error(ParserErrorKind::UnknownTag(tag.to_string()));
+				error.column_number = start - 1;
+				Err(error)
+			}
+		}
+	}
+
+	/// Parses any literal, including tagged literals and the ones of
+	/// [ParserOptions::primitive_parser] hooks, which aren't called with
+	/// [Profile::Strict].
+	pub fn parse_primitive(&mut self) -> ParserResult<Option<PrimitiveValue>> {
+		let options = self.options;
+		let hooks = match options.profile {
+			Some(Profile::Strict) => &[][..],
+			_ => &options.primitive_parsers[..],
+		};
+		for PrimitiveParserHook(parser) in hooks {
+			self.record();
+			match parser.parse(self)? {
+				Some(value) => {
+					self.cancel_restore();
+					return Ok(Some(value));
+				}
+				None => self.restore(),
+			}
+		}
+
+		if let Some(value) = self.parse_tagged_primitive()? {
+			Ok(Some(value))
+		} else {
+			self.parse_untagged_primitive()
+		}
+	}
+
+	fn parse_untagged_primitive(&mut self) -> ParserResult<Option<PrimitiveValue>> {
+		if let Some(value) = self.parse_string_literal()? {
+			return Ok(Some(PrimitiveValue::String(value)));
+		}
+
+		// the literals of features are extensions
+		#[cfg(any(
+			feature = "uuid",
+			feature = "ipaddr",
+			feature = "semver",
+			feature = "quantities"
+		))]
+		let start = self.i;
+
+		#[cfg(any(feature = "uuid", feature = "ipaddr"))]
+		if let Some(value) = self.parse_address_literal()? {
+			let extension = match value {
+				#[cfg(feature = "uuid")]
+				PrimitiveValue::Uuid(_) => "UUID literals",
+				_ => "IP address literals",
+			};
+			self.check_extension(start, extension)?;
+			return Ok(Some(value));
+		}
+
+		#[cfg(feature = "semver")]
+		if let Some(value) = self.parse_version_literal()? {
+			self.check_extension(start, "version literals")?;
+			return Ok(Some(value));
+		}
+
+		#[cfg(feature = "quantities")]
+		if let Some(value) = self.parse_quantity_literal() {
+			self.check_extension(start, "quantity literals")?;
+			return Ok(Some(value));
+		}
+
+		if let Some(value) = self.parse_numerical_literal() {
+			Ok(Some(PrimitiveValue::Number(value)))
+		} else if let Some(value) = self.parse_boolean_literal() {
+			Ok(Some(PrimitiveValue::Boolean(value)))
+		} else if self.parse_null_literal() {
+			Ok(Some(PrimitiveValue::Null))
+		} else {
+			self.check_near_miss_literal()?;
+			Ok(None)
+		}
+	}
+
+	/// Helper for `have_indentions`
+	fn have_indentions_helper(&mut self, indention: Indention, amount: usize) -> bool {
+		match indention {
+			Indention::Tabs => {
+				for _ in 0..amount {
+					if self.see(" ") {
+						return false;
+					}
+
+					if !self.have("\t") {
+						return false;
+					}
+				}
+			}
+			Indention::Spaces(spaces) => {
+				for _ in 0..amount {
+					for _ in 0..spaces {
+						if self.see("\t") {
+							return false;
+						}
+						if !self.have(" ") {
+							return false;
+						}
+					}
+				}
+			}
+		};
+
+		true
+	}
+
+	/// If sees a specific amount of a certain indention, returns true and
+	/// consumes it. Otherwise returns false.
+	pub fn have_indentions(&mut self, indention: Indention, amount: usize) -> bool {
+		self.record();
+		if self.have_indentions_helper(indention, amount) {
+			self.cancel_restore();
+			true
+		} else {
+			self.restore();
+			false
+		}
+	}
+}
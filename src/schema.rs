@@ -0,0 +1,559 @@
+//! The expected shape of a document, used to report keys the application
+//! doesn't know about. Typos in optional keys otherwise go unnoticed, as the
+//! misspelled key is ignored and the default is used instead.
+//!
+//! Schemas are usually obtained from [Decode::schema], which is implemented
+//! by `#[derive(KvonDecode)]`:
+//!
+//! ```rust
+//! use kvon_rs::{
+//!     decode::Decode,
+//!     parse_string,
+//!     schema::Schema,
+//! };
+//!
+//! let schema = Schema::object([("host", String::schema as fn() -> Schema)]);
+//! let value = parse_string("hots: 'localhost'").unwrap();
+//!
+//! let warnings = schema.unknown_keys(&value);
+//! assert_eq!(warnings[0].suggestion.as_deref(), Some("host"));
+//! assert_eq!(
+//!     warnings[0].to_string(),
+//!     "unknown key 'hots', did you mean 'host'?"
+//! );
+//! ```
+//!
+//! Schemas can also constrain values, which [Schema::validate] checks:
+//!
+//! ```rust
+//! use kvon_rs::{
+//!     parse_string,
+//!     schema::{Constraint, Schema},
+//! };
+//!
+//! fn env() -> Schema {
+//!     Schema::String.constrain(Constraint::OneOf(vec!["dev".into(), "prod".into()]))
+//! }
+//!
+//! let schema = Schema::object([("env", env as fn() -> Schema)]);
+//! let value = parse_string("env: 'staging'").unwrap();
+//!
+//! let violations = schema.validate(&value);
+//! assert_eq!(
+//!     violations[0].to_string(),
+//!     "env: 'staging' is not one of 'dev', 'prod'"
+//! );
+//! ```
+
+use std::{borrow::Cow, fmt};
+
+use regex::Regex;
+
+use crate::{
+	decode::Decode,
+	encoder::encode_primitive_lines,
+	path::{KvonPath, Path, PathSegment},
+	value::{PrimitiveValue, Value},
+	EncodeOptions,
+};
+
+/// A key of an object and the schema of its value.
+pub type Field = (Cow<'static, str>, SchemaRef);
+
+/// A nested schema. Schemas of types are functions, so that recursive types
+/// have finite schemas, while schemas built at runtime are stored.
+#[derive(Debug, Clone)]
+pub enum SchemaRef {
+	Fn(fn() -> Schema),
+	Owned(Box<Schema>),
+}
+
+impl SchemaRef {
+	pub fn get(&self) -> Cow<'_, Schema> {
+		match self {
+			Self::Fn(f) => Cow::Owned(f()),
+			Self::Owned(schema) => Cow::Borrowed(schema),
+		}
+	}
+}
+
+impl From<fn() -> Schema> for SchemaRef {
+	fn from(f: fn() -> Schema) -> Self {
+		Self::Fn(f)
+	}
+}
+
+impl From<Schema> for SchemaRef {
+	fn from(schema: Schema) -> Self {
+		Self::Owned(Box::new(schema))
+	}
+}
+
+/// The expected shape of a value.
+#[derive(Debug, Clone)]
+pub enum Schema {
+	/// Any value, e.g. values without a known structure.
+	Any,
+	Number,
+	Boolean,
+	String,
+	/// An object with a fixed set of keys.
+	Object {
+		fields: Vec<Field>,
+		/// Whether keys other than `fields` are allowed, e.g. because they
+		/// are collected into a map.
+		open: bool,
+	},
+	/// An object with arbitrary keys whose values follow the schema.
+	Map(SchemaRef),
+	/// An array whose items follow the schema.
+	Array(SchemaRef),
+	/// A value following the schema, `null` or a missing key.
+	Optional(SchemaRef),
+	/// A value following the schema that also satisfies the constraints.
+	Constrained(Box<Schema>, Vec<Constraint>),
+}
+
+/// A requirement on a primitive beyond its type, see [Schema::constrain].
+/// Constraints only apply to values of their type, e.g. a pattern is
+/// ignored for numbers.
+#[derive(Debug, Clone)]
+pub enum Constraint {
+	/// Strings must match the regular expression, see
+	/// [Constraint::pattern].
+	Pattern(Regex),
+	/// Strings must have at least this many characters.
+	MinLength(usize),
+	/// Strings must have at most this many characters.
+	MaxLength(usize),
+	/// Strings must be one of the values.
+	OneOf(Vec<String>),
+	/// Numbers must be greater than or equal to this.
+	Minimum(f32),
+	/// Numbers must be less than or equal to this.
+	Maximum(f32),
+	/// Numbers must be greater than this.
+	ExclusiveMinimum(f32),
+	/// Numbers must be less than this.
+	ExclusiveMaximum(f32),
+	/// Numbers must be a multiple of this, e.g. `0.5`.
+	MultipleOf(f32),
+}
+
+impl Constraint {
+	/// Compiles a [Constraint::Pattern]. The pattern isn't anchored, so it
+	/// should start with `^` and end with `$` to match whole strings.
+	pub fn pattern(pattern: &str) -> Result<Self, regex::Error> {
+		Regex::new(pattern).map(Self::Pattern)
+	}
+
+	/// Returns false if the primitive violates the constraint.
+	pub fn is_satisfied_by(&self, primitive: &PrimitiveValue) -> bool {
+		match (self, primitive) {
+			(Self::Pattern(regex), PrimitiveValue::String(s)) => regex.is_match(s),
+			(Self::MinLength(min), PrimitiveValue::String(s)) => s.chars().count() >= *min,
+			(Self::MaxLength(max), PrimitiveValue::String(s)) => s.chars().count() <= *max,
+			(Self::OneOf(values), PrimitiveValue::String(s)) => values.contains(s),
+			(Self::Minimum(min), PrimitiveValue::Number(n)) => n >= min,
+			(Self::Maximum(max), PrimitiveValue::Number(n)) => n <= max,
+			(Self::ExclusiveMinimum(min), PrimitiveValue::Number(n)) => n > min,
+			(Self::ExclusiveMaximum(max), PrimitiveValue::Number(n)) => n < max,
+			(Self::MultipleOf(factor), PrimitiveValue::Number(n)) => {
+				// dividing floats is inexact, e.g. 0.3 / 0.1 isn't exactly 3
+				let quotient = n / factor;
+				(quotient - quotient.round()).abs() < 1e-4
+			}
+			_ => true,
+		}
+	}
+}
+
+impl fmt::Display for Constraint {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Pattern(regex) => write!(f, "doesn't match pattern '{}'", regex.as_str()),
+			Self::MinLength(min) => write!(f, "is shorter than {min} characters"),
+			Self::MaxLength(max) => write!(f, "is longer than {max} characters"),
+			Self::OneOf(values) => {
+				let values: Vec<_> = values.iter().map(|v| format!("'{v}'")).collect();
+				write!(f, "is not one of {}", values.join(", "))
+			}
+			Self::Minimum(min) => write!(f, "is less than {min}"),
+			Self::Maximum(max) => write!(f, "is greater than {max}"),
+			Self::ExclusiveMinimum(min) => write!(f, "is not greater than {min}"),
+			Self::ExclusiveMaximum(max) => write!(f, "is not less than {max}"),
+			Self::MultipleOf(factor) => write!(f, "is not a multiple of {factor}"),
+		}
+	}
+}
+
+/// A primitive violating a constraint of its schema, see
+/// [Schema::validate].
+#[derive(Debug, Clone)]
+pub struct Violation {
+	pub path: KvonPath,
+	pub value: PrimitiveValue,
+	pub constraint: Constraint,
+}
+
+impl fmt::Display for Violation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if !self.path.is_empty() {
+			write!(f, "{}: ", self.path)?;
+		}
+		let value = encode_primitive_lines(&self.value, &EncodeOptions::new(), String::new(), 0);
+		write!(f, "{} {}", value.join("\n"), self.constraint)
+	}
+}
+
+impl Schema {
+	/// A closed object with the given fields.
+	pub fn object<K, S>(fields: impl IntoIterator<Item = (K, S)>) -> Self
+	where
+		K: Into<Cow<'static, str>>,
+		S: Into<SchemaRef>,
+	{
+		Self::Object {
+			fields: fields
+				.into_iter()
+				.map(|(key, schema)| (key.into(), schema.into()))
+				.collect(),
+			open: false,
+		}
+	}
+
+	/// Adds a constraint to the schema.
+	pub fn constrain(self, constraint: Constraint) -> Self {
+		match self {
+			Self::Constrained(schema, mut constraints) => {
+				constraints.push(constraint);
+				Self::Constrained(schema, constraints)
+			}
+			schema => Self::Constrained(Box::new(schema), vec![constraint]),
+		}
+	}
+
+	/// Guesses the schema of an example document, see [Schema::infer_all].
+	pub fn infer(example: &Value) -> Self {
+		Self::infer_all([example])
+	}
+
+	/// Guesses the schema of documents from examples, as a starting point to
+	/// refine by hand. Objects are closed and keys missing from some of the
+	/// examples or array items are optional. Numbers are constrained to the
+	/// observed range and values of different types accept anything.
+	pub fn infer_all<'a>(examples: impl IntoIterator<Item = &'a Value>) -> Self {
+		Self::infer_values(examples.into_iter().collect())
+	}
+
+	fn infer_values(examples: Vec<&Value>) -> Self {
+		let (nulls, values): (Vec<&Value>, Vec<&Value>) = examples
+			.into_iter()
+			.partition(|value| matches!(value, Value::Primitive(PrimitiveValue::Null)));
+
+		let schema = match values.first() {
+			None => Self::Any,
+			Some(Value::Primitive(PrimitiveValue::Number(_))) => {
+				let numbers: Option<Vec<f32>> = values
+					.iter()
+					.map(|value| value.get_primitive().ok()?.get_number().ok())
+					.collect();
+				match numbers {
+					Some(numbers) => {
+						let min = numbers.iter().copied().fold(f32::INFINITY, f32::min);
+						let max = numbers.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+						Self::Number
+							.constrain(Constraint::Minimum(min))
+							.constrain(Constraint::Maximum(max))
+					}
+					None => Self::Any,
+				}
+			}
+			Some(Value::Primitive(PrimitiveValue::String(_)))
+				if values
+					.iter()
+					.all(|value| matches!(value, Value::Primitive(PrimitiveValue::String(_)))) =>
+			{
+				Self::String
+			}
+			Some(Value::Primitive(PrimitiveValue::Boolean(_)))
+				if values
+					.iter()
+					.all(|value| matches!(value, Value::Primitive(PrimitiveValue::Boolean(_)))) =>
+			{
+				Self::Boolean
+			}
+			Some(Value::Object(_)) if values.iter().all(|value| value.is_object()) => {
+				let objects: Vec<_> = values.iter().filter_map(|v| v.get_objects().ok()).collect();
+				let mut keys: Vec<&String> = objects.iter().flat_map(|obj| obj.keys()).collect();
+				keys.sort();
+				keys.dedup();
+				Self::object(keys.into_iter().map(|key| {
+					let present: Vec<_> = objects.iter().filter_map(|obj| obj.get(key)).collect();
+					let mut schema = Self::infer_values(present.clone());
+					if present.len() < objects.len() && !matches!(schema, Self::Optional(_)) {
+						schema = Self::Optional(schema.into());
+					}
+					(key.clone(), schema)
+				}))
+			}
+			Some(Value::Array(_)) if values.iter().all(|value| value.is_array()) => {
+				let items = values
+					.iter()
+					.filter_map(|value| value.get_vector().ok())
+					.flatten()
+					.collect();
+				Self::Array(Self::infer_values(items).into())
+			}
+			Some(_) => Self::Any,
+		};
+
+		match schema {
+			Self::Any => Self::Any,
+			schema if !nulls.is_empty() => Self::Optional(schema.into()),
+			schema => schema,
+		}
+	}
+
+	/// Returns the schema of the values at `path`, or `None` if the schema
+	/// has no place for them, e.g. a misspelled key of a closed object.
+	pub fn at(&self, path: &Path) -> Option<Schema> {
+		let Some((segment, rest)) = path.split_first() else {
+			return Some(self.clone());
+		};
+		match (self, segment) {
+			(Self::Any, _) => Some(Self::Any),
+			(Self::Constrained(schema, _), _) => schema.at(path),
+			(Self::Optional(schema), _) => schema.get().at(path),
+			(Self::Object { fields, open }, PathSegment::Key(key)) => {
+				match fields.iter().find(|(name, _)| name == key) {
+					Some((_, schema)) => schema.get().at(rest),
+					None if *open => Some(Self::Any),
+					None => None,
+				}
+			}
+			(Self::Map(schema), PathSegment::Key(_))
+			| (Self::Array(schema), PathSegment::Index(_)) => schema.get().at(rest),
+			_ => None,
+		}
+	}
+
+	/// Returns the primitives of `value` violating their constraints. Type
+	/// mismatches are reported when decoding instead.
+	pub fn validate(&self, value: &Value) -> Vec<Violation> {
+		let mut violations = Vec::new();
+		self.collect_violations(value, &mut KvonPath::root(), &mut violations);
+		violations
+	}
+
+	fn collect_violations(
+		&self,
+		value: &Value,
+		path: &mut KvonPath,
+		violations: &mut Vec<Violation>,
+	) {
+		match (self, value) {
+			(Self::Constrained(schema, constraints), value) => {
+				if let Value::Primitive(primitive) = value {
+					for constraint in constraints {
+						if !constraint.is_satisfied_by(primitive) {
+							violations.push(Violation {
+								path: path.clone(),
+								value: primitive.clone(),
+								constraint: constraint.clone(),
+							});
+						}
+					}
+				}
+				schema.collect_violations(value, path, violations);
+			}
+			(Self::Optional(schema), value) => {
+				schema.get().collect_violations(value, path, violations)
+			}
+			(Self::Object { fields, .. }, Value::Object(obj)) => {
+				for (key, schema) in fields {
+					if let Some(value) = obj.get(key.as_ref()) {
+						path.push(PathSegment::Key(key.to_string()));
+						schema.get().collect_violations(value, path, violations);
+						path.pop();
+					}
+				}
+			}
+			(Self::Map(schema), Value::Object(obj)) => {
+				let schema = schema.get();
+				let mut keys: Vec<_> = obj.keys().collect();
+				keys.sort();
+				for key in keys {
+					path.push(PathSegment::Key(key.clone()));
+					schema.collect_violations(&obj[key], path, violations);
+					path.pop();
+				}
+			}
+			(Self::Array(schema), Value::Array(arr)) => {
+				let schema = schema.get();
+				for (i, item) in arr.iter().enumerate() {
+					path.push(PathSegment::Index(i));
+					schema.collect_violations(item, path, violations);
+					path.pop();
+				}
+			}
+			_ => {}
+		}
+	}
+
+	/// Adds the keys of a flattened value to the fields of an object. Values
+	/// that aren't closed objects make the object open.
+	pub fn flatten_into(self, fields: &mut Vec<Field>, open: &mut bool) {
+		match self {
+			Self::Object {
+				fields: flattened,
+				open: flattened_open,
+			} => {
+				fields.extend(flattened);
+				*open |= flattened_open;
+			}
+			_ => *open = true,
+		}
+	}
+
+	/// Returns the keys of `value` that don't appear in this schema.
+	pub fn unknown_keys(&self, value: &Value) -> Vec<UnknownKey> {
+		let mut unknown = Vec::new();
+		self.collect_unknown_keys(value, &mut KvonPath::root(), &mut unknown);
+		unknown
+	}
+
+	fn collect_unknown_keys(
+		&self,
+		value: &Value,
+		path: &mut KvonPath,
+		unknown: &mut Vec<UnknownKey>,
+	) {
+		match (self, value) {
+			(Self::Constrained(schema, _), value) => {
+				schema.collect_unknown_keys(value, path, unknown)
+			}
+			(Self::Optional(schema), value) => {
+				schema.get().collect_unknown_keys(value, path, unknown)
+			}
+			(Self::Object { fields, open }, Value::Object(obj)) => {
+				// sorted, so that the warnings appear in a stable order
+				let mut keys: Vec<_> = obj.keys().collect();
+				keys.sort();
+				for key in keys {
+					match fields.iter().find(|(name, _)| name == key) {
+						Some((_, schema)) => {
+							path.push(PathSegment::Key(key.clone()));
+							schema.get().collect_unknown_keys(&obj[key], path, unknown);
+							path.pop();
+						}
+						None if *open => {}
+						None => unknown.push(UnknownKey {
+							path: path.clone(),
+							key: key.clone(),
+							suggestion: suggest(key, fields.iter().map(|(name, _)| name.as_ref()))
+								.map(ToString::to_string),
+						}),
+					}
+				}
+			}
+			(Self::Map(schema), Value::Object(obj)) => {
+				let schema = schema.get();
+				let mut keys: Vec<_> = obj.keys().collect();
+				keys.sort();
+				for key in keys {
+					path.push(PathSegment::Key(key.clone()));
+					schema.collect_unknown_keys(&obj[key], path, unknown);
+					path.pop();
+				}
+			}
+			(Self::Array(schema), Value::Array(arr)) => {
+				let schema = schema.get();
+				for (i, item) in arr.iter().enumerate() {
+					path.push(PathSegment::Index(i));
+					schema.collect_unknown_keys(item, path, unknown);
+					path.pop();
+				}
+			}
+			// mismatching types are reported when decoding
+			_ => {}
+		}
+	}
+}
+
+/// Returns the keys of `value` that `T` doesn't know about.
+pub fn unknown_keys<T: Decode>(value: &Value) -> Vec<UnknownKey> {
+	T::schema().unknown_keys(value)
+}
+
+/// A primitive converted to the type its schema expects, see
+/// [Value::coerce].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coercion {
+	pub path: KvonPath,
+	pub from: PrimitiveValue,
+	pub to: PrimitiveValue,
+}
+
+/// A key that is present in a document but missing from its schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey {
+	/// The location of the object containing the key.
+	pub path: KvonPath,
+	pub key: String,
+	/// A known key with a similar spelling.
+	pub suggestion: Option<String>,
+}
+
+impl fmt::Display for UnknownKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if !self.path.is_empty() {
+			write!(f, "{}: ", self.path)?;
+		}
+		write!(f, "unknown key '{}'", self.key)?;
+		if let Some(suggestion) = &self.suggestion {
+			write!(f, ", did you mean '{suggestion}'?")?;
+		}
+		Ok(())
+	}
+}
+
+/// Returns the known key closest to `key`, if it is close enough to likely
+/// be a typo.
+fn suggest<'a>(key: &str, known: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+	let max_distance = (key.chars().count() / 3).max(1);
+	known
+		.map(|name| (edit_distance(key, name), name))
+		.filter(|(distance, _)| *distance <= max_distance)
+		.min_by_key(|(distance, _)| *distance)
+		.map(|(_, name)| name)
+}
+
+/// The edit distance between two strings counted in characters, where
+/// swapping two adjacent characters counts as a single edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+	let a: Vec<char> = a.chars().collect();
+	let b: Vec<char> = b.chars().collect();
+
+	// distances[i][j] is the distance between the first i characters of `a`
+	// and the first j characters of `b`
+	let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+	for (i, row) in distances.iter_mut().enumerate() {
+		row[0] = i;
+	}
+	distances[0] = (0..=b.len()).collect();
+
+	for i in 1..=a.len() {
+		for j in 1..=b.len() {
+			let cost = usize::from(a[i - 1] != b[j - 1]);
+			let mut distance = (distances[i - 1][j] + 1)
+				.min(distances[i][j - 1] + 1)
+				.min(distances[i - 1][j - 1] + cost);
+			if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+				distance = distance.min(distances[i - 2][j - 2] + 1);
+			}
+			distances[i][j] = distance;
+		}
+	}
+	distances[a.len()][b.len()]
+}
@@ -0,0 +1,952 @@
+//! `Serialize` and `Deserialize` implementations for [Value], enabled by the
+//! `serde` feature. Values map onto serde's data model the same way JSON
+//! does: objects are maps, arrays are sequences and null is a unit.
+//!
+//! [to_value] and [from_value] convert between [Value]s and any type
+//! implementing `Serialize` or `Deserialize`. Types borrowing strings, e.g.
+//! structs with `&str` fields, can be deserialized from a `&Value` with
+//! `T::deserialize(&value)`.
+
+use std::{collections::HashMap, fmt};
+
+use serde::{
+	de::{
+		value::{BorrowedStrDeserializer, StrDeserializer},
+		DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+		Visitor,
+	},
+	ser::{SerializeMap, SerializeSeq},
+	Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+	encoder::encode_string,
+	error::ParserError,
+	path::{KvonPath, PathSegment, Span},
+	value::{PrimitiveValue, Value},
+	EncodeOptions, Parser,
+};
+
+impl Serialize for PrimitiveValue {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		match self {
+			Self::Number(n) => serializer.serialize_f32(*n),
+			Self::String(s) => serializer.serialize_str(s),
+			Self::Boolean(b) => serializer.serialize_bool(*b),
+			Self::Null => serializer.serialize_unit(),
+			// values without a serde equivalent are serialized as their
+			// encoded literal
+			#[cfg(feature = "quantities")]
+			Self::Duration(_) | Self::ByteSize(_) => serializer.serialize_str(&encode_literal(self)),
+			#[cfg(feature = "uuid")]
+			Self::Uuid(_) => serializer.serialize_str(&encode_literal(self)),
+			#[cfg(feature = "ipaddr")]
+			Self::Ip(_) => serializer.serialize_str(&encode_literal(self)),
+			#[cfg(feature = "semver")]
+			Self::Version(_) => serializer.serialize_str(&encode_literal(self)),
+			Self::Custom(_) => serializer.serialize_str(&encode_literal(self)),
+		}
+	}
+}
+
+/// Encodes a primitive into the literal text it would have in a document.
+fn encode_literal(p: &PrimitiveValue) -> String {
+	let encoded = encode_string(
+		&Value::key_value_pair("v", p.clone()),
+		&EncodeOptions::new(),
+	);
+	encoded.trim_start().trim_start_matches("v: ").to_string()
+}
+
+impl Serialize for Value {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		match self {
+			Self::Primitive(p) => p.serialize(serializer),
+			Self::Array(arr) => {
+				let mut seq = serializer.serialize_seq(Some(arr.len()))?;
+				for value in arr {
+					seq.serialize_element(value)?;
+				}
+				seq.end()
+			}
+			Self::Object(obj) => {
+				let mut map = serializer.serialize_map(Some(obj.len()))?;
+				for (key, value) in obj {
+					map.serialize_entry(key, value)?;
+				}
+				map.end()
+			}
+			Self::Raw(raw) => match raw.parse() {
+				Ok(value) => value.serialize(serializer),
+				Err(e) => Err(serde::ser::Error::custom(e)),
+			},
+		}
+	}
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+	type Value = Value;
+
+	fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "a KVON value")
+	}
+
+	fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+		Ok(Value::from(v))
+	}
+
+	fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+		Ok(Value::from(v as f32))
+	}
+
+	fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+		Ok(Value::from(v as f32))
+	}
+
+	fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+		Ok(Value::from(v as f32))
+	}
+
+	fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+		Ok(Value::from(v))
+	}
+
+	fn visit_string<E>(self, v: String) -> Result<Value, E> {
+		Ok(Value::from(v))
+	}
+
+	fn visit_unit<E>(self) -> Result<Value, E> {
+		Ok(Value::null())
+	}
+
+	fn visit_none<E>(self) -> Result<Value, E> {
+		Ok(Value::null())
+	}
+
+	fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+		Value::deserialize(deserializer)
+	}
+
+	fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+		let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+		while let Some(value) = seq.next_element()? {
+			values.push(value);
+		}
+		Ok(Value::Array(values))
+	}
+
+	fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+		let mut values = HashMap::with_capacity(map.size_hint().unwrap_or(0));
+		while let Some((key, value)) = map.next_entry()? {
+			values.insert(key, value);
+		}
+		Ok(Value::Object(values))
+	}
+}
+
+impl<'de> Deserialize<'de> for Value {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		deserializer.deserialize_any(ValueVisitor)
+	}
+}
+
+/// Errors produced by [to_value] and [from_value].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerdeError {
+	pub message: String,
+	/// The location of the value that failed to deserialize.
+	pub path: KvonPath,
+}
+
+impl SerdeError {
+	/// Prepends a segment to the path, used while unwinding out of nested
+	/// values.
+	pub fn within(mut self, segment: PathSegment) -> Self {
+		self.path.prepend(segment);
+		self
+	}
+}
+
+impl fmt::Display for SerdeError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if !self.path.is_empty() {
+			write!(f, "{}: ", self.path)?;
+		}
+		write!(f, "{}", self.message)
+	}
+}
+
+impl std::error::Error for SerdeError {}
+
+impl serde::ser::Error for SerdeError {
+	fn custom<T: fmt::Display>(msg: T) -> Self {
+		Self {
+			message: msg.to_string(),
+			path: KvonPath::root(),
+		}
+	}
+}
+
+impl serde::de::Error for SerdeError {
+	fn custom<T: fmt::Display>(msg: T) -> Self {
+		Self {
+			message: msg.to_string(),
+			path: KvonPath::root(),
+		}
+	}
+}
+
+/// Errors produced by [from_str_path_err].
+#[derive(Debug)]
+pub enum FromStrError {
+	Parser(ParserError),
+	Deserialize {
+		error: SerdeError,
+		/// Where the value at the error's path starts, or its closest parent
+		/// that appears in the source.
+		span: Option<Span>,
+	},
+}
+
+impl fmt::Display for FromStrError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Parser(e) => write!(f, "{e}"),
+			Self::Deserialize {
+				error,
+				span: Some(span),
+			} => write!(f, "{}:{}: {error}", span.line_number, span.column_number),
+			Self::Deserialize { error, span: None } => write!(f, "{error}"),
+		}
+	}
+}
+
+impl std::error::Error for FromStrError {}
+
+impl From<ParserError> for FromStrError {
+	fn from(e: ParserError) -> Self {
+		Self::Parser(e)
+	}
+}
+
+/// Parses a string and deserializes it into any deserializable type. On
+/// failure, the error includes the path of the offending value, e.g.
+/// `servers[2].port`, and where that value starts in the source.
+pub fn from_str_path_err<T: serde::de::DeserializeOwned>(s: &str) -> Result<T, FromStrError> {
+	let mut parser = Parser::new().with_spans();
+	for line in s.lines() {
+		parser.next_line(line)?;
+	}
+	let (value, spans) = parser.finish_with_spans()?;
+
+	from_value(value).map_err(|error| {
+		let span = (0..=error.path.len())
+			.rev()
+			.find_map(|len| spans.get(&error.path[..len]))
+			.copied();
+		FromStrError::Deserialize { error, span }
+	})
+}
+
+/// Converts any serializable type into a [Value].
+///
+/// Enums are externally tagged unless configured otherwise with serde's
+/// attributes: unit variants become strings, e.g. `'Red'`, and other variants
+/// objects with the variant's name as their only key, e.g. `Rgb: [255 0 0]`.
+/// Internally tagged (`#[serde(tag = "type")]`) and untagged
+/// (`#[serde(untagged)]`) enums are supported as well.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, SerdeError> {
+	value.serialize(ValueSerializer)
+}
+
+/// Converts a [Value] into any deserializable type. Enums are represented
+/// as described in [to_value].
+pub fn from_value<T: serde::de::DeserializeOwned>(value: Value) -> Result<T, SerdeError> {
+	T::deserialize(value)
+}
+
+/// Serializes into [Value]s.
+struct ValueSerializer;
+
+impl Serializer for ValueSerializer {
+	type Ok = Value;
+	type Error = SerdeError;
+	type SerializeSeq = SerializeArray;
+	type SerializeTuple = SerializeArray;
+	type SerializeTupleStruct = SerializeArray;
+	type SerializeTupleVariant = SerializeVariant<SerializeArray>;
+	type SerializeMap = SerializeObject;
+	type SerializeStruct = SerializeObject;
+	type SerializeStructVariant = SerializeVariant<SerializeObject>;
+
+	fn serialize_bool(self, v: bool) -> Result<Value, SerdeError> {
+		Ok(Value::from(v))
+	}
+
+	fn serialize_i8(self, v: i8) -> Result<Value, SerdeError> {
+		Ok(Value::from(v as f32))
+	}
+
+	fn serialize_i16(self, v: i16) -> Result<Value, SerdeError> {
+		Ok(Value::from(v as f32))
+	}
+
+	fn serialize_i32(self, v: i32) -> Result<Value, SerdeError> {
+		Ok(Value::from(v as f32))
+	}
+
+	fn serialize_i64(self, v: i64) -> Result<Value, SerdeError> {
+		Ok(Value::from(v as f32))
+	}
+
+	fn serialize_u8(self, v: u8) -> Result<Value, SerdeError> {
+		Ok(Value::from(v as f32))
+	}
+
+	fn serialize_u16(self, v: u16) -> Result<Value, SerdeError> {
+		Ok(Value::from(v as f32))
+	}
+
+	fn serialize_u32(self, v: u32) -> Result<Value, SerdeError> {
+		Ok(Value::from(v as f32))
+	}
+
+	fn serialize_u64(self, v: u64) -> Result<Value, SerdeError> {
+		Ok(Value::from(v as f32))
+	}
+
+	fn serialize_f32(self, v: f32) -> Result<Value, SerdeError> {
+		Ok(Value::from(v))
+	}
+
+	fn serialize_f64(self, v: f64) -> Result<Value, SerdeError> {
+		Ok(Value::from(v as f32))
+	}
+
+	fn serialize_char(self, v: char) -> Result<Value, SerdeError> {
+		Ok(Value::from(v))
+	}
+
+	fn serialize_str(self, v: &str) -> Result<Value, SerdeError> {
+		Ok(Value::from(v))
+	}
+
+	fn serialize_bytes(self, v: &[u8]) -> Result<Value, SerdeError> {
+		Ok(Value::Array(
+			v.iter().map(|b| Value::from(*b as f32)).collect(),
+		))
+	}
+
+	fn serialize_none(self) -> Result<Value, SerdeError> {
+		Ok(Value::null())
+	}
+
+	fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, SerdeError> {
+		value.serialize(self)
+	}
+
+	fn serialize_unit(self) -> Result<Value, SerdeError> {
+		Ok(Value::null())
+	}
+
+	fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, SerdeError> {
+		Ok(Value::null())
+	}
+
+	fn serialize_unit_variant(
+		self,
+		_name: &'static str,
+		_index: u32,
+		variant: &'static str,
+	) -> Result<Value, SerdeError> {
+		Ok(Value::from(variant))
+	}
+
+	fn serialize_newtype_struct<T: Serialize + ?Sized>(
+		self,
+		_name: &'static str,
+		value: &T,
+	) -> Result<Value, SerdeError> {
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_variant<T: Serialize + ?Sized>(
+		self,
+		_name: &'static str,
+		_index: u32,
+		variant: &'static str,
+		value: &T,
+	) -> Result<Value, SerdeError> {
+		Ok(Value::key_value_pair(variant, to_value(value)?))
+	}
+
+	fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray, SerdeError> {
+		Ok(SerializeArray(Vec::with_capacity(len.unwrap_or(0))))
+	}
+
+	fn serialize_tuple(self, len: usize) -> Result<SerializeArray, SerdeError> {
+		self.serialize_seq(Some(len))
+	}
+
+	fn serialize_tuple_struct(
+		self,
+		_name: &'static str,
+		len: usize,
+	) -> Result<SerializeArray, SerdeError> {
+		self.serialize_seq(Some(len))
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		_name: &'static str,
+		_index: u32,
+		variant: &'static str,
+		len: usize,
+	) -> Result<SerializeVariant<SerializeArray>, SerdeError> {
+		Ok(SerializeVariant {
+			variant,
+			content: self.serialize_seq(Some(len))?,
+		})
+	}
+
+	fn serialize_map(self, len: Option<usize>) -> Result<SerializeObject, SerdeError> {
+		Ok(SerializeObject {
+			values: HashMap::with_capacity(len.unwrap_or(0)),
+			pending_key: None,
+		})
+	}
+
+	fn serialize_struct(
+		self,
+		_name: &'static str,
+		len: usize,
+	) -> Result<SerializeObject, SerdeError> {
+		self.serialize_map(Some(len))
+	}
+
+	fn serialize_struct_variant(
+		self,
+		_name: &'static str,
+		_index: u32,
+		variant: &'static str,
+		len: usize,
+	) -> Result<SerializeVariant<SerializeObject>, SerdeError> {
+		Ok(SerializeVariant {
+			variant,
+			content: self.serialize_map(Some(len))?,
+		})
+	}
+}
+
+struct SerializeArray(Vec<Value>);
+
+impl SerializeSeq for SerializeArray {
+	type Ok = Value;
+	type Error = SerdeError;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+		self.0.push(to_value(value)?);
+		Ok(())
+	}
+
+	fn end(self) -> Result<Value, SerdeError> {
+		Ok(Value::Array(self.0))
+	}
+}
+
+impl serde::ser::SerializeTuple for SerializeArray {
+	type Ok = Value;
+	type Error = SerdeError;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+		SerializeSeq::serialize_element(self, value)
+	}
+
+	fn end(self) -> Result<Value, SerdeError> {
+		SerializeSeq::end(self)
+	}
+}
+
+impl serde::ser::SerializeTupleStruct for SerializeArray {
+	type Ok = Value;
+	type Error = SerdeError;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+		SerializeSeq::serialize_element(self, value)
+	}
+
+	fn end(self) -> Result<Value, SerdeError> {
+		SerializeSeq::end(self)
+	}
+}
+
+struct SerializeObject {
+	values: HashMap<String, Value>,
+	pending_key: Option<String>,
+}
+
+impl SerializeMap for SerializeObject {
+	type Ok = Value;
+	type Error = SerdeError;
+
+	fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+		// keys have to be strings, but numbers and booleans are converted
+		let key = match to_value(key)? {
+			Value::Primitive(PrimitiveValue::String(s)) => s,
+			Value::Primitive(p @ (PrimitiveValue::Number(_) | PrimitiveValue::Boolean(_))) => {
+				encode_literal(&p)
+			}
+			_ => return Err(serde::ser::Error::custom("object keys must be strings")),
+		};
+		self.pending_key = Some(key);
+		Ok(())
+	}
+
+	fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+		let key = self
+			.pending_key
+			.take()
+			.ok_or_else(|| serde::ser::Error::custom("value without a key"))?;
+		self.values.insert(key, to_value(value)?);
+		Ok(())
+	}
+
+	fn end(self) -> Result<Value, SerdeError> {
+		Ok(Value::Object(self.values))
+	}
+}
+
+impl serde::ser::SerializeStruct for SerializeObject {
+	type Ok = Value;
+	type Error = SerdeError;
+
+	fn serialize_field<T: Serialize + ?Sized>(
+		&mut self,
+		key: &'static str,
+		value: &T,
+	) -> Result<(), SerdeError> {
+		self.values.insert(key.to_string(), to_value(value)?);
+		Ok(())
+	}
+
+	fn end(self) -> Result<Value, SerdeError> {
+		SerializeMap::end(self)
+	}
+}
+
+/// Wraps the content of a tuple or struct variant into `variant: content`.
+struct SerializeVariant<S> {
+	variant: &'static str,
+	content: S,
+}
+
+impl serde::ser::SerializeTupleVariant for SerializeVariant<SerializeArray> {
+	type Ok = Value;
+	type Error = SerdeError;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+		SerializeSeq::serialize_element(&mut self.content, value)
+	}
+
+	fn end(self) -> Result<Value, SerdeError> {
+		Ok(Value::key_value_pair(
+			self.variant,
+			SerializeSeq::end(self.content)?,
+		))
+	}
+}
+
+impl serde::ser::SerializeStructVariant for SerializeVariant<SerializeObject> {
+	type Ok = Value;
+	type Error = SerdeError;
+
+	fn serialize_field<T: Serialize + ?Sized>(
+		&mut self,
+		key: &'static str,
+		value: &T,
+	) -> Result<(), SerdeError> {
+		serde::ser::SerializeStruct::serialize_field(&mut self.content, key, value)
+	}
+
+	fn end(self) -> Result<Value, SerdeError> {
+		Ok(Value::key_value_pair(
+			self.variant,
+			SerializeMap::end(self.content)?,
+		))
+	}
+}
+
+impl<'de> IntoDeserializer<'de, SerdeError> for Value {
+	type Deserializer = Self;
+
+	fn into_deserializer(self) -> Self {
+		self
+	}
+}
+
+impl<'de> IntoDeserializer<'de, SerdeError> for &'de Value {
+	type Deserializer = Self;
+
+	fn into_deserializer(self) -> Self {
+		self
+	}
+}
+
+/// Passes integral numbers as integers, as the visitors of integer types don't
+/// accept floats.
+fn visit_number<'de, V: Visitor<'de>>(n: f32, visitor: V) -> Result<V::Value, SerdeError> {
+	if n.fract() == 0.0 && n >= 0.0 && n < u64::MAX as f32 {
+		visitor.visit_u64(n as u64)
+	} else if n.fract() == 0.0 && n >= i64::MIN as f32 {
+		visitor.visit_i64(n as i64)
+	} else {
+		visitor.visit_f32(n)
+	}
+}
+
+fn visit_array<'de, D, I, V>(items: I, visitor: V) -> Result<V::Value, SerdeError>
+where
+	D: Deserializer<'de, Error = SerdeError>,
+	I: ExactSizeIterator<Item = D>,
+	V: Visitor<'de>,
+{
+	let len = items.len();
+	let mut access = ArrayAccess { items, index: 0 };
+	let value = visitor.visit_seq(&mut access)?;
+	if access.items.len() > 0 {
+		return Err(serde::de::Error::invalid_length(len, &"fewer elements"));
+	}
+	Ok(value)
+}
+
+fn visit_object<'de, K, D, I, V>(entries: I, visitor: V) -> Result<V::Value, SerdeError>
+where
+	K: ObjectKey<'de>,
+	D: Deserializer<'de, Error = SerdeError>,
+	I: ExactSizeIterator<Item = (K, D)>,
+	V: Visitor<'de>,
+{
+	let len = entries.len();
+	let mut access = ObjectAccess {
+		entries,
+		key: None,
+		value: None,
+	};
+	let value = visitor.visit_map(&mut access)?;
+	if access.entries.len() > 0 {
+		return Err(serde::de::Error::invalid_length(len, &"fewer entries"));
+	}
+	Ok(value)
+}
+
+/// Visits array items, adding their index to the path of errors.
+struct ArrayAccess<I> {
+	items: I,
+	index: usize,
+}
+
+impl<'de, D, I> SeqAccess<'de> for ArrayAccess<I>
+where
+	D: Deserializer<'de, Error = SerdeError>,
+	I: ExactSizeIterator<Item = D>,
+{
+	type Error = SerdeError;
+
+	fn next_element_seed<T: DeserializeSeed<'de>>(
+		&mut self,
+		seed: T,
+	) -> Result<Option<T::Value>, SerdeError> {
+		let Some(item) = self.items.next() else {
+			return Ok(None);
+		};
+		let index = self.index;
+		self.index += 1;
+		seed.deserialize(item)
+			.map(Some)
+			.map_err(|e| e.within(PathSegment::Index(index)))
+	}
+
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.items.len())
+	}
+}
+
+/// Keys of owned and borrowed objects.
+trait ObjectKey<'de> {
+	fn as_str(&self) -> &str;
+
+	fn deserialize<T: DeserializeSeed<'de>>(&self, seed: T) -> Result<T::Value, SerdeError>;
+}
+
+impl<'de> ObjectKey<'de> for String {
+	fn as_str(&self) -> &str {
+		self
+	}
+
+	fn deserialize<T: DeserializeSeed<'de>>(&self, seed: T) -> Result<T::Value, SerdeError> {
+		seed.deserialize(StrDeserializer::new(self))
+	}
+}
+
+impl<'de> ObjectKey<'de> for &'de String {
+	fn as_str(&self) -> &str {
+		self
+	}
+
+	fn deserialize<T: DeserializeSeed<'de>>(&self, seed: T) -> Result<T::Value, SerdeError> {
+		seed.deserialize(BorrowedStrDeserializer::new(self))
+	}
+}
+
+/// Visits object entries, adding their key to the path of errors.
+struct ObjectAccess<I, K, D> {
+	entries: I,
+	key: Option<K>,
+	value: Option<D>,
+}
+
+impl<'de, K, D, I> MapAccess<'de> for ObjectAccess<I, K, D>
+where
+	K: ObjectKey<'de>,
+	D: Deserializer<'de, Error = SerdeError>,
+	I: ExactSizeIterator<Item = (K, D)>,
+{
+	type Error = SerdeError;
+
+	fn next_key_seed<T: DeserializeSeed<'de>>(
+		&mut self,
+		seed: T,
+	) -> Result<Option<T::Value>, SerdeError> {
+		let Some((key, value)) = self.entries.next() else {
+			return Ok(None);
+		};
+		let result = key.deserialize(seed)?;
+		self.key = Some(key);
+		self.value = Some(value);
+		Ok(Some(result))
+	}
+
+	fn next_value_seed<T: DeserializeSeed<'de>>(
+		&mut self,
+		seed: T,
+	) -> Result<T::Value, SerdeError> {
+		let (Some(key), Some(value)) = (self.key.take(), self.value.take()) else {
+			return Err(serde::de::Error::custom("value requested before its key"));
+		};
+		seed.deserialize(value)
+			.map_err(|e| e.within(PathSegment::Key(key.as_str().to_string())))
+	}
+
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.entries.len())
+	}
+}
+
+fn invalid_enum() -> SerdeError {
+	serde::de::Error::custom(
+		"expected an enum variant, either a string or an object with a single key",
+	)
+}
+
+impl<'de> Deserializer<'de> for Value {
+	type Error = SerdeError;
+
+	fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+		match self {
+			Value::Primitive(p) => match p {
+				PrimitiveValue::Number(n) => visit_number(n, visitor),
+				PrimitiveValue::String(s) => visitor.visit_string(s),
+				PrimitiveValue::Boolean(b) => visitor.visit_bool(b),
+				PrimitiveValue::Null => visitor.visit_unit(),
+				p => visitor.visit_string(encode_literal(&p)),
+			},
+			Value::Array(arr) => visit_array(arr.into_iter(), visitor),
+			Value::Object(obj) => visit_object(obj.into_iter(), visitor),
+			Value::Raw(raw) => raw
+				.parse()
+				.map_err(serde::de::Error::custom)?
+				.deserialize_any(visitor),
+		}
+	}
+
+	fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+		match self {
+			Value::Primitive(PrimitiveValue::Null) => visitor.visit_none(),
+			value => visitor.visit_some(value),
+		}
+	}
+
+	fn deserialize_newtype_struct<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		visitor: V,
+	) -> Result<V::Value, SerdeError> {
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_enum<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		_variants: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, SerdeError> {
+		let (variant, content) = match self {
+			Value::Primitive(PrimitiveValue::String(variant)) => (variant, None),
+			Value::Object(obj) if obj.len() == 1 => {
+				let (variant, content) = obj.into_iter().next().unwrap();
+				(variant, Some(content))
+			}
+			_ => return Err(invalid_enum()),
+		};
+		visitor.visit_enum(EnumDeserializer { variant, content })
+	}
+
+	serde::forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+		identifier ignored_any
+	}
+}
+
+/// Deserializes from a borrowed value, which allows borrowing strings, e.g.
+/// into `&str` fields.
+impl<'de> Deserializer<'de> for &'de Value {
+	type Error = SerdeError;
+
+	fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+		match self {
+			Value::Primitive(p) => match p {
+				PrimitiveValue::Number(n) => visit_number(*n, visitor),
+				PrimitiveValue::String(s) => visitor.visit_borrowed_str(s),
+				PrimitiveValue::Boolean(b) => visitor.visit_bool(*b),
+				PrimitiveValue::Null => visitor.visit_unit(),
+				p => visitor.visit_string(encode_literal(p)),
+			},
+			Value::Array(arr) => visit_array(arr.iter(), visitor),
+			Value::Object(obj) => visit_object(obj.iter(), visitor),
+			// raw values have to be parsed first, so nothing can be borrowed
+			Value::Raw(raw) => raw
+				.parse()
+				.map_err(serde::de::Error::custom)?
+				.deserialize_any(visitor),
+		}
+	}
+
+	fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+		match self {
+			Value::Primitive(PrimitiveValue::Null) => visitor.visit_none(),
+			value => visitor.visit_some(value),
+		}
+	}
+
+	fn deserialize_newtype_struct<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		visitor: V,
+	) -> Result<V::Value, SerdeError> {
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_enum<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		_variants: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, SerdeError> {
+		let (variant, content) = match self {
+			Value::Primitive(PrimitiveValue::String(variant)) => (variant, None),
+			Value::Object(obj) if obj.len() == 1 => {
+				let (variant, content) = obj.iter().next().unwrap();
+				(variant, Some(content))
+			}
+			_ => return Err(invalid_enum()),
+		};
+		visitor.visit_enum(EnumDeserializer {
+			variant: BorrowedStrDeserializer::new(variant),
+			content,
+		})
+	}
+
+	serde::forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+		identifier ignored_any
+	}
+}
+
+/// An externally tagged enum variant and its content, which unit variants
+/// don't have.
+struct EnumDeserializer<S, D> {
+	variant: S,
+	content: Option<D>,
+}
+
+impl<'de, S, D> EnumAccess<'de> for EnumDeserializer<S, D>
+where
+	S: IntoDeserializer<'de, SerdeError>,
+	D: Deserializer<'de, Error = SerdeError>,
+{
+	type Error = SerdeError;
+	type Variant = VariantDeserializer<D>;
+
+	fn variant_seed<V: DeserializeSeed<'de>>(
+		self,
+		seed: V,
+	) -> Result<(V::Value, VariantDeserializer<D>), SerdeError> {
+		let variant = seed.deserialize(self.variant.into_deserializer())?;
+		Ok((
+			variant,
+			VariantDeserializer {
+				content: self.content,
+			},
+		))
+	}
+}
+
+struct VariantDeserializer<D> {
+	content: Option<D>,
+}
+
+impl<D> VariantDeserializer<D> {
+	fn content(self) -> Result<D, SerdeError> {
+		self.content
+			.ok_or_else(|| serde::de::Error::custom("expected the content of the enum variant"))
+	}
+}
+
+impl<'de, D: Deserializer<'de, Error = SerdeError>> VariantAccess<'de> for VariantDeserializer<D> {
+	type Error = SerdeError;
+
+	fn unit_variant(self) -> Result<(), SerdeError> {
+		match self.content {
+			// unit variants may only be followed by null
+			Some(content) => <()>::deserialize(content),
+			None => Ok(()),
+		}
+	}
+
+	fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+		self,
+		seed: T,
+	) -> Result<T::Value, SerdeError> {
+		seed.deserialize(self.content()?)
+	}
+
+	fn tuple_variant<V: Visitor<'de>>(
+		self,
+		_len: usize,
+		visitor: V,
+	) -> Result<V::Value, SerdeError> {
+		self.content()?.deserialize_seq(visitor)
+	}
+
+	fn struct_variant<V: Visitor<'de>>(
+		self,
+		_fields: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, SerdeError> {
+		self.content()?.deserialize_map(visitor)
+	}
+}
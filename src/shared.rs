@@ -0,0 +1,304 @@
+//! A [Value] representation that is cheap to clone and share between
+//! threads. Objects, arrays and strings are reference counted, so cloning a
+//! [SharedValue] never copies the document. Mutation is copy-on-write: only
+//! the parts of the tree that are modified while shared are copied.
+//!
+//! Documents repeating the same strings many times, e.g. enum-like fields of
+//! records, can share a single allocation per distinct string by converting
+//! them with a [StringInterner]:
+//!
+//! ```rust
+//! use kvon_rs::{shared::{parse_string_interned, StringInterner}, ParserOptions};
+//!
+//! let mut interner = StringInterner::new();
+//! let source = "events:--\n  -\n    kind: 'click'\n  -\n    kind: 'click'\n";
+//! let events = parse_string_interned(source, &ParserOptions::new(), &mut interner).unwrap();
+//! let kinds: Vec<_> = events.get("events").unwrap().get_vector().unwrap().iter()
+//!     .map(|event| event.get("kind").unwrap())
+//!     .collect();
+//! assert!(kinds[0].ptr_eq(kinds[1]));
+//! assert_eq!(interner.len(), 1);
+//! ```
+//!
+//! See `examples/interned_strings.rs` for how much memory that saves.
+
+use std::{
+	collections::{HashMap, HashSet},
+	sync::Arc,
+};
+
+use crate::{
+	parser::Tree,
+	value::{GetterResult, PrimitiveValue, RawValue, Value},
+	Parser, ParserOptions, ParserResult,
+};
+
+/// A [Value] whose objects, arrays and strings are behind an [Arc].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SharedValue {
+	/// A primitive other than a string.
+	Primitive(PrimitiveValue),
+	String(Arc<str>),
+	Object(Arc<HashMap<String, SharedValue>>),
+	Array(Arc<Vec<SharedValue>>),
+	Raw(Arc<RawValue>),
+}
+
+#[allow(clippy::result_unit_err)]
+impl SharedValue {
+	pub fn is_object(&self) -> bool {
+		matches!(self, Self::Object(_))
+	}
+
+	pub fn is_array(&self) -> bool {
+		matches!(self, Self::Array(_))
+	}
+
+	pub fn get_objects(&self) -> GetterResult<&HashMap<String, SharedValue>> {
+		match self {
+			Self::Object(obj) => Ok(obj),
+			_ => Err(()),
+		}
+	}
+
+	pub fn get_vector(&self) -> GetterResult<&Vec<SharedValue>> {
+		match self {
+			Self::Array(arr) => Ok(arr),
+			_ => Err(()),
+		}
+	}
+
+	/// Returns the primitive, which is only possible for non string
+	/// primitives. See [SharedValue::get_string] for strings.
+	pub fn get_primitive(&self) -> GetterResult<&PrimitiveValue> {
+		match self {
+			Self::Primitive(primitive) => Ok(primitive),
+			_ => Err(()),
+		}
+	}
+
+	pub fn get_string(&self) -> GetterResult<&str> {
+		match self {
+			Self::String(s) => Ok(s),
+			_ => Err(()),
+		}
+	}
+
+	pub fn get_number(&self) -> GetterResult<f32> {
+		self.get_primitive()?.get_number()
+	}
+
+	pub fn get_boolean(&self) -> GetterResult<bool> {
+		self.get_primitive()?.get_boolean()
+	}
+
+	/// Returns the value of a key of an object.
+	pub fn get(&self, key: &str) -> Option<&SharedValue> {
+		self.get_objects().ok()?.get(key)
+	}
+
+	/// Returns an element of an array.
+	pub fn get_index(&self, index: usize) -> Option<&SharedValue> {
+		self.get_vector().ok()?.get(index)
+	}
+
+	/// Returns the entries of an object for modification, copying them first
+	/// if they are shared with other values.
+	pub fn make_object_mut(&mut self) -> GetterResult<&mut HashMap<String, SharedValue>> {
+		match self {
+			Self::Object(obj) => Ok(Arc::make_mut(obj)),
+			_ => Err(()),
+		}
+	}
+
+	/// Returns the elements of an array for modification, copying them first
+	/// if they are shared with other values.
+	pub fn make_array_mut(&mut self) -> GetterResult<&mut Vec<SharedValue>> {
+		match self {
+			Self::Array(arr) => Ok(Arc::make_mut(arr)),
+			_ => Err(()),
+		}
+	}
+
+	/// Returns true if both values point to the same allocation, i.e. one is
+	/// an unmodified clone of the other. Always false for non string
+	/// primitives.
+	pub fn ptr_eq(&self, other: &SharedValue) -> bool {
+		match (self, other) {
+			(Self::String(a), Self::String(b)) => Arc::ptr_eq(a, b),
+			(Self::Object(a), Self::Object(b)) => Arc::ptr_eq(a, b),
+			(Self::Array(a), Self::Array(b)) => Arc::ptr_eq(a, b),
+			(Self::Raw(a), Self::Raw(b)) => Arc::ptr_eq(a, b),
+			_ => false,
+		}
+	}
+}
+
+impl SharedValue {
+	/// Converts a [Value] like [SharedValue::from], but equal strings share
+	/// the allocation kept by `interner`. Keys aren't shared.
+	pub fn interned(value: Value, interner: &mut StringInterner) -> Self {
+		match value {
+			Value::Primitive(PrimitiveValue::String(s)) => Self::String(interner.intern(&s)),
+			Value::Primitive(p) => Self::Primitive(p),
+			Value::Object(obj) => Self::Object(Arc::new(
+				obj.into_iter()
+					.map(|(k, v)| (k, Self::interned(v, interner)))
+					.collect(),
+			)),
+			Value::Array(arr) => Self::Array(Arc::new(
+				arr.into_iter()
+					.map(|v| Self::interned(v, interner))
+					.collect(),
+			)),
+			Value::Raw(raw) => Self::Raw(Arc::new(raw)),
+		}
+	}
+}
+
+/// The distinct strings of values converted by [SharedValue::interned]. An
+/// interner can be reused for many documents, so that they share their
+/// strings as well.
+#[derive(Debug, Clone, Default)]
+pub struct StringInterner {
+	strings: HashSet<Arc<str>>,
+}
+
+impl StringInterner {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Returns the shared allocation of a string, adding it if it is new.
+	pub fn intern(&mut self, s: &str) -> Arc<str> {
+		if let Some(shared) = self.strings.get(s) {
+			return Arc::clone(shared);
+		}
+		let shared: Arc<str> = Arc::from(s);
+		self.strings.insert(Arc::clone(&shared));
+		shared
+	}
+
+	/// The number of distinct strings.
+	pub fn len(&self) -> usize {
+		self.strings.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.strings.is_empty()
+	}
+}
+
+/// Parses a string into a [SharedValue] whose equal strings share their
+/// allocation, see [SharedValue::interned]. Strings are interned as they are
+/// parsed, so the document is never held as a [Value].
+pub fn parse_string_interned(
+	s: &str,
+	options: &ParserOptions,
+	interner: &mut StringInterner,
+) -> ParserResult<SharedValue> {
+	let mut parser = Parser::with_builder(options.clone(), std::mem::take(interner));
+	let result = parser.parse_lines(s);
+	let result = result.and_then(|_| parser.finish_tree());
+	*interner = std::mem::take(parser.builder_mut());
+	result
+}
+
+impl Tree for SharedValue {
+	type Builder = StringInterner;
+	type Map = HashMap<String, SharedValue>;
+	type List = Vec<SharedValue>;
+
+	fn primitive(interner: &mut StringInterner, primitive: PrimitiveValue) -> Self {
+		match primitive {
+			PrimitiveValue::String(s) => Self::String(interner.intern(&s)),
+			p => Self::Primitive(p),
+		}
+	}
+
+	fn value(interner: &mut StringInterner, value: Value) -> Self {
+		Self::interned(value, interner)
+	}
+
+	fn raw(_: &mut StringInterner, raw: RawValue) -> Self {
+		Self::Raw(Arc::new(raw))
+	}
+
+	fn new_object(_: &StringInterner, capacity: usize) -> Self::Map {
+		HashMap::with_capacity(capacity)
+	}
+
+	fn new_array(_: &StringInterner) -> Self::List {
+		Vec::new()
+	}
+
+	fn object(entries: Self::Map) -> Self {
+		Self::Object(Arc::new(entries))
+	}
+
+	fn array(items: Self::List) -> Self {
+		Self::Array(Arc::new(items))
+	}
+
+	fn into_object(self) -> Option<Self::Map> {
+		match self {
+			Self::Object(obj) => Some(Arc::unwrap_or_clone(obj)),
+			_ => None,
+		}
+	}
+
+	fn into_array(self) -> Option<Self::List> {
+		match self {
+			Self::Array(arr) => Some(Arc::unwrap_or_clone(arr)),
+			_ => None,
+		}
+	}
+
+	fn as_object(&self) -> Option<&Self::Map> {
+		self.get_objects().ok()
+	}
+
+	fn object_mut(&mut self) -> Option<&mut Self::Map> {
+		self.make_object_mut().ok()
+	}
+}
+
+impl From<Value> for SharedValue {
+	fn from(value: Value) -> Self {
+		match value {
+			Value::Primitive(PrimitiveValue::String(s)) => Self::String(s.into()),
+			Value::Primitive(p) => Self::Primitive(p),
+			Value::Object(obj) => Self::Object(Arc::new(
+				obj.into_iter().map(|(k, v)| (k, v.into())).collect(),
+			)),
+			Value::Array(arr) => Self::Array(Arc::new(arr.into_iter().map(Into::into).collect())),
+			Value::Raw(raw) => Self::Raw(Arc::new(raw)),
+		}
+	}
+}
+
+impl From<SharedValue> for Value {
+	/// Converts back into a [Value], moving out of the parts of the tree
+	/// that aren't shared and copying the rest.
+	fn from(value: SharedValue) -> Self {
+		match value {
+			SharedValue::Primitive(p) => Value::Primitive(p),
+			SharedValue::String(s) => Value::from(s.as_ref()),
+			SharedValue::Object(obj) => Value::Object(
+				Arc::try_unwrap(obj)
+					.unwrap_or_else(|obj| (*obj).clone())
+					.into_iter()
+					.map(|(k, v)| (k, v.into()))
+					.collect(),
+			),
+			SharedValue::Array(arr) => Value::Array(
+				Arc::try_unwrap(arr)
+					.unwrap_or_else(|arr| (*arr).clone())
+					.into_iter()
+					.map(Into::into)
+					.collect(),
+			),
+			SharedValue::Raw(raw) => Value::Raw(Arc::unwrap_or_clone(raw)),
+		}
+	}
+}
@@ -0,0 +1,152 @@
+use std::{
+	any::Any,
+	collections::HashMap,
+	fmt::{self, Debug},
+	panic::{RefUnwindSafe, UnwindSafe},
+	sync::Arc,
+};
+
+use crate::{
+	encoder::{quote_inline, QuoteStyle},
+	value::PrimitiveValue,
+};
+
+/// A scalar type defined by the application, stored in
+/// [PrimitiveValue::Custom]. Custom scalars are written as tagged literals,
+/// e.g. `timeout: !duration '5s'`.
+///
+/// Scalars must be unwind safe, so that [crate::value::Value]s can be used
+/// across `catch_unwind`.
+pub trait CustomScalar: Debug + Send + Sync + UnwindSafe + RefUnwindSafe {
+	/// The tag name, without the leading `!`.
+	fn tag(&self) -> &str;
+
+	/// The literal written after the tag when encoding. Two custom scalars
+	/// are considered equal if their tags and encoded literals are equal.
+	///
+	/// Tagged literals are written on a single line, so the literal can't be
+	/// another custom scalar, an empty string or a string with line breaks.
+	/// [CustomValue::new] rejects scalars encoding into one of these.
+	fn encode(&self) -> PrimitiveValue;
+
+	fn clone_box(&self) -> Box<dyn CustomScalar>;
+
+	fn as_any(&self) -> &dyn Any;
+}
+
+impl Clone for Box<dyn CustomScalar> {
+	fn clone(&self) -> Self {
+		self.clone_box()
+	}
+}
+
+/// A [CustomScalar] together with its literal, which is checked to be
+/// writable as a tagged literal when the value is created.
+#[derive(Debug, Clone)]
+pub struct CustomValue {
+	scalar: Box<dyn CustomScalar>,
+	literal: Box<PrimitiveValue>,
+}
+
+impl CustomValue {
+	/// Encodes the scalar's literal, or returns an error if the scalar can't
+	/// be written as a tagged literal.
+	pub fn new(scalar: impl CustomScalar + 'static) -> Result<Self, InvalidCustomScalar> {
+		let error = |message| InvalidCustomScalar {
+			tag: scalar.tag().to_string(),
+			message,
+		};
+		let tag = scalar.tag();
+		if tag.is_empty() || tag.contains(|c: char| c.is_whitespace() || matches!(c, '#' | ']')) {
+			return Err(error(
+				"the tag must be non-empty and can't contain whitespace, '#' or ']'",
+			));
+		}
+		let literal = scalar.encode();
+		match &literal {
+			PrimitiveValue::Custom(_) => {
+				return Err(error("the literal can't be another custom scalar"));
+			}
+			PrimitiveValue::String(s) if quote_inline(s, QuoteStyle::default()).is_none() => {
+				return Err(error(
+					"the literal must be a non-empty string without line breaks",
+				));
+			}
+			_ => {}
+		}
+		Ok(Self {
+			scalar: Box::new(scalar),
+			literal: Box::new(literal),
+		})
+	}
+
+	pub fn tag(&self) -> &str {
+		self.scalar.tag()
+	}
+
+	/// The literal written after the tag, as encoded when the value was
+	/// created.
+	pub fn literal(&self) -> &PrimitiveValue {
+		&self.literal
+	}
+
+	pub fn scalar(&self) -> &dyn CustomScalar {
+		self.scalar.as_ref()
+	}
+}
+
+/// Why a [CustomScalar] can't be stored in a [CustomValue].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidCustomScalar {
+	pub tag: String,
+	pub message: &'static str,
+}
+
+impl fmt::Display for InvalidCustomScalar {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "invalid custom scalar '!{}': {}", self.tag, self.message)
+	}
+}
+
+impl std::error::Error for InvalidCustomScalar {}
+
+/// A hook converting the literal following a tag into a primitive. Returns a
+/// message describing the problem if the literal is invalid.
+pub type TagParser = dyn Fn(PrimitiveValue) -> Result<PrimitiveValue, String> + Send + Sync;
+
+/// The set of tags the parser recognizes.
+#[derive(Clone, Default)]
+pub struct TagRegistry {
+	parsers: HashMap<String, Arc<TagParser>>,
+}
+
+impl TagRegistry {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Registers a hook for `!tag` literals. Registering the same tag again
+	/// replaces the previous hook.
+	pub fn register(
+		&mut self,
+		tag: impl ToString,
+		parser: impl Fn(PrimitiveValue) -> Result<PrimitiveValue, String> + Send + Sync + 'static,
+	) -> &mut Self {
+		self.parsers.insert(tag.to_string(), Arc::new(parser));
+		self
+	}
+
+	pub fn contains(&self, tag: &str) -> bool {
+		self.parsers.contains_key(tag)
+	}
+
+	pub fn get(&self, tag: &str) -> Option<&TagParser> {
+		self.parsers.get(tag).map(|parser| parser.as_ref())
+	}
+}
+
+impl Debug for TagRegistry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_set().entries(self.parsers.keys()).finish()
+	}
+}
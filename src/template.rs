@@ -0,0 +1,573 @@
+//! Documents with placeholders that are filled in from parameters, e.g. a
+//! single template for the configs of several environments.
+//!
+//! ```rust
+//! use kvon_rs::{object, template::Template};
+//!
+//! let template = Template::parse(
+//!     "
+//! server:
+//!     host: '<<host>>'
+//!     port: !param 'port'
+//!     url: 'http://<<host>>:<<port>>/'
+//! replicas: !param 'scale.replicas'
+//! ",
+//! )
+//! .unwrap();
+//! assert_eq!(template.parameters(), ["host", "port", "scale.replicas"]);
+//!
+//! let params = object! { host: "example.com", port: 8080, scale: { replicas: 3 } };
+//! assert_eq!(
+//!     template.render(&params).unwrap(),
+//!     object! {
+//!         server: { host: "example.com", port: 8080, url: "http://example.com:8080/" },
+//!         replicas: 3,
+//!     }
+//! );
+//! assert_eq!(
+//!     template.render(&object! { host: "example.com" }).unwrap_err().to_string(),
+//!     "replicas: missing parameter 'scale.replicas'"
+//! );
+//! ```
+//!
+//! Parameters are named by their path in the parameters, see [KvonPath].
+//! A `!param 'name'` literal or a string holding nothing but `<<name>>` is
+//! replaced by the parameter, which can be of any type. Placeholders inside
+//! of longer strings are replaced by the parameter written without quotes,
+//! so they only accept primitives. Text between `<<` and `>>` that isn't a
+//! path is kept as it is.
+//!
+//! Keys starting with `!if` or `!for` are directives, which are evaluated
+//! when rendering and don't appear in the output:
+//!
+//! ```rust
+//! use kvon_rs::{object, template::Template};
+//!
+//! let template = Template::parse(
+//!     "
+//! name: 'app'
+//! !if tls:
+//!     port: 443
+//!     cert: '/etc/<<domain>>.pem'
+//! !if not tls:
+//!     port: 80
+//! upstreams:
+//!     !for server in servers:
+//!         url: 'http://<<server.host>>'
+//! ",
+//! )
+//! .unwrap();
+//! assert_eq!(template.parameters(), ["domain", "servers", "tls"]);
+//!
+//! let params = object! { tls: false, servers: [{ host: "a" }, { host: "b" }] };
+//! assert_eq!(
+//!     template.render(&params).unwrap(),
+//!     object! {
+//!         name: "app",
+//!         port: 80,
+//!         upstreams: [{ url: "http://a" }, { url: "http://b" }],
+//!     }
+//! );
+//! ```
+//!
+//! - `!if name:` adds the entries of its object to the surrounding object if
+//!   the parameter is `true`, and `!if not name:` if it is `false` or
+//!   `null`. They override the other entries of the object.
+//! - An object holding only `!for item in name:` renders as an array, with
+//!   its value rendered once for each item of the array parameter. `item`
+//!   names the item in placeholders and directives within the value.
+//! - In arrays, an item holding only a directive is replaced by the
+//!   rendered value, by nothing if the condition doesn't hold, or by one
+//!   item for each item of the array parameter.
+//!
+//! Directives are keys, so they can also be written as quoted keys, e.g.
+//! `'!if tls':`, which [Template::from_value] requires.
+
+use std::{borrow::Cow, collections::HashMap, fmt};
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+use crate::{
+	env::var_value,
+	error::ParserError,
+	parse_string_with_options,
+	path::{KvonPath, PathSegment},
+	tags::CustomScalar,
+	value::{PrimitiveValue, Value},
+	ParserOptions,
+};
+
+/// The tag of parameters, see [Param].
+pub const PARAM_TAG: &str = "param";
+
+/// A `!param 'name'` literal of a template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param(pub KvonPath);
+
+impl CustomScalar for Param {
+	fn tag(&self) -> &str {
+		PARAM_TAG
+	}
+
+	fn encode(&self) -> PrimitiveValue {
+		PrimitiveValue::String(self.0.to_string())
+	}
+
+	fn clone_box(&self) -> Box<dyn CustomScalar> {
+		Box::new(self.clone())
+	}
+
+	fn as_any(&self) -> &dyn std::any::Any {
+		self
+	}
+}
+
+/// Why a template can't be rendered with the given parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+	/// A parameter used at `path` of the template is missing.
+	MissingParameter { path: KvonPath, name: String },
+	/// An object or array parameter is used inside of a longer string.
+	NotInlinable { path: KvonPath, name: String },
+	/// The parameter of an `!if` isn't a boolean or `null`.
+	NotABoolean { path: KvonPath, name: String },
+	/// The parameter of a `!for` isn't an array.
+	NotAnArray { path: KvonPath, name: String },
+	/// A directive is malformed or used where it can't be.
+	InvalidDirective {
+		path: KvonPath,
+		message: &'static str,
+	},
+}
+
+impl fmt::Display for TemplateError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let (Self::MissingParameter { path, .. }
+		| Self::NotInlinable { path, .. }
+		| Self::NotABoolean { path, .. }
+		| Self::NotAnArray { path, .. }
+		| Self::InvalidDirective { path, .. }) = self;
+		if !path.is_empty() {
+			write!(f, "{path}: ")?;
+		}
+		match self {
+			Self::MissingParameter { name, .. } => write!(f, "missing parameter '{name}'"),
+			Self::NotInlinable { name, .. } => {
+				write!(f, "parameter '{name}' can't be written inside of a string")
+			}
+			Self::NotABoolean { name, .. } => write!(f, "parameter '{name}' isn't a boolean"),
+			Self::NotAnArray { name, .. } => write!(f, "parameter '{name}' isn't an array"),
+			Self::InvalidDirective { message, .. } => write!(f, "{message}"),
+		}
+	}
+}
+
+impl std::error::Error for TemplateError {}
+
+/// A document with placeholders, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+	value: Value,
+}
+
+impl Template {
+	/// Parses a template, recognizing `!param` literals and directives.
+	pub fn parse(source: &str) -> Result<Self, ParserError> {
+		Self::parse_with_options(source, &ParserOptions::new())
+	}
+
+	/// Parses a template using the given options, adding the `!param` tag
+	/// and quoting directive keys.
+	pub fn parse_with_options(source: &str, options: &ParserOptions) -> Result<Self, ParserError> {
+		let mut options = options.clone();
+		options.tags.register(PARAM_TAG, |literal| {
+			let name = literal.get_string().map_err(|_| "expected a string")?;
+			let path = name
+				.parse()
+				.map_err(|_| "expected a path such as 'db.host'")?;
+			PrimitiveValue::custom(Param(path)).map_err(|error| error.message.to_string())
+		});
+		let source = quote_directives(source);
+		Ok(Self::from_value(parse_string_with_options(
+			&source, &options,
+		)?))
+	}
+
+	/// Treats a value as a template. Its strings can hold placeholders and
+	/// [Param]s can be added as custom primitives.
+	pub fn from_value(value: Value) -> Self {
+		Self { value }
+	}
+
+	/// The template with its placeholders.
+	pub fn value(&self) -> &Value {
+		&self.value
+	}
+
+	/// The names of the parameters the template uses, sorted. Items of
+	/// `!for` aren't parameters.
+	pub fn parameters(&self) -> Vec<String> {
+		fn add(name: &str, path: &KvonPath, items: &[String], names: &mut Vec<String>) {
+			let item = matches!(path.first(), Some(PathSegment::Key(key)) if items.contains(key));
+			if !item {
+				names.push(name.to_string());
+			}
+		}
+
+		fn collect(value: &Value, items: &mut Vec<String>, names: &mut Vec<String>) {
+			match value {
+				Value::Object(obj) => {
+					for (key, value) in obj {
+						match directive(key) {
+							Some(Ok(Directive::If { name, path, .. })) => {
+								add(name, &path, items, names);
+								collect(value, items, names);
+							}
+							Some(Ok(Directive::For { item, name, path })) => {
+								add(name, &path, items, names);
+								items.push(item.to_string());
+								collect(value, items, names);
+								items.pop();
+							}
+							_ => collect(value, items, names),
+						}
+					}
+				}
+				Value::Array(arr) => arr.iter().for_each(|value| collect(value, items, names)),
+				Value::Primitive(p) => {
+					if let Ok(Param(path)) = p.get_custom::<Param>() {
+						add(&path.to_string(), path, items, names);
+					} else if let PrimitiveValue::String(s) = p {
+						for (_, name, path) in placeholders(s) {
+							add(name, &path, items, names);
+						}
+					}
+				}
+				Value::Raw(_) => {}
+			}
+		}
+
+		let mut names = Vec::new();
+		collect(&self.value, &mut Vec::new(), &mut names);
+		names.sort();
+		names.dedup();
+		names
+	}
+
+	/// Replaces the placeholders with the parameters they name and
+	/// evaluates the directives.
+	pub fn render(&self, params: &Value) -> Result<Value, TemplateError> {
+		let scope = Scope {
+			params,
+			items: Vec::new(),
+		};
+		scope.render(&self.value, &mut KvonPath::root())
+	}
+}
+
+/// A `!if` or `!for` key, see the [module documentation](self).
+enum Directive<'a> {
+	If {
+		negated: bool,
+		name: &'a str,
+		path: KvonPath,
+	},
+	For {
+		item: &'a str,
+		name: &'a str,
+		path: KvonPath,
+	},
+}
+
+/// Parses a directive key. Returns `None` for other keys.
+fn directive(key: &str) -> Option<Result<Directive<'_>, &'static str>> {
+	let (keyword, rest) = key.split_once(' ').unwrap_or((key, ""));
+	let words: Vec<&str> = rest.split_whitespace().collect();
+	Some(match (keyword, words.as_slice()) {
+		("!if", ["not", name] | [name]) => match name.parse() {
+			Ok(path) => Ok(Directive::If {
+				negated: words.len() == 2,
+				name,
+				path,
+			}),
+			Err(_) => Err("expected '!if <parameter>' or '!if not <parameter>'"),
+		},
+		("!if", _) => Err("expected '!if <parameter>' or '!if not <parameter>'"),
+		("!for", [item, "in", name]) => match name.parse() {
+			Ok(path) if !item.contains(['.', '[', ']']) => Ok(Directive::For { item, name, path }),
+			_ => Err("expected '!for <item> in <parameter>'"),
+		},
+		("!for", _) => Err("expected '!for <item> in <parameter>'"),
+		_ => return None,
+	})
+}
+
+/// Quotes the directive keys of a template, which aren't valid unquoted
+/// keys as they contain spaces. The lines of multi-line strings are left as
+/// they are.
+fn quote_directives(source: &str) -> Cow<'_, str> {
+	lazy_static! {
+		static ref DIRECTIVE: Regex =
+			Regex::new(r#"^([ \t]*(?:- *)?)(!(?:if|for) [^:#'"]*[^:#'"\s])[ \t]*:"#).unwrap();
+		// `key: |` or `- |`, starting a multi-line string
+		static ref BLOCK: Regex = Regex::new(r"(?:^[ \t]*-|:)[ \t]*\|[ \t]*(?:#.*)?$").unwrap();
+	}
+
+	if !source.contains("!if ") && !source.contains("!for ") {
+		return Cow::Borrowed(source);
+	}
+
+	let mut quoted = String::with_capacity(source.len());
+	// the indention of the line starting the current multi-line string
+	let mut block_indent = None;
+	for line in source.split_inclusive('\n') {
+		let content = line.trim_end_matches(['\n', '\r']);
+		let indent = content.len() - content.trim_start().len();
+		if let Some(block_indent) = block_indent {
+			if content.trim().is_empty() || indent > block_indent {
+				quoted.push_str(line);
+				continue;
+			}
+		}
+		block_indent = BLOCK.is_match(content).then_some(indent);
+		quoted.push_str(&DIRECTIVE.replace(line, |captures: &Captures| {
+			format!("{}'{}':", &captures[1], &captures[2])
+		}));
+	}
+	Cow::Owned(quoted)
+}
+
+/// The parameters and the items of the `!for` directives being rendered.
+struct Scope<'a> {
+	params: &'a Value,
+	items: Vec<(&'a str, &'a Value)>,
+}
+
+impl<'a> Scope<'a> {
+	fn lookup(
+		&self,
+		name: &str,
+		param: &KvonPath,
+		path: &KvonPath,
+	) -> Result<&'a Value, TemplateError> {
+		let value = match param.split_first() {
+			Some((PathSegment::Key(key), rest)) => {
+				match self.items.iter().rev().find(|(item, _)| item == key) {
+					Some((_, value)) => value.get_path(rest),
+					None => self.params.get_path(param),
+				}
+			}
+			_ => self.params.get_path(param),
+		};
+		value.ok_or_else(|| TemplateError::MissingParameter {
+			path: path.clone(),
+			name: name.to_string(),
+		})
+	}
+
+	fn render(&self, value: &'a Value, path: &mut KvonPath) -> Result<Value, TemplateError> {
+		Ok(match value {
+			Value::Object(obj) => self.render_object(obj, path)?,
+			Value::Array(arr) => {
+				let mut rendered = Vec::with_capacity(arr.len());
+				for (i, value) in arr.iter().enumerate() {
+					path.push(PathSegment::Index(i));
+					self.render_item(value, path, &mut rendered)?;
+					path.pop();
+				}
+				Value::Array(rendered)
+			}
+			Value::Primitive(p) => match (p.get_custom::<Param>(), p) {
+				(Ok(Param(param)), _) => self.lookup(&param.to_string(), param, path)?.clone(),
+				(_, PrimitiveValue::String(s)) => self.render_string(value, s, path)?,
+				_ => value.clone(),
+			},
+			Value::Raw(_) => value.clone(),
+		})
+	}
+
+	fn render_object(
+		&self,
+		obj: &'a HashMap<String, Value>,
+		path: &mut KvonPath,
+	) -> Result<Value, TemplateError> {
+		// sorted, so that the first error is the same every time
+		let mut keys: Vec<&'a String> = obj.keys().collect();
+		keys.sort();
+
+		let mut rendered = HashMap::with_capacity(obj.len());
+		let mut conditional = Vec::new();
+		for key in keys {
+			path.push(PathSegment::Key(key.clone()));
+			let value = &obj[key];
+			match directive(key) {
+				None => {
+					rendered.insert(key.clone(), self.render(value, path)?);
+				}
+				Some(Ok(Directive::For { .. })) if obj.len() == 1 => {
+					let mut items = Vec::new();
+					self.render_directive(key, value, path, &mut items)?;
+					path.pop();
+					return Ok(Value::Array(items));
+				}
+				Some(Ok(Directive::For { .. })) => {
+					return Err(TemplateError::InvalidDirective {
+						path: path.clone(),
+						message: "'!for' has to be the only key of its object",
+					});
+				}
+				Some(_) => {
+					let mut values = Vec::new();
+					self.render_directive(key, value, path, &mut values)?;
+					for value in values {
+						let Value::Object(entries) = value else {
+							return Err(TemplateError::InvalidDirective {
+								path: path.clone(),
+								message: "'!if' in an object needs an object of entries",
+							});
+						};
+						conditional.extend(entries);
+					}
+				}
+			}
+			path.pop();
+		}
+		rendered.extend(conditional);
+		Ok(Value::Object(rendered))
+	}
+
+	/// Renders an array item, which may be replaced by none or several
+	/// items if it holds a directive.
+	fn render_item(
+		&self,
+		value: &'a Value,
+		path: &mut KvonPath,
+		items: &mut Vec<Value>,
+	) -> Result<(), TemplateError> {
+		if let Value::Object(obj) = value {
+			if let Some((key, value)) = obj.iter().next().filter(|_| obj.len() == 1) {
+				if directive(key).is_some() {
+					path.push(PathSegment::Key(key.clone()));
+					self.render_directive(key, value, path, items)?;
+					path.pop();
+					return Ok(());
+				}
+			}
+		}
+		items.push(self.render(value, path)?);
+		Ok(())
+	}
+
+	/// Renders the value of a directive into `values`, once for each item of
+	/// a `!for` and if the condition of an `!if` holds.
+	fn render_directive(
+		&self,
+		key: &str,
+		value: &'a Value,
+		path: &mut KvonPath,
+		values: &mut Vec<Value>,
+	) -> Result<(), TemplateError> {
+		let directive = directive(key)
+			.expect("the key is a directive")
+			.map_err(|message| TemplateError::InvalidDirective {
+				path: path.clone(),
+				message,
+			})?;
+		match directive {
+			Directive::If {
+				negated,
+				name,
+				path: param,
+			} => {
+				let condition = match self.lookup(name, &param, path)? {
+					Value::Primitive(PrimitiveValue::Boolean(b)) => *b,
+					Value::Primitive(PrimitiveValue::Null) => false,
+					_ => {
+						return Err(TemplateError::NotABoolean {
+							path: path.clone(),
+							name: name.to_string(),
+						})
+					}
+				};
+				if condition != negated {
+					values.push(self.render(value, path)?);
+				}
+			}
+			Directive::For {
+				item,
+				name,
+				path: param,
+			} => {
+				let Value::Array(arr) = self.lookup(name, &param, path)? else {
+					return Err(TemplateError::NotAnArray {
+						path: path.clone(),
+						name: name.to_string(),
+					});
+				};
+				for (i, item_value) in arr.iter().enumerate() {
+					let mut items = self.items.clone();
+					items.push((item, item_value));
+					let scope = Scope {
+						params: self.params,
+						items,
+					};
+					path.push(PathSegment::Index(i));
+					values.push(scope.render(value, path)?);
+					path.pop();
+				}
+			}
+		}
+		Ok(())
+	}
+
+	fn render_string(
+		&self,
+		value: &Value,
+		s: &str,
+		path: &KvonPath,
+	) -> Result<Value, TemplateError> {
+		let mut placeholders = placeholders(s).peekable();
+		Ok(match placeholders.peek() {
+			// the whole string is a placeholder
+			Some((range, name, param)) if range.len() == s.len() => {
+				self.lookup(name, param, path)?.clone()
+			}
+			Some(_) => {
+				let mut rendered = String::new();
+				let mut end = 0;
+				for (range, name, param) in placeholders {
+					let Value::Primitive(p) = self.lookup(name, &param, path)? else {
+						return Err(TemplateError::NotInlinable {
+							path: path.clone(),
+							name: name.to_string(),
+						});
+					};
+					rendered.push_str(&s[end..range.start]);
+					rendered.push_str(&var_value(p));
+					end = range.end;
+				}
+				rendered.push_str(&s[end..]);
+				Value::from(rendered)
+			}
+			None => value.clone(),
+		})
+	}
+}
+
+/// Finds the `<<name>>` placeholders of a string, with their byte ranges.
+fn placeholders(s: &str) -> impl Iterator<Item = (std::ops::Range<usize>, &str, KvonPath)> {
+	let mut start = 0;
+	std::iter::from_fn(move || loop {
+		let open = start + s[start..].find("<<")?;
+		let close = open + 2 + s[open + 2..].find(">>")?;
+		let name = &s[open + 2..close];
+		match name.parse::<KvonPath>() {
+			Ok(path) if !name.is_empty() && !name.contains(char::is_whitespace) => {
+				start = close + 2;
+				return Some((open..close + 2, name, path));
+			}
+			_ => start = open + 1,
+		}
+	})
+}
@@ -1,7 +1,9 @@
 use crate::{
+	array, encode_string,
 	error::{ParserError, ParserErrorKind},
 	object, parse_string,
 	value::Value,
+	EncodeOptions,
 };
 
 fn test(source: &str, target: Value) {
@@ -9,7 +11,7 @@ fn test(source: &str, target: Value) {
 	assert_eq!(parsed, target);
 }
 
-static SIMPLE_OBJECT: &'static str = "
+static SIMPLE_OBJECT: &str = "
 # object with one level of indent
 a:
 	# key value pairs
@@ -18,20 +20,20 @@ a:
 d: 0
 ";
 
-static INLINED_ARRAYS: &'static str = "
+static INLINED_ARRAYS: &str = "
 arrays:
 	1a: [1 true false 4]
 	1b: [1 [true false] 4]
 ";
 
-static MULTI_LINE_ARRAYS_A: &'static str = "
+static MULTI_LINE_ARRAYS_A: &str = "
 array:--
 	- 1 2
 	- 2 3
 	- [true false] [false true]
 ";
 
-static MULTI_LINE_ARRAYS_B: &'static str = "
+static MULTI_LINE_ARRAYS_B: &str = "
 arr:--
 	- 1 2
 	- 2: 3
@@ -43,7 +45,7 @@ arr:--
 			- false
 ";
 
-static MULTI_LINE_STRINGS: &'static str = "
+static MULTI_LINE_STRINGS: &str = "
 empty:|
 a: |
 	<line 1>
@@ -67,7 +69,7 @@ arr:--
 		<line 3>
 ";
 
-static ARRAY_OF_OBJECTS: &'static str = "
+static ARRAY_OF_OBJECTS: &str = "
 objects:--
 	- 'object 1':
 		'key 1-1': 'value 1-1'
@@ -200,7 +202,7 @@ fn test_sources() {
 	);
 }
 
-static INVALID_STRING: &'static str = "
+static INVALID_STRING: &str = "
 a: 'a'
 b: \"b\"
 c: ''using the \"'\" character inside''
@@ -208,12 +210,12 @@ d: 'd'
 bad: 'c
 ";
 
-static BAD_INITIAL_INDENT: &'static str = "
+static BAD_INITIAL_INDENT: &str = "
 a:
 		a: 0
 ";
 
-static BAD_INDENT: &'static str = "
+static BAD_INDENT: &str = "
 a:
 	a: 0
 b:
@@ -229,7 +231,7 @@ fn invalid_string() {
 			kind: ParserErrorKind::UnclosedString,
 			line_number: 5,
 			column_number: _,
-			line: _,
+			..
 		})
 	));
 }
@@ -243,11 +245,71 @@ fn bad_initial_indent() {
 			kind: ParserErrorKind::MultipleTabIndent,
 			line_number: 2,
 			column_number: _,
-			line: _,
+			..
 		})
 	));
 }
 
+#[test]
+fn lenient_tab_indention() {
+	use crate::{parse_string_with_options, ParserOptions};
+
+	let error = parse_string(BAD_INITIAL_INDENT).unwrap_err();
+	assert_eq!(
+		error.hint.as_deref(),
+		Some("this line is indented by 2 tabs, but nested blocks are indented by one tab more than their parent")
+	);
+
+	let options = ParserOptions::new().lenient_tab_indention(true);
+	let source = "a:\n\t\tb:\n\t\t\t\tc: |\n\t\t\t\t\t\tline\n\t\td: 1";
+	assert_eq!(
+		parse_string_with_options(source, &options).unwrap(),
+		object! { a: { b: { c: "line" }, d: 1 } }
+	);
+
+	let error = parse_string_with_options("a:\n\t\tb:\n\t\t\tc: 1", &options).unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::MisalignedIndention {
+			indention: crate::indention::Indention::Tabs,
+			found: 3,
+			shallower: 2,
+			deeper: 4
+		}
+	);
+	assert_eq!(error.code(), "E005_MISALIGNED_INDENT");
+	assert_eq!(
+		error.hint.as_deref(),
+		Some("indent by 2 tabs to continue the outer block, or by 4 to nest")
+	);
+}
+
+#[test]
+fn multi_line_string_indention() {
+	// the first line of the string defines the indention as the leading
+	// spaces, the tab after them is content
+	test(
+		"a: |\n  \tcmd\n  \t\n  done\nb:\n  c: 1",
+		object! { a: "\tcmd\n\t\ndone", b: { c: 1 } },
+	);
+
+	// with tabs, only the first tab is indention
+	test(
+		"a: |\n\t\tcmd\nb:\n\tc: 1",
+		object! { a: "\tcmd", b: { c: 1 } },
+	);
+
+	// the indention defined by a string applies to the rest of the document
+	let error = parse_string("a: |\n  text\nb:\n\tc: 1").unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::InconsistentIndention(
+			crate::indention::Indention::Spaces(2),
+			crate::indention::Indention::Tabs
+		)
+	);
+}
+
 #[test]
 fn bad_indent() {
 	let objects = parse_string(BAD_INDENT);
@@ -257,7 +319,7 @@ fn bad_indent() {
 			kind: ParserErrorKind::InvalidIndention,
 			line_number: 4,
 			column_number: _,
-			line: _,
+			..
 		})
 	));
 }
@@ -268,10 +330,10 @@ fn unexpected_characters() {
 	assert!(matches!(
 		objects,
 		Err(ParserError {
-			kind: ParserErrorKind::UnexpectedCharacter,
+			kind: ParserErrorKind::TrailingAfterMarker { marker: ":--" },
 			line_number: 0,
 			column_number: _,
-			line: _,
+			..
 		})
 	));
 
@@ -279,21 +341,25 @@ fn unexpected_characters() {
 	assert!(matches!(
 		objects,
 		Err(ParserError {
-			kind: ParserErrorKind::UnexpectedCharacter,
+			kind: ParserErrorKind::Unexpected { .. },
 			line_number: 0,
 			column_number: _,
-			line: _,
+			..
 		})
 	));
 
+	// a version literal with the semver feature
+	#[cfg(not(feature = "semver"))]
 	let objects = parse_string("a: 1.2.3");
+	#[cfg(feature = "semver")]
+	let objects = parse_string("a: 1.2.3.4.5");
 	assert!(matches!(
 		objects,
 		Err(ParserError {
-			kind: ParserErrorKind::UnexpectedCharacter,
+			kind: ParserErrorKind::Unexpected { .. },
 			line_number: 0,
 			column_number: _,
-			line: _,
+			..
 		})
 	));
 
@@ -301,10 +367,10 @@ fn unexpected_characters() {
 	assert!(matches!(
 		objects,
 		Err(ParserError {
-			kind: ParserErrorKind::UnexpectedCharacter,
+			kind: ParserErrorKind::Unexpected { .. },
 			line_number: 0,
 			column_number: _,
-			line: _,
+			..
 		})
 	));
 
@@ -312,26 +378,35 @@ fn unexpected_characters() {
 	assert!(matches!(
 		objects,
 		Err(ParserError {
-			kind: ParserErrorKind::UnexpectedCharacter,
+			kind: ParserErrorKind::Unexpected { .. },
 			line_number: 0,
 			column_number: _,
-			line: _,
+			..
 		})
 	));
 
+	let error = parse_string("a: 0 0").unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::unexpected("0", &["end of line", "comment"])
+	);
+	assert!(error
+		.to_string()
+		.starts_with("0:4: unexpected '0', expected end of line or comment"));
+
 	let objects = parse_string("a:: 123");
 	assert!(matches!(
 		objects,
 		Err(ParserError {
-			kind: ParserErrorKind::UnexpectedCharacter,
+			kind: ParserErrorKind::Unexpected { .. },
 			line_number: 0,
 			column_number: _,
-			line: _,
+			..
 		})
 	));
 }
 
-static EMPTY_OBJECT_VS_NULL: &'static str = "
+static EMPTY_OBJECT_VS_NULL: &str = "
 a:
 b:
 c: null
@@ -365,3 +440,4260 @@ fn empty_object_vs_null() {
 		},
 	);
 }
+
+#[test]
+fn consuming_getters() {
+	let object = parse_string("a: [1 2]\nb: 'text'\nc: true").unwrap();
+	let mut objects = object.into_object().unwrap();
+
+	let a = objects.remove("a").unwrap();
+	assert!(a.clone().into_object().is_err());
+	assert_eq!(
+		a.into_vector().unwrap(),
+		vec![Value::from(1), Value::from(2)]
+	);
+
+	let b = objects.remove("b").unwrap();
+	assert_eq!(b.clone().into_number(), Err(b.clone()));
+	assert_eq!(b.into_string().unwrap(), "text");
+
+	let c = objects.remove("c").unwrap();
+	assert!(c.clone().into_boolean().unwrap());
+	assert!(c.into_primitive().unwrap().into_string().is_err());
+}
+
+#[test]
+fn convenience_iterators() {
+	let object = parse_string("a:\n\tb: 0\n\tc: 1\narr: [1 2 3]\nn: null").unwrap();
+	let objects = object.get_objects().unwrap();
+
+	let mut keys = objects["a"].keys().cloned().collect::<Vec<_>>();
+	keys.sort();
+	assert_eq!(keys, vec!["b", "c"]);
+	assert_eq!(objects["a"].values().count(), 2);
+	assert_eq!(objects["a"].iter_object().count(), 2);
+	assert_eq!(objects["a"].iter_array().count(), 0);
+
+	assert_eq!(objects["arr"].iter_array().count(), 3);
+	assert_eq!(objects["arr"].keys().count(), 0);
+	assert_eq!(objects["arr"].len(), 3);
+
+	assert_eq!(objects["n"].len(), 0);
+	assert!(objects["n"].is_empty());
+	assert_eq!(objects["n"].iter_object().count(), 0);
+}
+
+#[test]
+fn value_ordering_and_hashing() {
+	use std::collections::{BTreeSet, HashSet};
+
+	assert_eq!(Value::from(f32::NAN), Value::from(f32::NAN));
+	assert_eq!(Value::from(-0.0), Value::from(0.0));
+	assert!(Value::from(f32::INFINITY) < Value::from(f32::NAN));
+
+	let mut arr = vec![
+		object! { a: 1 },
+		Value::from("b"),
+		array![1, 2],
+		Value::from(2),
+		Value::null(),
+		Value::from(true),
+		Value::from("a"),
+		array![1],
+		Value::from(2),
+	];
+	arr.sort();
+	arr.dedup();
+	assert_eq!(
+		arr,
+		vec![
+			Value::null(),
+			Value::from(true),
+			Value::from(2),
+			Value::from("a"),
+			Value::from("b"),
+			array![1],
+			array![1, 2],
+			object! { a: 1 },
+		]
+	);
+
+	let set = HashSet::from([object! { a: 1, b: 2 }, object! { b: 2, a: 1 }]);
+	assert_eq!(set.len(), 1);
+
+	let set = BTreeSet::from([Value::from(f32::NAN), Value::from(-f32::NAN)]);
+	assert_eq!(set.len(), 1);
+}
+
+#[test]
+fn deep_transform() {
+	use crate::path::PathSegment;
+
+	let object = object! {
+		name: "  padded  ",
+		timeout_ms: 1500,
+		servers: [
+			{ host: " a ", password: "hunter2" },
+			{ host: "b", password: "letmein" },
+		],
+	};
+
+	let trimmed = object.clone().map_strings(|s| s.trim().to_string());
+	assert_eq!(
+		trimmed.get_objects().unwrap()["name"],
+		Value::from("padded")
+	);
+
+	let seconds = object.clone().map_numbers(|n| n / 1000.0);
+	assert_eq!(
+		seconds.get_objects().unwrap()["timeout_ms"],
+		Value::from(1.5)
+	);
+
+	let mut paths = Vec::new();
+	let redacted = object.transform(&mut |path, value| {
+		paths.push(path.to_vec());
+		match path.last() {
+			Some(PathSegment::Key(key)) if key == "password" => Value::from("***"),
+			_ => value,
+		}
+	});
+	assert_eq!(
+		redacted.get_objects().unwrap()["servers"],
+		array![
+			{ host: " a ", password: "***" },
+			{ host: "b", password: "***" },
+		]
+	);
+	assert!(paths.contains(&vec![
+		PathSegment::Key("servers".to_string()),
+		PathSegment::Index(1),
+		PathSegment::Key("host".to_string()),
+	]));
+	assert_eq!(paths.last(), Some(&vec![]));
+}
+
+#[test]
+fn redacted_encoding() {
+	let object = object! {
+		user: "admin",
+		password: "hunter2",
+		api: {
+			Token: "abc",
+			client_secret: "def",
+			secret_name: "visible",
+		},
+	};
+
+	let options = EncodeOptions::new().redact_keys(["password", "token", "*_secret"]);
+	let encoded = encode_string(&object, &options);
+	assert!(!encoded.contains("hunter2"));
+	assert!(!encoded.contains("abc"));
+	assert!(!encoded.contains("def"));
+	assert!(encoded.contains("visible"));
+
+	let parsed = parse_string(&encoded).unwrap();
+	assert_eq!(
+		parsed,
+		object! {
+			user: "admin",
+			password: "<redacted>",
+			api: {
+				Token: "<redacted>",
+				client_secret: "<redacted>",
+				secret_name: "visible",
+			},
+		}
+	);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Seconds(f32);
+
+impl crate::tags::CustomScalar for Seconds {
+	fn tag(&self) -> &str {
+		"seconds"
+	}
+
+	fn encode(&self) -> crate::value::PrimitiveValue {
+		format!("{}s", self.0).into()
+	}
+
+	fn clone_box(&self) -> Box<dyn crate::tags::CustomScalar> {
+		Box::new(self.clone())
+	}
+
+	fn as_any(&self) -> &dyn std::any::Any {
+		self
+	}
+}
+
+#[test]
+fn tagged_literals() {
+	use crate::{
+		parse_string_with_options, tags::TagRegistry, value::PrimitiveValue, ParserOptions,
+	};
+
+	let mut tags = TagRegistry::new();
+	tags.register("seconds", |literal| {
+		let s = literal.get_string().map_err(|_| "expected a string")?;
+		let n = s.strip_suffix('s').ok_or("missing the 's' suffix")?;
+		let n = n.parse().map_err(|_| "not a number")?;
+		Ok(PrimitiveValue::custom(Seconds(n)).unwrap())
+	});
+	let options = ParserOptions::new().tags(tags);
+
+	let object =
+		parse_string_with_options("a: !seconds '5s'\nb: [1 !seconds '2s']", &options).unwrap();
+	let a = object.get_objects().unwrap()["a"].get_primitive().unwrap();
+	assert_eq!(a.get_custom::<Seconds>(), Ok(&Seconds(5.0)));
+
+	let encoded = encode_string(&object, &EncodeOptions::new());
+	assert!(encoded.contains("a: !seconds '5s'"));
+	assert_eq!(
+		parse_string_with_options(&encoded, &options).unwrap(),
+		object
+	);
+
+	let error = parse_string_with_options("a: !seconds 5", &options).unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::InvalidTaggedValue {
+			tag: "seconds".to_string(),
+			message: "expected a string".to_string(),
+		}
+	);
+
+	let error = parse_string("a: !minutes '5m'").unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::UnknownTag("minutes".to_string())
+	);
+	assert_eq!(error.column_number, 3);
+
+	let options = ParserOptions::new().allow_unknown_tags(true);
+	assert_eq!(
+		parse_string_with_options("a: !minutes '5m'", &options).unwrap(),
+		object! { a: "5m" }
+	);
+}
+
+#[derive(Debug, Clone)]
+struct Literal(crate::value::PrimitiveValue);
+
+impl crate::tags::CustomScalar for Literal {
+	fn tag(&self) -> &str {
+		"literal"
+	}
+
+	fn encode(&self) -> crate::value::PrimitiveValue {
+		self.0.clone()
+	}
+
+	fn clone_box(&self) -> Box<dyn crate::tags::CustomScalar> {
+		Box::new(self.clone())
+	}
+
+	fn as_any(&self) -> &dyn std::any::Any {
+		self
+	}
+}
+
+#[test]
+fn invalid_custom_scalars() {
+	use crate::value::PrimitiveValue;
+
+	let nested = PrimitiveValue::custom(Seconds(1.0)).unwrap();
+	for literal in [
+		PrimitiveValue::from(""),
+		PrimitiveValue::from("a\nb"),
+		PrimitiveValue::from("'\"'\"\"''\"\"\"'"),
+		nested,
+	] {
+		let error = PrimitiveValue::custom(Literal(literal.clone())).unwrap_err();
+		assert_eq!(error.tag, "literal", "{literal:?}");
+	}
+
+	let custom = PrimitiveValue::custom(Literal(PrimitiveValue::Number(2.5))).unwrap();
+	let encoded = encode_string(&Value::key_value_pair("a", custom), &EncodeOptions::new());
+	assert!(encoded.contains("a: !literal 2.5"));
+}
+
+#[cfg(feature = "quantities")]
+#[test]
+fn quantity_literals() {
+	use std::time::Duration;
+
+	let object =
+		parse_string("timeout: 2h30m\npoll: [1.5s 250ms]\nlimit: 10MiB\ndisk: 4GB").unwrap();
+	let objects = object.get_objects().unwrap();
+	assert_eq!(
+		objects["timeout"].get_primitive().unwrap().get_duration(),
+		Ok(Duration::from_secs(2 * 3600 + 30 * 60))
+	);
+	assert_eq!(
+		objects["poll"],
+		array![Duration::from_millis(1500), Duration::from_millis(250)]
+	);
+	assert_eq!(
+		objects["limit"].get_primitive().unwrap().get_byte_size(),
+		Ok(10 * 1024 * 1024)
+	);
+	assert_eq!(
+		objects["disk"].get_primitive().unwrap().get_byte_size(),
+		Ok(4_000_000_000)
+	);
+
+	let encoded = encode_string(&object, &EncodeOptions::new());
+	assert!(encoded.contains("timeout: 2h30m"));
+	assert!(encoded.contains("poll: [1s500ms 250ms]"));
+	assert!(encoded.contains("limit: 10MiB"));
+	assert!(encoded.contains("disk: 4GB"));
+	assert_eq!(parse_string(&encoded).unwrap(), object);
+
+	// plain numbers are unaffected
+	assert_eq!(parse_string("a: 5").unwrap(), object! { a: 5 });
+	assert!(parse_string("a: 5parsecs").is_err());
+}
+
+#[test]
+fn literal_aliases() {
+	use crate::{parse_string_with_options, ParserOptions};
+
+	// aliases are off by default
+	assert!(parse_string("a: yes").is_err());
+	assert!(parse_string("a: ~").is_err());
+
+	let options = ParserOptions::new().boolean_aliases(true).null_alias(true);
+	assert_eq!(
+		parse_string_with_options("a: yes\nb: off\nc: ~\nd: [on no ~]", &options).unwrap(),
+		object! {
+			a: true,
+			b: false,
+			c: Value::null(),
+			d: [true, false, Value::null()],
+		}
+	);
+	assert!(parse_string_with_options("a: one", &options).is_err());
+
+	let error = parse_string("a: True").unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::unexpected("True", &["value", "end of line"])
+	);
+
+	let options = ParserOptions::new().strict_literals(true);
+	let error = parse_string_with_options("a: [1 True]", &options).unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::NearMissLiteral {
+			found: "True".to_string(),
+			expected: "true".to_string(),
+		}
+	);
+	assert_eq!(error.column_number, 6);
+
+	let error = parse_string_with_options("a: YES", &options).unwrap_err();
+	assert_eq!(
+		error.to_string(),
+		"0:3: 'YES' is not a literal, did you mean 'true'?"
+	);
+}
+
+static RAW_DOCUMENT: &str = "
+route: 'users'
+payload:
+	id: 7
+	tags:--
+		- 'a' 'b'
+	bio: |
+		line 1
+			line 2
+inline: [1 2]
+";
+
+#[test]
+fn raw_values() {
+	use crate::{indention::Indention, parse_string_with_options, ParserOptions};
+
+	let options = ParserOptions::new().raw_paths(["payload", "inline"]);
+	let object = parse_string_with_options(RAW_DOCUMENT, &options).unwrap();
+	let objects = object.get_objects().unwrap();
+	assert_eq!(objects["route"], Value::from("users"));
+
+	let Value::Raw(payload) = &objects["payload"] else {
+		panic!("payload should be raw");
+	};
+	assert_eq!(payload.head(), "");
+	assert_eq!(
+		payload.body(),
+		[
+			"id: 7",
+			"tags:--",
+			"\t- 'a' 'b'",
+			"bio: |",
+			"\tline 1",
+			"\t\tline 2"
+		]
+	);
+
+	// the raw text parses into the same value as parsing it directly
+	let parsed = parse_string(RAW_DOCUMENT).unwrap();
+	let parsed = parsed.get_objects().unwrap();
+	assert_eq!(payload.parse().unwrap(), parsed["payload"]);
+	let Value::Raw(inline) = &objects["inline"] else {
+		panic!("inline should be raw");
+	};
+	assert_eq!(inline.parse().unwrap(), parsed["inline"]);
+
+	// raw values are spliced back verbatim, re-indented to the output style
+	let encoded = encode_string(
+		&object,
+		&EncodeOptions::new().indention(Indention::Spaces(2)),
+	);
+	assert!(encoded.contains("\n  tags:--\n    - 'a' 'b'\n"));
+	assert!(encoded.contains("\ninline: [1 2]"));
+
+	let encoded = encode_string(&object, &EncodeOptions::new());
+	assert_eq!(
+		parse_string(&encoded).unwrap(),
+		parse_string(RAW_DOCUMENT).unwrap()
+	);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_value() {
+	let object = object! {
+		a: [1, "two", true, Value::null()],
+		b: { c: 0.5 },
+	};
+
+	let json = serde_json::to_value(&object).unwrap();
+	assert_eq!(
+		json,
+		serde_json::json!({ "a": [1.0, "two", true, null], "b": { "c": 0.5 } })
+	);
+
+	let value: Value = serde_json::from_value(json).unwrap();
+	assert_eq!(value, object);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_enums() {
+	use serde::{Deserialize, Serialize};
+
+	#[derive(Debug, PartialEq, Serialize, Deserialize)]
+	enum Color {
+		Red,
+		Rgb(u8, u8, u8),
+		Named { name: String },
+	}
+
+	#[derive(Debug, PartialEq, Serialize, Deserialize)]
+	#[serde(tag = "type")]
+	enum Shape {
+		Circle { radius: f32 },
+		Empty,
+	}
+
+	#[derive(Debug, PartialEq, Serialize, Deserialize)]
+	#[serde(untagged)]
+	enum Port {
+		Number(u16),
+		Name(String),
+	}
+
+	let colors = [
+		(Color::Red, Value::from("Red")),
+		(Color::Rgb(1, 2, 3), object! { Rgb: [1, 2, 3] }),
+		(
+			Color::Named {
+				name: "teal".to_string(),
+			},
+			object! { Named: { name: "teal" } },
+		),
+	];
+	for (color, value) in colors {
+		assert_eq!(crate::to_value(&color).unwrap(), value);
+		assert_eq!(crate::from_value::<Color>(value).unwrap(), color);
+	}
+
+	let circle = parse_string("type: 'Circle'\nradius: 1.5").unwrap();
+	assert_eq!(
+		crate::to_value(&Shape::Circle { radius: 1.5 }).unwrap(),
+		circle
+	);
+	assert_eq!(
+		crate::from_value::<Shape>(circle).unwrap(),
+		Shape::Circle { radius: 1.5 }
+	);
+	assert_eq!(
+		crate::from_value::<Shape>(object! { type: "Empty" }).unwrap(),
+		Shape::Empty
+	);
+
+	assert_eq!(
+		crate::from_value::<Port>(Value::from(80)).unwrap(),
+		Port::Number(80)
+	);
+	assert_eq!(
+		crate::from_value::<Port>(Value::from("http")).unwrap(),
+		Port::Name("http".to_string())
+	);
+	assert!(crate::from_value::<Color>(object! { Red: 0, Rgb: 1 }).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_attributes() {
+	use std::collections::HashMap;
+
+	use serde::Deserialize;
+
+	#[derive(Debug, PartialEq, Deserialize)]
+	struct Server<'a> {
+		host: &'a str,
+		#[serde(default = "default_port")]
+		port: u16,
+		#[serde(flatten)]
+		limits: Limits,
+		#[serde(flatten)]
+		rest: HashMap<String, Value>,
+	}
+
+	#[derive(Debug, Default, PartialEq, Deserialize)]
+	struct Limits {
+		#[serde(default)]
+		connections: Option<u32>,
+		timeout: f32,
+	}
+
+	#[derive(Debug, PartialEq, Deserialize)]
+	#[serde(deny_unknown_fields)]
+	struct Strict {
+		#[serde(default)]
+		name: String,
+	}
+
+	fn default_port() -> u16 {
+		80
+	}
+
+	let value = parse_string(
+		"
+host: 'example.org'
+timeout: 2.5
+region: 'eu'
+",
+	)
+	.unwrap();
+	let server = Server::deserialize(&value).unwrap();
+	assert_eq!(
+		server,
+		Server {
+			host: "example.org",
+			port: 80,
+			limits: Limits {
+				connections: None,
+				timeout: 2.5,
+			},
+			rest: HashMap::from([("region".to_string(), Value::from("eu"))]),
+		}
+	);
+
+	assert_eq!(
+		crate::from_value::<Strict>(Value::empty_object()).unwrap(),
+		Strict {
+			name: String::new()
+		}
+	);
+	let error = crate::from_value::<Strict>(object! { name: "a", nmae: "b" }).unwrap_err();
+	assert!(error.to_string().contains("unknown field `nmae`"));
+
+	// strings can only be borrowed from a borrowed value
+	assert!(Server::deserialize(value).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_error_paths() {
+	use serde::Deserialize;
+
+	use crate::{from_str_path_err, path::Span, FromStrError};
+
+	#[derive(Debug, Deserialize)]
+	#[allow(dead_code)]
+	struct Config {
+		servers: Vec<Server>,
+	}
+
+	#[derive(Debug, Deserialize)]
+	#[allow(dead_code)]
+	struct Server {
+		host: String,
+		port: u16,
+	}
+
+	static SOURCE: &str = "
+servers:--
+	-
+		host: 'a'
+		port: 1
+	-
+		host: 'b'
+		port: 2
+	-
+		host: 'c'
+		port: 'http'
+";
+
+	let value = parse_string(SOURCE).unwrap();
+	let error = serde_path_to_error::deserialize::<_, Config>(value.clone()).unwrap_err();
+	assert_eq!(error.path().to_string(), "servers[2].port");
+
+	let error = crate::from_value::<Config>(value).unwrap_err();
+	assert_eq!(
+		error.to_string(),
+		"servers[2].port: invalid type: string \"http\", expected u16"
+	);
+
+	match from_str_path_err::<Config>(SOURCE) {
+		Err(FromStrError::Deserialize { error, span }) => {
+			assert_eq!(error.path.to_string(), "servers[2].port");
+			assert_eq!(
+				span,
+				Some(Span {
+					line_number: 10,
+					column_number: 2
+				})
+			);
+		}
+		other => panic!("unexpected result: {other:?}"),
+	}
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn unknown_keys() {
+	use crate::{schema::unknown_keys, KvonDecode};
+
+	#[derive(KvonDecode)]
+	#[allow(dead_code)]
+	struct Config {
+		name: String,
+		#[kvon(default)]
+		servers: Vec<Server>,
+		#[kvon(flatten)]
+		limits: Limits,
+	}
+
+	#[derive(KvonDecode)]
+	#[allow(dead_code)]
+	struct Server {
+		host: String,
+		#[kvon(default)]
+		port: Option<u16>,
+	}
+
+	#[derive(KvonDecode)]
+	#[allow(dead_code)]
+	struct Limits {
+		#[kvon(default)]
+		timeout: f32,
+	}
+
+	let value = parse_string(
+		"
+name: 'app'
+timout: 5
+servers:--
+	-
+		host: 'a'
+		prot: 80
+	- host: 'b'
+colour: 'red'
+",
+	)
+	.unwrap();
+
+	let warnings: Vec<_> = unknown_keys::<Config>(&value)
+		.iter()
+		.map(ToString::to_string)
+		.collect();
+	assert_eq!(
+		warnings,
+		[
+			"unknown key 'colour'",
+			"servers[0]: unknown key 'prot', did you mean 'port'?",
+			"unknown key 'timout', did you mean 'timeout'?",
+		]
+	);
+}
+
+#[test]
+fn defaults_and_coercion() {
+	use crate::{
+		decode::Decode,
+		path::KvonPath,
+		schema::{Coercion, Schema},
+		value::PrimitiveValue,
+	};
+
+	let mut value = parse_string(
+		"
+server:
+	port: '8080'
+	tls: 'true'
+name: 12
+",
+	)
+	.unwrap();
+
+	value.apply_defaults(&object! {
+		server: { host: "localhost", port: 80 },
+		debug: false,
+	});
+	assert_eq!(
+		value,
+		object! {
+			server: { host: "localhost", port: "8080", tls: "true" },
+			name: 12,
+			debug: false,
+		}
+	);
+
+	fn server() -> Schema {
+		Schema::object([
+			("port", u16::schema as fn() -> Schema),
+			("tls", bool::schema),
+			("host", String::schema),
+		])
+	}
+	let schema = Schema::object([
+		("server", server as fn() -> Schema),
+		("name", String::schema),
+		("debug", bool::schema),
+	]);
+
+	let coercions = value.coerce(&schema);
+	assert_eq!(
+		value,
+		object! {
+			server: { host: "localhost", port: 8080, tls: true },
+			name: "12",
+			debug: false,
+		}
+	);
+	assert_eq!(
+		coercions[0],
+		Coercion {
+			path: KvonPath::root().key("server").key("port"),
+			from: PrimitiveValue::from("8080"),
+			to: PrimitiveValue::Number(8080.),
+		}
+	);
+	assert_eq!(coercions.len(), 3);
+}
+
+#[test]
+fn migrations() {
+	use crate::migrations::{rename, MigrationError, Migrations};
+
+	let migrations = Migrations::new("version")
+		.step(|value| {
+			rename(value, "addr", "server.host");
+			Ok(())
+		})
+		.step(|value| match value.get("port") {
+			Some(_) => {
+				rename(value, "port", "server.port");
+				Ok(())
+			}
+			None => Err("missing port".to_string()),
+		});
+	assert_eq!(migrations.latest_version(), 3);
+
+	let legacy = parse_string("addr: 'localhost'\nport: 80").unwrap();
+	let changes: Vec<String> = migrations
+		.dry_run(&legacy)
+		.unwrap()
+		.iter()
+		.map(ToString::to_string)
+		.collect();
+	assert_eq!(
+		changes,
+		[
+			"- addr: 'localhost'",
+			"- port: 80",
+			"+ server: {...}",
+			"+ version: 3",
+		]
+	);
+
+	let mut migrated = legacy.clone();
+	assert_eq!(migrations.migrate(&mut migrated), Ok(1));
+	assert_eq!(
+		migrated,
+		object! { version: 3, server: { host: "localhost", port: 80 } }
+	);
+	// migrating again is a no-op
+	assert_eq!(migrations.dry_run(&migrated), Ok(vec![]));
+
+	// renaming into a primitive keeps the value where it was
+	let mut value = object! { a: { b: 1 }, c: 2 };
+	assert!(!rename(&mut value, "a.b", "c.d.e"));
+	assert_eq!(value, object! { a: { b: 1 }, c: 2 });
+	assert!(rename(&mut value, "a", "a.x"));
+	assert_eq!(value, object! { a: { x: { b: 1 } }, c: 2 });
+
+	let mut broken = object! { version: 2 };
+	assert_eq!(
+		migrations.migrate(&mut broken),
+		Err(MigrationError::Failed {
+			from: 2,
+			message: "missing port".to_string()
+		})
+	);
+	assert_eq!(
+		migrations.migrate(&mut object! { version: 4 }),
+		Err(MigrationError::UnsupportedVersion {
+			found: 4,
+			latest: 3
+		})
+	);
+}
+
+#[test]
+fn assert_kvon_eq() {
+	let value = object! { name: "app", ports: [80, 443] };
+	crate::assert_kvon_eq!(value, "name: 'app'\nports: [80 443]");
+	crate::assert_kvon_eq!(&value, value.clone());
+}
+
+#[test]
+#[should_panic(
+	expected = "changes from expected to actual:\n  ~ ports[1]: 443 -> 8443\n  + tls: true\n"
+)]
+fn assert_kvon_eq_diff() {
+	let value = object! { name: "app", ports: [80, 8443], tls: true };
+	crate::assert_kvon_eq!(value, "name: 'app'\nports: [80 443]");
+}
+
+#[test]
+fn pretty_debug() {
+	let value = object! { b: { c: [1, 2] }, a: "x" };
+	assert_eq!(value.pretty(), "a: 'x'\nb:\n\tc: [1 2]");
+	assert_eq!(format!("{value:#?}"), value.pretty());
+	assert_eq!(format!("{:?}", Value::from(0.5)), "Primitive(Number(0.5))");
+	assert_eq!(Value::from(true).pretty(), "true");
+}
+
+#[test]
+fn stable_snapshots() {
+	let mut value = object! {
+		servers: [
+			{ id: "a81f", host: "a", load: -0.0 },
+			{ id: "5c0e", host: "b", load: 0.25 },
+		],
+		created: "2024-01-01T00:00:00Z",
+	};
+
+	assert_eq!(value.redact_path("servers.*.id", "[id]"), 2);
+	assert_eq!(value.redact_path("created", "[timestamp]"), 1);
+	assert_eq!(value.redact_path("missing.key", "x"), 0);
+
+	assert_eq!(
+		value.to_stable_string(),
+		"created: '[timestamp]'
+servers:--
+	-
+		host: 'a'
+		id: '[id]'
+		load: 0
+	-
+		host: 'b'
+		id: '[id]'
+		load: 0.25"
+	);
+}
+
+#[test]
+fn unclosed_inline_array() {
+	let error = parse_string("a: [1 2 [3 4] # comment").unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::unexpected("#", &["value", "]"])
+	);
+	assert_eq!(
+		error.hint.as_deref(),
+		Some("inline arrays end on their line, so comments can only follow the closing `]`")
+	);
+
+	let error = parse_string("a: [1 2").unwrap_err();
+	assert_eq!(
+		error.to_string(),
+		"0:7: unexpected 'end of line', expected value or ] (hint: close the array with `]`)"
+	);
+}
+
+#[test]
+fn spaces_indentation() {
+	let value = object! { a: { b: { c: 0 }, d: [1, "x"] } };
+	let encoded = encode_string(
+		&value,
+		&EncodeOptions::new().indention(crate::indention::Indention::Spaces(2)),
+	);
+	assert_eq!(parse_string(&encoded).unwrap(), value);
+
+	// 3 spaces are between the blocks at 2 and 4 spaces
+	let error = parse_string("a:\n  b:\n    c: 0\n   d: 1").unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::MisalignedIndention {
+			indention: crate::indention::Indention::Spaces(2),
+			found: 3,
+			shallower: 2,
+			deeper: 4
+		}
+	);
+	assert_eq!(
+		error.to_string(),
+		"3:3: indented by 3 spaces, expected 2 or 4 (hint: indent by 2 spaces to continue the outer block, or by 4 to nest)"
+	);
+}
+
+#[test]
+fn error_codes() {
+	let error = parse_string(BAD_INDENT).unwrap_err();
+	assert_eq!(error.code(), "E003_INVALID_INDENT");
+	assert!(error.is_indentation_error());
+	assert!(!error.is_syntax_error());
+
+	let error = parse_string("a: 0 0").unwrap_err();
+	assert_eq!(error.code(), "E100_UNEXPECTED_CHARACTER");
+	assert!(error.is_syntax_error());
+
+	let error = parse_string("a:\n \tb: 0").unwrap_err();
+	assert_eq!(error.code(), "E001_MIXED_INDENT");
+}
+
+#[test]
+fn error_hints() {
+	let error = parse_string("a: 0 0").unwrap_err();
+	assert_eq!(
+		error.hint.as_deref(),
+		Some("multiple values must be wrapped in an inline array, e.g. `[1 2]`")
+	);
+	assert!(error
+		.to_string()
+		.ends_with("(hint: multiple values must be wrapped in an inline array, e.g. `[1 2]`)"));
+
+	let error = parse_string("a:: 1").unwrap_err();
+	assert_eq!(error.hint.as_deref(), Some("remove the duplicate colon"));
+
+	let error = parse_string("a 1").unwrap_err();
+	assert!(error.hint.unwrap().contains("colon"));
+
+	let error = parse_string("a:\n\tb:\n\t\t  c: 0").unwrap_err();
+	assert_eq!(error.kind, ParserErrorKind::MixedTabsAndSpaces);
+	assert!(error.hint.is_some());
+
+	let error = parse_string("a: 'b").unwrap_err();
+	assert_eq!(error.hint.as_deref(), Some("close the string with '"));
+}
+
+#[test]
+fn unclosed_blocks() {
+	use crate::{parse_string_with_options, ParserOptions};
+
+	// by default, empty blocks produce empty values
+	assert_eq!(parse_string("a: |").unwrap(), object! { a: "" });
+	assert_eq!(parse_string("a:--").unwrap(), object! { a: [] });
+
+	let options = ParserOptions::new().error_on_unclosed_blocks(true);
+	let error = parse_string_with_options("a: 0\nb: |", &options).unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::UnexpectedEof {
+			expected: "multi-line string content".to_string()
+		}
+	);
+	assert_eq!(error.line_number, 2);
+	assert_eq!(error.code(), "E104_UNEXPECTED_EOF");
+
+	let error = parse_string_with_options("a:--\n\t- 1\n\t--", &options).unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::UnexpectedEof {
+			expected: "array elements".to_string()
+		}
+	);
+
+	// empty objects are valid
+	assert!(parse_string_with_options("a:", &options).is_ok());
+	assert!(parse_string_with_options("a: |\n\ttext", &options).is_ok());
+}
+
+#[test]
+fn unicode_columns() {
+	let error = ParserError {
+		kind: ParserErrorKind::unexpected("0", &["end of line", "comment"]),
+		line_number: 0,
+		column_number: 30,
+		line: "'ключ': 'значение' 0".to_string(),
+		hint: None,
+	};
+	assert_eq!(error.char_column(), 18);
+	assert_eq!(error.utf16_column(), 18);
+	assert!(error.to_string().starts_with("0:18: "));
+
+	let error = ParserError {
+		column_number: 14,
+		line: "'🎉': '🎉' 0".to_string(),
+		..error
+	};
+	assert_eq!(error.char_column(), 8);
+	assert_eq!(error.utf16_column(), 10);
+}
+
+#[test]
+fn unicode_content() {
+	use std::collections::HashMap;
+
+	let value = parse_string(
+		"# комментарий 🎉
+ключ: 'значение' # 注释
+ключ2: 1
+emoji: '🎉 party 🎉'
+!ü: 'ö'
+text: |
+	mehrzeilig äöü
+	ünïcödé
+list: ['α' 'β' 'γ']",
+	)
+	.unwrap();
+
+	let expected: HashMap<String, Value> = [
+		("ключ", Value::from("значение")),
+		("ключ2", Value::from(1.)),
+		("emoji", Value::from("🎉 party 🎉")),
+		("!ü", Value::from("ö")),
+		("text", Value::from("mehrzeilig äöü\nünïcödé")),
+		("list", array!["α", "β", "γ"]),
+	]
+	.into_iter()
+	.map(|(k, v)| (k.to_string(), v))
+	.collect();
+	assert_eq!(value, Value::Object(expected));
+
+	let error = parse_string("'🎉': '🎉' 0").unwrap_err();
+	assert_eq!(error.char_column(), 8);
+
+	let error = parse_string("ключ: 'значение").unwrap_err();
+	assert_eq!(error.kind, ParserErrorKind::UnclosedString);
+}
+
+#[test]
+fn unicode_keys() {
+	use std::collections::HashMap;
+
+	use crate::{try_encode_string, KvonWrite, KvonWriter, UnencodableKey};
+
+	let value: HashMap<String, Value> = [
+		("größe", Value::from(1.)),
+		(" padded ", Value::from(2.)),
+		("with space", Value::from(3.)),
+		("a:b", Value::from(4.)),
+		("'quoted'", Value::from(5.)),
+		("日本語", Value::from("値")),
+	]
+	.into_iter()
+	.map(|(k, v)| (k.to_string(), v))
+	.collect();
+	let value = Value::Object(value);
+
+	let encoded = encode_string(&value, &EncodeOptions::new());
+	assert!(encoded.contains("größe: 1"));
+	assert!(encoded.contains("' padded ': 2"));
+	assert!(encoded.contains("日本語: '値'"));
+	assert_eq!(parse_string(&encoded).unwrap(), value);
+
+	// keys that can't be quoted are rejected instead of written unquoted
+	for key in ["a\nb", "'a\""] {
+		let value = Value::key_value_pair("outer", Value::key_value_pair(key, 1));
+		assert_eq!(
+			try_encode_string(&value, &EncodeOptions::new()),
+			Err(UnencodableKey(key.to_string()))
+		);
+		let mut writer = KvonWriter::new(Vec::new()).unwrap();
+		assert!(writer.key(key).is_err());
+	}
+}
+
+#[cfg(feature = "unicode-normalization")]
+#[test]
+fn normalized_keys() {
+	use crate::{parse_string_with_options, ParserOptions};
+
+	// a precomposed `é` and an `e` followed by a combining accent
+	let source = "caf\u{e9}: 1\ncafe\u{301}: 2";
+	assert_eq!(parse_string(source).unwrap().len(), 2);
+
+	let options = ParserOptions::new().normalize_keys(true);
+	let value = parse_string_with_options(source, &options).unwrap();
+	assert_eq!(value, object! { "caf\u{e9}": 2 });
+}
+
+#[test]
+fn empty_containers() {
+	let value = object! {
+		a: Value::empty_object(),
+		b: [],
+		c: Value::null(),
+		d: [Value::empty_object(), [], 1],
+		e: { f: Value::empty_object() },
+	};
+
+	let encoded = encode_string(&value, &EncodeOptions::new());
+	let lines = encoded.lines().collect::<Vec<_>>();
+	assert!(lines.contains(&"a:"));
+	assert!(lines.contains(&"b: []"));
+	assert!(lines.contains(&"c: null"));
+	assert!(lines.contains(&"\t-"));
+	assert!(lines.contains(&"\t- []"));
+	assert!(lines.contains(&"\tf:"));
+	assert!(lines.iter().all(|line| !line.ends_with(' ')));
+
+	assert_eq!(parse_string(&encoded).unwrap(), value);
+	assert_eq!(
+		encode_string(&Value::empty_object(), &EncodeOptions::new()),
+		""
+	);
+}
+
+#[test]
+fn wrapped_arrays() {
+	let value = object! {
+		a: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
+		b: [1, 2],
+	};
+
+	let options = EncodeOptions::new().max_line_width(10);
+	let encoded = encode_string(&value, &options);
+	assert!(encoded.contains("a:--\n\t- 1 2 3 4 5\n\t- 6 7 8 9 10"));
+	assert!(encoded.contains("b: [1 2]"));
+	assert_eq!(parse_string(&encoded).unwrap(), value);
+
+	let options = EncodeOptions::new().array_items_per_line(4);
+	let encoded = encode_string(&value, &options);
+	assert!(encoded.contains("a:--\n\t- 1 2 3 4\n\t- 5 6 7 8\n\t- 9 10"));
+	assert_eq!(parse_string(&encoded).unwrap(), value);
+}
+
+#[test]
+fn string_quoting() {
+	use crate::{MultiLineStrings, QuoteStyle};
+
+	let value = object! { a: "it's", b: "plain", c: "" };
+
+	let encoded = encode_string(&value, &EncodeOptions::new());
+	assert!(encoded.contains("a: \"it's\""));
+	assert!(encoded.contains("b: 'plain'"));
+	assert_eq!(parse_string(&encoded).unwrap(), value);
+
+	let options = EncodeOptions::new()
+		.quote_style(QuoteStyle::Double)
+		.quote_all_keys(true);
+	let encoded = encode_string(&value, &options);
+	assert!(encoded.contains("\"a\": \"it's\""));
+	assert!(encoded.contains("\"b\": \"plain\""));
+	assert_eq!(parse_string(&encoded).unwrap(), value);
+
+	let options = EncodeOptions::new().multi_line_strings(MultiLineStrings::WhenQuoted);
+	let encoded = encode_string(&value, &options);
+	assert!(encoded.contains("a: |\n\tit's"));
+	assert_eq!(parse_string(&encoded).unwrap(), value);
+}
+
+#[test]
+fn float_formatting() {
+	use crate::FloatFormat;
+
+	let value = object! { a: 1, b: 0.1, c: -2.5 };
+	let encode = |options: EncodeOptions| {
+		let encoded = encode_string(&value, &options);
+		assert_eq!(parse_string(&encoded).unwrap(), value);
+		encoded
+	};
+
+	let encoded = encode(EncodeOptions::new());
+	assert!(encoded.contains("a: 1\n") || encoded.ends_with("a: 1"));
+	assert!(encoded.contains("b: 0.1"));
+
+	let encoded = encode(EncodeOptions::new().always_include_fraction(true));
+	assert!(encoded.contains("a: 1.0"));
+	assert!(encoded.contains("c: -2.5"));
+
+	let encoded = encode(EncodeOptions::new().float_format(FloatFormat::Fixed(2)));
+	assert!(encoded.contains("a: 1.00"));
+	assert!(encoded.contains("b: 0.10"));
+	assert!(encoded.contains("c: -2.50"));
+
+	let options = EncodeOptions::new()
+		.float_format(FloatFormat::Fixed(0))
+		.always_include_fraction(true);
+	assert!(encode_string(&object! { a: 1.4 }, &options).ends_with("a: 1.0"));
+}
+
+#[test]
+fn encoder_layout() {
+	let value = object! {
+		a: 1,
+		b: [{ c: 1, d: 2 }, { c: 3 }, 4],
+	};
+
+	let options = EncodeOptions::new()
+		.header("generated file\ndo not edit")
+		.blank_lines_between_keys(true)
+		.blank_lines_between_objects(true);
+	let encoded = encode_string(&value, &options);
+	assert!(encoded.starts_with("# generated file\n# do not edit\n"));
+	assert!(encoded.contains("a: 1\n\nb:--") || encoded.contains("\n\na: 1"));
+	assert!(encoded.contains("\n\n\t-\n\t\tc: 3\n\t- 4"));
+	assert!(encoded.lines().all(|line| !line.ends_with(' ')));
+	assert_eq!(parse_string(&encoded).unwrap(), value);
+
+	// values other than objects start on a line after the header
+	let encoded = encode_string(&Value::from(1.0), &EncodeOptions::new().header(""));
+	assert_eq!(encoded, "1");
+	let options = EncodeOptions::new().header("generated");
+	assert_eq!(encode_string(&Value::from(1.0), &options), "# generated\n1");
+	assert_eq!(encode_string(&array![1, 2], &options), "# generated\n[1 2]");
+}
+
+#[test]
+fn key_order() {
+	use crate::path::PathSegment;
+
+	let value = object! {
+		version: 1,
+		dependencies: { b: 1, a: 2 },
+		name: "kvon",
+		authors: [],
+	};
+
+	let encoded = encode_string(&value, &EncodeOptions::new());
+	assert_eq!(
+		encoded,
+		"\nauthors: []\ndependencies:\n\ta: 2\n\tb: 1\nname: 'kvon'\nversion: 1"
+	);
+
+	let options = EncodeOptions::new().key_priority(["name", "version"]);
+	let encoded = encode_string(&value, &options);
+	assert!(encoded.starts_with("\nname: 'kvon'\nversion: 1\nauthors: []\n"));
+
+	let options = EncodeOptions::new().key_order(|path, keys| {
+		if path == [PathSegment::Key("dependencies".to_string())] {
+			keys.reverse();
+		}
+	});
+	let encoded = encode_string(&value, &options);
+	assert!(encoded.contains("dependencies:\n\tb: 1\n\ta: 2"));
+	assert_eq!(parse_string(&encoded).unwrap(), value);
+}
+
+#[test]
+fn streaming_writer() {
+	use crate::{value::PrimitiveValue, KvonWrite, KvonWriter};
+
+	let mut writer = KvonWriter::new(Vec::new()).unwrap();
+	writer.key("name").unwrap();
+	writer.value(&PrimitiveValue::from("kvon")).unwrap();
+	writer.key("rows").unwrap();
+	writer.begin_array().unwrap();
+	for i in 0..3 {
+		writer.begin_object().unwrap();
+		writer.key("id").unwrap();
+		writer.value(&PrimitiveValue::from(i as f32)).unwrap();
+		writer.end().unwrap();
+	}
+	writer.begin_array().unwrap();
+	writer.value(&PrimitiveValue::from(true)).unwrap();
+	writer.end().unwrap();
+	writer.end().unwrap();
+	writer.key("text").unwrap();
+	writer.value(&PrimitiveValue::from("a\nb")).unwrap();
+	writer.key("nested").unwrap();
+	writer.value_tree(&object! { b: [1, 2], a: {} }).unwrap();
+
+	let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+	assert_eq!(
+		output,
+		"name: 'kvon'\nrows:--\n\t-\n\t\tid: 0\n\t-\n\t\tid: 1\n\t-\n\t\tid: 2\n\t--\n\t\t- true\ntext: |\n\ta\n\tb\nnested:\n\ta:\n\tb:--\n\t\t- 1\n\t\t- 2\n"
+	);
+	assert_eq!(
+		parse_string(&output).unwrap(),
+		object! {
+			name: "kvon",
+			rows: [{ id: 0 }, { id: 1 }, { id: 2 }, [true]],
+			text: "a\nb",
+			nested: { a: {}, b: [1, 2] },
+		}
+	);
+
+	// misuse is reported instead of producing invalid output
+	let mut writer = KvonWriter::new(Vec::new()).unwrap();
+	assert!(writer.value(&PrimitiveValue::Null).is_err());
+	writer.key("a").unwrap();
+	writer.begin_object().unwrap();
+	assert!(writer.finish().is_err());
+}
+
+#[test]
+fn records() {
+	use crate::records::{encode_record, parse_records, RecordError};
+
+	let first = object! { level: "info", message: "started" };
+	let second = object! { level: "error", data: { code: 1 } };
+
+	let mut file = String::new();
+	file.push_str(&encode_record(&first));
+	file.push_str(&encode_record(&second));
+	assert!(file.starts_with("---\ndata") || file.starts_with("---\nlevel"));
+
+	let records = parse_records(file.as_bytes())
+		.collect::<Result<Vec<_>, _>>()
+		.unwrap();
+	assert_eq!(records, vec![first.clone(), second]);
+
+	// the first separator is optional, errors report file line numbers and
+	// reading continues with the next record
+	let records =
+		parse_records("# log\na: 1\n---\nb: 2 2\nd: 4\n---\nc: 3".as_bytes()).collect::<Vec<_>>();
+	assert_eq!(records.len(), 3);
+	assert_eq!(records[0].as_ref().unwrap(), &object! { a: 1 });
+	match &records[1] {
+		Err(RecordError::Parser(e)) => assert_eq!(e.line_number, 3),
+		other => panic!("unexpected {other:?}"),
+	}
+	assert_eq!(records[2].as_ref().unwrap(), &object! { c: 3 });
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing_records() {
+	use std::{
+		io,
+		sync::{Arc, Mutex},
+		time::{Duration, UNIX_EPOCH},
+	};
+
+	use tracing_subscriber::prelude::*;
+
+	use crate::{
+		records::parse_records,
+		tracing::{timestamp, KvonLayer},
+	};
+
+	#[derive(Clone, Default)]
+	struct Log(Arc<Mutex<Vec<u8>>>);
+
+	impl io::Write for Log {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			self.0.lock().unwrap().write(buf)
+		}
+
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+
+	let log = Log::default();
+	let subscriber = tracing_subscriber::registry().with(KvonLayer::new(log.clone()));
+	::tracing::subscriber::with_default(subscriber, || {
+		::tracing::warn!(target: "app", retries = 3_u64, "slow start");
+		let request = ::tracing::info_span!(
+			"request",
+			id = 7,
+			name = "get",
+			user = ::tracing::field::Empty
+		);
+		let _request = request.enter();
+		request.record("user", "ada");
+		let _query = ::tracing::debug_span!("query").entered();
+		::tracing::error!(target: "db", cause = ?Some("timeout"), ok = false);
+		::tracing::info!(ratio = f64::NAN, big = 1e300, small = -f64::INFINITY);
+		::tracing::info!("after");
+	});
+
+	let log = String::from_utf8(log.0.lock().unwrap().clone()).unwrap();
+	// each field is on a line of its own
+	assert!(log
+		.lines()
+		.any(|line| line.trim() == "message: 'slow start'"));
+	let records: Vec<Value> = parse_records(log.as_bytes())
+		.collect::<Result<_, _>>()
+		.unwrap();
+	assert_eq!(
+		records,
+		[
+			object! { level: "WARN", target: "app", fields: { message: "slow start", retries: 3 } },
+			object! {
+				level: "ERROR",
+				target: "db",
+				fields: { cause: "Some(\"timeout\")", ok: false },
+				spans: [
+					{ name: "request", fields: { id: 7, name: "get", user: "ada" } },
+					{ name: "query", fields: {} },
+				],
+			},
+			// floats KVON can't write are strings, so the records after them
+			// can still be read
+			object! {
+				level: "INFO",
+				target: "kvon_rs::tests",
+				fields: { ratio: "NaN", big: "1e300", small: "-inf" },
+				spans: [
+					{ name: "request", fields: { id: 7, name: "get", user: "ada" } },
+					{ name: "query", fields: {} },
+				],
+			},
+			object! {
+				level: "INFO",
+				target: "kvon_rs::tests",
+				fields: { message: "after" },
+				spans: [
+					{ name: "request", fields: { id: 7, name: "get", user: "ada" } },
+					{ name: "query", fields: {} },
+				],
+			},
+		]
+	);
+
+	let log = Log::default();
+	let subscriber =
+		tracing_subscriber::registry().with(KvonLayer::new(log.clone()).with_timestamps());
+	::tracing::subscriber::with_default(subscriber, || ::tracing::info!("tick"));
+	let log = log.0.lock().unwrap();
+	let record = parse_records(&log[..]).next().unwrap().unwrap();
+	assert!(record
+		.get("time")
+		.unwrap()
+		.get_primitive()
+		.unwrap()
+		.get_string()
+		.unwrap()
+		.ends_with('Z'));
+
+	assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+	let leap_day = UNIX_EPOCH + Duration::from_secs(951_782_400);
+	assert_eq!(timestamp(leap_day), "2000-02-29T00:00:00.000Z");
+	let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_250);
+	assert_eq!(timestamp(time), "2023-11-14T22:13:20.250Z");
+}
+
+#[test]
+fn shared_values() {
+	use crate::shared::SharedValue;
+
+	let value = object! { name: "kvon", servers: [{ port: 80 }, { port: 443 }] };
+	let shared = SharedValue::from(value.clone());
+	assert_eq!(shared.get("name").unwrap().get_string(), Ok("kvon"));
+
+	// clones share their contents
+	let mut copy = shared.clone();
+	assert!(copy.ptr_eq(&shared));
+	assert!(copy
+		.get("servers")
+		.unwrap()
+		.ptr_eq(shared.get("servers").unwrap()));
+
+	// modifying a clone copies only the modified path
+	copy.make_object_mut().unwrap().insert(
+		"name".to_string(),
+		SharedValue::from(Value::from("changed")),
+	);
+	assert!(!copy.ptr_eq(&shared));
+	assert!(copy
+		.get("servers")
+		.unwrap()
+		.ptr_eq(shared.get("servers").unwrap()));
+	assert_eq!(shared.get("name").unwrap().get_string(), Ok("kvon"));
+
+	let port = shared
+		.get("servers")
+		.and_then(|servers| servers.get_index(1))
+		.and_then(|server| server.get("port"));
+	assert_eq!(port.unwrap().get_number(), Ok(443.));
+
+	assert_eq!(Value::from(shared), value);
+}
+
+#[test]
+fn path_accessors() {
+	use crate::path::PathSegment;
+
+	let value = object! { a: { b: [1, { c: true }] } };
+	assert_eq!(
+		value.get("a").and_then(|a| a.get("b")).map(Value::len),
+		Some(2)
+	);
+	assert_eq!(value.get("b"), None);
+	assert_eq!(value.get_index(0), None);
+
+	let path = [
+		PathSegment::Key("a".to_string()),
+		PathSegment::Key("b".to_string()),
+		PathSegment::Index(1),
+		PathSegment::Key("c".to_string()),
+	];
+	assert_eq!(value.get_path(&path), Some(&Value::from(true)));
+	assert_eq!(value.get_path(&path[..0]), Some(&value));
+	assert_eq!(value.get_path(&path[1..]), None);
+}
+
+#[test]
+fn cancellation() {
+	use crate::{error::ErrorCategory, CancelToken, Parser, ParserOptions};
+
+	let token = CancelToken::new();
+	let mut parser = Parser::with_options(ParserOptions::new().with_cancel_token(token.clone()));
+	parser.next_line("a: 1").unwrap();
+	parser.next_line("b: 2").unwrap();
+
+	token.cancel();
+	let error = parser.next_line("c: 3").unwrap_err();
+	assert_eq!(error.kind, ParserErrorKind::Cancelled);
+	assert_eq!(error.line_number, 2);
+	assert_eq!(error.category(), ErrorCategory::Cancelled);
+	assert_eq!(error.code(), "E300_CANCELLED");
+}
+
+#[test]
+fn progress_callback() {
+	use crate::{
+		error::ParserErrorKind, parse_reader_with_options, parse_string_with_options,
+		ParserOptions, Progress,
+	};
+	use std::sync::{Arc, Mutex};
+
+	let reports = Arc::new(Mutex::new(Vec::new()));
+	let options = ParserOptions::new().progress({
+		let reports = Arc::clone(&reports);
+		move |progress| reports.lock().unwrap().push(progress)
+	});
+
+	let source = "a: 1\nb: 'two'\n";
+	parse_reader_with_options(source.as_bytes(), &options).unwrap();
+	assert_eq!(
+		*reports.lock().unwrap(),
+		vec![
+			Progress { bytes: 5, lines: 1 },
+			Progress {
+				bytes: 14,
+				lines: 2
+			},
+		]
+	);
+
+	// strings report the same, counting `\r\n` and a missing last line break
+	reports.lock().unwrap().clear();
+	let source = "a: 1\r\nb: 'two'";
+	assert_eq!(
+		parse_string_with_options(source, &options).unwrap(),
+		parse_reader_with_options(source.as_bytes(), &ParserOptions::new()).unwrap()
+	);
+	assert_eq!(
+		*reports.lock().unwrap(),
+		vec![
+			Progress { bytes: 6, lines: 1 },
+			Progress {
+				bytes: 14,
+				lines: 2
+			},
+		]
+	);
+
+	// readers hand lines to the parser without their line breaks too
+	for source in [
+		"s: |\n\tline1\n\tline2\nb: 1\n",
+		"a: 1\r\ns: |\r\n\tx\r\n\ty\r\nb: 'c'\r\n",
+	] {
+		let value = parse_string(source).unwrap();
+		assert_eq!(
+			parse_reader_with_options(source.as_bytes(), &ParserOptions::new()).unwrap(),
+			value
+		);
+	}
+	assert_eq!(
+		parse_reader_with_options("s: |\r\n\tx\r\n\ty\r\n".as_bytes(), &ParserOptions::new())
+			.unwrap(),
+		object! { s: "x\ny" }
+	);
+
+	let error = parse_reader_with_options(&b"a: 1\nb: '\xff'\n"[..], &options).unwrap_err();
+	assert!(matches!(error.kind, ParserErrorKind::Read(_)));
+	assert_eq!(error.code(), "E301_READ_FAILED");
+	assert_eq!(error.category(), crate::error::ErrorCategory::Io);
+	assert_eq!(error.line_number, 1);
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn derive_decode() {
+	use crate::{
+		decode::{Decode, DecodeErrorKind},
+		path::PathSegment,
+		KvonDecode,
+	};
+
+	fn default_port() -> u16 {
+		8080
+	}
+
+	#[derive(Debug, PartialEq, KvonDecode)]
+	struct Server {
+		host: String,
+		#[kvon(default = "default_port")]
+		port: u16,
+		#[kvon(rename = "max-connections", default)]
+		max_connections: u32,
+		tags: Option<Vec<String>>,
+	}
+
+	#[derive(Debug, PartialEq, KvonDecode)]
+	struct Config {
+		name: String,
+		servers: Vec<Server>,
+	}
+
+	let value = parse_string(
+		"name: 'api'
+servers:--
+	-
+		host: 'a'
+		max-connections: 10
+		tags: ['x']
+	-
+		host: 'b'
+		port: 81",
+	)
+	.unwrap();
+	assert_eq!(
+		Config::decode(&value).unwrap(),
+		Config {
+			name: "api".to_string(),
+			servers: vec![
+				Server {
+					host: "a".to_string(),
+					port: 8080,
+					max_connections: 10,
+					tags: Some(vec!["x".to_string()]),
+				},
+				Server {
+					host: "b".to_string(),
+					port: 81,
+					max_connections: 0,
+					tags: None,
+				},
+			],
+		}
+	);
+
+	let value = parse_string("name: 'api'\nservers:--\n\t-\n\t\tport: 1").unwrap();
+	let error = Config::decode(&value).unwrap_err();
+	assert_eq!(
+		error.kind,
+		DecodeErrorKind::MissingField("host".to_string())
+	);
+	assert_eq!(
+		error.path,
+		vec![
+			PathSegment::Key("servers".to_string()),
+			PathSegment::Index(0)
+		]
+	);
+	assert_eq!(error.to_string(), "servers[0]: missing field 'host'");
+
+	let value = parse_string("name: 'api'\nservers:--\n\t-\n\t\thost: 'a'\n\t\tport: -1").unwrap();
+	let error = Config::decode(&value).unwrap_err();
+	assert_eq!(error.to_string(), "servers[0].port: -1 is not a valid u16");
+}
+
+#[test]
+fn decode_integer_bounds() {
+	use crate::decode::Decode;
+
+	// the extremes of each type that are floats, and the closest floats out
+	// of range
+	macro_rules! assert_bounds {
+		($t:ty, [$($valid:expr),*], [$($invalid:expr),*]) => {
+			$(assert_eq!(<$t>::decode(&Value::from($valid as f32)).unwrap() as f64, $valid);)*
+			$(assert!(
+				<$t>::decode(&Value::from($invalid as f32)).is_err(),
+				"{} decoded into a {}",
+				$invalid,
+				stringify!($t)
+			);)*
+		};
+	}
+
+	assert_bounds!(u8, [0.0, 255.0], [-1.0, 256.0, 1.5]);
+	assert_bounds!(i8, [-128.0, 127.0], [-129.0, 128.0]);
+	assert_bounds!(u16, [0.0, 65535.0], [-1.0, 65536.0]);
+	assert_bounds!(i16, [-32768.0, 32767.0], [-32769.0, 32768.0]);
+	assert_bounds!(u32, [0.0, 4294967040.0], [-1.0, 4294967296.0]);
+	assert_bounds!(
+		i32,
+		[-2147483648.0, 2147483520.0],
+		[-2147483904.0, 2147483648.0]
+	);
+	assert_bounds!(
+		u64,
+		[0.0, 18446742974197923840.0],
+		[-1.0, 18446744073709551616.0]
+	);
+	assert_bounds!(
+		i64,
+		[-9223372036854775808.0, 9223371487098961920.0],
+		[-9223373136366403584.0, 9223372036854775808.0]
+	);
+	#[cfg(target_pointer_width = "64")]
+	{
+		assert_bounds!(
+			usize,
+			[0.0, 18446742974197923840.0],
+			[-1.0, 18446744073709551616.0]
+		);
+		assert_bounds!(
+			isize,
+			[-9223372036854775808.0, 9223371487098961920.0],
+			[-9223373136366403584.0, 9223372036854775808.0]
+		);
+	}
+	assert!(u32::decode(&Value::from(f32::NAN)).is_err());
+	assert!(u64::decode(&Value::from(f32::INFINITY)).is_err());
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn derive_encode() {
+	use crate::{decode::Decode, encode::Encode, KvonDecode, KvonEncode, KvonWriter};
+
+	#[derive(Debug, PartialEq, KvonEncode, KvonDecode)]
+	struct Metadata {
+		author: String,
+		version: u32,
+	}
+
+	#[derive(Debug, PartialEq, KvonEncode, KvonDecode)]
+	struct Document {
+		title: String,
+		#[kvon(rename = "page-count")]
+		pages: u32,
+		#[kvon(skip_if_none)]
+		summary: Option<String>,
+		#[kvon(flatten)]
+		metadata: Metadata,
+		tags: Vec<String>,
+	}
+
+	let document = Document {
+		title: "KVON".to_string(),
+		pages: 12,
+		summary: None,
+		metadata: Metadata {
+			author: "roy".to_string(),
+			version: 2,
+		},
+		tags: vec!["format".to_string()],
+	};
+
+	let value = document.encode();
+	assert_eq!(
+		value,
+		object! {
+			title: "KVON",
+			"page-count": 12,
+			author: "roy",
+			version: 2,
+			tags: ["format"],
+		}
+	);
+	assert_eq!(Document::decode(&value).unwrap(), document);
+
+	// writing directly keeps the declaration order
+	let mut writer = KvonWriter::new(Vec::new()).unwrap();
+	document.write_entries(&mut writer).unwrap();
+	let output = String::from_utf8(writer.finish().unwrap()).unwrap();
+	assert_eq!(
+		output,
+		"title: 'KVON'\npage-count: 12\nauthor: 'roy'\nversion: 2\ntags:--\n\t- 'format'\n"
+	);
+	assert_eq!(parse_string(&output).unwrap(), value);
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn derive_enums() {
+	use crate::{decode::Decode, encode::Encode, KvonDecode, KvonEncode};
+
+	#[derive(Debug, PartialEq, KvonEncode, KvonDecode)]
+	enum Color {
+		Red,
+		#[kvon(rename = "rgb")]
+		Rgb(Vec<u8>),
+		Named {
+			name: String,
+		},
+	}
+
+	#[derive(Debug, PartialEq, KvonEncode, KvonDecode)]
+	#[kvon(tag = "type")]
+	enum Shape {
+		Circle { radius: f32 },
+		Empty,
+	}
+
+	#[derive(Debug, PartialEq, KvonEncode, KvonDecode)]
+	#[kvon(untagged)]
+	enum Port {
+		Number(u16),
+		Name(String),
+		Disabled,
+	}
+
+	let cases = [
+		(Color::Red.encode(), Value::from("Red")),
+		(Color::Rgb(vec![1, 2]).encode(), object! { rgb: [1, 2] }),
+		(
+			Color::Named {
+				name: "teal".to_string(),
+			}
+			.encode(),
+			object! { Named: { name: "teal" } },
+		),
+		(
+			Shape::Circle { radius: 1. }.encode(),
+			object! { type: "Circle", radius: 1 },
+		),
+		(Shape::Empty.encode(), object! { type: "Empty" }),
+		(Port::Number(80).encode(), Value::from(80)),
+		(Port::Name("http".to_string()).encode(), Value::from("http")),
+		(Port::Disabled.encode(), Value::null()),
+	];
+	for (encoded, expected) in &cases {
+		assert_eq!(encoded, expected);
+	}
+
+	assert_eq!(Color::decode(&cases[0].1).unwrap(), Color::Red);
+	assert_eq!(Color::decode(&cases[1].1).unwrap(), Color::Rgb(vec![1, 2]));
+	assert_eq!(
+		Color::decode(&cases[2].1).unwrap(),
+		Color::Named {
+			name: "teal".to_string()
+		}
+	);
+	assert_eq!(
+		Shape::decode(&cases[3].1).unwrap(),
+		Shape::Circle { radius: 1. }
+	);
+	assert_eq!(Shape::decode(&cases[4].1).unwrap(), Shape::Empty);
+	assert_eq!(Port::decode(&cases[5].1).unwrap(), Port::Number(80));
+	assert_eq!(
+		Port::decode(&cases[6].1).unwrap(),
+		Port::Name("http".to_string())
+	);
+	assert_eq!(Port::decode(&cases[7].1).unwrap(), Port::Disabled);
+
+	let error = Color::decode(&Value::from("Blue")).unwrap_err();
+	assert_eq!(
+		error.to_string(),
+		"unknown variant 'Blue', expected one of: Red, rgb, Named"
+	);
+	let error = Color::decode(&object! { rgb: [1, 300] }).unwrap_err();
+	assert_eq!(error.to_string(), "rgb[1]: 300 is not a valid u8");
+	assert!(Port::decode(&Value::from(true)).is_err());
+}
+
+#[test]
+fn comments() {
+	use crate::{
+		path::{KvonPath, Span},
+		Comment, Parser,
+	};
+
+	let mut parser = Parser::new().with_comments();
+	for line in
+		"# header\na: 1 # one\nb: |\n\t# not a comment\nc:-- #list\n\t- 2\n\t\t# nested".lines()
+	{
+		parser.next_line(line).unwrap();
+	}
+	assert_eq!(
+		parser.comments(),
+		[
+			("header", 0, 0),
+			("one", 1, 5),
+			("list", 4, 5),
+			("nested", 6, 2)
+		]
+		.map(|(text, line_number, column_number)| Comment {
+			span: Span {
+				line_number,
+				column_number
+			},
+			text: text.to_string(),
+			path: None
+		})
+		.map(|comment| Comment {
+			path: match comment.text.as_str() {
+				"one" => Some(KvonPath::root().key("a")),
+				"list" => Some(KvonPath::root().key("c")),
+				_ => None,
+			},
+			..comment
+		})
+	);
+	assert_eq!(
+		parser.finish().unwrap(),
+		object! { a: 1, b: "# not a comment", c: [2] }
+	);
+
+	// disabled by default
+	let mut parser = Parser::new();
+	parser.next_line("# header").unwrap();
+	assert!(parser.comments().is_empty());
+}
+
+#[test]
+fn encoded_comments() {
+	use crate::Parser;
+
+	let mut parser = Parser::new().with_comments();
+	for line in "server:\n\thost: 'localhost' # local only\n\tports: [80] # http\nnotes: | # free text\n\tline 1\n\tline 2".lines() {
+		parser.next_line(line).unwrap();
+	}
+	let comments = parser.comments().to_vec();
+	let mut value = parser.finish().unwrap();
+	value.redact_path("server.host", "example.com");
+
+	let options = EncodeOptions::new()
+		.comments(comments)
+		.comment("server", "set by installer");
+	let encoded = encode_string(&value, &options);
+	assert_eq!(
+		encoded,
+		"\nnotes: | # free text\n\tline 1\n\tline 2\nserver: # set by installer\n\thost: 'example.com' # local only\n\tports: [80] # http"
+	);
+	assert_eq!(parse_string(&encoded).unwrap(), value);
+}
+
+#[test]
+fn comment_prefixes_and_directives() {
+	use crate::{parse_string_with_options, Directive, Parser, ParserOptions};
+
+	let options = ParserOptions::new().comment_prefixes(["//", ";"]);
+	let source = "// migrated\na: 1 // one\n; section\nb:\n\tc: 'x' ; two";
+	assert_eq!(
+		parse_string_with_options(source, &options).unwrap(),
+		object! { a: 1, b: { c: "x" } }
+	);
+	assert!(parse_string(source).is_err());
+
+	let mut parser = Parser::with_options(options).with_comments();
+	for line in
+		"#!/usr/bin/env app\n#kvon: schema app.kvon\n#kvon:strict\n// note\na: 1\n#kvon: late"
+			.lines()
+	{
+		parser.next_line(line).unwrap();
+	}
+	assert_eq!(parser.shebang(), Some("/usr/bin/env app"));
+	assert_eq!(
+		parser.directives(),
+		[
+			Directive {
+				name: "schema".to_string(),
+				value: "app.kvon".to_string(),
+				line_number: 1
+			},
+			Directive {
+				name: "strict".to_string(),
+				value: String::new(),
+				line_number: 2
+			}
+		]
+	);
+	// directives after the first key are plain comments
+	let comments: Vec<_> = parser.comments().iter().map(|c| c.text.as_str()).collect();
+	assert_eq!(comments, ["note", "kvon: late"]);
+}
+
+#[test]
+fn version_directive() {
+	use crate::{parse_string_with_options, FormatVersion, Parser, ParserOptions};
+
+	let mut parser = Parser::new();
+	for line in "#!/usr/bin/env app\n#kvon 1.0\na: 1".lines() {
+		parser.next_line(line).unwrap();
+	}
+	assert_eq!(parser.version(), Some(FormatVersion::LATEST));
+	assert_eq!(parser.finish().unwrap(), object! { a: 1 });
+
+	let error = parse_string("#kvon 1.1\na: 1").unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::UnsupportedVersion(FormatVersion { major: 1, minor: 1 })
+	);
+	assert_eq!(error.code(), "E105_UNSUPPORTED_VERSION");
+	assert_eq!(
+		error.to_string(),
+		"0:0: unsupported KVON version 1.1 (hint: this parser supports KVON up to version 1.0, update it to read this document)"
+	);
+
+	let options = ParserOptions::new().allow_newer_versions(true);
+	assert_eq!(
+		parse_string_with_options("#kvon 2.0\na: 1", &options).unwrap(),
+		object! { a: 1 }
+	);
+
+	let error = parse_string("#kvon one").unwrap_err();
+	assert_eq!(
+		error.to_string(),
+		"0:6: unexpected 'one', expected version (hint: declare the version as major and minor number, e.g. `#kvon 1.0`)"
+	);
+
+	// only the header declares a version
+	assert_eq!(parse_string("a: 1\n#kvon 2.0").unwrap(), object! { a: 1 });
+}
+
+#[test]
+fn parse_output() {
+	use crate::{
+		indention::Indention, parse_string_output, path::KvonPath, FormatVersion, ParserOptions,
+		Warning,
+	};
+
+	let source = "#kvon 1.0\nserver:\n    port: 80 # default\n    port: 8080\nname: 'app'";
+	let output = parse_string_output(source, &ParserOptions::new()).unwrap();
+	assert_eq!(
+		output.value,
+		object! { server: { port: 8080 }, name: "app" }
+	);
+	assert_eq!(output.version(), Some(FormatVersion::LATEST));
+	assert_eq!(output.detected_indention, Some(Indention::Spaces(4)));
+	assert_eq!(output.line_count, 5);
+	assert_eq!(
+		output.warnings,
+		[Warning::DuplicateKey {
+			path: KvonPath::root().key("server").key("port"),
+			line_number: 3
+		}]
+	);
+
+	// re-encoding keeps the style of the document
+	assert_eq!(
+		encode_string(&output.value, &output.encode_options()),
+		"\nname: 'app'\nserver:\n    port: 8080 # default"
+	);
+}
+
+#[test]
+fn primitive_parser_hooks() {
+	use crate::{
+		parse_string_with_options, scanner::Scanner, value::PrimitiveValue, ParserOptions,
+	};
+
+	// `12s` as a custom scalar, with a hook that consumes the digits of
+	// other numbers before giving up
+	let options = ParserOptions::new()
+		.primitive_parser(|scanner: &mut Scanner| {
+			let digits = scanner
+				.rest()
+				.find(|c: char| !c.is_ascii_digit())
+				.unwrap_or(scanner.rest().len());
+			let seconds: f32 = match scanner.rest()[..digits].parse() {
+				Ok(seconds) => seconds,
+				Err(_) => return Ok(None),
+			};
+			scanner.advance_by(digits);
+			if !scanner.have("s") {
+				return Ok(None);
+			}
+			Ok(Some(PrimitiveValue::custom(Seconds(seconds)).unwrap()))
+		})
+		.primitive_parser(|scanner: &mut Scanner| {
+			Ok(scanner
+				.have("yes!")
+				.then_some(PrimitiveValue::Boolean(true)))
+		});
+
+	let value = parse_string_with_options("a: 12s\nb: [12 yes!]\nc: 'x'", &options).unwrap();
+	let Value::Object(obj) = &value else {
+		panic!("expected an object");
+	};
+	assert_eq!(
+		obj["a"].get_primitive().unwrap().get_custom::<Seconds>(),
+		Ok(&Seconds(12.))
+	);
+	assert_eq!(obj["b"], array![12, true]);
+	assert_eq!(obj["c"], Value::from("x"));
+}
+
+#[test]
+fn scanner_key_errors_drop_records() {
+	use crate::{scanner::Scanner, ParserOptions};
+
+	// a failed key leaves the positions recorded before it intact
+	let options = ParserOptions::new();
+	for parse in 0..3 {
+		let mut scanner = Scanner::new(0, "x 'unclosed", &options);
+		scanner.record();
+		scanner.advance_by(2);
+		let failed = match parse {
+			0 => scanner.parse_key_path().is_err(),
+			1 => scanner.parse_key_path_with_colon().is_err(),
+			_ => scanner.parse_key_with_colon().is_err(),
+		};
+		assert!(failed);
+		scanner.restore();
+		assert_eq!(scanner.rest(), "x 'unclosed");
+	}
+}
+
+#[cfg(all(feature = "uuid", feature = "ipaddr"))]
+#[test]
+fn address_literals() {
+	use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+	use crate::value::PrimitiveValue;
+
+	let object = parse_string(
+		"id: 67E55044-10B1-426F-9247-BB680E5FE0C8\nhosts: [10.0.0.1 fe80:0::1]\nport: 80",
+	)
+	.unwrap();
+	let objects = object.get_objects().unwrap();
+	assert_eq!(
+		objects["id"].get_primitive().unwrap().get_uuid(),
+		Ok(uuid::Uuid::from_u128(
+			0x67e55044_10b1_426f_9247_bb680e5fe0c8
+		))
+	);
+	assert_eq!(
+		objects["hosts"],
+		array![
+			PrimitiveValue::Ip(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
+			PrimitiveValue::Ip(IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1)))
+		]
+	);
+
+	// encoded canonically
+	let encoded = encode_string(&object, &EncodeOptions::new());
+	assert_eq!(
+		encoded,
+		"\nhosts: [10.0.0.1 fe80::1]\nid: 67e55044-10b1-426f-9247-bb680e5fe0c8\nport: 80"
+	);
+	assert_eq!(parse_string(&encoded).unwrap(), object);
+
+	let error = parse_string("host: 10.0.0.300").unwrap_err();
+	assert_eq!(error.to_string(), "0:6: invalid IP address '10.0.0.300'");
+	assert_eq!(error.code(), "E202_INVALID_LITERAL");
+	assert!(parse_string("id: 67e55044-10b1-426f-9247-bb680e5fe0cx").is_err());
+
+	// plain numbers are unaffected
+	assert_eq!(parse_string("a: 1.5").unwrap(), object! { a: 1.5 });
+}
+
+#[cfg(feature = "semver")]
+#[test]
+fn version_literals() {
+	use semver::{Version, VersionReq};
+
+	let mut object = parse_string("deps: [2.0.0 1.10.0 1.2.3-alpha+build 1.2.3]").unwrap();
+	let Value::Object(obj) = &mut object else {
+		panic!("expected an object");
+	};
+	let Value::Array(deps) = obj.get_mut("deps").unwrap() else {
+		panic!("expected an array");
+	};
+	assert_eq!(
+		deps[2].get_primitive().unwrap().get_version(),
+		Ok(&Version::parse("1.2.3-alpha+build").unwrap())
+	);
+
+	// sorted by precedence instead of as strings
+	deps.sort();
+	let encoded = encode_string(&object, &EncodeOptions::new());
+	assert_eq!(encoded, "\ndeps: [1.2.3-alpha+build 1.2.3 1.10.0 2.0.0]");
+	assert_eq!(parse_string(&encoded).unwrap(), object);
+
+	let requirement = VersionReq::parse("^1.2").unwrap();
+	let matching: Vec<_> = object.get_objects().unwrap()["deps"]
+		.get_vector()
+		.unwrap()
+		.iter()
+		.filter(|v| v.get_primitive().unwrap().matches_version(&requirement))
+		.collect();
+	assert_eq!(matching.len(), 2);
+
+	let error = parse_string("v: 1.02.3").unwrap_err();
+	assert_eq!(error.to_string(), "0:3: invalid version '1.02.3'");
+	assert_eq!(parse_string("a: 1.5").unwrap(), object! { a: 1.5 });
+}
+
+#[test]
+fn schema_string_constraints() {
+	use crate::schema::{Constraint, Schema, SchemaRef};
+
+	fn name() -> Schema {
+		Schema::String
+			.constrain(Constraint::MinLength(2))
+			.constrain(Constraint::MaxLength(4))
+	}
+	fn id() -> Schema {
+		Schema::String.constrain(Constraint::pattern("^[0-9]+$").unwrap())
+	}
+	fn env() -> Schema {
+		Schema::String.constrain(Constraint::OneOf(vec!["dev".into(), "prod".into()]))
+	}
+	fn ids() -> Schema {
+		Schema::Array(SchemaRef::Fn(id))
+	}
+	let schema = Schema::object([("name", name as fn() -> Schema), ("ids", ids), ("env", env)]);
+
+	let valid = parse_string("name: 'äöü'\nids: ['1' '23']\nenv: 'dev'").unwrap();
+	assert!(schema.validate(&valid).is_empty());
+	assert!(schema.unknown_keys(&valid).is_empty());
+
+	let invalid = parse_string("name: 'a'\nids: ['1' 'x2']\nenv: 'staging'").unwrap();
+	let violations: Vec<_> = schema
+		.validate(&invalid)
+		.iter()
+		.map(ToString::to_string)
+		.collect();
+	assert_eq!(
+		violations,
+		[
+			"name: 'a' is shorter than 2 characters",
+			"ids[1]: 'x2' doesn't match pattern '^[0-9]+$'",
+			"env: 'staging' is not one of 'dev', 'prod'",
+		]
+	);
+
+	// constraints only apply to values of their type
+	assert!(name().validate(&Value::from(12.)).is_empty());
+	assert!(Constraint::pattern("(").is_err());
+}
+
+#[test]
+fn schema_number_constraints() {
+	use crate::schema::{Constraint, Schema};
+
+	fn port() -> Schema {
+		Schema::Number
+			.constrain(Constraint::Minimum(1.))
+			.constrain(Constraint::Maximum(65535.))
+			.constrain(Constraint::MultipleOf(1.))
+	}
+	fn ratio() -> Schema {
+		Schema::Number
+			.constrain(Constraint::ExclusiveMinimum(0.))
+			.constrain(Constraint::ExclusiveMaximum(1.))
+			.constrain(Constraint::MultipleOf(0.1))
+	}
+	let schema = Schema::object([("port", port as fn() -> Schema), ("ratio", ratio)]);
+
+	let valid = parse_string("port: 65535\nratio: 0.3").unwrap();
+	assert!(schema.validate(&valid).is_empty());
+
+	let invalid = parse_string("port: 80.5\nratio: 1").unwrap();
+	let violations: Vec<_> = schema
+		.validate(&invalid)
+		.iter()
+		.map(ToString::to_string)
+		.collect();
+	assert_eq!(
+		violations,
+		[
+			"port: 80.5 is not a multiple of 1",
+			"ratio: 1 is not less than 1",
+		]
+	);
+
+	let invalid = parse_string("port: 0\nratio: 0.25").unwrap();
+	let violations: Vec<_> = schema
+		.validate(&invalid)
+		.iter()
+		.map(ToString::to_string)
+		.collect();
+	assert_eq!(
+		violations,
+		[
+			"port: 0 is less than 1",
+			"ratio: 0.25 is not a multiple of 0.1",
+		]
+	);
+	assert!(port().validate(&Value::from("0")).is_empty());
+}
+
+#[test]
+fn schema_infer() {
+	use crate::schema::Schema;
+
+	let example = parse_string(
+		"\
+name: 'app'
+servers:--
+	-
+		host: 'a'
+		port: 80
+	-
+		host: 'b'
+		port: 8080
+		tls: true
+	-
+		host: 'c'
+		port: 443
+		tls: null
+",
+	)
+	.unwrap();
+	let schema = Schema::infer(&example);
+
+	let Schema::Object { fields, open } = &schema else {
+		panic!("expected an object schema, got {schema:?}");
+	};
+	assert!(!open);
+	let keys: Vec<_> = fields.iter().map(|(key, _)| key.as_ref()).collect();
+	assert_eq!(keys, ["name", "servers"]);
+	let Schema::Array(server) = &*fields[1].1.get() else {
+		panic!("expected an array schema");
+	};
+	let Schema::Object { fields, .. } = &*server.get() else {
+		panic!("expected an object schema");
+	};
+	let keys: Vec<_> = fields.iter().map(|(key, _)| key.as_ref()).collect();
+	assert_eq!(keys, ["host", "port", "tls"]);
+	assert!(matches!(*fields[2].1.get(), Schema::Optional(_)));
+
+	// the observed range of numbers is kept
+	assert!(schema.validate(&example).is_empty());
+	let other = parse_string("name: 'app'\nservers:--\n\t-\n\t\thost: 'd'\n\t\tport: 22").unwrap();
+	let violations: Vec<_> = schema
+		.validate(&other)
+		.iter()
+		.map(ToString::to_string)
+		.collect();
+	assert_eq!(violations, ["servers[0].port: 22 is less than 80"]);
+	let warnings = schema.unknown_keys(&parse_string("nmae: 'app'").unwrap());
+	assert_eq!(warnings[0].suggestion.as_deref(), Some("name"));
+
+	// differing types accept anything
+	let mixed = [Value::from(1.), Value::from("a")];
+	assert!(matches!(Schema::infer_all(&mixed), Schema::Any));
+}
+
+#[test]
+fn json_schema() {
+	use crate::{
+		decode::Decode,
+		json_schema::{from_json_schema, to_json_schema},
+		schema::{Constraint, Schema, SchemaRef},
+	};
+
+	fn env() -> Schema {
+		Schema::String.constrain(Constraint::OneOf(vec!["dev".into(), "prod".into()]))
+	}
+	fn port() -> Schema {
+		u16::schema()
+			.constrain(Constraint::MultipleOf(1.))
+			.constrain(Constraint::Minimum(1.))
+	}
+	fn ports() -> Schema {
+		Schema::Array(SchemaRef::Fn(port))
+	}
+	let schema = Schema::object([
+		("env", env as fn() -> Schema),
+		("ports", ports),
+		("name", Option::<String>::schema),
+	]);
+
+	let json = to_json_schema(&schema);
+	let expected = object! {
+		"$schema": "https://json-schema.org/draft/2020-12/schema",
+		type: "object",
+		properties: {
+			env: { type: "string", enum: ["dev", "prod"] },
+			ports: { type: "array", items: { type: "integer", minimum: 1 } },
+			name: { anyOf: [{ type: "string" }, { type: "null" }] },
+		},
+		required: ["env", "ports"],
+		additionalProperties: false,
+	};
+	assert_eq!(json, expected);
+
+	let imported = from_json_schema(&json).unwrap();
+	assert_eq!(to_json_schema(&imported), json);
+	let value = parse_string("env: 'test'\nports: [0 80.5]\nnmae: null").unwrap();
+	let violations: Vec<_> = imported
+		.validate(&value)
+		.iter()
+		.map(ToString::to_string)
+		.collect();
+	assert_eq!(
+		violations,
+		[
+			"env: 'test' is not one of 'dev', 'prod'",
+			"ports[0]: 0 is less than 1",
+			"ports[1]: 80.5 is not a multiple of 1",
+		]
+	);
+	assert_eq!(
+		imported.unknown_keys(&value)[0].to_string(),
+		"unknown key 'nmae', did you mean 'name'?"
+	);
+
+	// references to definitions, including recursive ones
+	let json = object! {
+		"$ref": "#/$defs/node",
+		"$defs": {
+			node: {
+				type: "object",
+				properties: {
+					children: { type: "array", items: { "$ref": "#/$defs/node" } },
+				},
+			},
+		},
+	};
+	let tree = from_json_schema(&json).unwrap();
+	let value = object! { children: [{ children: [] }], extra: 1 };
+	assert!(tree.unknown_keys(&value).is_empty());
+
+	let error = from_json_schema(&object! {
+		type: "object",
+		properties: { id: { type: "string", pattern: "(" } },
+	})
+	.unwrap_err();
+	assert!(error
+		.to_string()
+		.starts_with("/properties/id: invalid pattern"));
+	let error = from_json_schema(&object! { type: "date" }).unwrap_err();
+	assert_eq!(error.to_string(), "unknown type 'date'");
+}
+
+#[test]
+fn codegen() {
+	use crate::{
+		codegen::{generate, object_literal, rust_structs},
+		schema::Schema,
+	};
+
+	let value = parse_string(
+		"\
+name: 'app'
+type: 'web'
+servers:--
+	-
+		host: 'a'
+		port: 80
+	-
+		host: 'b'
+		tls: true
+",
+	)
+	.unwrap();
+	assert_eq!(
+		generate("config", &value),
+		"\
+#[derive(Debug, Clone, kvon_rs::KvonDecode, kvon_rs::KvonEncode)]
+pub struct Config {
+    pub name: String,
+    pub servers: Vec<ServersItem>,
+    #[kvon(rename = \"type\")]
+    pub type_: String,
+}
+
+#[derive(Debug, Clone, kvon_rs::KvonDecode, kvon_rs::KvonEncode)]
+pub struct ServersItem {
+    pub host: String,
+    pub port: Option<i64>,
+    pub tls: Option<bool>,
+}
+
+pub fn example_config() -> kvon_rs::value::Value {
+    kvon_rs::object! {
+        name: \"app\",
+        servers: [{
+            host: \"a\",
+            port: 80,
+        }, {
+            host: \"b\",
+            tls: true,
+        }],
+        type: \"web\",
+    }
+}
+"
+	);
+
+	// keys without letters or digits get a unique name
+	let value = object! { "": 1, "-": 2, field: 3 };
+	assert_eq!(
+		rust_structs("keys", &Schema::infer(&value)),
+		"\
+#[derive(Debug, Clone, kvon_rs::KvonDecode, kvon_rs::KvonEncode)]
+pub struct Keys {
+    #[kvon(rename = \"\")]
+    pub field_: i64,
+    #[kvon(rename = \"-\")]
+    pub field__: i64,
+    pub field: i64,
+}
+"
+	);
+
+	let value = object! { "http-port": 0.5, list: [null, [1], {}] };
+	assert_eq!(
+		object_literal(&value),
+		"kvon_rs::object! {\n    \"http-port\": 0.5,\n    list: [null, [1], {}],\n}"
+	);
+}
+
+#[test]
+fn render() {
+	use crate::render::{to_ansi, to_html};
+
+	let value = object! { server: { tls: true, proxy: null }, tags: ["<a>", 2] };
+	assert_eq!(
+		to_ansi(&value),
+		"\
+\x1b[34mserver\x1b[0m:
+    \x1b[34mproxy\x1b[0m: \x1b[2mnull\x1b[0m
+    \x1b[34mtls\x1b[0m: \x1b[35mtrue\x1b[0m
+\x1b[34mtags\x1b[0m: [\x1b[32m'<a>'\x1b[0m \x1b[33m2\x1b[0m]
+"
+	);
+	assert_eq!(
+		to_html(&value),
+		"<div class=\"kvon\">\
+<details open><summary><span class=\"kvon-key\">server</span>:</summary>\
+<div>    <span class=\"kvon-key\">proxy</span>: <span class=\"kvon-null\">null</span></div>\
+<div>    <span class=\"kvon-key\">tls</span>: <span class=\"kvon-boolean\">true</span></div>\
+</details>\
+<div><span class=\"kvon-key\">tags</span>: [<span class=\"kvon-string\">'&lt;a&gt;'</span> <span class=\"kvon-number\">2</span>]</div>\
+</div>"
+	);
+
+	let value = array![{ text: "a\nb" }];
+	assert_eq!(
+		to_ansi(&value),
+		"-\n    \x1b[34mtext\x1b[0m: |\n        \x1b[32ma\x1b[0m\n        \x1b[32mb\x1b[0m\n"
+	);
+}
+
+#[test]
+fn flatten() {
+	let value = object! {
+		server: { host: "localhost", ports: [80, 443] },
+		empty: {},
+		list: [],
+		name: "app",
+	};
+	let flat = value.flatten(".");
+	assert_eq!(
+		flat,
+		object! {
+			"server.host": "localhost",
+			"server.ports.0": 80,
+			"server.ports.1": 443,
+			empty: {},
+			list: [],
+			name: "app",
+		}
+	);
+	assert_eq!(flat.unflatten("."), value);
+	assert_eq!(value.flatten("__").unflatten("__"), value);
+
+	// keys that aren't consecutive indices stay objects
+	let flat = object! { "a.0": 1, "a.2": 2, "b.1": 3, c: 4, "c.d": 5 };
+	assert_eq!(
+		flat.unflatten("."),
+		object! { a: { "0": 1, "2": 2 }, b: { "1": 3 }, c: { d: 5 } }
+	);
+	assert_eq!(Value::from(1.).flatten("."), Value::from(1.));
+}
+
+#[test]
+fn env_vars() {
+	use crate::env::{from_vars, to_env_vars};
+
+	let config = object! {
+		server: { host: "localhost", port: 8080, max_connections: 100 },
+		"log-level": "debug",
+		tags: ["a", "b"],
+		debug: false,
+		proxy: null,
+		empty: {},
+	};
+	let vars = to_env_vars(&config, "app");
+	let names: Vec<_> = vars
+		.iter()
+		.map(|(name, value)| format!("{name}={value}"))
+		.collect();
+	assert_eq!(
+		names,
+		[
+			"APP_DEBUG=false",
+			"APP_LOG__LEVEL=debug",
+			"APP_PROXY=null",
+			"APP_SERVER_HOST=localhost",
+			"APP_SERVER_MAX__CONNECTIONS=100",
+			"APP_SERVER_PORT=8080",
+			"APP_TAGS_0=a",
+			"APP_TAGS_1=b",
+		]
+	);
+
+	// other variables are ignored, and `-` is read back as `_`
+	let vars = vars
+		.into_iter()
+		.chain([("PATH".to_string(), "/bin".to_string())]);
+	assert_eq!(
+		from_vars("app", vars),
+		object! {
+			server: { host: "localhost", port: 8080, max_connections: 100 },
+			log_level: "debug",
+			tags: ["a", "b"],
+			debug: false,
+			proxy: null,
+		}
+	);
+	assert_eq!(
+		from_vars("", [("A_B".to_string(), " 1".to_string())]),
+		object! { a: { b: " 1" } }
+	);
+}
+
+#[cfg(unix)]
+#[test]
+fn env_vars_not_unicode() {
+	use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+	use crate::env::from_env_vars;
+
+	// unrelated variables that aren't valid Unicode don't make reading fail
+	std::env::set_var("KVON_TEST_INVALID", OsStr::from_bytes(b"\xff"));
+	std::env::set_var("KVONTEST_PORT", "80");
+	std::env::set_var("KVONTEST_NAME", OsStr::from_bytes(b"\xfe"));
+	assert_eq!(from_env_vars("kvontest"), object! { port: 80 });
+}
+
+#[test]
+fn dotted_keys() {
+	use crate::{parse_string_with_options, ParserOptions};
+
+	let options = ParserOptions::new().dotted_keys(true);
+	let source = "\
+server.host: 'localhost'
+server.tls.enabled: true
+'a.b': 1
+items:--
+	- x.y: 2
+	- z.w:
+		v: 3
+";
+	let value = parse_string_with_options(source, &options).unwrap();
+	let expected = object! {
+		server: { host: "localhost", tls: { enabled: true } },
+		"a.b": 1,
+		items: [{ x: { y: 2 } }, { z: { w: { v: 3 } } }],
+	};
+	assert_eq!(value, expected);
+
+	// without the option, dots are part of keys
+	let value = parse_string("server.host: 'localhost'").unwrap();
+	assert_eq!(value, object! { "server.host": "localhost" });
+
+	let error = parse_string_with_options("a..b: 1", &options).unwrap_err();
+	assert_eq!(error.column_number, 0);
+	assert_eq!(
+		parse_string_with_options("a:--\n\t- .5", &options).unwrap(),
+		object! { a: [0.5] }
+	);
+
+	let options = EncodeOptions::new().dotted_keys(true);
+	let value = object! {
+		server: { tls: { enabled: true } },
+		db: { host: "a", port: 1 },
+		"a.b": 1,
+	};
+	let encoded = encode_string(&value, &options);
+	assert_eq!(
+		encoded,
+		"\n'a.b': 1\ndb:\n\thost: 'a'\n\tport: 1\nserver.tls.enabled: true"
+	);
+	let parsed =
+		parse_string_with_options(&encoded, &ParserOptions::new().dotted_keys(true)).unwrap();
+	assert_eq!(parsed, value);
+}
+
+#[test]
+fn key_conflicts() {
+	use crate::{parse_string_with_options, value::UnflattenConflict, ParserOptions};
+
+	let options = ParserOptions::new().dotted_keys(true);
+	let error = parse_string_with_options("a: 1\nb: 2\na.b: 3", &options).unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::KeyConflict {
+			path: "a.b".to_string(),
+			other_line_number: 0,
+		}
+	);
+	assert_eq!(error.line_number, 2);
+	assert_eq!(error.code(), "E106_KEY_CONFLICT");
+
+	// replacing the object created by dotted keys
+	let error = parse_string_with_options("x:\n\ta.b: 1\n\ta:\n\t\tc: 2", &options).unwrap_err();
+	assert_eq!(
+		error.to_string(),
+		"2:1: key 'x.a' conflicts with the value on line 1 \
+		(hint: this would replace the keys nested under 'x.a' by dotted keys)"
+	);
+
+	// extending objects and plain duplicates are fine
+	let source = "a:\n\tb: 1\na.c: 2\nd.e: 3\nd.f: 4\nd.e: 5";
+	let output = crate::parse_string_output(source, &options).unwrap();
+	assert_eq!(
+		output.value,
+		object! { a: { b: 1, c: 2 }, d: { e: 5, f: 4 } }
+	);
+	assert_eq!(output.warnings.len(), 1);
+
+	let flat = object! { a: 1, "a.b": 2 };
+	assert_eq!(
+		flat.try_unflatten("."),
+		Err(UnflattenConflict {
+			key: "a.b".to_string(),
+			prefix: "a".to_string(),
+		})
+	);
+	assert_eq!(flat.unflatten("."), object! { a: { b: 2 } });
+	let flat = object! { a: {}, "a.b": 2 };
+	assert_eq!(flat.try_unflatten("."), Ok(object! { a: { b: 2 } }));
+}
+
+#[test]
+fn array_of_objects() {
+	let value =
+		parse_string("servers:--\n\t- host: 'a'\n\t-\n\t\thost: 'b'\n\t\tport: 80").unwrap();
+	let servers = value.get_array_of_objects("servers").unwrap();
+	assert_eq!(servers.len(), 2);
+	assert_eq!(servers[1]["port"], Value::from(80));
+
+	let value = parse_string(MULTI_LINE_ARRAYS_B).unwrap();
+	let error = value.get_array_of_objects("arr").unwrap_err();
+	assert_eq!(error.to_string(), "arr[0]: expected object, found number");
+	assert_eq!(
+		value
+			.get_array_of_objects("missing")
+			.unwrap_err()
+			.to_string(),
+		"missing field 'missing'"
+	);
+}
+
+#[test]
+fn fixed_size_arrays() {
+	use crate::{decode::Decode, encode::Encode};
+
+	let value = parse_string("position: [1.5 -2]\nversion: [1 4 0]\nname: ['a' 2]").unwrap();
+	let position: (f64, f64) = value.get("position").unwrap().get_tuple2().unwrap();
+	assert_eq!(position, (1.5, -2.));
+	assert_eq!(
+		value
+			.get("version")
+			.unwrap()
+			.get_array_of::<u32, 3>()
+			.unwrap(),
+		[1, 4, 0]
+	);
+	assert_eq!(
+		value
+			.get("name")
+			.unwrap()
+			.get_tuple2::<String, u8>()
+			.unwrap(),
+		("a".to_string(), 2)
+	);
+
+	let error = value
+		.get("version")
+		.unwrap()
+		.get_array_of::<u32, 2>()
+		.unwrap_err();
+	assert_eq!(error.to_string(), "expected 2 elements, found 3");
+	let error = value
+		.get("name")
+		.unwrap()
+		.get_tuple2::<String, String>()
+		.unwrap_err();
+	assert_eq!(error.to_string(), "[1]: expected string, found number");
+
+	let decoded = <(u8, String, bool)>::decode(&("x", 1).encode()).unwrap_err();
+	assert_eq!(decoded.to_string(), "expected 3 elements, found 2");
+	assert_eq!([1, 2].encode(), array![1, 2]);
+}
+
+#[test]
+fn matrices() {
+	use crate::matrix::Matrix;
+
+	let value = parse_string("m:--\n\t- [1 2 3]\n\t- [4 5 6]\nbad:--\n\t- [1 2]\n\t- [3]").unwrap();
+	let m = value.get("m").unwrap().get_matrix::<f64>().unwrap();
+	assert_eq!(m, Matrix::new(2, 3, vec![1., 2., 3., 4., 5., 6.]).unwrap());
+	assert_eq!(m.iter_rows().nth(1), Some(&[4., 5., 6.][..]));
+	let error = value.get("bad").unwrap().get_matrix::<f64>().unwrap_err();
+	assert_eq!(error.to_string(), "[1]: expected 2 elements, found 1");
+
+	let value = object! { m: [[1, -20.5], [300, 4]], v: [1, 2] };
+	let encoded = encode_string(&value, &EncodeOptions::new().align_matrices(true));
+	assert_eq!(
+		encoded,
+		"\nm:--\n\t- [  1 -20.5]\n\t- [300     4]\nv: [1 2]"
+	);
+	assert_eq!(parse_string(&encoded).unwrap(), value);
+}
+
+#[test]
+fn tables() {
+	let value = object! {
+		servers: [
+			{ host: "a.example", port: 80, tls: false },
+			{ host: "b.example", port: 8443, tls: true },
+		],
+		mixed: [{ a: 1 }, { b: 2 }],
+	};
+	let encoded = encode_string(&value, &EncodeOptions::new().tables(true));
+	assert_eq!(
+		encoded,
+		"
+mixed:--
+	-
+		a: 1
+	-
+		b: 2
+servers:--
+	#  host        port tls
+	- ['a.example' 80   false]
+	- ['b.example' 8443 true]"
+	);
+	assert_eq!(
+		parse_string(&encoded).unwrap().get("servers"),
+		Some(&array![["a.example", 80, false], ["b.example", 8443, true]])
+	);
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn csv() {
+	use crate::csv::{from_csv, to_csv, to_csv_with_options, CsvOptions};
+
+	let source = "name;port;tls\n\"a;b\";80;true\nc;;false\n";
+	let options = CsvOptions::new().delimiter(b';');
+	let value = from_csv(source.as_bytes(), &options).unwrap();
+	assert_eq!(
+		value,
+		array![
+			{ name: "a;b", port: 80, tls: true },
+			{ name: "c", port: null, tls: false },
+		]
+	);
+	assert_eq!(to_csv_with_options(&value, &options).unwrap(), source);
+
+	let value = from_csv("a\n1\n".as_bytes(), &CsvOptions::new().parse_values(false)).unwrap();
+	assert_eq!(value, array![{ a: "1" }]);
+
+	let error = to_csv(&array![{ a: 1 }, { b: [1] }]).unwrap_err();
+	assert_eq!(
+		error.to_string(),
+		"[1].b: expected an array of objects holding primitives"
+	);
+}
+
+#[test]
+fn ini() {
+	use crate::{
+		ini::{from_ini, to_ini, IniWarning},
+		path::KvonPath,
+	};
+
+	let source = r#"; global settings
+debug = false
+greeting = "hello ; world"
+
+[mysqld]
+port = 3306
+skip-networking
+port = 3307
+
+[remote "origin"]
+url = git@example.com:app.git
+fetch[] = +refs/heads/*
+fetch[] = +refs/tags/*
+
+[debug]
+server {
+    listen 80;
+}
+"#;
+	let import = from_ini(source);
+	assert_eq!(
+		import.value,
+		object! {
+			greeting: "hello ; world",
+			mysqld: { port: 3307, "skip-networking": true },
+			remote: { origin: { url: "git@example.com:app.git", fetch: ["+refs/heads/*", "+refs/tags/*"] } },
+			debug: {},
+		}
+	);
+	let path = |path: &str| path.parse::<KvonPath>().unwrap();
+	assert_eq!(
+		import.warnings,
+		[
+			IniWarning::KeyWithoutValue {
+				path: path("mysqld.skip-networking"),
+				line_number: 6
+			},
+			IniWarning::DuplicateKey {
+				path: path("mysqld.port"),
+				line_number: 7
+			},
+			IniWarning::Conflict {
+				path: path("debug"),
+				line_number: 14
+			},
+			IniWarning::UnreadableLine { line_number: 15 },
+			IniWarning::UnreadableLine { line_number: 16 },
+			IniWarning::UnreadableLine { line_number: 17 },
+		]
+	);
+	assert_eq!(
+		import.warnings[1].to_string(),
+		"7: duplicate key mysqld.port, replacing the previous value"
+	);
+
+	let value = object! {
+		version: "1.0",
+		empty: {},
+		app: { hosts: ["a", "b"], name: " padded " },
+	};
+	let ini = to_ini(&value).unwrap();
+	assert_eq!(
+		ini,
+		"version = \"1.0\"\n\n[app]\nhosts[] = a\nhosts[] = b\nname = \" padded \"\n\n[empty]\n"
+	);
+	assert_eq!(from_ini(&ini).value, value);
+
+	let error = to_ini(&object! { app: { servers: [{ port: 80 }] } }).unwrap_err();
+	assert_eq!(
+		error.to_string(),
+		"app.servers[0]: arrays can only hold primitives"
+	);
+	assert!(to_ini(&object! { text: "a\nb" }).is_err());
+	assert!(to_ini(&Value::from(1)).is_err());
+}
+
+#[cfg(feature = "properties")]
+#[test]
+fn properties() {
+	use crate::{
+		properties::{from_dotenv, from_properties, to_dotenv, to_properties, PropertiesError},
+		value::UnflattenConflict,
+	};
+
+	let config = object! {
+		name: " Zoë = #1\n",
+		tags: { "a b": true, empty: [] },
+		debug: null,
+	};
+	let properties = to_properties(&config);
+	assert_eq!(
+		properties,
+		"debug=null\nname=\\ Zo\\u00EB = #1\\n\ntags.a\\ b=true\n"
+	);
+	// empty arrays are skipped
+	assert_eq!(
+		from_properties(&properties).unwrap(),
+		object! { name: " Zoë = #1\n", tags: { "a b": true }, debug: null }
+	);
+
+	let source = "# comment\n! another\n  url : https://example.com\nlist = a, \\\n    b\nkey\\=with\\:colon value\nemoji=\\uD83D\\uDE00\nempty\n";
+	assert_eq!(
+		from_properties(source).unwrap(),
+		object! {
+			url: "https://example.com",
+			list: "a, b",
+			"key=with:colon": "value",
+			emoji: "😀",
+			empty: "",
+		}
+	);
+	assert_eq!(
+		from_properties("a=1\nb=\\u12").unwrap_err(),
+		PropertiesError::Syntax {
+			line_number: 1,
+			message: "expected 4 hexadecimal digits after '\\u'"
+		}
+	);
+	assert_eq!(
+		from_properties("a=1\na.b=2").unwrap_err().to_string(),
+		"key 'a.b' conflicts with the value of 'a'"
+	);
+
+	let config = object! { db: { url: "postgres://u:p@h/db", password: "a \"$b\"\nc" }, port: 80 };
+	let dotenv = to_dotenv(&config, "");
+	assert_eq!(
+		dotenv,
+		"DB_PASSWORD=\"a \\\"\\$b\\\"\\nc\"\nDB_URL=postgres://u:p@h/db\nPORT=80\n"
+	);
+	assert_eq!(from_dotenv(&dotenv, "").unwrap(), config);
+
+	let source = "# comment\nexport APP_NAME='$HOME \\n'\nAPP_DEBUG=true # enabled\nAPP_PATH=\"C:\\\\dir\" # quoted\nOTHER=1\n";
+	assert_eq!(
+		from_dotenv(source, "app").unwrap(),
+		object! { name: "$HOME \\n", debug: true, path: "C:\\dir" }
+	);
+	assert_eq!(
+		from_dotenv("A=1\nB='x", "").unwrap_err().to_string(),
+		"1: unclosed quote"
+	);
+	assert!(from_dotenv("not a variable", "").is_err());
+	assert_eq!(
+		from_dotenv("APP_A=1\nAPP_A_B=2\n", "app").unwrap_err(),
+		PropertiesError::Conflict(UnflattenConflict {
+			key: "a.b".to_string(),
+			prefix: "a".to_string(),
+		})
+	);
+}
+
+#[cfg(feature = "checksum")]
+#[test]
+fn checksum() {
+	use crate::{checksum::checksum, parse_string_output, ParserOptions};
+
+	let value = object! { name: "app", ports: [80, 443] };
+	let options = EncodeOptions::new().checksum(true).header("generated");
+	let encoded = encode_string(&value, &options);
+	assert_eq!(
+		encoded.lines().next(),
+		Some(format!("# sha256: {}", checksum(&value)).as_str())
+	);
+	assert!(encoded.contains("\n# generated\n"));
+
+	// reformatting keeps the checksum valid
+	let reformatted = encoded.replace('\t', "  ");
+	let output = parse_string_output(&reformatted, &ParserOptions::new()).unwrap();
+	assert_eq!(output.value, value);
+	assert!(output.warnings.is_empty());
+	assert_eq!(output.comments[0].text, "generated");
+
+	let output =
+		parse_string_output(&encoded.replace("443", "8443"), &ParserOptions::new()).unwrap();
+	assert_eq!(
+		output.warnings[0].to_string(),
+		"0: checksum mismatch, the document was changed after it was written"
+	);
+}
+
+#[test]
+fn save_to_path() {
+	use crate::save_to_path;
+
+	let dir = std::env::temp_dir().join(format!("kvon-save-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let path = dir.join("state.kvon");
+
+	std::fs::write(&path, "old: true\n").unwrap();
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+	}
+
+	let value = object! { count: 2, name: "app" };
+	save_to_path(&value, &path, &EncodeOptions::new()).unwrap();
+	assert_eq!(
+		std::fs::read_to_string(&path).unwrap(),
+		"count: 2\nname: 'app'\n"
+	);
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+		assert_eq!(mode & 0o777, 0o640);
+	}
+	// only the saved file is left
+	assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+	std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn config_watcher() {
+	use std::time::Duration;
+
+	use crate::{
+		schema::{Constraint, Schema},
+		watch::{ConfigWatcher, WatchError, WatchOptions},
+	};
+
+	let dir = std::env::temp_dir().join(format!("kvon-watch-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let path = dir.join("config.kvon");
+	std::fs::write(&path, "port: 80").unwrap();
+
+	let options = WatchOptions::new()
+		.poll_interval(Duration::from_millis(5))
+		.debounce(Duration::from_millis(20))
+		.schema(Schema::object([(
+			"port",
+			Schema::Number.constrain(Constraint::Maximum(65535.)),
+		)]));
+	let watcher = ConfigWatcher::with_options(&path, options).unwrap();
+	assert_eq!(*watcher.current(), object! { port: 80 });
+	let timeout = Duration::from_secs(5);
+
+	std::fs::write(&path, "port: 8080").unwrap();
+	let reload = watcher.events().recv_timeout(timeout).unwrap().unwrap();
+	assert_eq!(*reload.value, object! { port: 8080 });
+	assert_eq!(reload.changes[0].to_string(), "~ port: 80 -> 8080");
+
+	std::fs::write(&path, "port: 100000").unwrap();
+	let error = watcher.events().recv_timeout(timeout).unwrap().unwrap_err();
+	assert!(matches!(error, WatchError::Schema(_)));
+	std::fs::write(&path, "port: 'a").unwrap();
+	let error = watcher.events().recv_timeout(timeout).unwrap().unwrap_err();
+	assert!(matches!(error, WatchError::Parser(_)));
+	assert_eq!(*watcher.current(), object! { port: 8080 });
+
+	drop(watcher);
+	std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn overlay() {
+	use crate::{overlay::OverlayValue, path::PathSegment};
+
+	let defaults = object! {
+		server: { host: "localhost", port: 80, tls: { enabled: false } },
+		hosts: ["a", "b"],
+	};
+	let site = object! { server: { tls: "off" }, hosts: ["c"] };
+	let user = object! { server: { port: 8080 } };
+	let config = OverlayValue::new([&defaults, &site, &user]);
+
+	let server = config.get("server").unwrap();
+	assert_eq!(server.get("port").unwrap().top(), &Value::from(8080));
+	// the site's string hides the default object
+	assert_eq!(
+		server.get("tls").unwrap().as_single(),
+		Some(&Value::from("off"))
+	);
+	assert!(server.get("missing").is_none());
+	let path = [PathSegment::Key("hosts".to_string()), PathSegment::Index(0)];
+	assert_eq!(config.get_path(&path).unwrap().top(), &Value::from("c"));
+
+	assert_eq!(
+		config.to_value(),
+		object! {
+			server: { host: "localhost", port: 8080, tls: "off" },
+			hosts: ["c"],
+		}
+	);
+}
+
+#[test]
+fn layered_origins() {
+	use crate::{
+		layers::{LayerError, LayeredConfig},
+		path::KvonPath,
+	};
+
+	let mut config = LayeredConfig::new().with_spans();
+	config
+		.add_str(
+			"defaults.kvon",
+			"server:\n\thost: 'localhost'\n\tport: 80\n\ttls:\n\t\tenabled: false\nhosts: ['a' 'b']\n",
+		)
+		.unwrap();
+	config
+		.add_str("site.kvon", "server:\n\ttls: 'off'\n\tport: 8080\n")
+		.unwrap();
+	config.add_value("env", object! { hosts: ["c"] });
+	let merged = config.merge();
+	assert_eq!(merged.value(), &config.value());
+
+	let origin = |path: &str| {
+		let path: KvonPath = path.parse().unwrap();
+		merged.origin(&path).map(ToString::to_string)
+	};
+	assert_eq!(origin("server.host").as_deref(), Some("defaults.kvon:2"));
+	assert_eq!(origin("server.port").as_deref(), Some("site.kvon:3"));
+	// the site's string hides the default object
+	assert_eq!(origin("server.tls").as_deref(), Some("site.kvon:2"));
+	assert_eq!(origin("server.tls.enabled"), None);
+	// merged objects come from the last layer containing them
+	assert_eq!(origin("server").as_deref(), Some("site.kvon:1"));
+	assert_eq!(origin("hosts[0]").as_deref(), Some("env"));
+	assert_eq!(merged.origin(&[]).unwrap().layer, 2);
+
+	let error = config.add_str("broken.kvon", "a: 'b").unwrap_err();
+	assert!(matches!(error, LayerError::Parser { .. }));
+	assert!(error.to_string().starts_with("broken.kvon: 0:"));
+	assert_eq!(config.layers().len(), 3);
+}
+
+#[test]
+fn explain_report() {
+	use crate::{
+		explain::{explain, Candidate},
+		layers::LayeredConfig,
+		path::KvonPath,
+	};
+
+	let mut config = LayeredConfig::new().with_spans();
+	config
+		.add_str(
+			"defaults.kvon",
+			"server:\n\tport: 80\n\ttls: false\nhosts: ['a']\n",
+		)
+		.unwrap();
+	config
+		.add_str("site.kvon", "server:\n\ttls:\n\t\tcert: 'x.pem'\n")
+		.unwrap();
+	config.add_value("env", object! { server: { port: 8080 } });
+
+	let report = explain(&config);
+	let paths: Vec<String> = report
+		.entries()
+		.iter()
+		.map(|e| e.path.to_string())
+		.collect();
+	assert_eq!(
+		paths,
+		["hosts", "server.port", "server.tls", "server.tls.cert"]
+	);
+	let port: KvonPath = "server.port".parse().unwrap();
+	let port = report.get(&port).unwrap();
+	assert_eq!(port.origin.to_string(), "env");
+	assert_eq!(port.value, Value::from(8080));
+	assert_eq!(
+		port.overridden
+			.iter()
+			.map(|c| c.origin.to_string())
+			.collect::<Vec<_>>(),
+		["defaults.kvon:2"]
+	);
+
+	let report = report.within(&"server.tls".parse::<KvonPath>().unwrap());
+	assert_eq!(
+		report.to_table(),
+		"path             value    source\nserver.tls       {...}    site.kvon:2\n                 false    defaults.kvon:3\nserver.tls.cert  'x.pem'  site.kvon:3\n"
+	);
+	assert_eq!(
+		report.to_ansi().lines().nth(2),
+		Some("                 \x1b[2;9mfalse\x1b[0m    defaults.kvon:3")
+	);
+	assert_eq!(
+		report.to_kvon(),
+		"entries:--\n\t-\n\t\toverridden:--\n\t\t\t-\n\t\t\t\tsource: 'defaults.kvon:3'\n\t\t\t\tvalue: false\n\t\tpath: 'server.tls'\n\t\tsource: 'site.kvon:2'\n\t\tvalue:\n\t\t\tcert: 'x.pem'\n\t-\n\t\tpath: 'server.tls.cert'\n\t\tsource: 'site.kvon:3'\n\t\tvalue: 'x.pem'\n"
+	);
+	let tls = &report.entries()[0];
+	assert!(matches!(&tls.overridden[..], [Candidate { origin, .. }] if origin.layer == 0));
+}
+
+#[test]
+fn kvon_path() {
+	use crate::path::{KvonPath, PathSegment};
+
+	let path: KvonPath = "servers[1].host".parse().unwrap();
+	assert_eq!(path, KvonPath::root().key("servers").index(1).key("host"));
+	assert_eq!(path.to_string(), "servers[1].host");
+	assert_eq!(path.parent(), Some(&path[..2]));
+	assert_eq!("".parse::<KvonPath>().unwrap(), KvonPath::root());
+	assert_eq!("[0][2]".parse::<KvonPath>().unwrap().to_string(), "[0][2]");
+
+	// keys that can't be written bare are quoted
+	let path = KvonPath::root().key("a.b").key("it's").key("");
+	assert_eq!(path.to_string(), r"'a.b'.'it\'s'.''");
+	assert_eq!(path.to_string().parse::<KvonPath>().unwrap(), path);
+	assert_eq!(
+		"\"x y\"".parse::<KvonPath>().unwrap(),
+		vec![PathSegment::Key("x y".to_string())]
+	);
+
+	for invalid in ["a.", ".a", "a..b", "a.[0]", "a[", "a[x]", "'a", "a]"] {
+		assert!(invalid.parse::<KvonPath>().is_err(), "{invalid}");
+	}
+
+	let value = object! { servers: [{ host: "a" }, { host: "b" }] };
+	let path: KvonPath = "servers[1].host".parse().unwrap();
+	assert_eq!(value.get_path(&path), Some(&Value::from("b")));
+}
+
+#[test]
+fn array_line_errors() {
+	let error = parse_string("arr:-- a").unwrap_err();
+	assert_eq!(error.code(), "E107_TRAILING_AFTER_MARKER");
+	assert_eq!(error.column_number, 7);
+	assert!(error
+		.to_string()
+		.starts_with("0:7: expanded array marker ':--' must end the line"));
+
+	let error = parse_string("arr:--\n\t-- 1").unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::TrailingAfterMarker { marker: "--" }
+	);
+	// the content after a `|` used to be dropped silently in arrays
+	let error = parse_string("arr:--\n\t- | text\n\t\tmore").unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::TrailingAfterMarker { marker: "|" }
+	);
+
+	let error = parse_string("arr:--\n\t- 1 2 x: 3").unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::MixedArrayEntries {
+			entry: "key-value pair"
+		}
+	);
+	assert_eq!(error.code(), "E108_MIXED_ARRAY_ENTRIES");
+	assert_eq!((error.line_number, error.column_number), (1, 7));
+	let error = parse_string("arr:--\n\t- 1 |").unwrap_err();
+	assert_eq!(
+		error.kind,
+		ParserErrorKind::MixedArrayEntries {
+			entry: "multi-line string"
+		}
+	);
+
+	// comments may still follow markers
+	assert_eq!(
+		parse_string("arr:-- # list\n\t-- # nested\n\t\t- 1").unwrap(),
+		object! { arr: [[1]] }
+	);
+}
+
+#[test]
+fn comments_inside_blocks() {
+	// lines not indented like the string end it, which explains the error
+	// on the lines after them
+	let error = parse_string("s: |\n\tone\n\n\tthree").unwrap_err();
+	assert_eq!(
+		(error.kind, error.line_number),
+		(ParserErrorKind::InvalidIndention, 3)
+	);
+	assert_eq!(
+		error.hint.as_deref(),
+		Some("blank lines inside a multi-line string must be indented like its other lines")
+	);
+	let error = parse_string("s: |\n\tone\n# two\n\tthree").unwrap_err();
+	assert_eq!(
+		error.hint.as_deref(),
+		Some("a comment that isn't indented like a multi-line string's lines ends it")
+	);
+	assert_eq!(
+		parse_string("s: |\n\tone\n\t\n\t# three").unwrap(),
+		object! { s: "one\n\n# three" }
+	);
+}
+
+#[test]
+fn blank_lines_in_strings() {
+	use crate::{parse_string_with_options, ParserOptions};
+
+	let source = "script: |\n\techo one\n\n   \n\techo two\n\nnotes: |\n\tthree\n\nafter: 1";
+	let options = ParserOptions::new().blank_lines_in_strings(true);
+	assert_eq!(
+		parse_string_with_options(source, &options).unwrap(),
+		object! { script: "echo one\n\n\necho two", notes: "three", after: 1 }
+	);
+	// blank lines before a comment that ends the string aren't kept either
+	assert_eq!(
+		parse_string_with_options("s: |\n\tone\n\n# end\nb: 1", &options).unwrap(),
+		object! { s: "one", b: 1 }
+	);
+
+	let error = parse_string(source).unwrap_err();
+	assert_eq!(
+		(error.kind, error.line_number),
+		(ParserErrorKind::InvalidIndention, 4)
+	);
+}
+
+#[test]
+fn trailing_newlines() {
+	use crate::TrailingNewlines;
+
+	let encode = |s: &str, trailing_newlines| {
+		let options = EncodeOptions::new().trailing_newlines(trailing_newlines);
+		encode_string(&object! { s: s }, &options)
+	};
+
+	// strings keep their trailing line breaks by default, with `\r\n`
+	// written as `\n`
+	for s in [
+		"a\nb",
+		"a\nb\n",
+		"a\nb\n\n",
+		"\n",
+		"\na",
+		"a\r\nb",
+		"a\r\nb\r\n",
+	] {
+		let encoded = encode(s, TrailingNewlines::Keep);
+		let expected = s.replace("\r\n", "\n");
+		assert_eq!(
+			parse_string(&encoded).unwrap(),
+			object! { s: expected },
+			"{s:?}"
+		);
+	}
+	assert_eq!(
+		encode("a\nb\n", TrailingNewlines::Keep),
+		"\ns: |\n\ta\n\tb\n\t"
+	);
+
+	assert_eq!(
+		encode("a\nb\n\n", TrailingNewlines::Strip),
+		"\ns: |\n\ta\n\tb"
+	);
+	assert_eq!(
+		encode("a\nb", TrailingNewlines::Ensure),
+		"\ns: |\n\ta\n\tb\n\t"
+	);
+	assert_eq!(
+		encode("a\nb\n", TrailingNewlines::Ensure),
+		"\ns: |\n\ta\n\tb\n\t"
+	);
+}
+
+#[test]
+fn verify_roundtrip() {
+	use crate::roundtrip::{verify_roundtrip, RoundtripReport};
+
+	let source = "#kvon 1.0\nscript: |\n    echo one\n    \n    echo two\n    \nservers:--\n    -\n        host: 'a' # primary\n        port: 80\n    - [1 2]";
+	verify_roundtrip(source).unwrap();
+
+	let report = verify_roundtrip("a: 'unclosed").unwrap_err();
+	assert!(matches!(report, RoundtripReport::Invalid(_)));
+	assert!(report.to_string().starts_with("0:12: string not closed"));
+}
+
+#[test]
+fn format_str() {
+	use crate::{
+		format::{format_str, FormatOptions},
+		indention::Indention,
+		QuoteStyle,
+	};
+
+	let source = "#kvon 1.0\nname:   \"app\"   # the name\n\n\nservers:--\n  -\n    host : 'a'\n      # the port\n    port: 80\n  - [ 1  [2 3]  'x' ]\n  - k:  |\n        spaced\n      text\nz:\n\n";
+	let options = FormatOptions::new().indention(Indention::Tabs);
+	let formatted = format_str(source, &options).unwrap();
+	assert_eq!(
+		formatted,
+		"#kvon 1.0\nname: 'app' # the name\n\n\nservers:--\n\t-\n\t\thost: 'a'\n\t\t\t# the port\n\t\tport: 80\n\t- [1 [2 3] 'x']\n\t- k: |\n\t\t    spaced\n\t\t  text\nz:\n"
+	);
+	assert_eq!(
+		parse_string(&formatted).unwrap(),
+		parse_string(source).unwrap()
+	);
+
+	let options = FormatOptions::new().quote_style(QuoteStyle::Double);
+	assert_eq!(
+		format_str("a: ['x' 'say \"hi\"']", &options).unwrap(),
+		"a: [\"x\" 'say \"hi\"']\n"
+	);
+	assert!(format_str("a: [1", &options).is_err());
+}
+
+#[test]
+fn format_config() {
+	use crate::format::{format_str, FormatConfigError, FormatOptions, CONFIG_FILE_NAME};
+
+	let options =
+		FormatOptions::from_config("indention: 2\nmax_line_width: 14\nquote_style: 'double'\n")
+			.unwrap();
+	let source = "a:\n    ports: [8080 8081 8082 8083]\n    names: ['x' 'y']\nb:--\n    - 1 2 3 4 5 6 7 8 9\n";
+	let formatted = format_str(source, &options).unwrap();
+	assert_eq!(
+		formatted,
+		"a:\n  ports:--\n    - 8080 8081 8082\n    - 8083\n  names: [\"x\" \"y\"]\nb:--\n  - 1 2 3 4 5 6 7\n  - 8 9\n"
+	);
+	assert_eq!(format_str(&formatted, &options).unwrap(), formatted);
+	assert_eq!(
+		parse_string(&formatted).unwrap(),
+		parse_string(source).unwrap()
+	);
+
+	let options =
+		FormatOptions::from_config("indention: 'tabs'\narray_items_per_line: 2\n").unwrap();
+	assert_eq!(
+		format_str("a: [1 2 3]\n", &options).unwrap(),
+		"a:--\n\t- 1 2\n\t- 3\n"
+	);
+
+	let Err(FormatConfigError::Decode(e)) = FormatOptions::from_config("indent: 2\n") else {
+		panic!("unknown options are rejected");
+	};
+	assert_eq!(e.to_string(), "indent: unknown option");
+	assert!(matches!(
+		FormatOptions::from_config("indention: 'wide'\n"),
+		Err(FormatConfigError::Decode(_))
+	));
+	let Err(FormatConfigError::Decode(e)) = FormatOptions::from_config("indention: 0\n") else {
+		panic!("indention of 0 spaces is rejected");
+	};
+	assert_eq!(e.to_string(), "indention: expected at least 1 space");
+
+	let dir = std::env::temp_dir().join(format!("kvon-fmt-{}", std::process::id()));
+	let nested = dir.join("a").join("b");
+	std::fs::create_dir_all(&nested).unwrap();
+	std::fs::write(dir.join(CONFIG_FILE_NAME), "indention: 'tabs'\n").unwrap();
+	let options = FormatOptions::discover(nested.join("doc.kvon")).unwrap();
+	assert_eq!(
+		format_str("a:\n  b: 1\n", &options).unwrap(),
+		"a:\n\tb: 1\n"
+	);
+	std::fs::write(dir.join("a").join(CONFIG_FILE_NAME), "indention: 4\n").unwrap();
+	let options = FormatOptions::discover(&nested).unwrap();
+	assert_eq!(
+		format_str("a:\n  b: 1\n", &options).unwrap(),
+		"a:\n    b: 1\n"
+	);
+	std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sort_and_dedup_arrays() {
+	use crate::format::{format_str, FormatError, FormatOptions};
+
+	let mut value = object! {
+		allowed_ips: ["10.0.0.2", "10.0.0.1", "10.0.0.2"],
+		plugins: [{ name: "b" }, { name: "a", v: 1 }, { v: 0 }, { name: "a", v: 2 }],
+		groups: [[3, 1], [2, 2], "x"],
+	};
+	assert_eq!(value.sort_arrays_by("allowed_ips", Ord::cmp), 1);
+	assert_eq!(value.dedup_arrays("allowed_ips", ""), 1);
+	assert_eq!(
+		value.sort_arrays_by("plugins", |a, b| a.get("name").cmp(&b.get("name"))),
+		1
+	);
+	assert_eq!(value.dedup_arrays("plugins", "name"), 1);
+	// only arrays are sorted, by the path's wildcard
+	assert_eq!(value.sort_arrays_by("groups.*", |a, b| b.cmp(a)), 2);
+	assert_eq!(value.dedup_arrays("groups.*", ""), 1);
+	assert_eq!(value.dedup_arrays("missing", ""), 0);
+	assert_eq!(
+		value,
+		object! {
+			allowed_ips: ["10.0.0.1", "10.0.0.2"],
+			plugins: [{ v: 0 }, { name: "a", v: 1 }, { name: "b" }],
+			groups: [[3, 1], [2], "x"],
+		}
+	);
+
+	let options = FormatOptions::from_config(
+		"sort_arrays: ['allowed_ips']\ndedup_arrays: [['plugins' 'name']]\n",
+	)
+	.unwrap();
+	let source = "# kept\nallowed_ips: ['b' 'a'] # access\nplugins:--\n  -\n    name: 'x'\n  -\n    name: 'x'\n";
+	let formatted = format_str(source, &options).unwrap();
+	assert_eq!(
+		formatted,
+		"# kept\nallowed_ips: ['a' 'b'] # access\nplugins:--\n  -\n    name: 'x'\n"
+	);
+	assert_eq!(format_str(&formatted, &options).unwrap(), formatted);
+	// documents the steps don't change keep their layout
+	let source = "b: 1\na: ['a' 'b'] # sorted\n";
+	assert_eq!(format_str(source, &options).unwrap(), source);
+
+	// only the lines of the changed arrays are rewritten
+	let options = FormatOptions::new()
+		.sort_arrays("list")
+		.sort_arrays("servers.*.tags");
+	let source = "# head\n\nlist: [3 1 2] # tail\n\n# mid\nz: 1\na: 2\nservers:--\n\t- # first\n\t\ttags:--\n\t\t\t- 'b'\n\t\t\t- 'a'\n\n\t\tname: 'x'\n";
+	assert_eq!(
+		format_str(source, &options).unwrap(),
+		"# head\n\nlist: [1 2 3] # tail\n\n# mid\nz: 1\na: 2\nservers:--\n\t- # first\n\t\ttags: ['a' 'b']\n\n\t\tname: 'x'\n"
+	);
+	let error = format_str("list:--\n\t- 3 # three\n\t- 1\n", &options).unwrap_err();
+	assert!(matches!(
+		error,
+		FormatError::CommentsInArray { line_number: 1, .. }
+	));
+	assert_eq!(
+		error.to_string(),
+		"1: can't sort or deduplicate 'list' without losing its comments"
+	);
+
+	// the formatted document keeps the sorted value, not the source's
+	let (formatted, value) =
+		crate::format::format_str_with_value("list: [3 1 2]\n", &options).unwrap();
+	assert_eq!(value, object! { list: [1, 2, 3] });
+	crate::roundtrip::verify_encoding(&value, formatted.clone()).unwrap();
+	assert!(crate::roundtrip::verify_reencoded("list: [3 1 2]\n", formatted).is_err());
+}
+
+#[test]
+fn rewrite_entry() {
+	use crate::{
+		edit::set_input,
+		format::{rewrite_entry, FormatError},
+		path::KvonPath,
+	};
+
+	let source = "# config\nserver:\n\thost: \"localhost\" # the host\n\tport: 80\n\n# other\nlist: [1 2 3]\n";
+	let set = |path: &str, input: &str| {
+		let path: KvonPath = path.parse().unwrap();
+		let mut value = parse_string(source).unwrap();
+		set_input(&mut value, &path, input, None).unwrap();
+		let rewritten = rewrite_entry(source, &value, &path);
+		if let Ok(rewritten) = &rewritten {
+			assert_eq!(parse_string(rewritten).unwrap(), value);
+		}
+		rewritten
+	};
+
+	// comments, quotes and the order of the other keys are kept
+	assert_eq!(
+		set("server.port", "8080").unwrap(),
+		source.replace("port: 80", "port: 8080")
+	);
+	assert_eq!(
+		set("list[1]", "5").unwrap(),
+		source.replace("[1 2 3]", "[1 5 3]")
+	);
+	// missing keys are added after the other keys of their object
+	assert_eq!(
+		set("server.tls.enabled", "true").unwrap(),
+		source.replace("port: 80\n", "port: 80\n\ttls:\n\t\tenabled: true\n")
+	);
+	assert_eq!(
+		set("name", "kvon").unwrap(),
+		source.to_string() + "name: 'kvon'\n"
+	);
+	assert!(matches!(
+		set("server", "1"),
+		Err(FormatError::CommentsInEntry { line_number: 2, .. })
+	));
+}
+
+#[test]
+fn interned_strings() {
+	use crate::{
+		parse_string_with_options,
+		shared::{parse_string_interned, SharedValue, StringInterner},
+		ParserOptions,
+	};
+
+	let mut interner = StringInterner::new();
+	let first = parse_string_interned(
+		"a: 'x'\nb: ['x' 'y' 1]\n",
+		&ParserOptions::new(),
+		&mut interner,
+	)
+	.unwrap();
+	let second = SharedValue::interned(object! { c: "y" }, &mut interner);
+	assert_eq!(interner.len(), 2);
+
+	let b = first.get("b").unwrap();
+	assert!(first.get("a").unwrap().ptr_eq(b.get_index(0).unwrap()));
+	// documents converted with the same interner share their strings too
+	assert!(second.get("c").unwrap().ptr_eq(b.get_index(1).unwrap()));
+	assert_eq!(
+		Value::from(first.clone()),
+		object! { a: "x", b: ["x", "y", 1] }
+	);
+
+	// strings are interned while parsing, in every kind of block
+	let source = "server.host: 'x'\nlist:--\n\t- name: 'y'\n\t- |\n\t\tx\n\t--\n\t\t- 'x' 2\ntext: |\n\ty\nempty:\n";
+	let options = ParserOptions::new().dotted_keys(true);
+	let value = parse_string_interned(source, &options, &mut interner).unwrap();
+	assert_eq!(interner.len(), 2);
+	assert_eq!(
+		Value::from(value.clone()),
+		parse_string_with_options(source, &options).unwrap()
+	);
+	let list = value.get("list").unwrap();
+	assert!(list.get_index(1).unwrap().ptr_eq(b.get_index(0).unwrap()));
+	assert!(value.get("text").unwrap().ptr_eq(b.get_index(1).unwrap()));
+
+	// the interner keeps its strings when parsing fails
+	assert!(parse_string_interned("a: 'z'\nb: [", &options, &mut interner).is_err());
+	assert_eq!(interner.len(), 3);
+}
+
+#[test]
+fn compact_values() {
+	use crate::{
+		compact::{parse_string_compact, CompactValue, SMALL_OBJECT},
+		parse_string_with_options, ParserOptions,
+	};
+
+	let source = "server.host: 'x'\nserver.port: 80\nlist:--\n\t- name: 'y'\n\t- |\n\t\tx\n\t--\n\t\t- 'x' 2\ntext: |\n\ty\nempty:\ninline: [1 [2 3]]\n";
+	let options = ParserOptions::new().dotted_keys(true);
+	let value = parse_string_compact(source, &options).unwrap();
+	let expected = parse_string_with_options(source, &options).unwrap();
+	assert_eq!(value.to_value(), expected);
+	assert_eq!(Value::from(value.clone()), expected);
+	assert_eq!(CompactValue::from(expected), value);
+
+	// small objects keep their entries sorted by key
+	let keys: Vec<&str> = value
+		.get_objects()
+		.unwrap()
+		.iter()
+		.map(|(key, _)| key)
+		.collect();
+	assert_eq!(keys, ["empty", "inline", "list", "server", "text"]);
+	assert_eq!(
+		value
+			.get("server")
+			.unwrap()
+			.get("port")
+			.unwrap()
+			.get_number(),
+		Ok(80.)
+	);
+	assert_eq!(
+		value
+			.get("inline")
+			.unwrap()
+			.get_index(1)
+			.unwrap()
+			.get_index(0)
+			.unwrap()
+			.get_number(),
+		Ok(2.)
+	);
+
+	// objects growing past the small size are still looked up by key
+	let large: String = (0..=SMALL_OBJECT * 2)
+		.rev()
+		.map(|i| format!("k{i}: {i}\nk{i}: {}\n", i + 1))
+		.collect();
+	let value = parse_string_compact(&large, &ParserOptions::new()).unwrap();
+	assert_eq!(value.get_objects().unwrap().len(), SMALL_OBJECT * 2 + 1);
+	for i in 0..=SMALL_OBJECT * 2 {
+		assert_eq!(
+			value.get(&format!("k{i}")).unwrap().get_number(),
+			Ok(i as f32 + 1.)
+		);
+	}
+	assert_eq!(value.to_value(), crate::parse_string(&large).unwrap());
+
+	assert!(parse_string_compact("a: [", &options).is_err());
+}
+
+#[cfg(feature = "arena")]
+#[test]
+fn arena_parsing() {
+	use crate::arena::{parse_in, ArenaValue, Bump};
+
+	let source = "#kvon 1.0\nname: 'app'\nname: 'replaced'\nservers:--\n\t-\n\t\thost: 'a'\n\t\tports: [80 443]\n\t- k: |\n\t\tline 1\n\t\tline 2\n\t- single:\n\t\tnested: true\n\t--\n\t\t- 1 2\n\t- null\nnotes: |\n\tmulti\n\n# comment\nlast:\n";
+	let arena = Bump::new();
+	let value = parse_in(&arena, source).unwrap();
+	assert_eq!(value.to_value(), parse_string(source).unwrap());
+
+	// entries keep the order of the document, and repeated keys their first
+	// position
+	let keys: Vec<&str> = value
+		.get_entries()
+		.unwrap()
+		.iter()
+		.map(|(k, _)| *k)
+		.collect();
+	assert_eq!(keys, ["name", "servers", "notes", "last"]);
+	assert_eq!(value.get("name").unwrap().get_string(), Ok("replaced"));
+	let servers = value.get("servers").unwrap();
+	assert_eq!(
+		servers.get_index(1).and_then(|s| s.get("k")),
+		Some(&ArenaValue::String("line 1\nline 2"))
+	);
+	assert!(value.get("last").unwrap().is_object());
+
+	let source = "a: |\n\tline\n# comment\n\tline\n";
+	assert_eq!(
+		parse_in(&arena, source).unwrap_err().to_string(),
+		parse_string(source).unwrap_err().to_string()
+	);
+	assert!(parse_in(&arena, "#kvon one\n").is_err());
+}
+
+#[test]
+fn error_recovery() {
+	use crate::{path::PathSegment, Parser, ParserOptions};
+
+	let mut parser = Parser::new().with_error_recovery();
+	parser.next_line("list:--").unwrap();
+	// the values before the error aren't added either
+	assert!(parser.next_line("\t- 1 [2").is_err());
+	parser.next_line("\t- 3").unwrap();
+	parser.next_line("text: |").unwrap();
+	parser.next_line("\tfirst").unwrap();
+	// a line failing after ending the string leaves it open
+	assert!(parser.next_line("key: 'unclosed").is_err());
+	assert_eq!(parser.current_value(), object! { list: [3], text: "first" });
+	parser.next_line("\tsecond").unwrap();
+	parser.next_line("nested:").unwrap();
+	assert_eq!(
+		parser.current_value(),
+		object! { list: [3], text: "first\nsecond", nested: {} }
+	);
+	assert!(parser.next_line("\t\tdeep: 1").is_err());
+	parser.next_line("\tdeep: 1").unwrap();
+
+	let lines: Vec<usize> = parser.errors().iter().map(|e| e.line_number).collect();
+	assert_eq!(lines, [1, 5, 8]);
+	assert_eq!(
+		parser.finish().unwrap(),
+		object! { list: [3], text: "first\nsecond", nested: { deep: 1 } }
+	);
+
+	// replaced values, dotted keys, spans and warnings of failed lines are
+	// undone too
+	let options = ParserOptions::new().dotted_keys(true);
+	let mut parser = Parser::with_options(options)
+		.with_spans()
+		.with_error_recovery();
+	for line in [
+		"server.port: 80",
+		"server.host: 'a'",
+		"server.host: 'b' c",
+		"nested:",
+		"\tlist:--",
+		"\t\t- 1",
+		"\tother.x: 1 2",
+		"\t\t- 2",
+		"\tother: 5",
+	] {
+		let _ = parser.next_line(line);
+	}
+	let lines: Vec<usize> = parser.errors().iter().map(|e| e.line_number).collect();
+	assert_eq!(lines, [2, 6]);
+	assert!(parser.warnings().is_empty());
+	let (value, spans) = parser.finish_with_spans().unwrap();
+	assert_eq!(
+		value,
+		object! { server: { port: 80, host: "a" }, nested: { list: [1, 2], other: 5 } }
+	);
+	let host = [
+		PathSegment::Key("server".into()),
+		PathSegment::Key("host".into()),
+	];
+	assert_eq!(spans[host.as_slice()].line_number, 1);
+
+	// without recovery, parsing stops at the first error
+	let mut parser = Parser::new();
+	assert!(parser.next_line("a: [1").is_err());
+	assert!(parser.errors().is_empty());
+}
+
+#[test]
+fn edit_values() {
+	use crate::{
+		edit::{parse_input, set_input, set_path, typed_input, EditError},
+		path::KvonPath,
+		schema::{Constraint, Schema},
+	};
+
+	assert_eq!(
+		parse_input(" localhost ").unwrap(),
+		Value::from("localhost")
+	);
+	assert_eq!(parse_input("'a b'").unwrap(), Value::from("a b"));
+	assert_eq!(parse_input("[80 443]").unwrap(), array![80, 443]);
+	assert_eq!(parse_input("null").unwrap(), Value::null());
+	assert!(parse_input("[1").is_err());
+	assert!(parse_input("[1] 2").is_err());
+	// text that isn't exactly one value is a string
+	for input in [
+		"80 443",
+		"trueish",
+		"nullable",
+		"2024-05-01",
+		"'a' b",
+		"'unclosed",
+	] {
+		assert_eq!(parse_input(input).unwrap(), Value::from(input));
+	}
+	// literals of features are values when they are enabled
+	#[cfg(not(feature = "semver"))]
+	assert_eq!(parse_input("1.2.3").unwrap(), Value::from("1.2.3"));
+	#[cfg(not(feature = "ipaddr"))]
+	assert_eq!(parse_input("10.0.0.1").unwrap(), Value::from("10.0.0.1"));
+
+	fn ports() -> Schema {
+		Schema::Array(Schema::Number.constrain(Constraint::Minimum(1.)).into())
+	}
+	fn name() -> Schema {
+		Schema::Optional(Schema::String.into())
+	}
+	let schema = Schema::object([("ports", ports as fn() -> Schema), ("name", name)]);
+	let path = |s: &str| s.parse::<KvonPath>().unwrap();
+
+	// inputs are converted to the type of the schema
+	assert_eq!(
+		typed_input("8080", &path("name"), &schema).unwrap(),
+		Value::from("8080")
+	);
+	assert_eq!(
+		typed_input("null", &path("name"), &schema).unwrap(),
+		Value::null()
+	);
+	assert_eq!(
+		typed_input("'22'", &path("ports[3]"), &schema).unwrap(),
+		Value::from(22)
+	);
+	assert_eq!(
+		typed_input("[80 'x']", &path("ports"), &schema)
+			.unwrap_err()
+			.to_string(),
+		"ports[1]: expected number, found string"
+	);
+	assert_eq!(
+		typed_input("[0 1]", &path("ports"), &schema)
+			.unwrap_err()
+			.to_string(),
+		"ports[0]: 0 is less than 1"
+	);
+	assert!(matches!(
+		typed_input("1", &path("nmae"), &schema),
+		Err(EditError::NotInSchema(_))
+	));
+
+	let mut document = object! { ports: [80], name: "app" };
+	assert_eq!(
+		set_input(&mut document, &path("ports[1]"), "443", Some(&schema)).unwrap(),
+		None
+	);
+	assert_eq!(
+		set_input(&mut document, &path("name"), "web", Some(&schema)).unwrap(),
+		Some(Value::from("app"))
+	);
+	assert_eq!(document, object! { ports: [80, 443], name: "web" });
+
+	// missing objects are created on the way
+	set_path(&mut document, &path("server.tls.enabled"), true.into()).unwrap();
+	assert_eq!(
+		document.get_path(&path("server.tls")),
+		Some(&object! { enabled: true })
+	);
+	assert_eq!(
+		set_path(&mut document, &path("ports[5]"), 1.into())
+			.unwrap_err()
+			.to_string(),
+		"ports: can't contain the new value"
+	);
+	assert!(set_input(&mut document, &path("name.first"), "x", None).is_err());
+	// failing leaves the document unchanged
+	let before = document.clone();
+	assert_eq!(
+		set_path(&mut document, &path("new.list[0]"), 1.into())
+			.unwrap_err()
+			.to_string(),
+		"new.list: can't contain the new value"
+	);
+	assert_eq!(document, before);
+	set_path(&mut document, &KvonPath::root(), Value::null()).unwrap();
+	assert_eq!(document, Value::null());
+}
+
+#[test]
+fn patch_files() {
+	use crate::{
+		diff::diff,
+		patch::{apply_patch, apply_patch_file, encode_patch, parse_patch, PatchError},
+	};
+
+	let old = object! { name: "app", ports: [80, 443, 8080], tls: { cert: "a.pem" } };
+	let new = object! { name: "web", ports: [80], tls: null, tags: ["a"] };
+	let patch = encode_patch(&diff(&old, &new));
+	assert_eq!(parse_patch(&patch).unwrap(), diff(&old, &new));
+
+	// removing several array items doesn't shift the later ones
+	let mut value = old.clone();
+	apply_patch(&mut value, &parse_patch(&patch).unwrap()).unwrap();
+	assert_eq!(value, new);
+
+	// nothing is applied if one of the changes conflicts
+	let mut value = object! { name: "app", ports: [80, 443, 8081], tls: { cert: "a.pem" } };
+	let error = apply_patch(&mut value, &parse_patch(&patch).unwrap()).unwrap_err();
+	assert_eq!(error.to_string(), "ports[2]: expected 8080, found 8081");
+	assert_eq!(value.get("name"), Some(&Value::from("app")));
+
+	let changes = parse_patch("changes:--\n\t- add: 'a.b'\n").unwrap_err();
+	assert_eq!(changes.to_string(), "changes[0]: missing field 'value'");
+	let changes = parse_patch("changes:--\n\t-\n\t\tadd: 'a'\n\t\tto: 1\n").unwrap_err();
+	assert_eq!(
+		changes.to_string(),
+		"changes[0].to: unexpected key in 'add' change"
+	);
+	assert!(matches!(
+		parse_patch("changes: [1]"),
+		Err(PatchError::Decode(_))
+	));
+	let mut value = object! { a: 1 };
+	let error = apply_patch(
+		&mut value,
+		&parse_patch("changes:--\n\t-\n\t\tadd: 'a.b'\n\t\tvalue: 2\n").unwrap(),
+	);
+	assert_eq!(
+		error.unwrap_err().to_string(),
+		"a: can't contain the new value"
+	);
+
+	let dir = std::env::temp_dir().join(format!("kvon-patch-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let file = dir.join("change.kvon");
+	std::fs::write(&file, &patch).unwrap();
+	let mut value = old.clone();
+	apply_patch_file(&mut value, &file).unwrap();
+	assert_eq!(value, new);
+	std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn templates() {
+	use crate::{
+		encode_string,
+		template::{Param, Template, TemplateError},
+	};
+
+	let template = Template::parse(
+		"name: '<<app>>-<<env>>'\nports: !param 'ports'\nlabels: ['<<env>>' 'a << b >> c' '<<>>']\nnested:--\n\t- !param 'db.hosts[0]'\n",
+	)
+	.unwrap();
+	assert_eq!(
+		template.parameters(),
+		["app", "db.hosts[0]", "env", "ports"]
+	);
+
+	let params = object! { app: "shop", env: "prod", ports: [80, 443], db: { hosts: ["db1"] } };
+	assert_eq!(
+		template.render(&params).unwrap(),
+		object! {
+			name: "shop-prod",
+			ports: [80, 443],
+			labels: ["prod", "a << b >> c", "<<>>"],
+			nested: ["db1"],
+		}
+	);
+
+	let params = object! { app: ["shop"], env: "prod", ports: [], db: { hosts: [] } };
+	assert_eq!(
+		template.render(&params).unwrap_err(),
+		TemplateError::NotInlinable {
+			path: "name".parse().unwrap(),
+			name: "app".to_string()
+		}
+	);
+	let params = object! { app: "shop", env: "prod", ports: [], db: { hosts: [] } };
+	assert_eq!(
+		template.render(&params).unwrap_err().to_string(),
+		"nested[0]: missing parameter 'db.hosts[0]'"
+	);
+
+	// parameters are encoded as tagged literals, so templates can be written
+	let encoded = encode_string(template.value(), &EncodeOptions::new());
+	assert!(encoded.contains("ports: !param 'ports'"));
+	assert_eq!(Template::parse(&encoded).unwrap(), template);
+	assert_eq!(
+		template
+			.value()
+			.get("ports")
+			.unwrap()
+			.get_primitive()
+			.unwrap()
+			.get_custom(),
+		Ok(&Param("ports".parse().unwrap()))
+	);
+	assert!(Template::parse("a: !param 1").is_err());
+	assert!(Template::parse("a: !param 'a['").is_err());
+}
+
+#[test]
+fn template_directives() {
+	use crate::template::{Template, TemplateError};
+
+	let template = Template::parse(
+		"plugins:--\n\t- 'base'\n\t- !if metrics:\n\t\tname: 'prometheus'\n\t- !for p in extra:\n\t\tname: '<<p>>'\nzones:\n\t!for region in regions:\n\t\tname: '<<region.name>>'\n\t\thosts:\n\t\t\t!for host in region.hosts:\n\t\t\t\turl: 'https://<<host>>.<<domain>>'\n\t\t!if region.primary:\n\t\t\tprimary: true\n",
+	)
+	.unwrap();
+	assert_eq!(
+		template.parameters(),
+		["domain", "extra", "metrics", "regions"]
+	);
+
+	let params = object! {
+		metrics: false,
+		extra: ["a", "b"],
+		domain: "example.com",
+		regions: [
+			{ name: "eu", hosts: ["x"], primary: true },
+			{ name: "us", hosts: [], primary: null },
+		],
+	};
+	assert_eq!(
+		template.render(&params).unwrap(),
+		object! {
+			plugins: ["base", { name: "a" }, { name: "b" }],
+			zones: [
+				{ name: "eu", hosts: [{ url: "https://x.example.com" }], primary: true },
+				{ name: "us", hosts: [] },
+			],
+		}
+	);
+
+	let params = object! { metrics: "yes", extra: [], domain: "", regions: [] };
+	assert_eq!(
+		template.render(&params).unwrap_err(),
+		TemplateError::NotABoolean {
+			path: "plugins[1].'!if metrics'".parse().unwrap(),
+			name: "metrics".to_string(),
+		}
+	);
+	let params = object! { metrics: true, extra: {}, domain: "", regions: [] };
+	assert_eq!(
+		template.render(&params).unwrap_err().to_string(),
+		"plugins[2].!for p in extra: parameter 'extra' isn't an array"
+	);
+
+	// directives of values must be quoted
+	let template = Template::from_value(object! { "!if a": { b: 1 }, "!for x in xs": [] });
+	assert_eq!(
+		template
+			.render(&object! { a: true })
+			.unwrap_err()
+			.to_string(),
+		"!for x in xs: '!for' has to be the only key of its object"
+	);
+	let template = Template::from_value(object! { "!if a": 1, "!iffy": 2 });
+	assert_eq!(
+		template
+			.render(&object! { a: true })
+			.unwrap_err()
+			.to_string(),
+		"!if a: '!if' in an object needs an object of entries"
+	);
+	assert_eq!(
+		template.render(&object! { a: false }).unwrap(),
+		object! { "!iffy": 2 }
+	);
+	let template = Template::from_value(object! { "!if a b": {} });
+	assert!(matches!(
+		template.render(&object! {}),
+		Err(TemplateError::InvalidDirective { .. })
+	));
+
+	// the lines of multi-line strings aren't directives
+	let template = Template::parse(
+		"notes: |\n\t!if ready: ship it\n\t!for x in xs: y\n!if ready:\n\tdone: true\n",
+	)
+	.unwrap();
+	assert_eq!(
+		template.render(&object! { ready: true }).unwrap(),
+		object! { notes: "!if ready: ship it\n!for x in xs: y", done: true }
+	);
+}
+
+#[test]
+fn profiles() {
+	use crate::{
+		parse_string_with_options, scanner::Scanner, value::PrimitiveValue, ParserOptions, Profile,
+	};
+
+	let source = "debug: yes\nlevel: ~\nserver.port: 80";
+	let permissive = ParserOptions::new().profile(Profile::Permissive);
+	assert_eq!(
+		parse_string_with_options(source, &permissive).unwrap(),
+		object! { debug: true, level: null, server: { port: 80 } }
+	);
+
+	let strict = ParserOptions::new().profile(Profile::Strict);
+	let error = parse_string_with_options(source, &strict).unwrap_err();
+	assert_eq!(error.code(), "E103_NEAR_MISS_LITERAL");
+	let error = parse_string_with_options("a: 1\ntimeout: !duration '5s'", &strict).unwrap_err();
+	assert_eq!(error.code(), "E109_NOT_IN_SPEC");
+	assert_eq!(
+		error.to_string(),
+		"1:9: tagged literals are not part of the KVON spec"
+	);
+
+	// hooks are extensions as well
+	let hook = |scanner: &mut Scanner| Ok(scanner.have("@").then(|| PrimitiveValue::from("at")));
+	let options = ParserOptions::new().primitive_parser(hook);
+	assert!(parse_string_with_options("a: @", &options).is_ok());
+	assert!(parse_string_with_options("a: @", &options.profile(Profile::Strict)).is_err());
+
+	// options set afterwards override the profile
+	let options = ParserOptions::new()
+		.profile(Profile::Strict)
+		.boolean_aliases(true);
+	assert_eq!(
+		parse_string_with_options("debug: yes", &options).unwrap(),
+		object! { debug: true }
+	);
+
+	#[cfg(feature = "ipaddr")]
+	{
+		let error = parse_string_with_options("hosts: [10.0.0.1]", &strict).unwrap_err();
+		assert_eq!(
+			error.kind,
+			crate::error::ParserErrorKind::NotInSpec {
+				extension: "IP address literals"
+			}
+		);
+		assert_eq!(error.column_number, 8);
+	}
+}
+
+#[test]
+fn feature_detection() {
+	use crate::{
+		encode::Encode,
+		features::{supports, Feature, SUPPORTED_FEATURES},
+		parse_string_output, FormatVersion, ParserOptions, Profile,
+	};
+
+	assert_eq!(SUPPORTED_FEATURES.spec_version, FormatVersion::LATEST);
+	for feature in SUPPORTED_FEATURES.extensions {
+		assert!(supports(feature.name()));
+		assert_eq!(feature.to_string().parse(), Ok(*feature));
+	}
+	assert!(supports("boolean-aliases"));
+	assert_eq!(supports("uuid-literals"), cfg!(feature = "uuid"));
+	assert!(!supports("BooleanAliases"));
+	assert_eq!("inline-objects".parse::<Feature>(), Err(()));
+
+	let encoded = SUPPORTED_FEATURES.encode();
+	assert_eq!(encoded.get("spec_version"), Some(&Value::from("1.0")));
+	assert_eq!(
+		encoded.get("extensions").unwrap().get_vector().unwrap()[2],
+		Value::from("dotted-keys")
+	);
+
+	let options = ParserOptions::new().profile(Profile::Strict);
+	let output = parse_string_output("a: 1", &options).unwrap();
+	assert_eq!(output.profile(), Some(Profile::Strict));
+	let output = parse_string_output("a: 1", &ParserOptions::new()).unwrap();
+	assert_eq!(output.profile(), None);
+}
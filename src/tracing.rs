@@ -0,0 +1,246 @@
+//! Structured logs in KVON. A [KvonLayer] writes the events of
+//! [tracing](https://docs.rs/tracing) as records of a record file, which
+//! [crate::records::parse_records] reads back.
+//!
+//! ```rust
+//! use std::{
+//!     io::{self, Write},
+//!     sync::{Arc, Mutex},
+//! };
+//!
+//! use kvon_rs::{object, records::parse_records, tracing::KvonLayer};
+//! use tracing_subscriber::prelude::*;
+//!
+//! #[derive(Clone, Default)]
+//! struct Log(Arc<Mutex<Vec<u8>>>);
+//!
+//! impl Write for Log {
+//!     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+//!         self.0.lock().unwrap().write(buf)
+//!     }
+//!
+//!     fn flush(&mut self) -> io::Result<()> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let log = Log::default();
+//! let subscriber = tracing_subscriber::registry().with(KvonLayer::new(log.clone()));
+//! tracing::subscriber::with_default(subscriber, || {
+//!     let _span = tracing::info_span!("request", id = 7).entered();
+//!     tracing::info!(target: "app", port = 8080, "started");
+//! });
+//!
+//! let log = log.0.lock().unwrap();
+//! let records: Vec<_> = parse_records(&log[..]).collect::<Result<_, _>>().unwrap();
+//! assert_eq!(
+//!     records,
+//!     [object! {
+//!         level: "INFO",
+//!         target: "app",
+//!         fields: { message: "started", port: 8080 },
+//!         spans: [{ name: "request", fields: { id: 7 } }],
+//!     }]
+//! );
+//! ```
+//!
+//! Each event is a record holding its `level`, `target` and `fields`, and
+//! the `spans` it happened in, the outermost first. Spans have their `name`
+//! and `fields`. Records span several lines, as KVON has no single-line
+//! objects, but each field is on a line of its own so that logs can still be
+//! searched with `grep`. Numbers are stored as `f32`, like all KVON numbers,
+//! except for NaN, infinities and numbers out of range of `f32`, which KVON
+//! can't write and become strings like `'NaN'`. Fields recorded with `Debug`
+//! become strings.
+
+use std::{
+	collections::HashMap,
+	fmt,
+	io::Write,
+	sync::Mutex,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use ::tracing::{
+	field::{Field, Visit},
+	span, Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::{records::encode_record, value::Value};
+
+impl Value {
+	/// The record of an event, without the spans it happened in, see the
+	/// [module documentation](self).
+	pub fn from_event(event: &Event<'_>) -> Value {
+		let metadata = event.metadata();
+		let mut fields = FieldVisitor(HashMap::new());
+		event.record(&mut fields);
+
+		let mut obj = HashMap::new();
+		obj.insert("level".to_string(), Value::from(metadata.level().as_str()));
+		obj.insert("target".to_string(), Value::from(metadata.target()));
+		obj.insert("fields".to_string(), Value::Object(fields.0));
+		Value::Object(obj)
+	}
+}
+
+/// Collects the fields of an event or span into an object.
+struct FieldVisitor(HashMap<String, Value>);
+
+impl FieldVisitor {
+	fn insert(&mut self, field: &Field, value: Value) {
+		self.0.insert(field.name().to_string(), value);
+	}
+}
+
+impl Visit for FieldVisitor {
+	fn record_f64(&mut self, field: &Field, value: f64) {
+		match value as f32 {
+			n if n.is_finite() => self.insert(field, Value::from(n)),
+			_ => self.insert(field, Value::from(format!("{value:?}"))),
+		}
+	}
+
+	fn record_i64(&mut self, field: &Field, value: i64) {
+		self.insert(field, Value::from(value as f32));
+	}
+
+	fn record_u64(&mut self, field: &Field, value: u64) {
+		self.insert(field, Value::from(value as f32));
+	}
+
+	fn record_bool(&mut self, field: &Field, value: bool) {
+		self.insert(field, Value::from(value));
+	}
+
+	fn record_str(&mut self, field: &Field, value: &str) {
+		self.insert(field, Value::from(value));
+	}
+
+	fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+		self.insert(field, Value::from(format!("{value:?}")));
+	}
+}
+
+/// The fields of a span, stored in its extensions.
+struct SpanFields(HashMap<String, Value>);
+
+/// A [Layer] writing events as KVON records, see the
+/// [module documentation](self).
+pub struct KvonLayer<W> {
+	writer: Mutex<W>,
+	timestamps: bool,
+}
+
+impl<W: Write> KvonLayer<W> {
+	/// Writes records to `writer`, e.g. [std::io::stderr] or a file.
+	pub fn new(writer: W) -> Self {
+		Self {
+			writer: Mutex::new(writer),
+			timestamps: false,
+		}
+	}
+
+	/// Adds the time of each event as `time`, a UTC timestamp like
+	/// `'2024-05-01T12:30:00.250Z'`.
+	pub fn with_timestamps(mut self) -> Self {
+		self.timestamps = true;
+		self
+	}
+}
+
+impl<S, W> Layer<S> for KvonLayer<W>
+where
+	S: Subscriber + for<'a> LookupSpan<'a>,
+	W: Write + Send + 'static,
+{
+	fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+		let Some(span) = ctx.span(id) else {
+			return;
+		};
+		let mut fields = FieldVisitor(HashMap::new());
+		attrs.record(&mut fields);
+		span.extensions_mut().insert(SpanFields(fields.0));
+	}
+
+	fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+		let Some(span) = ctx.span(id) else {
+			return;
+		};
+		let mut extensions = span.extensions_mut();
+		if let Some(SpanFields(fields)) = extensions.get_mut::<SpanFields>() {
+			let mut visitor = FieldVisitor(std::mem::take(fields));
+			values.record(&mut visitor);
+			*fields = visitor.0;
+		}
+	}
+
+	fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+		let mut record = Value::from_event(event);
+		let Value::Object(obj) = &mut record else {
+			unreachable!()
+		};
+		if let Some(scope) = ctx.event_scope(event) {
+			let spans = scope
+				.from_root()
+				.map(|span| {
+					let fields = span
+						.extensions()
+						.get::<SpanFields>()
+						.map(|SpanFields(fields)| fields.clone())
+						.unwrap_or_default();
+					let mut obj = HashMap::new();
+					obj.insert("name".to_string(), Value::from(span.name()));
+					obj.insert("fields".to_string(), Value::Object(fields));
+					Value::Object(obj)
+				})
+				.collect();
+			obj.insert("spans".to_string(), Value::Array(spans));
+		}
+		if self.timestamps {
+			obj.insert(
+				"time".to_string(),
+				Value::from(timestamp(SystemTime::now())),
+			);
+		}
+
+		// logging must not fail the program, so errors are dropped like
+		// tracing-subscriber's own layers do
+		let encoded = encode_record(&record);
+		if let Ok(mut writer) = self.writer.lock() {
+			let _ = writer.write_all(encoded.as_bytes());
+		}
+	}
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp with milliseconds.
+pub(crate) fn timestamp(time: SystemTime) -> String {
+	let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+	let secs = since_epoch.as_secs();
+	let (days, secs) = (secs / 86400, secs % 86400);
+
+	// converts days since 1970-01-01 to a date of the proleptic Gregorian
+	// calendar, see http://howardhinnant.github.io/date_algorithms.html
+	let days = days + 719468;
+	let era = days / 146097;
+	let day_of_era = days % 146097;
+	let year_of_era =
+		(day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month_index = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+	let month = match month_index < 10 {
+		true => month_index + 3,
+		false => month_index - 9,
+	};
+	let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+	format!(
+		"{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+		secs / 3600,
+		secs / 60 % 60,
+		secs % 60,
+		since_epoch.subsec_millis()
+	)
+}
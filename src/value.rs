@@ -1,15 +1,57 @@
-use std::collections::HashMap;
+use std::{
+	cmp::Ordering,
+	collections::{HashMap, HashSet},
+	hash::{Hash, Hasher},
+};
+
+use crate::{
+	decode::{Decode, DecodeError, DecodeErrorKind},
+	encode_string,
+	indention::Indention,
+	matrix::Matrix,
+	parse_string,
+	path::{KvonPath, Path, PathSegment},
+	schema::{Coercion, Schema},
+	tags::{CustomScalar, CustomValue, InvalidCustomScalar},
+	EncodeOptions, FloatFormat, MultiLineStrings, ParserResult, QuoteStyle, TrailingNewlines,
+};
 
 pub type GetterResult<T> = Result<T, ()>;
 
-#[derive(Debug, Clone, PartialEq)]
+/// A scalar value.
+///
+/// Primitives are totally ordered:
+/// `null < booleans < numbers < strings < durations < byte sizes < UUIDs <
+/// IP addresses < versions < custom scalars`. Versions are compared by
+/// semver precedence.
+/// Numbers are compared with all NaNs being equal to each other and greater
+/// than every other number, and with `-0.0` being equal to `0.0`.
+#[derive(Debug, Clone)]
 pub enum PrimitiveValue {
 	Number(f32),
 	String(String),
 	Boolean(bool),
 	Null,
+	/// A duration literal such as `2h30m`.
+	#[cfg(feature = "quantities")]
+	Duration(std::time::Duration),
+	/// A byte size literal such as `10MiB`, in bytes.
+	#[cfg(feature = "quantities")]
+	ByteSize(u64),
+	/// A UUID literal such as `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+	#[cfg(feature = "uuid")]
+	Uuid(uuid::Uuid),
+	/// An IPv4 or IPv6 address literal such as `10.0.0.1` or `fe80::1`.
+	#[cfg(feature = "ipaddr")]
+	Ip(std::net::IpAddr),
+	/// A semantic version literal such as `1.2.3-alpha+build`.
+	#[cfg(feature = "semver")]
+	Version(semver::Version),
+	/// An application defined scalar, see [crate::tags].
+	Custom(CustomValue),
 }
 
+#[allow(clippy::result_unit_err)]
 impl PrimitiveValue {
 	pub fn is_number(&self) -> bool {
 		matches!(self, Self::Number(_))
@@ -27,6 +69,35 @@ impl PrimitiveValue {
 		matches!(self, Self::Null)
 	}
 
+	#[cfg(feature = "quantities")]
+	pub fn is_duration(&self) -> bool {
+		matches!(self, Self::Duration(_))
+	}
+
+	#[cfg(feature = "quantities")]
+	pub fn is_byte_size(&self) -> bool {
+		matches!(self, Self::ByteSize(_))
+	}
+
+	#[cfg(feature = "uuid")]
+	pub fn is_uuid(&self) -> bool {
+		matches!(self, Self::Uuid(_))
+	}
+
+	#[cfg(feature = "ipaddr")]
+	pub fn is_ip(&self) -> bool {
+		matches!(self, Self::Ip(_))
+	}
+
+	#[cfg(feature = "semver")]
+	pub fn is_version(&self) -> bool {
+		matches!(self, Self::Version(_))
+	}
+
+	pub fn is_custom(&self) -> bool {
+		matches!(self, Self::Custom(_))
+	}
+
 	pub fn get_number(&self) -> GetterResult<f32> {
 		match self {
 			Self::Number(n) => Ok(*n),
@@ -47,6 +118,76 @@ impl PrimitiveValue {
 			_ => Err(()),
 		}
 	}
+
+	#[cfg(feature = "quantities")]
+	pub fn get_duration(&self) -> GetterResult<std::time::Duration> {
+		match self {
+			Self::Duration(d) => Ok(*d),
+			_ => Err(()),
+		}
+	}
+
+	/// Returns the byte size in bytes.
+	#[cfg(feature = "quantities")]
+	pub fn get_byte_size(&self) -> GetterResult<u64> {
+		match self {
+			Self::ByteSize(b) => Ok(*b),
+			_ => Err(()),
+		}
+	}
+
+	#[cfg(feature = "uuid")]
+	pub fn get_uuid(&self) -> GetterResult<uuid::Uuid> {
+		match self {
+			Self::Uuid(uuid) => Ok(*uuid),
+			_ => Err(()),
+		}
+	}
+
+	#[cfg(feature = "ipaddr")]
+	pub fn get_ip(&self) -> GetterResult<std::net::IpAddr> {
+		match self {
+			Self::Ip(ip) => Ok(*ip),
+			_ => Err(()),
+		}
+	}
+
+	#[cfg(feature = "semver")]
+	pub fn get_version(&self) -> GetterResult<&semver::Version> {
+		match self {
+			Self::Version(version) => Ok(version),
+			_ => Err(()),
+		}
+	}
+
+	/// Returns true if the primitive is a version matching the requirement,
+	/// e.g. `^1.2`.
+	#[cfg(feature = "semver")]
+	pub fn matches_version(&self, requirement: &semver::VersionReq) -> bool {
+		matches!(self, Self::Version(version) if requirement.matches(version))
+	}
+
+	/// Wraps a custom scalar, see [CustomValue::new].
+	pub fn custom(scalar: impl CustomScalar + 'static) -> Result<Self, InvalidCustomScalar> {
+		CustomValue::new(scalar).map(Self::Custom)
+	}
+
+	/// Returns the custom scalar if it is of type `T`.
+	pub fn get_custom<T: CustomScalar + 'static>(&self) -> GetterResult<&T> {
+		match self {
+			Self::Custom(custom) => custom.scalar().as_any().downcast_ref().ok_or(()),
+			_ => Err(()),
+		}
+	}
+
+	/// Takes ownership of the string. Returns the primitive back if it isn't a
+	/// string.
+	pub fn into_string(self) -> Result<String, Self> {
+		match self {
+			Self::String(s) => Ok(s),
+			other => Err(other),
+		}
+	}
 }
 
 impl From<f32> for PrimitiveValue {
@@ -73,21 +214,308 @@ impl From<char> for PrimitiveValue {
 	}
 }
 
+#[cfg(feature = "quantities")]
+impl From<std::time::Duration> for PrimitiveValue {
+	fn from(value: std::time::Duration) -> Self {
+		Self::Duration(value)
+	}
+}
+
+#[cfg(feature = "uuid")]
+impl From<uuid::Uuid> for PrimitiveValue {
+	fn from(value: uuid::Uuid) -> Self {
+		Self::Uuid(value)
+	}
+}
+
+#[cfg(feature = "ipaddr")]
+impl From<std::net::IpAddr> for PrimitiveValue {
+	fn from(value: std::net::IpAddr) -> Self {
+		Self::Ip(value)
+	}
+}
+
+#[cfg(feature = "semver")]
+impl From<semver::Version> for PrimitiveValue {
+	fn from(value: semver::Version) -> Self {
+		Self::Version(value)
+	}
+}
+
 impl From<bool> for PrimitiveValue {
 	fn from(value: bool) -> Self {
 		Self::Boolean(value)
 	}
 }
 
+impl PrimitiveValue {
+	/// The position of the variant in the cross-type ordering.
+	fn type_rank(&self) -> u8 {
+		match self {
+			Self::Null => 0,
+			Self::Boolean(_) => 1,
+			Self::Number(_) => 2,
+			Self::String(_) => 3,
+			#[cfg(feature = "quantities")]
+			Self::Duration(_) => 4,
+			#[cfg(feature = "quantities")]
+			Self::ByteSize(_) => 5,
+			#[cfg(feature = "uuid")]
+			Self::Uuid(_) => 6,
+			#[cfg(feature = "ipaddr")]
+			Self::Ip(_) => 7,
+			#[cfg(feature = "semver")]
+			Self::Version(_) => 8,
+			Self::Custom(_) => 9,
+		}
+	}
+}
+
+/// Maps a number to a representative for which `total_cmp` agrees with the
+/// equality used by [PrimitiveValue].
+fn canonical_number(n: f32) -> f32 {
+	if n.is_nan() {
+		f32::NAN
+	} else if n == 0.0 {
+		0.0
+	} else {
+		n
+	}
+}
+
+impl PartialEq for PrimitiveValue {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+
+impl Eq for PrimitiveValue {}
+
+impl PartialOrd for PrimitiveValue {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for PrimitiveValue {
+	fn cmp(&self, other: &Self) -> Ordering {
+		match (self, other) {
+			(Self::Number(a), Self::Number(b)) => {
+				canonical_number(*a).total_cmp(&canonical_number(*b))
+			}
+			(Self::String(a), Self::String(b)) => a.cmp(b),
+			(Self::Boolean(a), Self::Boolean(b)) => a.cmp(b),
+			#[cfg(feature = "quantities")]
+			(Self::Duration(a), Self::Duration(b)) => a.cmp(b),
+			#[cfg(feature = "quantities")]
+			(Self::ByteSize(a), Self::ByteSize(b)) => a.cmp(b),
+			#[cfg(feature = "uuid")]
+			(Self::Uuid(a), Self::Uuid(b)) => a.cmp(b),
+			#[cfg(feature = "ipaddr")]
+			(Self::Ip(a), Self::Ip(b)) => a.cmp(b),
+			#[cfg(feature = "semver")]
+			(Self::Version(a), Self::Version(b)) => a.cmp(b),
+			(Self::Custom(a), Self::Custom(b)) => {
+				(a.tag(), a.literal()).cmp(&(b.tag(), b.literal()))
+			}
+			_ => self.type_rank().cmp(&other.type_rank()),
+		}
+	}
+}
+
+impl Hash for PrimitiveValue {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.type_rank().hash(state);
+		match self {
+			Self::Number(n) => canonical_number(*n).to_bits().hash(state),
+			Self::String(s) => s.hash(state),
+			Self::Boolean(b) => b.hash(state),
+			Self::Null => {}
+			#[cfg(feature = "quantities")]
+			Self::Duration(d) => d.hash(state),
+			#[cfg(feature = "quantities")]
+			Self::ByteSize(b) => b.hash(state),
+			#[cfg(feature = "uuid")]
+			Self::Uuid(uuid) => uuid.hash(state),
+			#[cfg(feature = "ipaddr")]
+			Self::Ip(ip) => ip.hash(state),
+			#[cfg(feature = "semver")]
+			Self::Version(version) => version.hash(state),
+			Self::Custom(custom) => {
+				custom.tag().hash(state);
+				custom.literal().hash(state);
+			}
+		}
+	}
+}
+
 /// Possible values keys can map to, or arrays contain.
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Values are totally ordered: `primitives < arrays < objects < raw values`.
+/// Arrays are compared lexicographically, and objects are compared as lists of
+/// entries sorted by key.
+///
+/// Objects are hash maps, so iterating over their entries visits the keys in
+/// an unspecified order that differs between runs. Sort the keys where the
+/// order matters, like [encode_string] does. Documents whose many small
+/// objects dominate the cost of parsing them can be parsed into a
+/// [CompactValue](crate::compact::CompactValue), whose small objects are lists
+/// sorted by key, or into an arena with the `arena` feature, whose objects are
+/// compact lists keeping the order of the document.
+///
+/// The alternate debug format, `{:#?}`, prints values as KVON documents, see
+/// [Value::pretty].
+#[derive(Clone)]
 pub enum Value {
 	Primitive(PrimitiveValue),
 	Object(HashMap<String, Value>),
 	Array(Vec<Value>),
+	/// Unparsed source text, see [crate::ParserOptions::raw_paths].
+	Raw(RawValue),
 }
 
+#[allow(clippy::result_unit_err)]
 impl Value {
+	/// Encodes the value as a document with sorted keys, for logs and test
+	/// output.
+	pub fn pretty(&self) -> String {
+		encode_string(self, &EncodeOptions::new())
+			.trim_start_matches('\n')
+			.to_string()
+	}
+
+	/// Encodes the value in a canonical form intended for snapshot tests,
+	/// e.g. with `insta::assert_snapshot!(value.to_stable_string())`. Keys are
+	/// sorted, numbers use their shortest representation with `-0` written as
+	/// `0`, and the format doesn't depend on the defaults of
+	/// [EncodeOptions], so snapshots only change when the value does.
+	///
+	/// Volatile values such as timestamps or generated ids can be replaced
+	/// with [Value::redact_path] first.
+	pub fn to_stable_string(&self) -> String {
+		let options = EncodeOptions::new()
+			.indention(Indention::Tabs)
+			.quote_style(QuoteStyle::Single)
+			.multi_line_strings(MultiLineStrings::WhenNeeded)
+			.trailing_newlines(TrailingNewlines::Keep)
+			.float_format(FloatFormat::Shortest)
+			.always_include_fraction(false);
+		let canonical = self.clone().map_numbers(|n| if n == 0.0 { 0.0 } else { n });
+		encode_string(&canonical, &options)
+			.trim_start_matches('\n')
+			.to_string()
+	}
+
+	/// Replaces the values at a dotted path, e.g. `servers.*.id`, with a
+	/// placeholder and returns how many were replaced. Segments match an
+	/// object's key or an array's index, and `*` matches all of them. The
+	/// empty path is the value itself.
+	pub fn redact_path(&mut self, path: &str, placeholder: impl Into<Value>) -> usize {
+		let placeholder = placeholder.into();
+		self.for_each_at(path, &mut |value| {
+			*value = placeholder.clone();
+			1
+		})
+	}
+
+	/// Sorts the arrays at a dotted path like the one of [Value::redact_path]
+	/// and returns how many were sorted, e.g. to keep `allowed_ips` in
+	/// order. The sort is stable.
+	///
+	/// ```rust
+	/// use kvon_rs::object;
+	///
+	/// let mut value = object! { servers: [{ ports: [443, 80] }, { ports: [8080, 22] }] };
+	/// assert_eq!(value.sort_arrays_by("servers.*.ports", Ord::cmp), 2);
+	/// assert_eq!(value, object! { servers: [{ ports: [80, 443] }, { ports: [22, 8080] }] });
+	/// ```
+	pub fn sort_arrays_by(
+		&mut self,
+		path: &str,
+		mut compare: impl FnMut(&Value, &Value) -> Ordering,
+	) -> usize {
+		self.for_each_at(path, &mut |value| match value {
+			Value::Array(arr) => {
+				arr.sort_by(&mut compare);
+				1
+			}
+			_ => 0,
+		})
+	}
+
+	/// Removes the values of the arrays at a dotted path like the one of
+	/// [Value::redact_path] that repeat the `key` of an earlier value, and
+	/// returns how many were removed. `key` is a dotted path into the values,
+	/// e.g. `name` for `plugins` listed by name, or empty to compare the
+	/// values themselves. Values without the key are kept.
+	///
+	/// ```rust
+	/// use kvon_rs::object;
+	///
+	/// let mut value = object! {
+	///     plugins: [{ name: "a", v: 1 }, { name: "b" }, { name: "a", v: 2 }],
+	///     tags: ["x", "y", "x"],
+	/// };
+	/// assert_eq!(value.dedup_arrays("plugins", "name"), 1);
+	/// assert_eq!(value.dedup_arrays("tags", ""), 1);
+	/// assert_eq!(
+	///     value,
+	///     object! { plugins: [{ name: "a", v: 1 }, { name: "b" }], tags: ["x", "y"] }
+	/// );
+	/// ```
+	pub fn dedup_arrays(&mut self, path: &str, key: &str) -> usize {
+		let key = path_segments(key);
+		self.for_each_at(path, &mut |value| {
+			let Value::Array(arr) = value else {
+				return 0;
+			};
+			let len = arr.len();
+			let mut seen = HashSet::new();
+			arr.retain(|item| {
+				let key = key.iter().try_fold(item, |value, segment| match value {
+					Value::Array(arr) => arr.get(segment.parse::<usize>().ok()?),
+					_ => value.get(segment),
+				});
+				key.is_none_or(|key| seen.insert(key.clone()))
+			});
+			len - arr.len()
+		})
+	}
+
+	/// Calls `f` with the values at a dotted path, see [Value::redact_path],
+	/// and sums up what it returns.
+	fn for_each_at(&mut self, path: &str, f: &mut impl FnMut(&mut Value) -> usize) -> usize {
+		fn helper(
+			value: &mut Value,
+			segments: &[&str],
+			f: &mut impl FnMut(&mut Value) -> usize,
+		) -> usize {
+			let Some((segment, rest)) = segments.split_first() else {
+				return f(value);
+			};
+
+			match value {
+				Value::Object(obj) if *segment == "*" => {
+					obj.values_mut().map(|value| helper(value, rest, f)).sum()
+				}
+				Value::Object(obj) => obj
+					.get_mut(*segment)
+					.map_or(0, |value| helper(value, rest, f)),
+				Value::Array(arr) if *segment == "*" => {
+					arr.iter_mut().map(|value| helper(value, rest, f)).sum()
+				}
+				Value::Array(arr) => segment
+					.parse::<usize>()
+					.ok()
+					.and_then(|i| arr.get_mut(i))
+					.map_or(0, |value| helper(value, rest, f)),
+				_ => 0,
+			}
+		}
+
+		helper(self, &path_segments(path), f)
+	}
+
 	pub fn empty_object() -> Value {
 		Value::Object(HashMap::new())
 	}
@@ -135,6 +563,441 @@ impl Value {
 		}
 	}
 
+	/// Returns the value of a key of an object.
+	pub fn get(&self, key: &str) -> Option<&Value> {
+		self.get_objects().ok()?.get(key)
+	}
+
+	/// Returns an element of an array.
+	pub fn get_index(&self, index: usize) -> Option<&Value> {
+		self.get_vector().ok()?.get(index)
+	}
+
+	/// Returns the objects of an array of objects, e.g. the `servers` of
+	/// `servers:--` followed by `-` blocks. Fails if the key is missing or one
+	/// of the elements isn't an object, with the path of the first offending
+	/// element, e.g. `servers[2]: expected object, found number`.
+	pub fn get_array_of_objects(
+		&self,
+		key: &str,
+	) -> Result<Vec<&HashMap<String, Value>>, DecodeError> {
+		let value = self
+			.get(key)
+			.ok_or_else(|| DecodeError::new(DecodeErrorKind::MissingField(key.to_string())))?;
+		let arr = value.get_vector().map_err(|_| {
+			DecodeError::invalid_type("array", value).within(PathSegment::Key(key.to_string()))
+		})?;
+		arr.iter()
+			.enumerate()
+			.map(|(i, item)| {
+				item.get_objects().map_err(|_| {
+					DecodeError::invalid_type("object", item)
+						.within(PathSegment::Index(i))
+						.within(PathSegment::Key(key.to_string()))
+				})
+			})
+			.collect()
+	}
+
+	/// Decodes an array of exactly `N` elements, e.g. an RGB color stored as
+	/// `[255 128 0]`.
+	///
+	/// ```rust
+	/// use kvon_rs::parse_string;
+	///
+	/// let value = parse_string("color: [255 128 0]").unwrap();
+	/// let rgb: [u8; 3] = value.get("color").unwrap().get_array_of().unwrap();
+	/// assert_eq!(rgb, [255, 128, 0]);
+	/// ```
+	pub fn get_array_of<T: Decode, const N: usize>(&self) -> Result<[T; N], DecodeError> {
+		<[T; N]>::decode(self)
+	}
+
+	/// Decodes a rectangular array of arrays, see [Matrix].
+	pub fn get_matrix<T: Decode>(&self) -> Result<Matrix<T>, DecodeError> {
+		Matrix::decode(self)
+	}
+
+	/// Decodes an array of two elements, e.g. a coordinate.
+	pub fn get_tuple2<A: Decode, B: Decode>(&self) -> Result<(A, B), DecodeError> {
+		<(A, B)>::decode(self)
+	}
+
+	/// Decodes an array of three elements, e.g. a version triple.
+	pub fn get_tuple3<A: Decode, B: Decode, C: Decode>(&self) -> Result<(A, B, C), DecodeError> {
+		<(A, B, C)>::decode(self)
+	}
+
+	/// Returns the value at the given path, e.g. the one produced by
+	/// [Value::transform].
+	pub fn get_path(&self, path: &Path) -> Option<&Value> {
+		path.iter().try_fold(self, |value, segment| match segment {
+			PathSegment::Key(key) => value.get(key),
+			PathSegment::Index(index) => value.get_index(*index),
+		})
+	}
+
+	/// Iterates over the keys of an object. Yields nothing for other values.
+	pub fn keys(&self) -> impl Iterator<Item = &String> {
+		self.get_objects()
+			.ok()
+			.into_iter()
+			.flat_map(|obj| obj.keys())
+	}
+
+	/// Iterates over the values of an object. Yields nothing for other values.
+	pub fn values(&self) -> impl Iterator<Item = &Value> {
+		self.get_objects()
+			.ok()
+			.into_iter()
+			.flat_map(|obj| obj.values())
+	}
+
+	/// Iterates over the entries of an object. Yields nothing for other
+	/// values.
+	pub fn iter_object(&self) -> impl Iterator<Item = (&String, &Value)> {
+		self.get_objects().ok().into_iter().flatten()
+	}
+
+	/// Iterates over the elements of an array. Yields nothing for other
+	/// values.
+	pub fn iter_array(&self) -> impl Iterator<Item = &Value> {
+		self.get_vector().ok().into_iter().flatten()
+	}
+
+	/// The amount of entries in an object or elements in an array. Primitives
+	/// have a length of zero.
+	pub fn len(&self) -> usize {
+		match self {
+			Self::Object(obj) => obj.len(),
+			Self::Array(arr) => arr.len(),
+			Self::Primitive(_) | Self::Raw(_) => 0,
+		}
+	}
+
+	/// Returns true if [Value::len] is zero.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Takes ownership of the object's map. Returns the value back if it isn't
+	/// an object.
+	pub fn into_object(self) -> Result<HashMap<String, Value>, Self> {
+		match self {
+			Self::Object(obj) => Ok(obj),
+			other => Err(other),
+		}
+	}
+
+	/// Takes ownership of the array's elements. Returns the value back if it
+	/// isn't an array.
+	pub fn into_vector(self) -> Result<Vec<Value>, Self> {
+		match self {
+			Self::Array(arr) => Ok(arr),
+			other => Err(other),
+		}
+	}
+
+	/// Takes ownership of the primitive. Returns the value back if it isn't a
+	/// primitive.
+	pub fn into_primitive(self) -> Result<PrimitiveValue, Self> {
+		match self {
+			Self::Primitive(primitive) => Ok(primitive),
+			other => Err(other),
+		}
+	}
+
+	/// Takes ownership of the string primitive. Returns the value back if it
+	/// isn't a string.
+	pub fn into_string(self) -> Result<String, Self> {
+		match self {
+			Self::Primitive(PrimitiveValue::String(s)) => Ok(s),
+			other => Err(other),
+		}
+	}
+
+	/// Returns the number, or the value back if it isn't a number.
+	pub fn into_number(self) -> Result<f32, Self> {
+		match self {
+			Self::Primitive(PrimitiveValue::Number(n)) => Ok(n),
+			other => Err(other),
+		}
+	}
+
+	/// Returns the boolean, or the value back if it isn't a boolean.
+	pub fn into_boolean(self) -> Result<bool, Self> {
+		match self {
+			Self::Primitive(PrimitiveValue::Boolean(b)) => Ok(b),
+			other => Err(other),
+		}
+	}
+
+	/// Rebuilds the tree bottom up, replacing every value with the result of
+	/// `f`. Children are transformed before their parents, so `f` receives
+	/// objects and arrays whose elements have already been transformed.
+	pub fn transform(self, f: &mut impl FnMut(&Path, Value) -> Value) -> Value {
+		fn helper(
+			path: &mut KvonPath,
+			value: Value,
+			f: &mut impl FnMut(&Path, Value) -> Value,
+		) -> Value {
+			let value = match value {
+				Value::Object(obj) => Value::Object(
+					obj.into_iter()
+						.map(|(key, value)| {
+							path.push(PathSegment::Key(key));
+							let value = helper(path, value, f);
+							match path.pop() {
+								Some(PathSegment::Key(key)) => (key, value),
+								_ => unreachable!(),
+							}
+						})
+						.collect(),
+				),
+				Value::Array(arr) => Value::Array(
+					arr.into_iter()
+						.enumerate()
+						.map(|(i, value)| {
+							path.push(PathSegment::Index(i));
+							let value = helper(path, value, f);
+							path.pop();
+							value
+						})
+						.collect(),
+				),
+				primitive => primitive,
+			};
+
+			f(path, value)
+		}
+
+		helper(&mut KvonPath::root(), self, f)
+	}
+
+	/// Replaces every string primitive in the tree with the result of `f`.
+	/// Keys are left untouched.
+	pub fn map_strings(self, mut f: impl FnMut(String) -> String) -> Value {
+		self.transform(&mut |_, value| match value {
+			Value::Primitive(PrimitiveValue::String(s)) => Value::from(f(s)),
+			value => value,
+		})
+	}
+
+	/// Replaces every number primitive in the tree with the result of `f`.
+	pub fn map_numbers(self, mut f: impl FnMut(f32) -> f32) -> Value {
+		self.transform(&mut |_, value| match value {
+			Value::Primitive(PrimitiveValue::Number(n)) => Value::from(f(n)),
+			value => value,
+		})
+	}
+
+	/// Fills in keys that are missing from this object, recursively, with the
+	/// values from `defaults`. Keys that are present are kept, even if their
+	/// value has a different type than the default.
+	pub fn apply_defaults(&mut self, defaults: &Value) {
+		if let (Value::Object(obj), Value::Object(defaults)) = (self, defaults) {
+			for (key, default) in defaults {
+				match obj.get_mut(key) {
+					Some(value) => value.apply_defaults(default),
+					None => {
+						obj.insert(key.clone(), default.clone());
+					}
+				}
+			}
+		}
+	}
+
+	/// Converts primitives into the type the schema expects where that is
+	/// lossless, e.g. `'8080'` into `8080` or `'true'` into `true`, and
+	/// returns every conversion performed. Values that can't be converted are
+	/// left as they are, to be reported when decoding.
+	pub fn coerce(&mut self, schema: &Schema) -> Vec<Coercion> {
+		fn helper(
+			path: &mut KvonPath,
+			value: &mut Value,
+			schema: &Schema,
+			coercions: &mut Vec<Coercion>,
+		) {
+			match (schema, value) {
+				(Schema::Constrained(schema, _), value) => helper(path, value, schema, coercions),
+				(Schema::Optional(schema), value) => helper(path, value, &schema.get(), coercions),
+				(Schema::Object { fields, .. }, Value::Object(obj)) => {
+					for (key, schema) in fields {
+						if let Some(value) = obj.get_mut(key.as_ref()) {
+							path.push(PathSegment::Key(key.to_string()));
+							helper(path, value, &schema.get(), coercions);
+							path.pop();
+						}
+					}
+				}
+				(Schema::Map(schema), Value::Object(obj)) => {
+					let schema = schema.get();
+					for (key, value) in obj {
+						path.push(PathSegment::Key(key.clone()));
+						helper(path, value, &schema, coercions);
+						path.pop();
+					}
+				}
+				(Schema::Array(schema), Value::Array(arr)) => {
+					let schema = schema.get();
+					for (i, value) in arr.iter_mut().enumerate() {
+						path.push(PathSegment::Index(i));
+						helper(path, value, &schema, coercions);
+						path.pop();
+					}
+				}
+				(schema, Value::Primitive(p)) => {
+					let coerced = match (schema, &*p) {
+						(Schema::Number, PrimitiveValue::String(s)) => s
+							.trim()
+							.parse::<f32>()
+							.ok()
+							.filter(|n| n.is_finite())
+							.map(PrimitiveValue::Number),
+						(Schema::Boolean, PrimitiveValue::String(s)) => match s.trim() {
+							"true" => Some(PrimitiveValue::Boolean(true)),
+							"false" => Some(PrimitiveValue::Boolean(false)),
+							_ => None,
+						},
+						(Schema::String, PrimitiveValue::Number(n)) => {
+							Some(PrimitiveValue::String(n.to_string()))
+						}
+						(Schema::String, PrimitiveValue::Boolean(b)) => {
+							Some(PrimitiveValue::String(b.to_string()))
+						}
+						_ => None,
+					};
+					if let Some(to) = coerced {
+						let from = std::mem::replace(p, to.clone());
+						coercions.push(Coercion {
+							path: path.clone(),
+							from,
+							to,
+						});
+					}
+				}
+				_ => {}
+			}
+		}
+
+		let mut coercions = Vec::new();
+		helper(&mut KvonPath::root(), self, schema, &mut coercions);
+		coercions
+	}
+
+	/// Converts nested objects and arrays into a single object whose keys
+	/// are the paths of the leaves joined by `separator`, e.g. `a.b.c` or
+	/// `servers.0.host` for array items. Empty objects and arrays are kept
+	/// as leaves. Values that aren't objects or arrays are returned as they
+	/// are.
+	pub fn flatten(&self, separator: &str) -> Value {
+		fn helper(
+			prefix: Option<String>,
+			value: &Value,
+			separator: &str,
+			flat: &mut HashMap<String, Value>,
+		) {
+			let key = |segment: &str| match &prefix {
+				Some(prefix) => format!("{prefix}{separator}{segment}"),
+				None => segment.to_string(),
+			};
+			match value {
+				Value::Object(obj) if !obj.is_empty() => {
+					for (k, v) in obj {
+						helper(Some(key(k)), v, separator, flat);
+					}
+				}
+				Value::Array(arr) if !arr.is_empty() => {
+					for (i, v) in arr.iter().enumerate() {
+						helper(Some(key(&i.to_string())), v, separator, flat);
+					}
+				}
+				value => {
+					flat.insert(prefix.unwrap_or_default(), value.clone());
+				}
+			}
+		}
+
+		match self {
+			Value::Object(_) | Value::Array(_) if !self.is_empty() => {
+				let mut flat = HashMap::new();
+				helper(None, self, separator, &mut flat);
+				Value::Object(flat)
+			}
+			value => value.clone(),
+		}
+	}
+
+	/// The inverse of [Value::flatten]: splits the keys of an object at
+	/// `separator` into nested objects. Objects whose keys are exactly `0`
+	/// to `n - 1` become arrays. If a key is both a leaf and the prefix of
+	/// other keys, e.g. `a` and `a.b`, the nested keys replace the leaf. See
+	/// [Value::try_unflatten] to detect such keys instead.
+	pub fn unflatten(&self, separator: &str) -> Value {
+		self.unflatten_with(separator, false).unwrap()
+	}
+
+	/// Like [Value::unflatten], but returns an error if a key is both a leaf
+	/// and the prefix of other keys. Empty objects can be extended.
+	pub fn try_unflatten(&self, separator: &str) -> Result<Value, UnflattenConflict> {
+		self.unflatten_with(separator, true)
+	}
+
+	fn unflatten_with(&self, separator: &str, strict: bool) -> Result<Value, UnflattenConflict> {
+		fn into_arrays(value: Value) -> Value {
+			match value {
+				Value::Object(obj) => {
+					let is_array = (0..obj.len()).all(|i| obj.contains_key(&i.to_string()));
+					let mut obj: HashMap<String, Value> =
+						obj.into_iter().map(|(k, v)| (k, into_arrays(v))).collect();
+					match is_array && !obj.is_empty() {
+						true => Value::Array(
+							(0..obj.len())
+								.map(|i| obj.remove(&i.to_string()).unwrap())
+								.collect(),
+						),
+						false => Value::Object(obj),
+					}
+				}
+				value => value,
+			}
+		}
+
+		let Value::Object(flat) = self else {
+			return Ok(self.clone());
+		};
+		// sorted, so that leaves come before the keys they prefix
+		let mut keys: Vec<_> = flat.keys().collect();
+		keys.sort();
+
+		let mut root = HashMap::new();
+		for key in keys {
+			let mut segments: Vec<&str> = key.split(separator).collect();
+			let last = segments.pop().unwrap();
+			let mut target = &mut root;
+			for (i, segment) in segments.iter().enumerate() {
+				let entry = target
+					.entry(segment.to_string())
+					.or_insert_with(Value::empty_object);
+				if !entry.is_object() {
+					if strict {
+						return Err(UnflattenConflict {
+							key: key.clone(),
+							prefix: segments[..=i].join(separator),
+						});
+					}
+					*entry = Value::empty_object();
+				}
+				let Value::Object(obj) = entry else {
+					unreachable!()
+				};
+				target = obj;
+			}
+			target.insert(last.to_string(), flat[key].clone());
+		}
+		Ok(into_arrays(Value::Object(root)))
+	}
+
 	pub fn object_from_iter<K, V, T>(iter: T) -> Value
 	where
 		K: ToString,
@@ -148,7 +1011,185 @@ impl Value {
 	}
 
 	pub fn object_from_vec(vec: Vec<(&str, Value)>) -> Value {
-		Self::object_from_iter(vec.into_iter())
+		Self::object_from_iter(vec)
+	}
+}
+
+/// A key of a flat object that is also the prefix of another key, see
+/// [Value::try_unflatten].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnflattenConflict {
+	pub key: String,
+	/// The leaf that `key` would be nested in.
+	pub prefix: String,
+}
+
+impl std::fmt::Display for UnflattenConflict {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"key '{}' conflicts with the value of '{}'",
+			self.key, self.prefix
+		)
+	}
+}
+
+impl std::error::Error for UnflattenConflict {}
+
+/// The source text of a value that was captured without being parsed.
+///
+/// A raw value consists of its head, the text following the key's colon, and
+/// its body, the indented lines below the key with the block's indention
+/// removed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RawValue {
+	head: String,
+	body: Vec<String>,
+	indention: Indention,
+}
+
+impl RawValue {
+	pub(crate) fn new(head: String, body: Vec<String>, indention: Indention) -> Self {
+		Self {
+			head,
+			body,
+			indention,
+		}
+	}
+
+	/// The text following the key's colon, e.g. `--`, ` |`, or ` [1 2]`.
+	pub fn head(&self) -> &str {
+		&self.head
+	}
+
+	/// The lines of the block below the key.
+	pub fn body(&self) -> &[String] {
+		&self.body
+	}
+
+	/// The indention used by the body.
+	pub fn indention(&self) -> Indention {
+		self.indention
+	}
+
+	/// Parses the captured text into a [Value].
+	pub fn parse(&self) -> ParserResult<Value> {
+		let indent = self.indention.to_string_unit();
+		let mut source = format!("raw:{}", self.head);
+		for line in &self.body {
+			source.push('\n');
+			source.push_str(&indent);
+			source.push_str(line);
+		}
+
+		let mut objects = parse_string(&source)?.into_object().unwrap();
+		Ok(objects.remove("raw").unwrap_or_else(Value::null))
+	}
+
+	/// Returns the body with its leading indention converted to `indent_str`
+	/// per level.
+	pub(crate) fn reindented_body(&self, indent_str: &str) -> Vec<String> {
+		let unit = self.indention.to_string_unit();
+		self.body
+			.iter()
+			.map(|line| {
+				let mut rest = line.as_str();
+				let mut reindented = String::new();
+				while let Some(stripped) = rest.strip_prefix(unit.as_str()) {
+					reindented.push_str(indent_str);
+					rest = stripped;
+				}
+				reindented.push_str(rest);
+				reindented
+			})
+			.collect()
+	}
+}
+
+impl Value {
+	/// The position of the variant in the cross-type ordering.
+	fn type_rank(&self) -> u8 {
+		match self {
+			Self::Primitive(_) => 0,
+			Self::Array(_) => 1,
+			Self::Object(_) => 2,
+			Self::Raw(_) => 3,
+		}
+	}
+}
+
+/// Splits a dotted path like the one of [Value::redact_path]. The empty path
+/// is the value itself.
+fn path_segments(path: &str) -> Vec<&str> {
+	match path.is_empty() {
+		true => Vec::new(),
+		false => path.split('.').collect(),
+	}
+}
+
+/// Returns the entries of an object sorted by key.
+fn sorted_entries(obj: &HashMap<String, Value>) -> Vec<(&String, &Value)> {
+	let mut entries = obj.iter().collect::<Vec<_>>();
+	entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+	entries
+}
+
+impl std::fmt::Debug for Value {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		if f.alternate() {
+			return f.write_str(&self.pretty());
+		}
+
+		match self {
+			Self::Primitive(p) => f.debug_tuple("Primitive").field(p).finish(),
+			Self::Object(obj) => f.debug_tuple("Object").field(obj).finish(),
+			Self::Array(arr) => f.debug_tuple("Array").field(arr).finish(),
+			Self::Raw(raw) => f.debug_tuple("Raw").field(raw).finish(),
+		}
+	}
+}
+
+impl PartialEq for Value {
+	fn eq(&self, other: &Self) -> bool {
+		match (self, other) {
+			(Self::Primitive(a), Self::Primitive(b)) => a == b,
+			(Self::Array(a), Self::Array(b)) => a == b,
+			(Self::Object(a), Self::Object(b)) => a == b,
+			(Self::Raw(a), Self::Raw(b)) => a == b,
+			_ => false,
+		}
+	}
+}
+
+impl Eq for Value {}
+
+impl PartialOrd for Value {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Value {
+	fn cmp(&self, other: &Self) -> Ordering {
+		match (self, other) {
+			(Self::Primitive(a), Self::Primitive(b)) => a.cmp(b),
+			(Self::Array(a), Self::Array(b)) => a.cmp(b),
+			(Self::Object(a), Self::Object(b)) => sorted_entries(a).cmp(&sorted_entries(b)),
+			(Self::Raw(a), Self::Raw(b)) => a.cmp(b),
+			_ => self.type_rank().cmp(&other.type_rank()),
+		}
+	}
+}
+
+impl Hash for Value {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.type_rank().hash(state);
+		match self {
+			Self::Primitive(p) => p.hash(state),
+			Self::Array(arr) => arr.hash(state),
+			Self::Object(obj) => sorted_entries(obj).hash(state),
+			Self::Raw(raw) => raw.hash(state),
+		}
 	}
 }
 
@@ -167,7 +1208,7 @@ impl From<i32> for Value {
 /// Adapted from https://docs.rs/json/0.12.4/src/json/lib.rs.html.
 #[macro_export]
 macro_rules! array {
-    [] => ($crate::value::new_array());
+    [] => ($crate::value::Value::Array(Vec::new()));
 
     // Handles for token tree items
     [@ITEM($( $i:expr, )*) $item:tt, $( $cont:tt )+] => {
@@ -218,7 +1259,7 @@ macro_rules! array {
 /// Adapted from https://docs.rs/json/0.12.4/src/json/lib.rs.html.
 #[macro_export]
 macro_rules! value {
-    ( null ) => { $crate::Null };
+    ( null ) => { $crate::value::Value::null() };
     ( [$( $token:tt )*] ) => {
         // 10
         $crate::array![ $( $token )* ]
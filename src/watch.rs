@@ -0,0 +1,266 @@
+//! Reloading documents when their file changes, e.g. to apply configuration
+//! changes to a running service.
+//!
+//! ```rust,no_run
+//! use kvon_rs::watch::ConfigWatcher;
+//!
+//! let watcher = ConfigWatcher::new("config.kvon").unwrap();
+//! println!("port: {:?}", watcher.current().get("port"));
+//! for event in watcher.events() {
+//!     match event {
+//!         Ok(reload) => {
+//!             for change in &reload.changes {
+//!                 println!("{change}");
+//!             }
+//!         }
+//!         Err(e) => eprintln!("keeping the previous config: {e}"),
+//!     }
+//! }
+//! ```
+//!
+//! The file is polled for changes in the background. A change is only read
+//! once the file stopped changing for a while, so editors saving in several
+//! steps don't produce partial reloads. Documents that fail to parse or to
+//! validate are reported and otherwise ignored, so [ConfigWatcher::current]
+//! always holds the last valid one.
+
+use std::{
+	fmt, fs, io,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		mpsc, Arc, Mutex,
+	},
+	thread,
+	time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+	diff::{diff, Change},
+	error::ParserError,
+	parse_string_with_options,
+	schema::{Schema, Violation},
+	value::Value,
+	ParserOptions,
+};
+
+/// Options for [ConfigWatcher::with_options].
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+	pub(crate) parser_options: ParserOptions,
+	pub(crate) schema: Option<Schema>,
+	pub(crate) poll_interval: Duration,
+	pub(crate) debounce: Duration,
+}
+
+impl Default for WatchOptions {
+	fn default() -> Self {
+		Self {
+			parser_options: ParserOptions::default(),
+			schema: None,
+			poll_interval: Duration::from_millis(500),
+			debounce: Duration::from_millis(200),
+		}
+	}
+}
+
+impl WatchOptions {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets the options the file is parsed with.
+	pub fn parser_options(mut self, options: ParserOptions) -> Self {
+		self.parser_options = options;
+		self
+	}
+
+	/// Rejects documents violating the constraints of `schema`, see
+	/// [Schema::validate].
+	pub fn schema(mut self, schema: Schema) -> Self {
+		self.schema = Some(schema);
+		self
+	}
+
+	/// Sets how often the file is checked for changes. Defaults to 500ms.
+	pub fn poll_interval(mut self, interval: Duration) -> Self {
+		self.poll_interval = interval;
+		self
+	}
+
+	/// Sets how long the file has to stay unchanged before it is read.
+	/// Defaults to 200ms.
+	pub fn debounce(mut self, debounce: Duration) -> Self {
+		self.debounce = debounce;
+		self
+	}
+}
+
+/// A new version of the watched document.
+#[derive(Debug, Clone)]
+pub struct Reload {
+	pub value: Arc<Value>,
+	/// The changes from the previous version, see [diff].
+	pub changes: Vec<Change>,
+}
+
+/// Errors that can happen while loading the watched document.
+#[derive(Debug)]
+pub enum WatchError {
+	Io(io::Error),
+	Parser(ParserError),
+	Schema(Vec<Violation>),
+}
+
+impl fmt::Display for WatchError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Io(e) => write!(f, "{e}"),
+			Self::Parser(e) => write!(f, "{e}"),
+			Self::Schema(violations) => {
+				let violations: Vec<String> = violations.iter().map(ToString::to_string).collect();
+				write!(f, "{}", violations.join(", "))
+			}
+		}
+	}
+}
+
+impl std::error::Error for WatchError {}
+
+impl From<io::Error> for WatchError {
+	fn from(e: io::Error) -> Self {
+		Self::Io(e)
+	}
+}
+
+impl From<ParserError> for WatchError {
+	fn from(e: ParserError) -> Self {
+		Self::Parser(e)
+	}
+}
+
+/// Watches a document and reloads it when its file changes. The background
+/// thread stops when the watcher is dropped.
+pub struct ConfigWatcher {
+	current: Arc<Mutex<Arc<Value>>>,
+	events: mpsc::Receiver<Result<Reload, WatchError>>,
+	stop: Arc<AtomicBool>,
+	thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ConfigWatcher {
+	/// Loads the document at `path` and starts watching it.
+	pub fn new(path: impl AsRef<Path>) -> Result<Self, WatchError> {
+		Self::with_options(path, WatchOptions::default())
+	}
+
+	/// Loads the document at `path` and starts watching it using the given
+	/// options. Fails if the document can't be loaded initially.
+	pub fn with_options(path: impl AsRef<Path>, options: WatchOptions) -> Result<Self, WatchError> {
+		let path = path.as_ref().to_path_buf();
+		let stamp = stamp(&path);
+		let current = Arc::new(Mutex::new(Arc::new(load(&path, &options)?)));
+		let (sender, events) = mpsc::channel();
+		let stop = Arc::new(AtomicBool::new(false));
+
+		let thread = thread::spawn({
+			let current = Arc::clone(&current);
+			let stop = Arc::clone(&stop);
+			move || poll(path, options, stamp, current, sender, stop)
+		});
+
+		Ok(Self {
+			current,
+			events,
+			stop,
+			thread: Some(thread),
+		})
+	}
+
+	/// The last version of the document that loaded successfully.
+	pub fn current(&self) -> Arc<Value> {
+		Arc::clone(&self.current.lock().unwrap())
+	}
+
+	/// The reloads and failed loads, in the order they happened. Iterating
+	/// blocks until the next event.
+	pub fn events(&self) -> &mpsc::Receiver<Result<Reload, WatchError>> {
+		&self.events
+	}
+}
+
+impl Drop for ConfigWatcher {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+/// What identifies a version of a file, or `None` if it can't be read.
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+	let metadata = fs::metadata(path).ok()?;
+	Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn load(path: &Path, options: &WatchOptions) -> Result<Value, WatchError> {
+	let source = fs::read_to_string(path)?;
+	let value = parse_string_with_options(&source, &options.parser_options)?;
+	if let Some(schema) = &options.schema {
+		let violations = schema.validate(&value);
+		if !violations.is_empty() {
+			return Err(WatchError::Schema(violations));
+		}
+	}
+	Ok(value)
+}
+
+fn poll(
+	path: PathBuf,
+	options: WatchOptions,
+	mut loaded: Stamp,
+	current: Arc<Mutex<Arc<Value>>>,
+	sender: mpsc::Sender<Result<Reload, WatchError>>,
+	stop: Arc<AtomicBool>,
+) {
+	// the last stamp seen and when it was first seen
+	let mut pending: Option<(Stamp, Instant)> = None;
+	while !stop.load(Ordering::Relaxed) {
+		thread::sleep(options.poll_interval);
+
+		let stamp = stamp(&path);
+		pending = match pending {
+			_ if stamp == loaded => None,
+			Some((pending, since)) if pending == stamp => Some((pending, since)),
+			_ => Some((stamp, Instant::now())),
+		};
+		let Some((stamp, since)) = pending else {
+			continue;
+		};
+		if since.elapsed() < options.debounce {
+			continue;
+		}
+
+		loaded = stamp;
+		pending = None;
+		let event = load(&path, &options).map(|value| {
+			let mut current = current.lock().unwrap();
+			let changes = diff(&current, &value);
+			*current = Arc::new(value);
+			Reload {
+				value: Arc::clone(&current),
+				changes,
+			}
+		});
+		// touching the file without changing it isn't a reload
+		if matches!(&event, Ok(reload) if reload.changes.is_empty()) {
+			continue;
+		}
+		if sender.send(event).is_err() {
+			return;
+		}
+	}
+}
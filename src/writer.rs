@@ -0,0 +1,212 @@
+//! Streaming encoding. A [KvonWriter] writes a document piece by piece as
+//! keys and values are passed to it, without building a [Value] first, so
+//! documents of any size can be written with constant memory.
+
+use std::io::{self, Write};
+
+use crate::{
+	encoder::{encode_key, encode_primitive_lines},
+	value::{PrimitiveValue, Value},
+	EncodeOptions,
+};
+
+/// A sink for the events making up a document. The root of the document is
+/// an object, so writing starts with a [KvonWrite::key].
+///
+/// Inside of objects every value is preceded by a key, inside of arrays
+/// values follow each other directly. [KvonWrite::end] closes the innermost
+/// object or array.
+pub trait KvonWrite {
+	/// Sets the key of the next value. Only valid inside of objects.
+	fn key(&mut self, key: &str) -> io::Result<()>;
+
+	fn value(&mut self, value: &PrimitiveValue) -> io::Result<()>;
+
+	fn begin_object(&mut self) -> io::Result<()>;
+
+	fn begin_array(&mut self) -> io::Result<()>;
+
+	fn end(&mut self) -> io::Result<()>;
+
+	/// Writes a whole [Value] as a sequence of events.
+	fn value_tree(&mut self, value: &Value) -> io::Result<()> {
+		match value {
+			Value::Primitive(p) => self.value(p),
+			Value::Object(obj) => {
+				self.begin_object()?;
+				let mut keys = obj.keys().collect::<Vec<_>>();
+				keys.sort_unstable();
+				for key in keys {
+					self.key(key)?;
+					self.value_tree(&obj[key])?;
+				}
+				self.end()
+			}
+			Value::Array(arr) => {
+				self.begin_array()?;
+				for value in arr {
+					self.value_tree(value)?;
+				}
+				self.end()
+			}
+			Value::Raw(raw) => match raw.parse() {
+				Ok(value) => self.value_tree(&value),
+				Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+			},
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+	Object,
+	Array,
+}
+
+/// Writes KVON to an [io::Write] as [KvonWrite] events arrive. Call
+/// [KvonWriter::finish] once done to check that all blocks were closed.
+///
+/// Key ordering options don't apply, as keys are written in the order they
+/// arrive, and arrays are always expanded.
+#[derive(Debug)]
+pub struct KvonWriter<W: Write> {
+	out: W,
+	options: EncodeOptions,
+	indention: String,
+	blocks: Vec<Block>,
+	pending_key: Option<String>,
+	wrote_root_key: bool,
+}
+
+impl<W: Write> KvonWriter<W> {
+	pub fn new(out: W) -> io::Result<Self> {
+		Self::with_options(out, EncodeOptions::new())
+	}
+
+	/// Creates a writer, writing the header comment of the options right
+	/// away.
+	pub fn with_options(mut out: W, options: EncodeOptions) -> io::Result<Self> {
+		if let Some(header) = &options.header {
+			for line in header.lines() {
+				writeln!(out, "# {line}")?;
+			}
+		}
+
+		Ok(Self {
+			out,
+			indention: options.indention.to_string_unit(),
+			options,
+			blocks: vec![Block::Object],
+			pending_key: None,
+			wrote_root_key: false,
+		})
+	}
+
+	/// Checks that all objects and arrays were closed, flushes and returns
+	/// the underlying writer.
+	pub fn finish(mut self) -> io::Result<W> {
+		if self.blocks.len() > 1 {
+			return Err(invalid_input("unclosed object or array"));
+		}
+		if self.pending_key.is_some() {
+			return Err(invalid_input("key without a value"));
+		}
+		self.out.flush()?;
+		Ok(self.out)
+	}
+
+	/// Returns the indented start of the line introducing the next value,
+	/// i.e. `key: ` inside of objects and `- ` inside of arrays.
+	fn value_prefix(&mut self) -> io::Result<String> {
+		let depth = self.blocks.len() - 1;
+		let mut prefix = self.indention.repeat(depth);
+
+		match self.blocks.last() {
+			Some(Block::Object) => {
+				let Some(key) = self.pending_key.take() else {
+					return Err(invalid_input("value inside of an object without a key"));
+				};
+				if depth == 0 {
+					if self.wrote_root_key && self.options.blank_lines_between_keys {
+						writeln!(self.out)?;
+					}
+					self.wrote_root_key = true;
+				}
+				prefix.push_str(&encode_key(&key, &self.options).unwrap());
+				prefix.push(':');
+			}
+			_ => prefix.push('-'),
+		}
+
+		Ok(prefix)
+	}
+}
+
+impl<W: Write> KvonWrite for KvonWriter<W> {
+	fn key(&mut self, key: &str) -> io::Result<()> {
+		if self.blocks.last() != Some(&Block::Object) {
+			return Err(invalid_input("key outside of an object"));
+		}
+		if self.pending_key.is_some() {
+			return Err(invalid_input("key without a value"));
+		}
+		if encode_key(key, &self.options).is_none() {
+			return Err(invalid_input("key that can't be written"));
+		}
+		self.pending_key = Some(key.to_string());
+		Ok(())
+	}
+
+	fn value(&mut self, value: &PrimitiveValue) -> io::Result<()> {
+		let redacted = match (self.blocks.last(), &self.pending_key) {
+			(Some(Block::Object), Some(key)) if self.options.should_redact(key) => Some(
+				PrimitiveValue::from(self.options.redaction_placeholder.as_str()),
+			),
+			_ => None,
+		};
+		let value = redacted.as_ref().unwrap_or(value);
+
+		let mut prefix = self.value_prefix()?;
+		prefix.push(' ');
+		let lines = encode_primitive_lines(value, &self.options, prefix, self.blocks.len() as i32);
+		for line in lines {
+			writeln!(self.out, "{line}")?;
+		}
+		Ok(())
+	}
+
+	fn begin_object(&mut self) -> io::Result<()> {
+		let prefix = self.value_prefix()?;
+		writeln!(self.out, "{prefix}")?;
+		self.blocks.push(Block::Object);
+		Ok(())
+	}
+
+	fn begin_array(&mut self) -> io::Result<()> {
+		let mut prefix = self.value_prefix()?;
+		// nested arrays are introduced by `--` instead of `- --`
+		if prefix.ends_with('-') {
+			prefix.push('-');
+		} else {
+			prefix.push_str("--");
+		}
+		writeln!(self.out, "{prefix}")?;
+		self.blocks.push(Block::Array);
+		Ok(())
+	}
+
+	fn end(&mut self) -> io::Result<()> {
+		if self.blocks.len() == 1 {
+			return Err(invalid_input("end without an open object or array"));
+		}
+		if self.pending_key.is_some() {
+			return Err(invalid_input("key without a value"));
+		}
+		self.blocks.pop();
+		Ok(())
+	}
+}
+
+fn invalid_input(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidInput, message)
+}
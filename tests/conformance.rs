@@ -0,0 +1,330 @@
+//! Data-driven conformance tests. Every `tests/corpus/<name>.kvon` must parse
+//! into the value in `<name>.json`, and every
+//! `tests/corpus/invalid/<name>.kvon` must fail with the error code in
+//! `<name>.error`, e.g. `E101_UNCLOSED_STRING`. New cases only need the two
+//! files. Valid documents must also keep their value when formatted, and
+//! formatting them twice must not change them further. Every case must
+//! parse the same into a `CompactValue`, and with the `arena` feature, into
+//! an arena.
+//!
+//! The corpus is exported to `tests/vectors/kvon-rs.json`, a format other
+//! implementations can run without knowing the layout of the corpus:
+//!
+//! ```json
+//! {
+//!   "spec_version": "1.0",
+//!   "vectors": [
+//!     { "name": "keys", "input": "a: 1\n", "expected": { "a": 1 } },
+//!     { "name": "invalid/unclosed_string", "input": "a: 'b\n", "error": "E101_UNCLOSED_STRING" }
+//!   ]
+//! }
+//! ```
+//!
+//! Valid inputs have the `expected` value as JSON, invalid ones an `error`,
+//! which is either one of the error codes of this crate or `true` for any
+//! error. Vectors using extensions of the spec list their names in
+//! `extensions`, see `kvon_rs::features`. They are parsed with
+//! `Profile::Permissive` and skipped if an extension isn't supported, while
+//! all other vectors are parsed with `Profile::Strict`.
+//!
+//! Every `tests/vectors/*.json` file is run, so vectors of other
+//! implementations only need to be copied there. After changing the corpus,
+//! regenerate the export with `UPDATE_VECTORS=1 cargo test --test
+//! conformance`.
+
+use std::{
+	collections::HashMap,
+	fs,
+	path::{Path, PathBuf},
+};
+
+use kvon_rs::{
+	features::supports,
+	format::{format_str, FormatOptions},
+	indention::Indention,
+	parse_string, parse_string_with_options,
+	value::Value,
+	FormatVersion, ParserOptions, Profile, QuoteStyle,
+};
+
+fn corpus_dir() -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus")
+}
+
+fn vectors_dir() -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/vectors")
+}
+
+/// Returns the `.kvon` files in a directory with the path of their
+/// companion file with the given extension, sorted by name.
+fn cases(dir: &Path, extension: &str) -> Vec<(PathBuf, PathBuf)> {
+	let mut cases: Vec<_> = fs::read_dir(dir)
+		.unwrap()
+		.map(|entry| entry.unwrap().path())
+		.filter(|path| path.extension().is_some_and(|e| e == "kvon"))
+		.map(|path| {
+			let expected = path.with_extension(extension);
+			(path, expected)
+		})
+		.collect();
+	cases.sort();
+	cases
+}
+
+fn from_json(json: serde_json::Value) -> Value {
+	match json {
+		serde_json::Value::Null => Value::null(),
+		serde_json::Value::Bool(b) => Value::from(b),
+		serde_json::Value::Number(n) => Value::from(n.as_f64().unwrap() as f32),
+		serde_json::Value::String(s) => Value::from(s),
+		serde_json::Value::Array(arr) => Value::Array(arr.into_iter().map(from_json).collect()),
+		serde_json::Value::Object(obj) => Value::Object(
+			obj.into_iter()
+				.map(|(key, value)| (key, from_json(value)))
+				.collect::<HashMap<_, _>>(),
+		),
+	}
+}
+
+#[test]
+fn valid_documents() {
+	let cases = cases(&corpus_dir(), "json");
+	assert!(!cases.is_empty());
+
+	let mut failures = Vec::new();
+	for (source, expected) in cases {
+		let name = source.file_stem().unwrap().to_string_lossy().to_string();
+		let expected = fs::read_to_string(&expected)
+			.unwrap_or_else(|_| panic!("{name}: missing {}", expected.display()));
+		let expected = from_json(serde_json::from_str(&expected).unwrap());
+
+		match parse_string(&fs::read_to_string(&source).unwrap()) {
+			Ok(value) if value == expected => {}
+			Ok(value) => failures.push(format!(
+				"{name}: expected\n{}\nbut parsed\n{}",
+				expected.pretty(),
+				value.pretty()
+			)),
+			Err(e) => failures.push(format!("{name}: {e}")),
+		}
+	}
+
+	assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn invalid_documents() {
+	let cases = cases(&corpus_dir().join("invalid"), "error");
+	assert!(!cases.is_empty());
+
+	let mut failures = Vec::new();
+	for (source, expected) in cases {
+		let name = source.file_stem().unwrap().to_string_lossy().to_string();
+		let expected = fs::read_to_string(&expected)
+			.unwrap_or_else(|_| panic!("{name}: missing {}", expected.display()));
+		let expected = expected.trim();
+
+		match parse_string(&fs::read_to_string(&source).unwrap()) {
+			Err(e) if e.code() == expected => {}
+			Err(e) => failures.push(format!("{name}: expected {expected}, but got {e:?}")),
+			Ok(value) => failures.push(format!(
+				"{name}: expected {expected}, but parsed\n{}",
+				value.pretty()
+			)),
+		}
+	}
+
+	assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn formatting_is_idempotent() {
+	let cases = cases(&corpus_dir(), "json");
+	let styles = [
+		FormatOptions::new(),
+		FormatOptions::new()
+			.indention(Indention::Spaces(4))
+			.quote_style(QuoteStyle::Double),
+		FormatOptions::new()
+			.max_line_width(6)
+			.array_items_per_line(2),
+	];
+
+	let mut failures = Vec::new();
+	for (source, _) in cases {
+		let name = source.file_stem().unwrap().to_string_lossy().to_string();
+		let source = fs::read_to_string(&source).unwrap();
+		for (i, options) in styles.iter().enumerate() {
+			let formatted = format_str(&source, options).unwrap();
+			if parse_string(&formatted).unwrap() != parse_string(&source).unwrap() {
+				failures.push(format!("{name}, style {i}: changed the value\n{formatted}"));
+			}
+			let twice = format_str(&formatted, options).unwrap();
+			if twice != formatted {
+				failures.push(format!(
+					"{name}, style {i}: formatted\n{formatted}\nbut then\n{twice}"
+				));
+			}
+		}
+	}
+
+	assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn compact_parsing_matches() {
+	use kvon_rs::{compact::parse_string_compact, ParserOptions};
+
+	let mut cases = cases(&corpus_dir(), "json");
+	cases.extend(self::cases(&corpus_dir().join("invalid"), "error"));
+
+	let mut failures = Vec::new();
+	for (source, _) in cases {
+		let name = source.file_stem().unwrap().to_string_lossy().to_string();
+		let source = fs::read_to_string(&source).unwrap();
+		let parsed =
+			parse_string_compact(&source, &ParserOptions::new()).map(|value| value.to_value());
+		let expected = parse_string(&source);
+		match (parsed, expected) {
+			(Ok(parsed), Ok(expected)) if parsed == expected => {}
+			(Err(e), Err(expected)) if e.to_string() == expected.to_string() => {}
+			(parsed, expected) => {
+				failures.push(format!("{name}: expected {expected:?}, but got {parsed:?}"))
+			}
+		}
+	}
+
+	assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[cfg(feature = "arena")]
+#[test]
+fn arena_parsing_matches() {
+	use kvon_rs::arena::{parse_in, Bump};
+
+	let mut cases = cases(&corpus_dir(), "json");
+	cases.extend(self::cases(&corpus_dir().join("invalid"), "error"));
+
+	let mut failures = Vec::new();
+	for (source, _) in cases {
+		let name = source.file_stem().unwrap().to_string_lossy().to_string();
+		let source = fs::read_to_string(&source).unwrap();
+		let arena = Bump::new();
+		let parsed = parse_in(&arena, &source).map(|value| value.to_value());
+		let expected = parse_string(&source);
+		match (parsed, expected) {
+			(Ok(parsed), Ok(expected)) if parsed == expected => {}
+			(Err(e), Err(expected)) if e.to_string() == expected.to_string() => {}
+			(parsed, expected) => {
+				failures.push(format!("{name}: expected {expected:?}, but got {parsed:?}"))
+			}
+		}
+	}
+
+	assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+/// The corpus as test vectors, see the module documentation.
+fn corpus_vectors() -> serde_json::Value {
+	let mut vectors = Vec::new();
+	for (dir, prefix, extension) in [
+		(corpus_dir(), "", "json"),
+		(corpus_dir().join("invalid"), "invalid/", "error"),
+	] {
+		for (source, expected) in cases(&dir, extension) {
+			let name = source.file_stem().unwrap().to_string_lossy().to_string();
+			let mut vector = serde_json::Map::new();
+			vector.insert("name".into(), format!("{prefix}{name}").into());
+			vector.insert("input".into(), fs::read_to_string(&source).unwrap().into());
+			let expected = fs::read_to_string(&expected).unwrap();
+			match extension {
+				"json" => {
+					vector.insert("expected".into(), serde_json::from_str(&expected).unwrap())
+				}
+				_ => vector.insert("error".into(), expected.trim().into()),
+			};
+			vectors.push(serde_json::Value::Object(vector));
+		}
+	}
+	serde_json::json!({
+		"spec_version": FormatVersion::LATEST.to_string(),
+		"vectors": vectors,
+	})
+}
+
+#[test]
+fn exported_vectors_are_up_to_date() {
+	let path = vectors_dir().join("kvon-rs.json");
+	let exported = serde_json::to_string_pretty(&corpus_vectors()).unwrap() + "\n";
+	if std::env::var_os("UPDATE_VECTORS").is_some() {
+		fs::write(&path, exported).unwrap();
+		return;
+	}
+	let found = fs::read_to_string(&path).unwrap_or_default();
+	assert!(
+		found == exported,
+		"{} is outdated, update it with `UPDATE_VECTORS=1 cargo test --test conformance`",
+		path.display()
+	);
+}
+
+#[test]
+fn vectors() {
+	let mut files: Vec<_> = fs::read_dir(vectors_dir())
+		.unwrap()
+		.map(|entry| entry.unwrap().path())
+		.filter(|path| path.extension().is_some_and(|e| e == "json"))
+		.collect();
+	files.sort();
+	assert!(!files.is_empty());
+
+	let mut failures = Vec::new();
+	for file in files {
+		let file_name = file.file_name().unwrap().to_string_lossy().to_string();
+		let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&file).unwrap())
+			.unwrap_or_else(|e| panic!("{file_name}: {e}"));
+		for vector in json["vectors"].as_array().unwrap() {
+			let name = format!("{file_name}: {}", vector["name"].as_str().unwrap_or("?"));
+			let extensions: Vec<&str> = vector["extensions"]
+				.as_array()
+				.map(|extensions| extensions.iter().filter_map(|e| e.as_str()).collect())
+				.unwrap_or_default();
+			if !extensions.iter().all(|extension| supports(extension)) {
+				continue;
+			}
+			let options = ParserOptions::new().profile(match extensions.is_empty() {
+				true => Profile::Strict,
+				false => Profile::Permissive,
+			});
+
+			let parsed = parse_string_with_options(vector["input"].as_str().unwrap(), &options);
+			match (parsed, &vector["error"]) {
+				(Err(e), serde_json::Value::String(code)) if e.code() != code => {
+					failures.push(format!("{name}: expected {code}, but got {e:?}"))
+				}
+				(Err(_), serde_json::Value::String(_) | serde_json::Value::Bool(true)) => {}
+				(
+					Ok(value),
+					error @ (serde_json::Value::String(_) | serde_json::Value::Bool(true)),
+				) => failures.push(format!(
+					"{name}: expected error {error}, but parsed\n{}",
+					value.pretty()
+				)),
+				(parsed, _) => {
+					let expected = from_json(vector["expected"].clone());
+					match parsed {
+						Ok(value) if value == expected => {}
+						Ok(value) => failures.push(format!(
+							"{name}: expected\n{}\nbut parsed\n{}",
+							expected.pretty(),
+							value.pretty()
+						)),
+						Err(e) => failures.push(format!("{name}: {e}")),
+					}
+				}
+			}
+		}
+	}
+
+	assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
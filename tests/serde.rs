@@ -0,0 +1,188 @@
+//! Round trips serde types through KVON documents: each type is serialized
+//! into a [Value], encoded, parsed back and deserialized again.
+
+#![cfg(feature = "serde")]
+
+use std::{borrow::Cow, collections::HashMap, fmt::Debug};
+
+use kvon_rs::{
+	encode_string, from_str_path_err, from_value, parse_string, to_value, value::Value,
+	EncodeOptions,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Encodes a value into a document and deserializes it back.
+fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) -> String {
+	let document = encode_string(&to_value(value).unwrap(), &EncodeOptions::new());
+	let parsed: T = from_str_path_err(&document).unwrap();
+	assert_eq!(&parsed, value, "{document}");
+	document
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Service {
+	name: String,
+	#[serde(flatten)]
+	listen: Listen,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Listen {
+	host: String,
+	port: u16,
+}
+
+#[test]
+fn flatten_with_deny_unknown_fields() {
+	let service = Service {
+		name: "api".to_string(),
+		listen: Listen {
+			host: "localhost".to_string(),
+			port: 8080,
+		},
+	};
+	let document = round_trip(&service);
+	assert!(document.contains("host: 'localhost'"));
+
+	// fields of the flattened struct are known, any other field is not
+	let error =
+		from_str_path_err::<Service>("name: 'api'\nhost: 'a'\nport: 1\nhots: 'b'\n").unwrap_err();
+	assert!(error.to_string().contains("hots"), "{error}");
+	assert!(from_str_path_err::<Service>("name: 'api'\nhost: 'a'\n").is_err());
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Endpoint {
+	Port(u16),
+	Address { host: String, port: u16 },
+	Path(String),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "spec")]
+enum Storage {
+	Memory,
+	Disk { path: String, size: u32 },
+	Remote(Vec<String>),
+}
+
+#[test]
+fn untagged_and_adjacently_tagged_enums() {
+	#[derive(Debug, PartialEq, Serialize, Deserialize)]
+	struct Config {
+		endpoints: Vec<Endpoint>,
+		storage: Vec<Storage>,
+	}
+
+	let config = Config {
+		endpoints: vec![
+			Endpoint::Port(80),
+			Endpoint::Address {
+				host: "example.org".to_string(),
+				port: 443,
+			},
+			Endpoint::Path("/run/app.sock".to_string()),
+		],
+		storage: vec![
+			Storage::Memory,
+			Storage::Disk {
+				path: "/var/lib/app".to_string(),
+				size: 1024,
+			},
+			Storage::Remote(vec!["a".to_string(), "b".to_string()]),
+		],
+	};
+	round_trip(&config);
+
+	let storage: Storage =
+		from_str_path_err("kind: 'Disk'\nspec:\n\tpath: '/tmp'\n\tsize: 1\n").unwrap();
+	assert_eq!(
+		storage,
+		Storage::Disk {
+			path: "/tmp".to_string(),
+			size: 1
+		}
+	);
+	assert!(from_str_path_err::<Storage>("kind: 'Tape'\n").is_err());
+	assert!(from_value::<Endpoint>(Value::from(true)).is_err());
+}
+
+#[test]
+fn defaults_and_skipped_fields() {
+	#[derive(Debug, PartialEq, Serialize, Deserialize)]
+	struct Options {
+		#[serde(default, skip_serializing_if = "Option::is_none")]
+		timeout: Option<f32>,
+		#[serde(default, skip_serializing_if = "Vec::is_empty")]
+		tags: Vec<String>,
+		#[serde(
+			default = "default_retries",
+			skip_serializing_if = "is_default_retries"
+		)]
+		retries: u8,
+		#[serde(default)]
+		verbose: bool,
+	}
+
+	fn default_retries() -> u8 {
+		3
+	}
+
+	fn is_default_retries(retries: &u8) -> bool {
+		*retries == default_retries()
+	}
+
+	let defaults = Options {
+		timeout: None,
+		tags: Vec::new(),
+		retries: 3,
+		verbose: false,
+	};
+	// skipped fields are left out of the document and come back as their
+	// defaults
+	assert_eq!(round_trip(&defaults).trim(), "verbose: false");
+	assert_eq!(from_str_path_err::<Options>("").unwrap(), defaults);
+
+	round_trip(&Options {
+		timeout: Some(1.5),
+		tags: vec!["x".to_string()],
+		retries: 5,
+		verbose: true,
+	});
+}
+
+#[test]
+fn borrowed_data() {
+	#[derive(Debug, PartialEq, Deserialize)]
+	struct Record<'a> {
+		name: &'a str,
+		#[serde(borrow)]
+		note: Cow<'a, str>,
+		#[serde(borrow)]
+		labels: HashMap<&'a str, &'a str>,
+		tags: Vec<&'a str>,
+	}
+
+	let value =
+		parse_string("name: 'disk'\nnote: 'full'\nlabels:\n\tzone: 'eu'\ntags: ['a' 'b']\n")
+			.unwrap();
+	let record = Record::deserialize(&value).unwrap();
+	assert_eq!(record.name, "disk");
+	assert!(matches!(record.note, Cow::Borrowed("full")));
+	assert_eq!(record.labels, HashMap::from([("zone", "eu")]));
+	assert_eq!(record.tags, ["a", "b"]);
+
+	// the strings point into the value
+	let Value::Object(obj) = &value else {
+		panic!("expected an object");
+	};
+	assert!(std::ptr::eq(
+		record.name,
+		obj["name"].get_primitive().unwrap().get_string().unwrap()
+	));
+
+	// an owned value can't lend its strings
+	assert!(Record::deserialize(value.clone()).is_err());
+}